@@ -0,0 +1,61 @@
+//! FASTQ parsing throughput on synthetic short and long reads.
+//!
+//! Run with `cargo bench --bench fastq`. Like the minimizer benchmark it uses
+//! a plain timing loop, so it needs no extra dependencies.
+
+use seqkmer::{FastqReader, OptionPair, Reader};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const INPUT_BYTES: usize = 64 * 1024 * 1024;
+
+fn synthetic_fastq(read_len: usize) -> Vec<u8> {
+    let mut state = 0x9e3779b97f4a7c15u64;
+    let mut next = || {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+        state >> 58
+    };
+    let mut out = Vec::with_capacity(INPUT_BYTES + 2 * read_len + 64);
+    let mut index = 0;
+    while out.len() < INPUT_BYTES {
+        index += 1;
+        out.extend_from_slice(format!("@read{} 1:N:0:ACGTACGT\n", index).as_bytes());
+        out.extend((0..read_len).map(|_| b"ACGT"[(next() & 3) as usize]));
+        out.extend_from_slice(b"\n+\n");
+        out.extend((0..read_len).map(|_| b'!' + (next() % 41) as u8));
+        out.push(b'\n');
+    }
+    out
+}
+
+fn bench(name: &str, input: &[u8], keep_qual: bool) {
+    let iterations = 5;
+    let mut best = Duration::MAX;
+    let mut count = 0;
+    for _ in 0..iterations {
+        let start = Instant::now();
+        let mut reader = FastqReader::new(OptionPair::Single(input), 0, 0)
+            .with_qualities(keep_qual)
+            .with_batch_size(1000);
+        let mut batch = Vec::new();
+        let mut n = 0;
+        while reader.next_into(&mut batch).expect("valid input") {
+            n += black_box(&batch).len();
+        }
+        count = n;
+        best = best.min(start.elapsed());
+    }
+    let mbps = input.len() as f64 / best.as_secs_f64() / 1e6;
+    println!(
+        "{:<24} {:>10.2?}  {:>8.1} MB/s  {} records",
+        name, best, mbps, count
+    );
+}
+
+fn main() {
+    let short = synthetic_fastq(150);
+    let long = synthetic_fastq(10_000);
+    bench("short reads", &short, false);
+    bench("short reads + quals", &short, true);
+    bench("long reads", &long, false);
+}
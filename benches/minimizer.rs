@@ -0,0 +1,82 @@
+//! Minimizer scanning throughput on random and repetitive sequences.
+//!
+//! Run with `cargo bench --bench minimizer`. Uses a plain timing loop so it
+//! runs on stable without extra dependencies.
+
+use seqkmer::{for_each_minimizer, Cursor, Meros, MinimizerIterator, MinimizerWindow};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const SEQ_LEN: usize = 4 * 1024 * 1024;
+
+fn random_seq(len: usize) -> Vec<u8> {
+    let mut state = 0x9e3779b97f4a7c15u64;
+    (0..len)
+        .map(|_| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+            b"ACGT"[(state >> 62) as usize]
+        })
+        .collect()
+}
+
+fn repetitive_seq(len: usize) -> Vec<u8> {
+    b"AAAAAAAAAAAAACACACACACAGGGGGGGGT"
+        .iter()
+        .copied()
+        .cycle()
+        .take(len)
+        .collect()
+}
+
+fn bench(name: &str, seq: &[u8], meros: &Meros) {
+    let iterations = 5;
+    let mut best = Duration::MAX;
+    let mut count = 0;
+    for _ in 0..iterations {
+        let start = Instant::now();
+        let cursor = Cursor::new(meros);
+        let window = MinimizerWindow::new(meros.window_size());
+        count = black_box(MinimizerIterator::new(seq, cursor, window, meros).count());
+        best = best.min(start.elapsed());
+    }
+    let mbps = seq.len() as f64 / best.as_secs_f64() / 1e6;
+    println!(
+        "{:<24} {:>10.2?}  {:>8.1} Mbp/s  {} minimizers",
+        name, best, mbps, count
+    );
+}
+
+fn bench_fast_path(name: &str, seq: &[u8], meros: &Meros) {
+    let iterations = 5;
+    let mut best = Duration::MAX;
+    let mut count = 0;
+    for _ in 0..iterations {
+        let start = Instant::now();
+        let mut n = 0usize;
+        for_each_minimizer(seq, meros, |_, hash| {
+            black_box(hash);
+            n += 1;
+        });
+        count = n;
+        best = best.min(start.elapsed());
+    }
+    let mbps = seq.len() as f64 / best.as_secs_f64() / 1e6;
+    println!(
+        "{:<24} {:>10.2?}  {:>8.1} Mbp/s  {} minimizers",
+        name, best, mbps, count
+    );
+}
+
+fn main() {
+    let meros = Meros::default();
+    let random = random_seq(SEQ_LEN);
+    let repetitive = repetitive_seq(SEQ_LEN);
+    bench("random k35 l31", &random, &meros);
+    bench("repetitive k35 l31", &repetitive, &meros);
+    bench_fast_path("fixed random k35 l31", &random, &meros);
+    bench_fast_path("fixed repetitive k35 l31", &repetitive, &meros);
+
+    let wide = Meros::new(35, 15, None, None, None);
+    bench("random k35 l15", &random, &wide);
+    bench("repetitive k35 l15", &repetitive, &wide);
+}
@@ -0,0 +1,133 @@
+use std::io;
+use std::path::Path;
+
+/// Where the threads of a parallel pipeline run.
+///
+/// Pipeline threads are numbered in start order: the producer is 0, the
+/// workers follow, and the collector comes last. Pinning only takes effect
+/// with the `affinity` feature on Linux; elsewhere every thread floats.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::Affinity;
+///
+/// let affinity = Affinity::Cores(vec![2, 3]);
+/// assert_eq!(affinity.cores_for(0), vec![2]);
+/// assert_eq!(affinity.cores_for(3), vec![3]);
+/// assert!(Affinity::Unpinned.cores_for(0).is_empty());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Affinity {
+    /// Threads go wherever the OS schedules them.
+    #[default]
+    Unpinned,
+    /// Thread `i` is pinned to `cores[i % cores.len()]`.
+    Cores(Vec<usize>),
+    /// Every thread may run on any core of these NUMA nodes, which keeps
+    /// the batches passed between threads in local memory.
+    NumaNodes(Vec<usize>),
+}
+
+impl Affinity {
+    /// Returns the cores pipeline thread `index` may run on; empty when the
+    /// thread is not pinned, or the NUMA topology cannot be read.
+    pub fn cores_for(&self, index: usize) -> Vec<usize> {
+        match self {
+            Affinity::Unpinned => Vec::new(),
+            Affinity::Cores(cores) if cores.is_empty() => Vec::new(),
+            Affinity::Cores(cores) => vec![cores[index % cores.len()]],
+            Affinity::NumaNodes(nodes) => {
+                let mut cores: Vec<usize> = nodes
+                    .iter()
+                    .filter_map(|&node| numa_node_cores(node).ok())
+                    .flatten()
+                    .collect();
+                cores.sort_unstable();
+                cores.dedup();
+                cores
+            }
+        }
+    }
+
+    /// Pins the calling thread as pipeline thread `index`. Returns whether
+    /// its affinity was changed.
+    pub fn pin(&self, index: usize) -> bool {
+        if *self == Affinity::Unpinned {
+            return false;
+        }
+        pin_current_thread(&self.cores_for(index))
+    }
+}
+
+/// Parses a Linux CPU list such as `0-3,8,10-11`.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::parse_cpu_list;
+///
+/// assert_eq!(parse_cpu_list("0-2,8\n"), Some(vec![0, 1, 2, 8]));
+/// assert_eq!(parse_cpu_list(""), Some(vec![]));
+/// assert_eq!(parse_cpu_list("3-1"), None);
+/// ```
+pub fn parse_cpu_list(list: &str) -> Option<Vec<usize>> {
+    let mut cores = Vec::new();
+    for part in list.trim().split(',').filter(|part| !part.is_empty()) {
+        match part.split_once('-') {
+            Some((start, end)) => {
+                let (start, end): (usize, usize) = (start.parse().ok()?, end.parse().ok()?);
+                if start > end {
+                    return None;
+                }
+                cores.extend(start..=end);
+            }
+            None => cores.push(part.parse().ok()?),
+        }
+    }
+    Some(cores)
+}
+
+/// Returns the cores of NUMA node `node`, read from sysfs.
+pub fn numa_node_cores(node: usize) -> io::Result<Vec<usize>> {
+    let path = Path::new("/sys/devices/system/node")
+        .join(format!("node{}", node))
+        .join("cpulist");
+    let list = std::fs::read_to_string(&path)?;
+    parse_cpu_list(&list).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("malformed CPU list in {}", path.display()),
+        )
+    })
+}
+
+/// Restricts the calling thread to `cores`. Returns false, leaving the
+/// thread as it was, when `cores` is empty, the call fails, or pinning is
+/// not supported in this build.
+#[cfg(all(feature = "affinity", target_os = "linux"))]
+pub fn pin_current_thread(cores: &[usize]) -> bool {
+    // 与 glibc 的 cpu_set_t 相同: 1024 位
+    const SET_WORDS: usize = 16;
+    extern "C" {
+        fn sched_setaffinity(pid: i32, cpusetsize: usize, mask: *const u64) -> i32;
+    }
+
+    let mut mask = [0u64; SET_WORDS];
+    for &core in cores.iter().filter(|&&core| core < SET_WORDS * 64) {
+        mask[core / 64] |= 1 << (core % 64);
+    }
+    if mask.iter().all(|&word| word == 0) {
+        return false;
+    }
+    // pid 0 表示当前线程
+    unsafe { sched_setaffinity(0, std::mem::size_of_val(&mask), mask.as_ptr()) == 0 }
+}
+
+/// Restricts the calling thread to `cores`. Returns false, leaving the
+/// thread as it was, when `cores` is empty, the call fails, or pinning is
+/// not supported in this build.
+#[cfg(not(all(feature = "affinity", target_os = "linux")))]
+pub fn pin_current_thread(_cores: &[usize]) -> bool {
+    false
+}
@@ -0,0 +1,123 @@
+use crate::feat::Meros;
+use crate::mmscanner::{Cursor, MinimizerIterator, MinimizerWindow};
+use std::collections::HashMap;
+
+/// A minimizer shared by a query and a reference. Positions are offsets just
+/// past the residue that completed the minimizer (see
+/// [`MinimizerIterator::seq_pos`]), so query and reference use the same
+/// convention and diagonals are `ref_pos - query_pos`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Anchor {
+    pub ref_id: usize,
+    pub ref_pos: usize,
+    pub query_pos: usize,
+    pub hash: u64,
+}
+
+/// Returns the `(seq_pos, hash)` of every minimizer of `seq`.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{minimizer_positions, Meros};
+///
+/// let meros = Meros::new(5, 3, None, None, None);
+/// let mins = minimizer_positions(b"ACGTTGCAAC", &meros);
+/// assert!(!mins.is_empty());
+/// assert!(mins.windows(2).all(|w| w[0].0 < w[1].0));
+/// ```
+pub fn minimizer_positions(seq: &[u8], meros: &Meros) -> Vec<(usize, u64)> {
+    let cursor = Cursor::new(meros);
+    let window = MinimizerWindow::new(meros.window_size());
+    let mut m_iter = MinimizerIterator::new(seq, cursor, window, meros);
+    let mut out = Vec::new();
+    while let Some((_, hash)) = m_iter.next() {
+        out.push((m_iter.seq_pos(), hash));
+    }
+    out
+}
+
+/// Reference minimizer index: hash → every `(ref_id, ref_pos)` it occurs at.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{Meros, PositionIndex};
+///
+/// let meros = Meros::new(11, 5, None, None, None);
+/// let reference = b"TTGACCGATGCATCGGATCCATTGACGTAGCTAGGCTTAACG";
+/// let mut index = PositionIndex::new();
+/// index.add_sequence(7, reference, &meros);
+///
+/// // 读段取自参考序列的第 10 位之后
+/// let anchors = index.anchors(&reference[10..34], &meros);
+/// assert!(anchors.iter().all(|a| a.ref_id == 7));
+/// let on_diagonal = anchors.iter().filter(|a| a.ref_pos == a.query_pos + 10).count();
+/// assert!(on_diagonal * 2 > anchors.len());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PositionIndex {
+    positions: HashMap<u64, Vec<(usize, usize)>>,
+    max_occurrences: Option<usize>,
+}
+
+impl PositionIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Skips minimizers occurring more than `max` times in the reference
+    /// when building anchors, which keeps repeats from flooding the chainer.
+    pub fn with_max_occurrences(mut self, max: usize) -> Self {
+        self.max_occurrences = Some(max);
+        self
+    }
+
+    /// Indexes every minimizer of a reference sequence.
+    pub fn add_sequence(&mut self, ref_id: usize, seq: &[u8], meros: &Meros) {
+        for (pos, hash) in minimizer_positions(seq, meros) {
+            self.positions.entry(hash).or_default().push((ref_id, pos));
+        }
+    }
+
+    /// Number of distinct minimizers.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Returns the `(ref_id, ref_pos)` occurrences of a minimizer.
+    pub fn positions(&self, hash: u64) -> &[(usize, usize)] {
+        self.positions.get(&hash).map_or(&[], |v| v.as_slice())
+    }
+
+    /// Scans `seq` and returns its anchors (see [`anchors`]).
+    pub fn anchors(&self, seq: &[u8], meros: &Meros) -> Vec<Anchor> {
+        anchors(&minimizer_positions(seq, meros), self)
+    }
+}
+
+/// Pairs each query minimizer `(query_pos, hash)` with its reference
+/// occurrences. The result is sorted by reference id, then reference
+/// position, then query position, the order chaining expects.
+pub fn anchors(query: &[(usize, u64)], index: &PositionIndex) -> Vec<Anchor> {
+    let mut out = Vec::new();
+    for &(query_pos, hash) in query {
+        let hits = index.positions(hash);
+        if index.max_occurrences.is_some_and(|max| hits.len() > max) {
+            continue;
+        }
+        out.extend(hits.iter().map(|&(ref_id, ref_pos)| Anchor {
+            ref_id,
+            ref_pos,
+            query_pos,
+            hash,
+        }));
+    }
+    out.sort_unstable();
+    out.dedup();
+    out
+}
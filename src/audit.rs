@@ -0,0 +1,194 @@
+//! Reference implementation of minimizer selection used to audit the
+//! streaming scanner.
+//!
+//! With the `audit` feature enabled, every `MinimizerIterator` and
+//! `MultiSeedIterator` that runs to completion compares its output with
+//! [`naive_minimizers`] and panics with a shrunken reproducer on divergence.
+
+use crate::feat::{fmix64 as murmur_hash3, Meros, Residue};
+use crate::mmscanner::{to_candidate_lmer, Cursor, MinimizerIterator, MinimizerWindow};
+
+/// Computes the minimizer hashes of a sequence by brute force: every window
+/// of l-mers is scanned in full, with no incremental state. Consecutive
+/// duplicates are collapsed, so the result is comparable with the streaming
+/// scanner after the same collapse.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{naive_minimizers, Meros};
+///
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+/// let hashes = naive_minimizers(b"ATCGATCGATCGNATCGATCGATCG", &meros);
+/// assert!(!hashes.is_empty());
+/// ```
+pub fn naive_minimizers(seq: &[u8], meros: &Meros) -> Vec<u64> {
+    let bits = meros.alphabet.bits_per_char();
+    let capacity = meros.window_size();
+    let window_len = if capacity <= 1 { 1 } else { capacity + 1 };
+
+    let mut hashes = Vec::new();
+    let mut run: Vec<u64> = Vec::new();
+    let mut candidates: Vec<u64> = Vec::new();
+    for (pos, &c) in seq.iter().enumerate() {
+        if c == b'\n' || c == b'\r' {
+            continue;
+        }
+        let code = match meros.encode_residue(c, pos) {
+            Residue::Code(code) => code,
+            Residue::Skip => continue,
+            Residue::Reset => {
+                run.clear();
+                candidates.clear();
+                continue;
+            }
+        };
+        if meros.hpc && run.last() == Some(&code) {
+            continue;
+        }
+        run.push(code);
+        if run.len() < meros.l_mer {
+            continue;
+        }
+        let lmer = run[run.len() - meros.l_mer..]
+            .iter()
+            .fold(0u64, |acc, &code| (acc << bits) | code)
+            & meros.mask;
+        candidates.push(to_candidate_lmer(meros, lmer));
+        if candidates.len() < window_len {
+            continue;
+        }
+        let minimum = *candidates[candidates.len() - window_len..]
+            .iter()
+            .min()
+            .expect("window is not empty");
+        if !meros.passes_entropy(minimum) {
+            continue;
+        }
+        let hash = murmur_hash3(minimum ^ meros.toggle_mask);
+        if hashes.last() != Some(&hash) {
+            hashes.push(hash);
+        }
+    }
+    hashes
+}
+
+/// Collapses consecutive duplicates of streamed hashes.
+pub(crate) fn collapse(hashes: impl IntoIterator<Item = u64>) -> Vec<u64> {
+    let mut out: Vec<u64> = Vec::new();
+    for hash in hashes {
+        if out.last() != Some(&hash) {
+            out.push(hash);
+        }
+    }
+    out
+}
+
+fn diverges(seq: &[u8], meros: &Meros) -> bool {
+    let cursor = Cursor::new(meros);
+    let window = MinimizerWindow::new(meros.window_size());
+    let streamed = MinimizerIterator::new(seq, cursor, window, meros).map(|(_, hash)| hash);
+    collapse(streamed) != naive_minimizers(seq, meros)
+}
+
+/// Checks the streaming scanner against the oracle on `seq`. On divergence,
+/// returns the shortest subsequence (found by trimming both ends) that still
+/// diverges.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{find_divergence, Meros};
+///
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+/// assert_eq!(find_divergence(b"ATCGATCGATCGTTAGGCA", &meros), None);
+/// ```
+pub fn find_divergence(seq: &[u8], meros: &Meros) -> Option<Vec<u8>> {
+    if !diverges(seq, meros) {
+        return None;
+    }
+    let (mut start, mut end) = (0, seq.len());
+    loop {
+        if end - start > 1 && diverges(&seq[start + 1..end], meros) {
+            start += 1;
+        } else if end - start > 1 && diverges(&seq[start..end - 1], meros) {
+            end -= 1;
+        } else {
+            return Some(seq[start..end].to_vec());
+        }
+    }
+}
+
+/// Panics with a minimal reproducer if the streamed hashes differ from the
+/// oracle.
+#[cfg(feature = "audit")]
+pub(crate) fn assert_matches_oracle(seq: &[u8], meros: &Meros, streamed: &[u64]) {
+    if collapse(streamed.iter().copied()) == naive_minimizers(seq, meros) {
+        return;
+    }
+    let reproducer = find_divergence(seq, meros).unwrap_or_else(|| seq.to_vec());
+    panic!(
+        "minimizer scanner diverged from the naive oracle\n  sequence: {}\n  meros: {:?}",
+        String::from_utf8_lossy(&reproducer),
+        meros
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_streaming_matches_oracle() {
+        let mut state = 0x2545f4914f6cdd1du64;
+        let mut next = || {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+            state >> 33
+        };
+        let seq: Vec<u8> = (0..3000)
+            .map(|_| b"ACGTACGTACGTACGTN\n"[(next() % 18) as usize])
+            .collect();
+
+        for (k, l) in [(11, 3), (11, 10), (35, 31), (15, 5)] {
+            for hpc in [false, true] {
+                let meros = Meros::new(k, l, None, None, None).with_hpc(hpc);
+                assert_eq!(find_divergence(&seq, &meros), None, "k={} l={}", k, l);
+            }
+        }
+    }
+
+    #[test]
+    fn test_multi_seed_matches_oracle() {
+        use crate::mmscanner::MultiSeedIterator;
+
+        let mut state = 0x9e3779b97f4a7c15u64;
+        let mut next = || {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+            state >> 33
+        };
+        // 混入低复杂度片段, 让熵过滤真正生效
+        let mut seq: Vec<u8> = Vec::new();
+        for _ in 0..40 {
+            seq.extend((0..60).map(|_| b"ACGTACGTACGTN\n"[(next() % 14) as usize]));
+            seq.extend_from_slice(b"ACACACACACACACACAAAAAAAAAAAA");
+        }
+        let masks = [0, 0b1111_0011_1100_1111_1111, 0b1111_1111_0000_1111_1111];
+
+        for min_entropy in [None, Some(1.0), Some(1.5)] {
+            for hpc in [false, true] {
+                let mut meros = Meros::new(15, 10, Some(0), None, None).with_hpc(hpc);
+                meros.min_entropy = min_entropy;
+                let pairs: Vec<(usize, u64)> =
+                    MultiSeedIterator::new(&seq, &meros, &masks).collect();
+                for (seed_id, &spaced_seed_mask) in masks.iter().enumerate() {
+                    let seed = Meros {
+                        spaced_seed_mask,
+                        ..meros
+                    };
+                    let got = collapse(pairs.iter().filter(|p| p.0 == seed_id).map(|p| p.1));
+                    assert_eq!(got, naive_minimizers(&seq, &seed), "{:?}", seed);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use crate::error::Result;
+use crate::fasta::FastaReader;
+use crate::fastq::FastqReader;
+use crate::feat::Meros;
+use crate::mmscanner::scan_sequence;
+use crate::reader::{sniff_format, Reader};
+use crate::seq::SeqFormat;
+use crate::stats::{SeqStats, StatsSummary};
+use crate::utils::OptionPair;
+use flate2::read::MultiGzDecoder;
+use std::collections::BTreeSet;
+use std::io::Read;
+
+/// Reads a FASTA or FASTQ file held in memory, e.g. one a browser handed
+/// over before upload. Gzip input is decompressed and the format is
+/// sniffed; FASTQ qualities are kept. Needs neither threads nor a file
+/// system, so it also works on `wasm32`.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{reader_from_bytes, Reader};
+///
+/// # fn main() -> Result<(), seqkmer::SeqKmerError> {
+/// let data = std::fs::read("tests/data/test.fastq")?;
+/// let mut reader = reader_from_bytes(&data, 0)?;
+/// let seqs = reader.next()?.unwrap();
+/// assert_eq!(seqs.len(), 3);
+/// assert!(seqs[0].qual.is_some());
+/// # Ok(())
+/// # }
+/// ```
+pub fn reader_from_bytes(data: &[u8], file_index: usize) -> Result<Box<dyn Reader + '_>> {
+    let stream: Box<dyn Read + Send + '_> = if data.starts_with(&[0x1f, 0x8b]) {
+        Box::new(MultiGzDecoder::new(data))
+    } else {
+        Box::new(data)
+    };
+    let (format, stream) = sniff_format(stream)?;
+    Ok(match format {
+        SeqFormat::Fasta => Box::new(FastaReader::new(stream, file_index)),
+        SeqFormat::Fastq => Box::new(
+            FastqReader::new(OptionPair::Single(stream), file_index, 0).with_qualities(true),
+        ),
+    })
+}
+
+/// Returns a bottom-`size` sketch of an in-memory FASTA or FASTQ file: its
+/// `size` smallest distinct minimizer hashes, in ascending order. Sketches
+/// of two files can be compared to estimate their similarity.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{sketch_from_bytes, Meros};
+///
+/// # fn main() -> Result<(), seqkmer::SeqKmerError> {
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+/// let fasta = std::fs::read("tests/data/test.fasta")?;
+/// let fastq = std::fs::read("tests/data/test.fastq")?;
+///
+/// let sketch = sketch_from_bytes(&fasta, &meros, 8)?;
+/// assert!(!sketch.is_empty() && sketch.len() <= 8);
+/// assert!(sketch.windows(2).all(|pair| pair[0] < pair[1]));
+/// // 同样的序列, 同样的草图
+/// assert_eq!(sketch, sketch_from_bytes(&fastq, &meros, 8)?);
+/// # Ok(())
+/// # }
+/// ```
+pub fn sketch_from_bytes(data: &[u8], meros: &Meros, size: usize) -> Result<Vec<u64>> {
+    let mut sketch = BTreeSet::new();
+    let mut reader = reader_from_bytes(data, 0)?;
+    while let Some(seqs) = reader.next()? {
+        for seq in &seqs {
+            for hash in scan_sequence(seq, meros).unique_minimizers() {
+                // 只保留最小的 size 个
+                if sketch.len() < size {
+                    sketch.insert(hash);
+                } else if sketch.last().is_some_and(|&max| hash < max) && sketch.insert(hash) {
+                    sketch.pop_last();
+                }
+            }
+        }
+    }
+    Ok(sketch.into_iter().collect())
+}
+
+/// Computes read statistics (lengths, N50, GC, Q20/Q30) of an in-memory
+/// FASTA or FASTQ file.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::stats_from_bytes;
+///
+/// # fn main() -> Result<(), seqkmer::SeqKmerError> {
+/// let summary = stats_from_bytes(&std::fs::read("tests/data/test.fastq")?)?;
+/// assert_eq!((summary.num_seqs, summary.sum_len), (3, 60));
+/// assert_eq!(summary.q30, Some(1.0));
+/// # Ok(())
+/// # }
+/// ```
+pub fn stats_from_bytes(data: &[u8]) -> Result<StatsSummary> {
+    let mut stats = SeqStats::new();
+    let mut reader = reader_from_bytes(data, 0)?;
+    while let Some(seqs) = reader.next()? {
+        seqs.iter().for_each(|seq| stats.add(seq));
+    }
+    Ok(stats.summary())
+}
@@ -0,0 +1,85 @@
+use crate::error::Result;
+use crate::reader::Reader;
+use crate::seq::Base;
+use std::collections::VecDeque;
+
+/// Reads several readers one after another as a single stream, e.g. the
+/// lanes or technical replicates of a sample in one parallel pass. Records
+/// are passed through unchanged, so each keeps the `file_index` and
+/// `reads_index` its own reader gave it; give the readers distinct file
+/// indices to tell the sources apart. A batch never mixes two readers.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{ChainedReader, FastaReader, FastqReader, OptionPair, Reader};
+/// use std::path::Path;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let fasta = FastaReader::from_path(Path::new("tests/data/test.fasta"), 0)?;
+/// let fastq = FastqReader::from_path(OptionPair::Single("tests/data/test.fastq"), 1, 0)?;
+/// let mut reader = ChainedReader::new(vec![Box::new(fasta)]).with_reader(Box::new(fastq));
+///
+/// let mut sources = Vec::new();
+/// while let Some(seqs) = reader.next()? {
+///     sources.extend(seqs.iter().map(|seq| seq.header.file_index));
+/// }
+/// assert_eq!(&sources[..3], &[0, 0, 0]);
+/// assert!(sources.len() > 3 && sources[3..].iter().all(|&index| index == 1));
+/// # Ok(())
+/// # }
+/// ```
+pub struct ChainedReader {
+    /// 尚未读完的读取器, 队首为当前读取器
+    readers: VecDeque<Box<dyn Reader + Send>>,
+    batch_size: Option<usize>,
+}
+
+impl ChainedReader {
+    pub fn new(readers: Vec<Box<dyn Reader + Send>>) -> Self {
+        Self {
+            readers: readers.into(),
+            batch_size: None,
+        }
+    }
+
+    /// Appends a reader, read after all those already in the chain.
+    pub fn with_reader(mut self, mut reader: Box<dyn Reader + Send>) -> Self {
+        if let Some(batch_size) = self.batch_size {
+            reader.set_batch_size(batch_size);
+        }
+        self.readers.push_back(reader);
+        self
+    }
+
+    /// Number of readers not yet exhausted.
+    pub fn remaining(&self) -> usize {
+        self.readers.len()
+    }
+}
+
+impl Reader for ChainedReader {
+    fn next(&mut self) -> Result<Option<Vec<Base<Vec<u8>>>>> {
+        let mut seqs = Vec::new();
+        Ok(self.next_into(&mut seqs)?.then_some(seqs))
+    }
+
+    fn next_into(&mut self, batch: &mut Vec<Base<Vec<u8>>>) -> Result<bool> {
+        while let Some(reader) = self.readers.front_mut() {
+            if reader.next_into(batch)? {
+                return Ok(true);
+            }
+            // 当前读取器已读完, 换下一个
+            self.readers.pop_front();
+        }
+        batch.clear();
+        Ok(false)
+    }
+
+    fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = Some(batch_size);
+        for reader in self.readers.iter_mut() {
+            reader.set_batch_size(batch_size);
+        }
+    }
+}
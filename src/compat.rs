@@ -0,0 +1,254 @@
+//! Compatibility modes that pin minimizer values to other tools' definitions,
+//! aiming for databases built by either side to be usable by the other.
+
+/// Kraken 2 compatibility.
+///
+/// Kraken 2 keys its database by `MurmurHash3` of the minimizer l-mer, after
+/// canonicalization, spaced seed masking and toggle-mask ordering.
+/// [`minimizers`] reimplements the selection logic of Kraken 2's
+/// `MinimizerScanner` from its source, and the streaming scanner of this
+/// crate is tested to agree with it for the configurations accepted by
+/// [`check`].
+///
+/// Compatibility with Kraken 2 itself is unverified: the reference values
+/// in the tests are derived by hand, not produced by a Kraken 2 build, so
+/// the two implementations could share a misreading of Kraken 2. Until
+/// output of a real Kraken 2 run is checked in as test data, do not rely on
+/// databases being interchangeable.
+pub mod kraken2 {
+    use crate::feat::{Alphabet, IupacPolicy, Meros};
+    use crate::mmscanner::to_candidate_lmer;
+    use std::collections::VecDeque;
+    use std::io;
+
+    /// Builds a configuration from Kraken 2's scanner parameters: k-mer
+    /// length, minimizer length, the expanded spaced seed mask (0 for none)
+    /// and the toggle mask, as stored in a Kraken 2 `opts.k2d` file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::compat::kraken2;
+    /// use seqkmer::DEFAULT_TOGGLE_MASK;
+    ///
+    /// let meros = kraken2::meros(35, 31, 0, DEFAULT_TOGGLE_MASK).unwrap();
+    /// assert_eq!(meros.toggle_mask, DEFAULT_TOGGLE_MASK & meros.mask);
+    /// assert!(kraken2::meros(32, 31, 0, DEFAULT_TOGGLE_MASK).is_err());
+    /// ```
+    pub fn meros(
+        k_mer: usize,
+        l_mer: usize,
+        spaced_seed_mask: u64,
+        toggle_mask: u64,
+    ) -> io::Result<Meros> {
+        let meros = Meros::try_new(
+            k_mer,
+            l_mer,
+            Some(spaced_seed_mask),
+            Some(toggle_mask),
+            None,
+        )?;
+        check(&meros)?;
+        Ok(meros)
+    }
+
+    /// Checks that `meros` is a configuration for which the streaming
+    /// scanner follows Kraken 2's minimizer selection. Rejected are the
+    /// extensions Kraken 2 does not have (homopolymer compression, entropy
+    /// filtering, IUPAC policies other than [`IupacPolicy::Reset`]),
+    /// alphabets other than DNA and Kraken 2's reduced protein alphabet, and
+    /// `k_mer == l_mer + 1`: the crate treats a one-residue window as "every
+    /// l-mer is a minimizer", while Kraken 2 takes the smaller of the two
+    /// l-mers in each k-mer.
+    ///
+    /// `min_clear_hash_value` is accepted; like Kraken 2, callers apply it
+    /// to the hashes after scanning.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::compat::kraken2;
+    /// use seqkmer::Meros;
+    ///
+    /// let meros = Meros::kraken2_defaults(35, 31).unwrap();
+    /// assert!(kraken2::check(&meros).is_ok());
+    /// assert!(kraken2::check(&meros.with_hpc(true)).is_err());
+    /// ```
+    pub fn check(meros: &Meros) -> io::Result<()> {
+        meros.validate()?;
+        let unsupported = if meros.hpc {
+            Some("homopolymer compression".to_string())
+        } else if meros.min_entropy.is_some() {
+            Some("entropy filtering".to_string())
+        } else if meros.iupac != IupacPolicy::Reset {
+            Some(format!("IUPAC policy {:?}", meros.iupac))
+        } else if !matches!(meros.alphabet, Alphabet::Dna | Alphabet::Reduced15) {
+            Some(format!("alphabet {:?}", meros.alphabet))
+        } else if meros.window_size() < 2 {
+            Some(format!(
+                "k_mer {} with l_mer {} (the window must span at least 3 l-mers)",
+                meros.k_mer, meros.l_mer
+            ))
+        } else {
+            None
+        };
+        match unsupported {
+            Some(what) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("not Kraken 2 compatible: {}", what),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the minimizer l-mers Kraken 2 is expected to report for
+    /// `seq` (see the module docs for what has been verified), one per run
+    /// of k-mers sharing a minimizer, skipping k-mers that contain a
+    /// non-ACGT residue, as Kraken 2's classifier does. Hash them with
+    /// [`fmix64`](crate::fmix64) to get database keys. Any valid
+    /// configuration is scanned with Kraken 2's rules, including those
+    /// [`check`] rejects.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::compat::kraken2;
+    /// use seqkmer::{fmix64, scan_slice, Meros};
+    ///
+    /// let meros = Meros::kraken2_defaults(35, 31).unwrap();
+    /// let seq = b"GATTACAGATTACACCGGTTAACCGGTTAAGGCCATTAGCAGCAGCANNACGTACGTTGCA";
+    /// let hashes: Vec<u64> = kraken2::minimizers(seq, &meros).into_iter().map(fmix64).collect();
+    ///
+    /// let mut streamed: Vec<u64> = scan_slice(seq, &meros).map(|(_, hash)| hash).collect();
+    /// streamed.dedup();
+    /// assert_eq!(streamed, hashes);
+    /// ```
+    pub fn minimizers(seq: &[u8], meros: &Meros) -> Vec<u64> {
+        let bits = meros.alphabet.bits_per_char();
+        let span = meros.window_size();
+        // (candidate, queue_pos), 与 Kraken 2 的 MinimizerData 相同
+        let mut queue: VecDeque<(u64, usize)> = VecDeque::new();
+        let mut queue_pos = 0;
+        let mut lmer = 0u64;
+        let mut loaded_ch = 0;
+        // 自上一个歧义字符以来读入的字符数, 不足 k 的 k-mer 是歧义的
+        let mut clean_ch = 0;
+        let mut values = Vec::new();
+        for &c in seq {
+            if c == b'\n' || c == b'\r' {
+                continue;
+            }
+            let Some(code) = meros.alphabet.encode(c) else {
+                queue.clear();
+                queue_pos = 0;
+                lmer = 0;
+                loaded_ch = 0;
+                clean_ch = 0;
+                continue;
+            };
+            lmer = ((lmer << bits) | code) & meros.mask;
+            loaded_ch = (loaded_ch + 1).min(meros.l_mer);
+            clean_ch += 1;
+            if loaded_ch < meros.l_mer {
+                continue;
+            }
+            let candidate = to_candidate_lmer(meros, lmer);
+            while queue.back().is_some_and(|&(back, _)| back > candidate) {
+                queue.pop_back();
+            }
+            queue.push_back((candidate, queue_pos));
+            // 每次只加入一个 l-mer, 队首最多只有一个过期
+            if queue
+                .front()
+                .is_some_and(|&(_, pos)| pos + span < queue_pos)
+            {
+                queue.pop_front();
+            }
+            queue_pos += 1;
+            if clean_ch < meros.k_mer {
+                continue;
+            }
+            let (front, _) = queue.front().expect("window is not empty");
+            let value = front ^ meros.toggle_mask;
+            if values.last() != Some(&value) {
+                values.push(value);
+            }
+        }
+        values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::kraken2;
+    use crate::feat::{fmix64, Meros, DEFAULT_TOGGLE_MASK};
+    use crate::mmscanner::scan_slice;
+
+    fn streamed(seq: &[u8], meros: &Meros) -> Vec<u64> {
+        let mut hashes: Vec<u64> = scan_slice(seq, meros).map(|(_, hash)| hash).collect();
+        hashes.dedup();
+        hashes
+    }
+
+    // 以下期望值为手工推导, 并非 Kraken 2 的实际输出; 真实向量入库前兼容性未经验证
+    #[test]
+    fn test_hand_derived_values() {
+        // 手工推导: GA->8, AT->3, TT->0 (AA), TA->12, AC->1, CA->4 (TG 更大),
+        // 每个 4-mer 含 3 个 2-mer, 最小值依次为 0, 0, 0, 1
+        let meros = kraken2::meros(4, 2, 0, 0).unwrap();
+        assert_eq!(kraken2::minimizers(b"GATTACA", &meros), vec![0, 1]);
+        // N 之前的 k-mer 不足 4 个字符, 不产生最小化子
+        assert_eq!(kraken2::minimizers(b"GATNTACA", &meros), vec![1]);
+
+        // toggle mask 只改变排序, 报告的是原始 l-mer: 异或后候选值为
+        // 10, 1, 2, 14, 3, 6, 最小值依次对应 AT(3), AT(3), AA(0), AC(1)
+        let meros = kraken2::meros(4, 2, 0, 0b10).unwrap();
+        assert_eq!(kraken2::minimizers(b"GATTACA", &meros), vec![3, 0, 1]);
+
+        // 间隔种子屏蔽每个 2-mer 的第二个碱基: GA->8, AT->0, AA->0, TA->12, AC->0, CA->4
+        let meros = kraken2::meros(4, 2, 0b1100, 0).unwrap();
+        assert_eq!(kraken2::minimizers(b"GATTACA", &meros), vec![0]);
+    }
+
+    #[test]
+    fn test_streaming_scanner_matches_kraken2() {
+        let mut state = 0x9e3779b97f4a7c15u64;
+        let mut next = || {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+            state >> 33
+        };
+        let seq: Vec<u8> = (0..5000)
+            .map(|_| b"ACGTACGTACGTACGTacgtN\n"[(next() % 22) as usize])
+            .collect();
+
+        let mut configs = vec![
+            kraken2::meros(11, 3, 0, DEFAULT_TOGGLE_MASK).unwrap(),
+            kraken2::meros(15, 5, 0, 0).unwrap(),
+            kraken2::meros(35, 31, 0, DEFAULT_TOGGLE_MASK).unwrap(),
+        ];
+        configs.push(Meros::kraken2_defaults(35, 31).unwrap());
+        configs.push(Meros::kraken2_defaults(32, 28).unwrap());
+        for meros in configs {
+            kraken2::check(&meros).unwrap();
+            let expected: Vec<u64> = kraken2::minimizers(&seq, &meros)
+                .into_iter()
+                .map(fmix64)
+                .collect();
+            assert!(!expected.is_empty());
+            assert_eq!(streamed(&seq, &meros), expected, "{:?}", meros);
+        }
+    }
+
+    #[test]
+    fn test_one_residue_window_is_rejected() {
+        // k = l + 1 时 Kraken 2 比较两个 l-mer, 流式扫描器则逐个输出
+        let meros = Meros::new(4, 3, Some(0), Some(0), None);
+        assert!(kraken2::check(&meros).is_err());
+        let seq = b"AAAC";
+        let expected: Vec<u64> = kraken2::minimizers(seq, &meros)
+            .into_iter()
+            .map(fmix64)
+            .collect();
+        assert_ne!(streamed(seq, &meros), expected);
+    }
+}
@@ -0,0 +1,216 @@
+use crate::export::{for_each_canonical_kmer, invalid_data, MAX_EXPORT_K};
+use crate::feat::{dna_to_value, Alphabet};
+use crate::reader::Reader;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Result};
+
+/// Canonical k-mer counts.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::KmerCounts;
+///
+/// let mut counts = KmerCounts::new(3);
+/// counts.add_sequence(b"ACGTT");
+/// // ACG and CGT are reverse complements of each other
+/// assert_eq!(counts.get_str(b"ACG"), 2);
+/// assert_eq!(counts.get_str(b"AAC"), 1);
+/// assert_eq!(counts.len(), 2);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KmerCounts {
+    pub k: usize,
+    pub counts: HashMap<u64, u64>,
+}
+
+impl KmerCounts {
+    /// Creates an empty counter for k-mers of length `k` (at most 31).
+    pub fn new(k: usize) -> Self {
+        assert!(k > 0 && k <= MAX_EXPORT_K, "k must be in 1..=31");
+        Self {
+            k,
+            counts: HashMap::new(),
+        }
+    }
+
+    /// Adds `count` occurrences of a packed k-mer, canonicalizing it first.
+    #[inline]
+    pub fn add(&mut self, kmer: u64, count: u64) {
+        let kmer = Alphabet::Dna.canonical(kmer, self.k);
+        *self.counts.entry(kmer).or_insert(0) += count;
+    }
+
+    /// Counts every k-mer of a sequence.
+    pub fn add_sequence(&mut self, seq: &[u8]) {
+        let counts = &mut self.counts;
+        for_each_canonical_kmer(seq, self.k, |kmer| *counts.entry(kmer).or_insert(0) += 1);
+    }
+
+    /// Counts every k-mer of every sequence a reader yields.
+    pub fn add_reader<R: Reader>(&mut self, reader: &mut R) -> Result<()> {
+        while let Some(seqs) = reader.next()? {
+            for seq in seqs {
+                seq.body.apply(|bytes| self.add_sequence(bytes));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the count of a packed k-mer, in either orientation.
+    #[inline]
+    pub fn get(&self, kmer: u64) -> u64 {
+        let kmer = Alphabet::Dna.canonical(kmer, self.k);
+        self.counts.get(&kmer).copied().unwrap_or(0)
+    }
+
+    /// Returns the count of a k-mer spelled with `ACGT`, or 0 if it is not a
+    /// valid k-mer of this length.
+    pub fn get_str(&self, kmer: &[u8]) -> u64 {
+        if kmer.len() != self.k {
+            return 0;
+        }
+        kmer.iter()
+            .try_fold(0u64, |acc, &c| {
+                dna_to_value(c).map(|code| (acc << 2) | code)
+            })
+            .map_or(0, |kmer| self.get(kmer))
+    }
+
+    /// Number of distinct canonical k-mers.
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Returns the k-mers and their counts as two parallel columns sorted
+    /// by k-mer, the counterpart of `MinimizerColumns` for count tables.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::KmerCounts;
+    ///
+    /// let mut counts = KmerCounts::new(3);
+    /// counts.add_sequence(b"ACGTT");
+    /// let (kmers, values) = counts.to_columns();
+    /// // AAC < ACG
+    /// assert_eq!(kmers, [0b000001, 0b000110]);
+    /// assert_eq!(values, [1, 2]);
+    /// ```
+    pub fn to_columns(&self) -> (Vec<u64>, Vec<u64>) {
+        let mut rows: Vec<(u64, u64)> = self.counts.iter().map(|(&k, &c)| (k, c)).collect();
+        rows.sort_unstable();
+        rows.into_iter().unzip()
+    }
+
+    /// Adds all counts of `other` into this counter.
+    pub fn merge(&mut self, other: &KmerCounts) {
+        assert_eq!(self.k, other.k, "cannot merge counts of different k");
+        for (&kmer, &count) in &other.counts {
+            *self.counts.entry(kmer).or_insert(0) += count;
+        }
+    }
+}
+
+/// Parses one `kmer count` record into the counter, fixing k on the first one.
+fn add_dump_record(
+    counts: &mut Option<KmerCounts>,
+    kmer: &str,
+    count: &str,
+    line_no: usize,
+) -> Result<()> {
+    let counts = counts.get_or_insert_with(|| KmerCounts::new(kmer.len().clamp(1, MAX_EXPORT_K)));
+    if kmer.len() != counts.k {
+        return Err(invalid_data(format!(
+            "line {}: expected a {}-mer, found length {}",
+            line_no,
+            counts.k,
+            kmer.len()
+        )));
+    }
+    let value = kmer
+        .bytes()
+        .try_fold(0u64, |acc, c| dna_to_value(c).map(|code| (acc << 2) | code))
+        .ok_or_else(|| invalid_data(format!("line {}: invalid k-mer {:?}", line_no, kmer)))?;
+    let count = count
+        .parse::<u64>()
+        .map_err(|e| invalid_data(format!("line {}: invalid count: {}", line_no, e)))?;
+    counts.add(value, count);
+    Ok(())
+}
+
+/// Loads a Jellyfish dump, either the default FASTA-like layout
+/// (`>count` then the k-mer) or column output from `jellyfish dump -c`.
+/// K-mers are canonicalized on load, so dumps of non-canonical databases are
+/// folded onto their canonical form.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::read_jellyfish_dump;
+///
+/// let fasta_style = b">3\nACG\n>1\nAAC\n";
+/// let counts = read_jellyfish_dump(&fasta_style[..]).unwrap();
+/// assert_eq!(counts.get_str(b"CGT"), 3);
+///
+/// let column_style = b"ACG 3\nAAC 1\n";
+/// assert_eq!(read_jellyfish_dump(&column_style[..]).unwrap(), counts);
+/// ```
+pub fn read_jellyfish_dump<R: Read>(input: R) -> Result<KmerCounts> {
+    let mut counts = None;
+    let mut pending_count: Option<String> = None;
+    for (i, line) in BufReader::new(input).lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(count) = line.strip_prefix('>') {
+            pending_count = Some(count.to_string());
+        } else if let Some(count) = pending_count.take() {
+            add_dump_record(&mut counts, line, &count, i + 1)?;
+        } else {
+            let mut fields = line.split_whitespace();
+            match (fields.next(), fields.next()) {
+                (Some(kmer), Some(count)) => add_dump_record(&mut counts, kmer, count, i + 1)?,
+                _ => return Err(invalid_data(format!("line {}: missing count", i + 1))),
+            }
+        }
+    }
+    Ok(counts.unwrap_or_default())
+}
+
+/// Loads a KMC text dump as produced by `kmc_dump` or `kmc_tools transform
+/// ... dump` (`kmer<TAB>count` per line). KMC's binary `.kmc_pre`/`.kmc_suf`
+/// databases must be dumped to text first.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::read_kmc_dump;
+///
+/// let dump = b"AAC\t1\nACG\t3\n";
+/// let counts = read_kmc_dump(&dump[..]).unwrap();
+/// assert_eq!(counts.k, 3);
+/// assert_eq!(counts.get_str(b"ACG"), 3);
+/// ```
+pub fn read_kmc_dump<R: Read>(input: R) -> Result<KmerCounts> {
+    let mut counts = None;
+    for (i, line) in BufReader::new(input).lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        match (fields.next(), fields.next()) {
+            (Some(kmer), Some(count)) => add_dump_record(&mut counts, kmer, count, i + 1)?,
+            _ => return Err(invalid_data(format!("line {}: missing count", i + 1))),
+        }
+    }
+    Ok(counts.unwrap_or_default())
+}
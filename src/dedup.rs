@@ -0,0 +1,127 @@
+use crate::feat::Meros;
+use crate::filter::Filter;
+use crate::mmscanner::scan_sequence;
+use crate::seq::Base;
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// What to do with a read after duplicate detection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Keep,
+    Drop,
+}
+
+/// Identity of a read (pair) for duplicate detection: the length of each
+/// mate and its first and last few minimizers.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DuplicateKey {
+    pub lengths: Vec<usize>,
+    pub minimizers: Vec<u64>,
+}
+
+#[derive(Debug, Default)]
+struct SeenPool {
+    keys: HashSet<DuplicateKey>,
+    order: VecDeque<DuplicateKey>,
+}
+
+/// Flags PCR duplicates: a read is dropped when a read with the same
+/// [`DuplicateKey`] is among the last `pool_size` distinct reads kept.
+///
+/// It implements [`Filter`], so it plugs into `read_parallel_filtered` as a
+/// dedup stage. Under parallel processing which copy of a duplicate survives
+/// depends on scheduling.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{Base, Decision, Deduplicator, Meros, OptionPair, SeqFormat, SeqHeader};
+///
+/// let record = |id: &str, seq: &[u8]| {
+///     let header = SeqHeader::new(id, SeqFormat::Fastq);
+///     Base::new(header, OptionPair::Single(seq.to_vec()))
+/// };
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+/// let dedup = Deduplicator::new(meros, 3, 1000);
+///
+/// assert_eq!(dedup.decide(&record("r1", b"ATCGATCGATCGTTAGGCAGGT")), Decision::Keep);
+/// assert_eq!(dedup.decide(&record("r2", b"TTAGGCAATCGATCGATCGAAC")), Decision::Keep);
+/// assert_eq!(dedup.decide(&record("r3", b"ATCGATCGATCGTTAGGCAGGT")), Decision::Drop);
+/// assert_eq!(dedup.duplicates(), 1);
+/// ```
+#[derive(Debug)]
+pub struct Deduplicator {
+    meros: Meros,
+    n_minimizers: usize,
+    pool_size: usize,
+    pool: Mutex<SeenPool>,
+    duplicates: AtomicUsize,
+}
+
+impl Deduplicator {
+    /// Compares reads on their `n_minimizers` first and last minimizers,
+    /// remembering up to `pool_size` reads.
+    pub fn new(meros: Meros, n_minimizers: usize, pool_size: usize) -> Self {
+        assert!(pool_size > 0, "pool_size must be positive");
+        Self {
+            meros,
+            n_minimizers,
+            pool_size,
+            pool: Mutex::new(SeenPool::default()),
+            duplicates: AtomicUsize::new(0),
+        }
+    }
+
+    /// Computes the duplicate key of a record.
+    pub fn key(&self, seq: &Base<Vec<u8>>) -> DuplicateKey {
+        let mut record = scan_sequence(seq, &self.meros);
+        let mut key = DuplicateKey {
+            lengths: Vec::with_capacity(2),
+            minimizers: Vec::new(),
+        };
+        for m_iter in record.body.iter_mut() {
+            key.lengths.push(m_iter.seq_size());
+            let hashes: Vec<u64> = m_iter.by_ref().map(|(_, hash)| hash).collect();
+            let n = self.n_minimizers;
+            if hashes.len() <= 2 * n {
+                key.minimizers.extend(&hashes);
+            } else {
+                key.minimizers.extend(&hashes[..n]);
+                key.minimizers.extend(&hashes[hashes.len() - n..]);
+            }
+        }
+        key
+    }
+
+    /// Decides whether a record is kept, remembering it if so.
+    pub fn decide(&self, seq: &Base<Vec<u8>>) -> Decision {
+        let key = self.key(seq);
+        let mut pool = self.pool.lock().unwrap();
+        if pool.keys.contains(&key) {
+            self.duplicates.fetch_add(1, Ordering::Relaxed);
+            return Decision::Drop;
+        }
+        // 滑动窗口: 超出容量时淘汰最早的 key
+        if pool.order.len() == self.pool_size {
+            if let Some(oldest) = pool.order.pop_front() {
+                pool.keys.remove(&oldest);
+            }
+        }
+        pool.keys.insert(key.clone());
+        pool.order.push_back(key);
+        Decision::Keep
+    }
+
+    /// Number of reads dropped so far.
+    pub fn duplicates(&self) -> usize {
+        self.duplicates.load(Ordering::Relaxed)
+    }
+}
+
+impl Filter for Deduplicator {
+    fn accept(&self, seq: &Base<Vec<u8>>) -> bool {
+        self.decide(seq) == Decision::Keep
+    }
+}
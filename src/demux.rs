@@ -0,0 +1,253 @@
+use crate::seq::{Base, ReadTags};
+use crate::trim::Transform;
+use crate::utils::OptionPair;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Where barcodes and UMIs are read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagSource {
+    /// Illumina headers: the barcode is the last field of the comment
+    /// (`1:N:0:ACGTACGT+TTGCAAGT`) and the UMI the eighth colon-separated
+    /// field of the read name (`M0:1:FC:1:1101:1000:2000:UMI`). Needs the
+    /// reader to keep descriptions for the barcode.
+    IlluminaHeader,
+    /// Fixed positions at the start of the first mate.
+    SequencePrefix {
+        barcode: Option<Range<usize>>,
+        umi: Option<Range<usize>>,
+    },
+}
+
+/// Extracts barcodes and UMIs into `SeqHeader::tags`, optionally
+/// hard-clipping in-read tags from the sequence (and qualities) so they are
+/// not scanned. Use it as a `Transform` ahead of scanning.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{Base, OptionPair, SeqFormat, SeqHeader, TagExtractor, Transform};
+///
+/// let header = SeqHeader {
+///     reads_index: 1,
+///     desc: Some("1:N:0:ACGTACGT".to_string()),
+///     ..SeqHeader::new("M0:1:FC:1:1101:1000:2000:GATTACA", SeqFormat::Fastq)
+/// };
+/// let mut read = Base::new(header.clone(), OptionPair::Single(b"TTTTCCGGACGTACGT".to_vec()));
+/// TagExtractor::illumina_header().apply(&mut read);
+/// let tags = read.header.tags.unwrap();
+/// assert_eq!(tags.barcode.as_deref(), Some("ACGTACGT"));
+/// assert_eq!(tags.umi.as_deref(), Some("GATTACA"));
+///
+/// // 4 bp barcode then 4 bp UMI at the start of R1, clipped before scanning
+/// let mut read = Base::new(header, OptionPair::Single(b"TTTTCCGGACGTACGT".to_vec()));
+/// TagExtractor::sequence_prefix(Some(0..4), Some(4..8)).with_clip(true).apply(&mut read);
+/// let tags = read.header.tags.as_ref().unwrap();
+/// assert_eq!(tags.barcode.as_deref(), Some("TTTT"));
+/// assert_eq!(tags.umi.as_deref(), Some("CCGG"));
+/// assert_eq!(read.body.single().unwrap(), b"ACGTACGT");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagExtractor {
+    pub source: TagSource,
+    pub clip: bool,
+}
+
+impl TagExtractor {
+    pub fn illumina_header() -> Self {
+        Self {
+            source: TagSource::IlluminaHeader,
+            clip: false,
+        }
+    }
+
+    /// Reads the barcode and UMI from fixed ranges of the first mate.
+    pub fn sequence_prefix(barcode: Option<Range<usize>>, umi: Option<Range<usize>>) -> Self {
+        Self {
+            source: TagSource::SequencePrefix { barcode, umi },
+            clip: false,
+        }
+    }
+
+    /// Removes in-read tags, and everything before them, from the first
+    /// mate. Has no effect on header tags.
+    pub fn with_clip(mut self, clip: bool) -> Self {
+        self.clip = clip;
+        self
+    }
+
+    /// Returns the tags of a record without modifying it.
+    pub fn extract(&self, seq: &Base<Vec<u8>>) -> ReadTags {
+        match &self.source {
+            TagSource::IlluminaHeader => ReadTags {
+                barcode: seq
+                    .header
+                    .desc
+                    .as_deref()
+                    .and_then(|desc| desc.split_whitespace().next())
+                    .and_then(|comment| comment.rsplit(':').next())
+                    .filter(|barcode| !barcode.is_empty())
+                    .map(str::to_string),
+                umi: seq
+                    .header
+                    .id
+                    .split(':')
+                    .nth(7)
+                    .filter(|umi| !umi.is_empty())
+                    .map(str::to_string),
+            },
+            TagSource::SequencePrefix { barcode, umi } => {
+                let first = seq.body.first();
+                let slice = |range: &Option<Range<usize>>| {
+                    range
+                        .as_ref()
+                        .and_then(|r| first.get(r.clone()))
+                        .map(|tag| String::from_utf8_lossy(tag).into_owned())
+                };
+                ReadTags {
+                    barcode: slice(barcode),
+                    umi: slice(umi),
+                }
+            }
+        }
+    }
+
+    /// Number of leading bases of the first mate taken up by tags.
+    fn clip_len(&self) -> usize {
+        match &self.source {
+            TagSource::SequencePrefix { barcode, umi } if self.clip => barcode
+                .iter()
+                .chain(umi.iter())
+                .map(|r| r.end)
+                .max()
+                .unwrap_or(0),
+            _ => 0,
+        }
+    }
+}
+
+impl Transform for TagExtractor {
+    fn apply(&self, seq: &mut Base<Vec<u8>>) {
+        seq.header.tags = Some(self.extract(seq));
+        let clip = self.clip_len();
+        if clip > 0 {
+            let body = seq
+                .body
+                .iter_mut()
+                .next()
+                .expect("a record has a first mate");
+            body.drain(..clip.min(body.len()));
+            if let Some(qual) = seq.qual.as_mut().map(OptionPair::first_mut) {
+                qual.drain(..clip.min(qual.len()));
+            }
+        }
+    }
+}
+
+/// Known sample barcodes. A read's barcode is assigned to the sample whose
+/// barcode it matches exactly, or within `max_mismatches` (1 by default)
+/// mismatches if exactly one sample is that close.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::BarcodeWhitelist;
+///
+/// let whitelist = BarcodeWhitelist::new(["ACGTACGT", "TTGGCCAA"]);
+/// assert_eq!(whitelist.assign("ACGTACGT"), Some(0));
+/// assert_eq!(whitelist.assign("TTGGCCAT"), Some(1));
+/// assert_eq!(whitelist.assign("TTGGCGAT"), None);
+/// assert_eq!(whitelist.with_max_mismatches(0).assign("TTGGCCAT"), None);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BarcodeWhitelist {
+    barcodes: Vec<Vec<u8>>,
+    exact: HashMap<Vec<u8>, usize>,
+    max_mismatches: usize,
+}
+
+impl BarcodeWhitelist {
+    /// Creates a whitelist; sample `i` is the `i`-th barcode.
+    pub fn new<I, B>(barcodes: I) -> Self
+    where
+        I: IntoIterator<Item = B>,
+        B: AsRef<[u8]>,
+    {
+        let barcodes: Vec<Vec<u8>> = barcodes
+            .into_iter()
+            .map(|b| b.as_ref().to_ascii_uppercase())
+            .collect();
+        let exact = barcodes
+            .iter()
+            .enumerate()
+            .map(|(i, b)| (b.clone(), i))
+            .collect();
+        Self {
+            barcodes,
+            exact,
+            max_mismatches: 1,
+        }
+    }
+
+    pub fn with_max_mismatches(mut self, max_mismatches: usize) -> Self {
+        self.max_mismatches = max_mismatches;
+        self
+    }
+
+    /// Number of samples.
+    pub fn len(&self) -> usize {
+        self.barcodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.barcodes.is_empty()
+    }
+
+    /// Returns the sample index for a barcode, or None if it matches no
+    /// sample or more than one.
+    pub fn assign(&self, barcode: &str) -> Option<usize> {
+        let barcode = barcode.as_bytes().to_ascii_uppercase();
+        if let Some(&sample) = self.exact.get(&barcode) {
+            return Some(sample);
+        }
+        if self.max_mismatches == 0 {
+            return None;
+        }
+        let mut hits = self.barcodes.iter().enumerate().filter(|(_, known)| {
+            known.len() == barcode.len()
+                && known.iter().zip(&barcode).filter(|(a, b)| a != b).count() <= self.max_mismatches
+        });
+        match (hits.next(), hits.next()) {
+            (Some((sample, _)), None) => Some(sample),
+            _ => None,
+        }
+    }
+}
+
+/// Pairs a tag extractor with a barcode whitelist to decide which sample a
+/// record belongs to.
+#[derive(Debug, Clone)]
+pub struct Demultiplexer {
+    pub extractor: TagExtractor,
+    pub whitelist: BarcodeWhitelist,
+}
+
+impl Demultiplexer {
+    pub fn new(extractor: TagExtractor, whitelist: BarcodeWhitelist) -> Self {
+        Self {
+            extractor,
+            whitelist,
+        }
+    }
+
+    /// Extracts the record's tags (clipping them if configured) and returns
+    /// its sample, if any.
+    pub fn assign(&self, seq: &mut Base<Vec<u8>>) -> Option<usize> {
+        self.extractor.apply(seq);
+        seq.header
+            .tags
+            .as_ref()
+            .and_then(|tags| tags.barcode.as_deref())
+            .and_then(|barcode| self.whitelist.assign(barcode))
+    }
+}
@@ -0,0 +1,261 @@
+use crate::seq::Base;
+use crate::trim::Transform;
+use std::collections::VecDeque;
+use std::ops::Range;
+
+/// Default score threshold of `sdust` (and Kraken2's `k2mask`).
+pub const SDUST_THRESHOLD: usize = 20;
+/// Default window length of `sdust`.
+pub const SDUST_WINDOW: usize = 64;
+
+/// 三联体的种类数 (4^3)
+const TRIPLETS: usize = 64;
+
+/// A perfect interval found in the current window.
+#[derive(Debug, Clone, Copy)]
+struct Perfect {
+    start: usize,
+    finish: usize,
+    r: usize,
+    l: usize,
+}
+
+/// Triplet counts of the current window; `cv`, `rv` and `len` describe its
+/// suffix of length `len` whose score stays below the threshold.
+struct DustWindow {
+    triplets: VecDeque<usize>,
+    cw: [usize; TRIPLETS],
+    cv: [usize; TRIPLETS],
+    rw: usize,
+    rv: usize,
+    len: usize,
+}
+
+impl DustWindow {
+    fn new() -> Self {
+        Self {
+            triplets: VecDeque::new(),
+            cw: [0; TRIPLETS],
+            cv: [0; TRIPLETS],
+            rw: 0,
+            rv: 0,
+            len: 0,
+        }
+    }
+
+    fn shift(&mut self, t: usize, threshold: usize, window: usize) {
+        if self.triplets.len() + 3 > window {
+            let s = self.triplets.pop_front().expect("window is not empty");
+            self.cw[s] -= 1;
+            self.rw -= self.cw[s];
+            if self.len > self.triplets.len() {
+                self.len -= 1;
+                self.cv[s] -= 1;
+                self.rv -= self.cv[s];
+            }
+        }
+        self.triplets.push_back(t);
+        self.len += 1;
+        self.rw += self.cw[t];
+        self.cw[t] += 1;
+        self.rv += self.cv[t];
+        self.cv[t] += 1;
+        if self.cv[t] * 10 > threshold * 2 {
+            // 缩短后缀, 直到去掉上一个 t
+            loop {
+                let s = self.triplets[self.triplets.len() - self.len];
+                self.cv[s] -= 1;
+                self.rv -= self.cv[s];
+                self.len -= 1;
+                if s == t {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Adds the perfect intervals ending at the last triplet to `perfect`,
+    /// which is kept sorted by decreasing start.
+    fn find_perfect(&self, perfect: &mut Vec<Perfect>, threshold: usize, start: usize) {
+        let mut c = self.cv;
+        let mut r = self.rv;
+        let (mut max_r, mut max_l) = (0, 0);
+        let size = self.triplets.len();
+        for i in (0..size - self.len).rev() {
+            let t = self.triplets[i];
+            r += c[t];
+            c[t] += 1;
+            let (new_r, new_l) = (r, size - i - 1);
+            if new_r * 10 > threshold * new_l {
+                let mut j = 0;
+                while j < perfect.len() && perfect[j].start >= i + start {
+                    let p = &perfect[j];
+                    if max_r == 0 || p.r * max_l > max_r * p.l {
+                        max_r = p.r;
+                        max_l = p.l;
+                    }
+                    j += 1;
+                }
+                if max_r == 0 || new_r * max_l >= max_r * new_l {
+                    max_r = new_r;
+                    max_l = new_l;
+                    perfect.insert(
+                        j,
+                        Perfect {
+                            start: i + start,
+                            finish: size + 2 + start,
+                            r: new_r,
+                            l: new_l,
+                        },
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Moves the perfect intervals that start before `start` out of the window,
+/// merging the last one into `masked`.
+fn save_masked(masked: &mut Vec<Range<usize>>, perfect: &mut Vec<Perfect>, start: usize) {
+    let Some(p) = perfect.last().copied() else {
+        return;
+    };
+    if p.start >= start {
+        return;
+    }
+    match masked.last_mut() {
+        Some(last) if p.start <= last.end => last.end = last.end.max(p.finish),
+        _ => masked.push(p.start..p.finish),
+    }
+    while perfect.last().is_some_and(|p| p.start < start) {
+        perfect.pop();
+    }
+}
+
+/// Symmetric DUST (SDUST, Morgulis et al. 2006) low-complexity masking,
+/// with the parameters of `dustmasker` and Kraken2's `k2mask`.
+///
+/// Masked bases are replaced with `N`, which resets the minimizer scanner,
+/// so repeats such as microsatellites yield no minimizers. Bases other
+/// than `ACGT` split the sequence into independent pieces.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::Sdust;
+///
+/// let seq = b"GATTCGAGCTTAGCCATGCAGTACTCACACACACACACACACACACACACACAGGTAACTTGCTAACG";
+/// let sdust = Sdust::default();
+/// assert_eq!(sdust.intervals(seq), vec![25..53]);
+///
+/// let mut masked = seq.to_vec();
+/// assert_eq!(sdust.mask(&mut masked), 28);
+/// assert!(masked[25..53].iter().all(|&b| b == b'N'));
+/// assert_eq!(&masked[..25], &seq[..25]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sdust {
+    pub threshold: usize,
+    pub window: usize,
+}
+
+impl Default for Sdust {
+    fn default() -> Self {
+        Self::new(SDUST_THRESHOLD, SDUST_WINDOW)
+    }
+}
+
+impl Sdust {
+    pub fn new(threshold: usize, window: usize) -> Self {
+        Self {
+            threshold,
+            window: window.max(4),
+        }
+    }
+
+    /// Returns the low-complexity intervals of `seq`, sorted and merged.
+    pub fn intervals(&self, seq: &[u8]) -> Vec<Range<usize>> {
+        let mut masked = Vec::new();
+        let mut perfect = Vec::new();
+        let mut window = DustWindow::new();
+        let (mut l, mut t): (usize, usize) = (0, 0);
+
+        for i in 0..=seq.len() {
+            let code = match seq.get(i).map(u8::to_ascii_uppercase) {
+                Some(b'A') => Some(0),
+                Some(b'C') => Some(1),
+                Some(b'G') => Some(2),
+                Some(b'T') => Some(3),
+                _ => None,
+            };
+            match code {
+                Some(code) => {
+                    l += 1;
+                    t = ((t << 2) | code) & (TRIPLETS - 1);
+                    if l >= 3 {
+                        let start = l.saturating_sub(self.window) + (i + 1 - l);
+                        save_masked(&mut masked, &mut perfect, start);
+                        window.shift(t, self.threshold, self.window);
+                        if window.rw * 10 > window.len * self.threshold {
+                            window.find_perfect(&mut perfect, self.threshold, start);
+                        }
+                    }
+                }
+                None => {
+                    // N 或序列末尾: 保存剩余区间, 之后重新开始
+                    let mut start = (l + 1).saturating_sub(self.window) + (i + 1 - l);
+                    while !perfect.is_empty() {
+                        save_masked(&mut masked, &mut perfect, start);
+                        start += 1;
+                    }
+                    window = DustWindow::new();
+                    l = 0;
+                    t = 0;
+                }
+            }
+        }
+        masked
+    }
+
+    /// Replaces the low-complexity bases of `seq` with `N` and returns how
+    /// many were masked.
+    pub fn mask(&self, seq: &mut [u8]) -> usize {
+        self.intervals(seq)
+            .into_iter()
+            .map(|range| {
+                seq[range.clone()].fill(b'N');
+                range.len()
+            })
+            .sum()
+    }
+}
+
+/// Masks every mate of `seq` with `sdust` and returns the number of bases
+/// masked.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{sdust_mask, FastaReader, Reader, Sdust};
+///
+/// # fn main() -> Result<(), seqkmer::SeqKmerError> {
+/// let input = b">r1\nACGATCGGCTAATTTTTTTTTTTTTTTTTTTTTTTTTTTGCATGCAAGT\n".as_slice();
+/// let mut reader = FastaReader::new(input, 0);
+/// let mut seq = reader.next()?.unwrap().remove(0);
+/// assert!(sdust_mask(&mut seq, &Sdust::default()) > 0);
+/// assert!(seq.body.single().unwrap().contains(&b'N'));
+/// # Ok(())
+/// # }
+/// ```
+pub fn sdust_mask(seq: &mut Base<Vec<u8>>, sdust: &Sdust) -> usize {
+    seq.body
+        .apply_mut(|s| sdust.mask(s))
+        .reduce(0, |acc, &n| acc + n)
+}
+
+/// As a pre-scan stage, e.g. in `read_parallel_transform`.
+impl Transform for Sdust {
+    fn apply(&self, seq: &mut Base<Vec<u8>>) {
+        sdust_mask(seq, self);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::reader::FormatMismatch;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Errors returned by the readers, format detection and the parallel
+/// drivers.
+///
+/// It converts to and from `io::Error`, so it can be propagated with `?` in
+/// functions returning `io::Result`, and a `SeqKmerError` that went through
+/// an `io::Error` comes back out as the same variant.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{FastqReader, OptionPair, Reader, SeqKmerError};
+///
+/// let input: &[u8] = b"@read1\nACGT\n+\nIII\n";
+/// let mut reader = FastqReader::new(OptionPair::Single(input), 0, 0);
+///
+/// match reader.next() {
+///     Err(SeqKmerError::Parse { line, reason, .. }) => {
+///         assert_eq!(line, Some(4));
+///         assert!(reason.contains("quality"));
+///     }
+///     other => panic!("expected a parse error, got {:?}", other.map(|_| ())),
+/// }
+///
+/// // 缺失的文件仍是 io 错误
+/// let err = FastqReader::from_path(OptionPair::Single("missing.fq"), 0, 0).err().unwrap();
+/// assert!(matches!(err, SeqKmerError::Io(_)));
+/// assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+/// ```
+#[derive(Debug)]
+pub enum SeqKmerError {
+    /// Opening or reading the input failed.
+    Io(io::Error),
+    /// A record is malformed. `line` is 1-based when known.
+    Parse {
+        file: Option<PathBuf>,
+        line: Option<u64>,
+        reason: String,
+    },
+    /// The input files have formats that cannot be read together.
+    FormatMismatch(FormatMismatch),
+    /// The two files of a pair disagree (ids, record counts).
+    PairMismatch(String),
+    /// The compressed stream is corrupt.
+    CompressionError(String),
+    /// A worker or channel of a parallel driver failed.
+    ThreadError(String),
+}
+
+/// Result type of the readers and parallel drivers.
+pub type Result<T> = std::result::Result<T, SeqKmerError>;
+
+impl SeqKmerError {
+    /// A parse error without location; see [`with_file`](Self::with_file).
+    pub fn parse(line: Option<u64>, reason: impl Into<String>) -> Self {
+        SeqKmerError::Parse {
+            file: None,
+            line,
+            reason: reason.into(),
+        }
+    }
+
+    /// Attaches `path` to a parse error that does not name its file yet.
+    pub fn with_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        if let SeqKmerError::Parse {
+            file: file @ None, ..
+        } = &mut self
+        {
+            *file = Some(path.as_ref().to_path_buf());
+        }
+        self
+    }
+
+    /// The `io::ErrorKind` this error converts to.
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            SeqKmerError::Io(e) => e.kind(),
+            SeqKmerError::Parse { .. }
+            | SeqKmerError::PairMismatch(_)
+            | SeqKmerError::CompressionError(_) => io::ErrorKind::InvalidData,
+            SeqKmerError::FormatMismatch(_) => io::ErrorKind::InvalidInput,
+            SeqKmerError::ThreadError(_) => io::ErrorKind::Other,
+        }
+    }
+}
+
+/// Cloning an `Io` error keeps its kind and message, not its source.
+impl Clone for SeqKmerError {
+    fn clone(&self) -> Self {
+        match self {
+            SeqKmerError::Io(e) => SeqKmerError::Io(io::Error::new(e.kind(), e.to_string())),
+            SeqKmerError::Parse { file, line, reason } => SeqKmerError::Parse {
+                file: file.clone(),
+                line: *line,
+                reason: reason.clone(),
+            },
+            SeqKmerError::FormatMismatch(e) => SeqKmerError::FormatMismatch(e.clone()),
+            SeqKmerError::PairMismatch(msg) => SeqKmerError::PairMismatch(msg.clone()),
+            SeqKmerError::CompressionError(msg) => SeqKmerError::CompressionError(msg.clone()),
+            SeqKmerError::ThreadError(msg) => SeqKmerError::ThreadError(msg.clone()),
+        }
+    }
+}
+
+impl fmt::Display for SeqKmerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SeqKmerError::Io(e) => write!(f, "{}", e),
+            SeqKmerError::Parse { file, line, reason } => {
+                write!(f, "parse error")?;
+                if let Some(file) = file {
+                    write!(f, " in {}", file.display())?;
+                }
+                if let Some(line) = line {
+                    write!(f, " at line {}", line)?;
+                }
+                write!(f, ": {}", reason)
+            }
+            SeqKmerError::FormatMismatch(e) => write!(f, "{}", e),
+            SeqKmerError::PairMismatch(msg) => write!(f, "pair mismatch: {}", msg),
+            SeqKmerError::CompressionError(msg) => write!(f, "compression error: {}", msg),
+            SeqKmerError::ThreadError(msg) => write!(f, "thread error: {}", msg),
+        }
+    }
+}
+
+impl Error for SeqKmerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SeqKmerError::Io(e) => Some(e),
+            SeqKmerError::FormatMismatch(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SeqKmerError {
+    /// Unwraps a `SeqKmerError` (or [`FormatMismatch`]) carried inside the
+    /// `io::Error`, e.g. raised below a `Read` implementation.
+    fn from(e: io::Error) -> Self {
+        let carried = e
+            .get_ref()
+            .is_some_and(|inner| inner.is::<SeqKmerError>() || inner.is::<FormatMismatch>());
+        if !carried {
+            return SeqKmerError::Io(e);
+        }
+        let inner = e.into_inner().expect("checked above");
+        match inner.downcast::<SeqKmerError>() {
+            Ok(err) => *err,
+            Err(inner) => match inner.downcast::<FormatMismatch>() {
+                Ok(err) => SeqKmerError::FormatMismatch(*err),
+                Err(_) => unreachable!("checked above"),
+            },
+        }
+    }
+}
+
+impl From<SeqKmerError> for io::Error {
+    fn from(e: SeqKmerError) -> Self {
+        match e {
+            SeqKmerError::Io(e) => e,
+            e => io::Error::new(e.kind(), e),
+        }
+    }
+}
+
+impl From<FormatMismatch> for SeqKmerError {
+    fn from(e: FormatMismatch) -> Self {
+        SeqKmerError::FormatMismatch(e)
+    }
+}
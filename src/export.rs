@@ -0,0 +1,228 @@
+use crate::feat::{dna_to_value, Alphabet};
+use crate::reader::Reader;
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, BufReader, Read, Result, Write};
+
+/// Magic bytes opening a binary k-mer set file.
+pub const KMER_SET_MAGIC: &[u8; 4] = b"SKKS";
+/// Largest k a k-mer set can hold, 2 bits per base in a u64.
+pub const MAX_EXPORT_K: usize = 31;
+
+/// On-disk layout of a k-mer set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KmerFormat {
+    /// One k-mer per line, spelled with `ACGT`.
+    Text,
+    /// `KMER_SET_MAGIC`, k as one byte, the count as a little-endian u64,
+    /// then each k-mer packed 2 bits per base as a little-endian u64.
+    Binary,
+}
+
+/// Calls `f` with every canonical k-mer of a sequence, skipping line breaks
+/// and restarting after bases outside `ACGT`.
+pub(crate) fn for_each_canonical_kmer<F: FnMut(u64)>(bytes: &[u8], k: usize, mut f: F) {
+    let mask = (1u64 << (2 * k)) - 1;
+    let mut value = 0u64;
+    let mut len = 0;
+    for &c in bytes {
+        if c == b'\n' || c == b'\r' {
+            continue;
+        }
+        match dna_to_value(c) {
+            Some(code) => {
+                value = ((value << 2) | code) & mask;
+                len += 1;
+                if len >= k {
+                    f(Alphabet::Dna.canonical(value, k));
+                }
+            }
+            None => len = 0,
+        }
+    }
+}
+
+/// Collects the distinct canonical k-mers of every sequence a reader yields.
+/// Bases outside `ACGT` break the k-mer, as in the minimizer scanner.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{collect_kmers, FastaReader};
+/// use std::path::Path;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut reader = FastaReader::from_path(Path::new("tests/data/test.fasta"), 0)?;
+/// let kmers = collect_kmers(&mut reader, 4)?;
+/// // ATCG is its own reverse complement
+/// assert!(kmers.contains(&0b00110110));
+/// # Ok(())
+/// # }
+/// ```
+pub fn collect_kmers<R: Reader>(reader: &mut R, k: usize) -> Result<BTreeSet<u64>> {
+    assert!(k > 0 && k <= MAX_EXPORT_K, "k must be in 1..=31");
+    let mut kmers = BTreeSet::new();
+    while let Some(seqs) = reader.next()? {
+        for seq in seqs {
+            seq.body.apply(|bytes| {
+                for_each_canonical_kmer(bytes, k, |kmer| {
+                    kmers.insert(kmer);
+                })
+            });
+        }
+    }
+    Ok(kmers)
+}
+
+/// Spells a packed k-mer with `ACGT`.
+pub(crate) fn decode_kmer(kmer: u64, k: usize) -> Vec<u8> {
+    (0..k)
+        .rev()
+        .map(|i| b"ACGT"[((kmer >> (2 * i)) & 0x3) as usize])
+        .collect()
+}
+
+/// Writes a sorted k-mer set in the given format.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{write_kmer_set, KmerFormat};
+/// use std::collections::BTreeSet;
+///
+/// let kmers: BTreeSet<u64> = [0b0001, 0b1010].into_iter().collect();
+/// let mut out = Vec::new();
+/// write_kmer_set(&kmers, 2, KmerFormat::Text, &mut out).unwrap();
+/// assert_eq!(out, b"AC\nGG\n");
+/// ```
+pub fn write_kmer_set<W: Write>(
+    kmers: &BTreeSet<u64>,
+    k: usize,
+    format: KmerFormat,
+    mut out: W,
+) -> Result<()> {
+    assert!(k > 0 && k <= MAX_EXPORT_K, "k must be in 1..=31");
+    match format {
+        KmerFormat::Text => {
+            for &kmer in kmers {
+                out.write_all(&decode_kmer(kmer, k))?;
+                out.write_all(b"\n")?;
+            }
+        }
+        KmerFormat::Binary => {
+            out.write_all(KMER_SET_MAGIC)?;
+            out.write_all(&[k as u8])?;
+            out.write_all(&(kmers.len() as u64).to_le_bytes())?;
+            for &kmer in kmers {
+                out.write_all(&kmer.to_le_bytes())?;
+            }
+        }
+    }
+    out.flush()
+}
+
+/// Writes the distinct canonical k-mer set of a reader, sorted, and returns
+/// the number of k-mers written.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{kmer_set, read_kmer_set, FastaReader, KmerFormat};
+/// use std::path::Path;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut reader = FastaReader::from_path(Path::new("tests/data/test.fasta"), 0)?;
+/// let mut out = Vec::new();
+/// let n = kmer_set(&mut reader, 5, KmerFormat::Binary, &mut out)?;
+///
+/// let (k, kmers) = read_kmer_set(out.as_slice(), KmerFormat::Binary)?;
+/// assert_eq!((k, kmers.len()), (5, n));
+/// # Ok(())
+/// # }
+/// ```
+pub fn kmer_set<R: Reader, W: Write>(
+    reader: &mut R,
+    k: usize,
+    format: KmerFormat,
+    out: W,
+) -> Result<usize> {
+    let kmers = collect_kmers(reader, k)?;
+    write_kmer_set(&kmers, k, format, out)?;
+    Ok(kmers.len())
+}
+
+pub(crate) fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Reads a k-mer set written by [`kmer_set`] or [`write_kmer_set`],
+/// returning k and the k-mers in file order. An empty text file yields k = 0.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{read_kmer_set, KmerFormat};
+///
+/// let (k, kmers) = read_kmer_set(&b"AC\nGG\n"[..], KmerFormat::Text).unwrap();
+/// assert_eq!(k, 2);
+/// assert_eq!(kmers, vec![0b0001, 0b1010]);
+/// ```
+pub fn read_kmer_set<R: Read>(input: R, format: KmerFormat) -> Result<(usize, Vec<u64>)> {
+    let mut input = BufReader::new(input);
+    match format {
+        KmerFormat::Text => {
+            let mut k = 0;
+            let mut kmers = Vec::new();
+            for (line_no, line) in input.lines().enumerate() {
+                let line = line?;
+                let line = line.trim_end();
+                if line.is_empty() {
+                    continue;
+                }
+                if k == 0 {
+                    k = line.len();
+                    if k > MAX_EXPORT_K {
+                        return Err(invalid_data(format!("k-mer longer than {}", MAX_EXPORT_K)));
+                    }
+                } else if line.len() != k {
+                    return Err(invalid_data(format!(
+                        "line {}: expected a {}-mer, found length {}",
+                        line_no + 1,
+                        k,
+                        line.len()
+                    )));
+                }
+                let mut value = 0u64;
+                for c in line.bytes() {
+                    let code = dna_to_value(c).ok_or_else(|| {
+                        invalid_data(format!(
+                            "line {}: invalid base {:?}",
+                            line_no + 1,
+                            c as char
+                        ))
+                    })?;
+                    value = (value << 2) | code;
+                }
+                kmers.push(value);
+            }
+            Ok((k, kmers))
+        }
+        KmerFormat::Binary => {
+            let mut magic = [0u8; 4];
+            input.read_exact(&mut magic)?;
+            if &magic != KMER_SET_MAGIC {
+                return Err(invalid_data("not a seqkmer k-mer set".to_string()));
+            }
+            let mut k = [0u8; 1];
+            input.read_exact(&mut k)?;
+            let mut word = [0u8; 8];
+            input.read_exact(&mut word)?;
+            let count = u64::from_le_bytes(word) as usize;
+            let mut kmers = Vec::with_capacity(count.min(1 << 20));
+            for _ in 0..count {
+                input.read_exact(&mut word)?;
+                kmers.push(u64::from_le_bytes(word));
+            }
+            Ok((k[0] as usize, kmers))
+        }
+    }
+}
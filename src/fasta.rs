@@ -1,8 +1,15 @@
-use crate::reader::{dyn_reader, trim_end, Reader, BUFSIZE};
-use crate::seq::{Base, SeqFormat, SeqHeader};
+use crate::error::{Result, SeqKmerError};
+use crate::progress::{Progress, ProgressEvent};
+use crate::reader::{
+    dyn_reader, fasta_files_in, glob_paths, header_desc, open_byte_range, trim_end, trim_pair_info,
+    Reader, Recycled, BATCH_BYTES, BUFSIZE,
+};
+use crate::seq::{Base, IdPolicy, SeqChunk, SeqFormat, SeqHeader, SoftMask};
 use crate::utils::OptionPair;
-use std::io::{BufRead, BufReader, Read, Result};
-use std::path::Path;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 const SEQ_LIMIT: u64 = u64::pow(2, 32);
 
@@ -39,6 +46,64 @@ where
 
     // 批量读取
     batch_size: usize,
+    batch_bytes: usize,
+
+    // 长序列分块: (分块长度, 重叠长度)
+    chunking: Option<(usize, usize)>,
+    pending: VecDeque<(usize, Base<Vec<u8>>)>,
+    keep_desc: bool,
+    soft_mask: SoftMask,
+
+    // 进度上报
+    progress: Option<Arc<dyn Progress>>,
+    bytes_read: u64,
+
+    // 多文件输入: 待读取的文件及打开方式
+    files: VecDeque<PathBuf>,
+    open: Option<fn(&Path) -> Result<R>>,
+}
+
+/// Splits a record into chunks of at most `chunk_size` bases, consecutive
+/// chunks sharing `overlap` bases. Line breaks are removed first so that the
+/// overlap is measured in bases.
+fn split_into_chunks(
+    record: Base<Vec<u8>>,
+    chunk_size: usize,
+    overlap: usize,
+) -> Vec<(usize, Base<Vec<u8>>)> {
+    let bases: Vec<u8> = record
+        .body
+        .single()
+        .map(|seq| {
+            seq.iter()
+                .copied()
+                .filter(|&c| c != b'\n' && c != b'\r')
+                .collect()
+        })
+        .unwrap_or_default();
+    let len = bases.len();
+    if len <= chunk_size {
+        return vec![(len, Base::new(record.header, OptionPair::Single(bases)))];
+    }
+
+    let step = chunk_size - overlap;
+    let count = (len - overlap).div_ceil(step);
+    (0..count)
+        .map(|index| {
+            let start = index * step;
+            let end = (start + chunk_size).min(len);
+            let mut header = record.header.clone();
+            header.chunk = Some(SeqChunk {
+                index,
+                count,
+                offset: start,
+            });
+            (
+                end - start,
+                Base::new(header, OptionPair::Single(bases[start..end].to_vec())),
+            )
+        })
+        .collect()
 }
 
 impl<R> FastaReader<R>
@@ -86,27 +151,175 @@ where
             header: Vec::new(),
             seq: Vec::new(),
             batch_size,
+            batch_bytes: BATCH_BYTES,
+            chunking: None,
+            pending: VecDeque::new(),
+            keep_desc: true,
+            soft_mask: SoftMask::Keep,
+            progress: None,
+            bytes_read: 0,
+            files: VecDeque::new(),
+            open: None,
         }
     }
 
+    /// Splits sequences longer than `chunk_size` bases into chunks that
+    /// overlap by `k_mer - 1` bases, so no k-mer is lost at a chunk boundary
+    /// and the chunks of one long sequence can be scanned by different
+    /// workers. Each chunk carries its position in `SeqHeader::chunk`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{FastaReader, Reader};
+    /// use std::path::Path;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let path = Path::new("tests/data/test.fasta");
+    /// let mut reader = FastaReader::from_path(path, 0)?.with_chunking(10, 5);
+    /// let seqs = reader.next()?.unwrap();
+    ///
+    /// // 20 bases, chunks of 10 overlapping by 4: [0, 10), [6, 16), [12, 20)
+    /// let chunks: Vec<_> = seqs.iter().filter(|s| s.header.id == "seq1").collect();
+    /// assert_eq!(chunks.len(), 3);
+    /// assert_eq!(chunks[1].header.chunk.unwrap().offset, 6);
+    /// assert_eq!(chunks[2].body.single().unwrap().len(), 8);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_chunking(mut self, chunk_size: usize, k_mer: usize) -> Self {
+        let overlap = k_mer.saturating_sub(1);
+        assert!(chunk_size > overlap, "chunk size must exceed k_mer - 1");
+        self.chunking = Some((chunk_size, overlap));
+        self
+    }
+
+    /// Sets the maximum number of records returned per batch.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{FastaReader, Reader};
+    /// use std::path::Path;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let path = Path::new("tests/data/test.fasta");
+    /// let mut reader = FastaReader::from_path(path, 0)?.with_batch_size(2);
+    /// assert_eq!(reader.next()?.unwrap().len(), 2);
+    /// assert_eq!(reader.next()?.unwrap().len(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Sets the sequence byte budget per batch. A batch is closed as soon as
+    /// the accumulated sequence length exceeds it, so a batch always holds at
+    /// least one record.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{FastaReader, Reader};
+    /// use std::path::Path;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let path = Path::new("tests/data/test.fasta");
+    /// let mut reader = FastaReader::from_path(path, 0)?.with_batch_bytes(10);
+    /// assert_eq!(reader.next()?.unwrap().len(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_batch_bytes(mut self, batch_bytes: usize) -> Self {
+        self.batch_bytes = batch_bytes;
+        self
+    }
+
+    /// Keeps the header text after the id in `SeqHeader::desc` (the
+    /// default). Turn it off to save one allocation per record.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{FastaReader, Reader};
+    /// use std::path::Path;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let path = Path::new("tests/data/test.fasta");
+    /// let mut reader = FastaReader::from_path(path, 0)?;
+    /// let seqs = reader.next()?.unwrap();
+    /// assert_eq!(seqs[0].header.desc.as_deref(), Some("Sample sequence 1"));
+    ///
+    /// let mut reader = FastaReader::from_path(path, 0)?.with_descriptions(false);
+    /// assert_eq!(reader.next()?.unwrap()[0].header.desc, None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_descriptions(mut self, keep_desc: bool) -> Self {
+        self.keep_desc = keep_desc;
+        self
+    }
+
+    /// Reports bytes read and records emitted to `progress` after every
+    /// batch.
+    pub fn with_progress(mut self, progress: Arc<dyn Progress>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Sets how lower-case bases are treated (see [`SoftMask`]); with
+    /// chunking, each chunk is counted separately.
+    pub fn with_soft_mask(mut self, soft_mask: SoftMask) -> Self {
+        self.soft_mask = soft_mask;
+        self
+    }
+
     pub fn read_next(&mut self) -> Result<Option<()>> {
         // 读取fastq文件header部分
         self.header.clear();
-        if self.reader.read_until(b'\n', &mut self.header)? == 0 {
+        let header_bytes = self.reader.read_until(b'\n', &mut self.header)?;
+        if header_bytes == 0 {
             return Ok(None);
         }
+        self.bytes_read += header_bytes as u64;
         // 读取fasta文件seq部分
         self.seq.clear();
-        if self.reader.read_until(b'>', &mut self.seq)? == 0 {
+        let seq_bytes = self.reader.read_until(b'>', &mut self.seq)?;
+        if seq_bytes == 0 {
             return Ok(None);
         }
+        self.bytes_read += seq_bytes as u64;
         trim_end(&mut self.seq);
         Ok(Some(()))
     }
 
     pub fn _next(&mut self) -> Result<Option<(usize, Base<Vec<u8>>)>> {
-        if self.read_next()?.is_none() {
-            return Ok(None);
+        self.next_reusing(None)
+    }
+
+    /// Switches to the next pending input file, if any, giving it the next
+    /// `file_index` and restarting `reads_index`.
+    fn open_next_file(&mut self) -> Result<bool> {
+        let (Some(open), Some(path)) = (self.open, self.files.pop_front()) else {
+            return Ok(false);
+        };
+        self.reader = BufReader::with_capacity(self.reader.capacity(), open(&path)?);
+        self.file_index += 1;
+        self.reads_index = 0;
+        Ok(true)
+    }
+
+    /// Like `_next`, copying the sequence into the buffer of `spare`.
+    fn next_reusing(
+        &mut self,
+        spare: Option<Base<Vec<u8>>>,
+    ) -> Result<Option<(usize, Base<Vec<u8>>)>> {
+        while self.read_next()?.is_none() {
+            if !self.open_next_file()? {
+                return Ok(None);
+            }
         }
 
         let seq_len = self.seq.len();
@@ -116,7 +329,7 @@ where
             return Ok(None);
         }
 
-        let seq_id = unsafe {
+        let (seq_id, rest) = unsafe {
             let slice = if self.header.starts_with(b">") {
                 &self.header[1..]
             } else {
@@ -129,21 +342,46 @@ where
                 .unwrap_or(s.len());
 
             // 直接从原始切片创建第一个单词的切片
-            &s[..first_space_index]
+            (&s[..first_space_index], &s[first_space_index..])
         };
         self.reads_index += 1;
 
         let seq_header = SeqHeader {
             file_index: self.file_index,
             reads_index: self.reads_index,
-            format: SeqFormat::Fasta,
-            id: seq_id.to_owned(),
+            desc: header_desc(rest, self.keep_desc),
+            ..SeqHeader::new(seq_id, SeqFormat::Fasta)
         };
         Ok(Some((
             seq_len,
-            Base::new(seq_header, OptionPair::Single(self.seq.to_owned())),
+            Base::new(
+                seq_header,
+                OptionPair::Single(Recycled::new(spare).body(0, &self.seq)),
+            ),
         )))
     }
+
+    /// Returns the next record, or the next chunk of it when chunking is on.
+    fn next_record(
+        &mut self,
+        spare: Option<Base<Vec<u8>>>,
+    ) -> Result<Option<(usize, Base<Vec<u8>>)>> {
+        let mut item = match self.pending.pop_front() {
+            Some(item) => Some(item),
+            None => match (self.next_reusing(spare)?, self.chunking) {
+                (Some((seq_len, seq)), Some((chunk_size, overlap))) if seq_len > chunk_size => {
+                    self.pending
+                        .extend(split_into_chunks(seq, chunk_size, overlap));
+                    self.pending.pop_front()
+                }
+                (item, _) => item,
+            },
+        };
+        if let Some((_, seq)) = item.as_mut() {
+            self.soft_mask.apply(seq);
+        }
+        Ok(item)
+    }
 }
 
 impl FastaReader<Box<dyn Read + Send>> {
@@ -166,19 +404,143 @@ impl FastaReader<Box<dyn Read + Send>> {
         let reader = dyn_reader(path)?;
         Ok(Self::new(reader, file_index))
     }
+
+    /// Reads the records of an uncompressed FASTA file that start in
+    /// `start..end`; see [`open_byte_range`](crate::open_byte_range).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{FastaReader, Reader};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// // 第二条记录从第 45 字节开始
+    /// let mut reader = FastaReader::from_byte_range("tests/data/test.fasta", 1, 46, 0)?;
+    /// let seqs = reader.next()?.unwrap();
+    /// assert_eq!(seqs.len(), 1);
+    /// assert_eq!(seqs[0].header.id, "seq2");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_byte_range<P: AsRef<Path>>(
+        path: P,
+        start: u64,
+        end: u64,
+        file_index: usize,
+    ) -> Result<Self> {
+        let reader = open_byte_range(path, start, end, SeqFormat::Fasta)?;
+        Ok(Self::new(reader, file_index))
+    }
+
+    /// Reads several FASTA files, in order, as one stream. Records of the
+    /// `i`-th file have `file_index` `i` and their own `reads_index` count
+    /// starting at 1. A batch may hold records of more than one file. Files
+    /// are opened as the stream reaches them; fails if `paths` is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{FastaReader, Reader};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let path = "tests/data/test.fasta";
+    /// let mut reader = FastaReader::from_files([path, path])?;
+    /// let seqs = reader.next()?.unwrap();
+    ///
+    /// let index: Vec<_> = seqs.iter().map(|s| (s.header.file_index, s.header.reads_index)).collect();
+    /// assert_eq!(index, vec![(0, 1), (0, 2), (0, 3), (1, 1), (1, 2), (1, 3)]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_files<I, P>(paths: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let mut files: VecDeque<PathBuf> = paths
+            .into_iter()
+            .map(|path| path.as_ref().to_path_buf())
+            .collect();
+        let first = files
+            .pop_front()
+            .ok_or_else(|| no_input("no FASTA files to read".to_string()))?;
+        let mut reader = Self::from_path(first, 0)?;
+        reader.files = files;
+        reader.open = Some(|path| Ok(dyn_reader(path)?));
+        Ok(reader)
+    }
+
+    /// Reads every FASTA file directly inside `dir` as one stream, in name
+    /// order; see [`fasta_files_in`](crate::fasta_files_in) and
+    /// [`FastaReader::from_files`]. Fails if there are none.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{FastaReader, Reader};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut reader = FastaReader::from_dir("tests/data")?;
+    /// assert_eq!(reader.next()?.unwrap().len(), 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let files = fasta_files_in(&dir)?;
+        if files.is_empty() {
+            return Err(no_input(format!(
+                "no FASTA files in {}",
+                dir.as_ref().display()
+            )));
+        }
+        Self::from_files(files)
+    }
+
+    /// Reads the files matching `pattern` as one stream, in name order; see
+    /// [`glob_paths`](crate::glob_paths) and [`FastaReader::from_files`].
+    /// Fails if nothing matches.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{FastaReader, Reader};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut reader = FastaReader::from_glob("tests/data/*.fasta")?;
+    /// assert_eq!(reader.next()?.unwrap().len(), 3);
+    /// assert!(FastaReader::from_glob("tests/data/*.none").is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_glob(pattern: &str) -> Result<Self> {
+        let files = glob_paths(pattern)?;
+        if files.is_empty() {
+            return Err(no_input(format!("no files match {}", pattern)));
+        }
+        Self::from_files(files)
+    }
+}
+
+fn no_input(msg: String) -> SeqKmerError {
+    io::Error::new(io::ErrorKind::InvalidInput, msg).into()
 }
 
 impl<R: Read + Send> Reader for FastaReader<R> {
     fn next(&mut self) -> Result<Option<Vec<Base<Vec<u8>>>>> {
         let mut seqs = Vec::new();
+        Ok(self.next_into(&mut seqs)?.then_some(seqs))
+    }
+
+    fn next_into(&mut self, seqs: &mut Vec<Base<Vec<u8>>>) -> Result<bool> {
+        // 旧记录的缓冲区用于新记录
+        let mut spare = std::mem::take(seqs);
         let mut total_bytes = 0;
-        let max_bytes = 10 * 1024 * 1024;
 
         for _ in 0..self.batch_size {
-            if let Some((seq_len, seq)) = self._next()? {
+            if let Some((seq_len, seq)) = self.next_record(spare.pop())? {
                 seqs.push(seq);
                 total_bytes += seq_len;
-                if total_bytes > max_bytes {
+                if total_bytes > self.batch_bytes {
                     break;
                 }
             } else {
@@ -186,8 +548,142 @@ impl<R: Read + Send> Reader for FastaReader<R> {
             }
         }
 
+        if let Some(progress) = &self.progress {
+            progress.update(ProgressEvent::BytesRead(std::mem::take(
+                &mut self.bytes_read,
+            )));
+            progress.update(ProgressEvent::RecordsEmitted(seqs.len()));
+        }
+        Ok(!seqs.is_empty())
+    }
+
+    fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size.max(1);
+    }
+}
+
+/// Reads paired FASTA files (e.g. already-trimmed mates exported as FASTA)
+/// record by record in lockstep, returning `OptionPair::Pair` records like
+/// a paired [`FastqReader`](crate::FastqReader) without qualities.
+///
+/// Mate ids are compared after removing `/1`, `/2` suffixes, and a
+/// mismatch is resolved by the [`IdPolicy`]. Files with different record
+/// counts are an `InvalidData` error.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{FastaPairReader, IdPolicy, Reader};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let r1: &[u8] = b">read1/1\nACGT\n>read2/1\nGGCC\n";
+/// let r2: &[u8] = b">read1/2\nTTAA\n>read2/2\nCCAA\n";
+/// let mut reader = FastaPairReader::new(r1, r2, 0).with_id_policy(IdPolicy::Error);
+/// let seqs = reader.next()?.unwrap();
+///
+/// assert_eq!(seqs.len(), 2);
+/// assert_eq!(seqs[0].header.id, "read1");
+/// assert_eq!(seqs[1].body.second().unwrap(), b"CCAA");
+/// # Ok(())
+/// # }
+/// ```
+pub struct FastaPairReader<R>
+where
+    R: Read + Send,
+{
+    readers: (FastaReader<R>, FastaReader<R>),
+    file_index: usize,
+    reads_index: usize,
+    batch_size: usize,
+    id_policy: IdPolicy,
+}
+
+impl<R> FastaPairReader<R>
+where
+    R: Read + Send,
+{
+    /// Creates a reader over the R1 and R2 streams.
+    pub fn new(reader1: R, reader2: R, file_index: usize) -> Self {
+        Self {
+            readers: (
+                FastaReader::new(reader1, file_index),
+                FastaReader::new(reader2, file_index),
+            ),
+            file_index,
+            reads_index: 0,
+            batch_size: 30,
+            id_policy: IdPolicy::default(),
+        }
+    }
+
+    /// Sets how differing mate ids are handled.
+    pub fn with_id_policy(mut self, id_policy: IdPolicy) -> Self {
+        self.id_policy = id_policy;
+        self
+    }
+
+    /// Sets the maximum number of pairs returned per batch.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    fn next_pair(&mut self) -> Result<Option<Base<Vec<u8>>>> {
+        let mate1 = self.readers.0._next()?;
+        let mate2 = self.readers.1._next()?;
+        let (mate1, mate2) = match (mate1, mate2) {
+            (Some((_, mate1)), Some((_, mate2))) => (mate1, mate2),
+            (None, None) => return Ok(None),
+            _ => {
+                return Err(SeqKmerError::PairMismatch(
+                    "paired FASTA files have different numbers of records".to_string(),
+                ))
+            }
+        };
+
+        let id1 = trim_pair_info(&mate1.header.id);
+        let id2 = trim_pair_info(&mate2.header.id);
+        self.reads_index += 1;
+        let header = SeqHeader {
+            id: self.id_policy.resolve(&id1, &id2)?,
+            file_index: self.file_index,
+            reads_index: self.reads_index,
+            ..mate1.header
+        };
+        let body = match (mate1.body, mate2.body) {
+            (OptionPair::Single(seq1), OptionPair::Single(seq2)) => OptionPair::Pair(seq1, seq2),
+            _ => unreachable!("FastaReader returns single records"),
+        };
+        Ok(Some(Base::new(header, body)))
+    }
+}
+
+impl FastaPairReader<Box<dyn Read + Send>> {
+    /// Creates a new FastaPairReader from the R1 and R2 file paths.
+    pub fn from_paths<P: AsRef<Path>>(path1: P, path2: P, file_index: usize) -> Result<Self> {
+        Ok(Self::new(
+            dyn_reader(path1)?,
+            dyn_reader(path2)?,
+            file_index,
+        ))
+    }
+}
+
+impl<R: Read + Send> Reader for FastaPairReader<R> {
+    fn next(&mut self) -> Result<Option<Vec<Base<Vec<u8>>>>> {
+        let mut seqs = Vec::new();
+        while seqs.len() < self.batch_size {
+            match self.next_pair()? {
+                Some(seq) => seqs.push(seq),
+                None => break,
+            }
+        }
         Ok(if seqs.is_empty() { None } else { Some(seqs) })
     }
+
+    fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size.max(1);
+    }
 }
 
 /// BufferFastaReader for reading FASTA format files with buffering.
@@ -224,6 +720,7 @@ where
 
     // 批量读取
     batch_size: usize,
+    keep_desc: bool,
 }
 
 impl<R> BufferFastaReader<R>
@@ -272,21 +769,27 @@ where
             header: Vec::new(),
             seq: Vec::new(),
             batch_size,
+            keep_desc: true,
         }
     }
 
+    /// Keeps the header text after the id in `SeqHeader::desc` (the
+    /// default).
+    pub fn with_descriptions(mut self, keep_desc: bool) -> Self {
+        self.keep_desc = keep_desc;
+        self
+    }
+
     pub fn read_next(&mut self) -> Result<Option<()>> {
         // 读取fastq文件header部分
-        if self.header.is_empty() {
-            if self.reader.read_until(b'\n', &mut self.header)? == 0 {
-                return Ok(None);
-            }
+        if self.header.is_empty() && self.reader.read_until(b'\n', &mut self.header)? == 0 {
+            return Ok(None);
         }
 
         if self.reader.read_until(b'\n', &mut self.seq)? == 0 {
             return Ok(None);
         }
-        if self.seq.starts_with(&[b'>']) {
+        if self.seq.starts_with(b">") {
             self.header = self.seq.clone();
             self.seq.clear();
             if self.reader.read_until(b'\n', &mut self.seq)? == 0 {
@@ -313,7 +816,7 @@ where
             return Ok(None);
         }
 
-        let seq_id = unsafe {
+        let (seq_id, rest) = unsafe {
             let slice = if self.header.starts_with(b">") {
                 &self.header[1..]
             } else {
@@ -331,15 +834,15 @@ where
             //     .unwrap_or(s.len());
 
             // 直接从原始切片创建第一个单词的切片
-            &s[..first_space_index]
+            (&s[..first_space_index], &s[first_space_index..])
         };
         self.reads_index += 1;
 
         let seq_header = SeqHeader {
             file_index: self.file_index,
             reads_index: self.reads_index,
-            format: SeqFormat::Fasta,
-            id: seq_id.to_owned(),
+            desc: header_desc(rest, self.keep_desc),
+            ..SeqHeader::new(seq_id, SeqFormat::Fasta)
         };
         Ok(Some(Base::new(
             seq_header,
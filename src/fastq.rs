@@ -1,24 +1,148 @@
-use crate::reader::{dyn_reader, trim_end, trim_pair_info, Reader, BUFSIZE};
-use crate::seq::{Base, SeqFormat, SeqHeader};
+use crate::error::{Result, SeqKmerError};
+use crate::mmscanner::Mate;
+use crate::progress::{Progress, ProgressEvent};
+use crate::reader::{
+    dyn_reader, open_byte_range, strip_pair_suffix, trim_end, PairSuffixes, Reader, Recycled,
+    BATCH_BYTES, BUFSIZE,
+};
+use crate::seq::{
+    Base, IdPolicy, OrphanPolicy, PhredEncoding, QualityPolicy, SeqFormat, SeqHeader, SoftMask,
+};
 use crate::utils::OptionPair;
-use std::io::{BufRead, BufReader, Read, Result};
-use std::path::Path;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{BufRead, BufReader, Read};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 struct QReader<R: Read + Send> {
     reader: BufReader<R>,
-    quality_score: i32,
+    quality_policy: QualityPolicy,
 
     header: Vec<u8>,
     seq: Vec<u8>,
     plus: Vec<u8>,
     quals: Vec<u8>,
+    /// 已读取的字节数 (绝对偏移)
+    bytes_read: u64,
+    /// 已上报给进度的字节数
+    reported: u64,
+    /// 已读取的行数, 用于错误定位
+    line: u64,
+    path: Option<PathBuf>,
+
+    // 宽松模式: 解析错误后跳到下一个 '@' 行
+    lenient: bool,
+    /// header 已由重新同步读入
+    pending_header: bool,
+    /// 当前记录的起始、结束偏移和最后一行
+    record_start: u64,
+    record_end: u64,
+    record_line: u64,
+    warnings: Vec<ParseWarning>,
+
+    // 质量值编码; `phred_sample` 条记录读入 `sampled` 后再判断
+    phred: PhredEncoding,
+    phred_sample: usize,
+    sampled: VecDeque<Sampled>,
+    /// 采样结束后、最后一条缓存记录之后的警告
+    deferred: Vec<ParseWarning>,
+}
+
+/// 编码检测期间缓存的记录
+struct Sampled {
+    header: Vec<u8>,
+    seq: Vec<u8>,
+    quals: Vec<u8>,
+    start: u64,
+    end: u64,
+    line: u64,
+    /// 在该记录之前跳过的记录
+    warnings: Vec<ParseWarning>,
+}
+
+/// A reasonable sample size for [`FastqReader::with_phred_sample`].
+pub const PHRED_SAMPLE: usize = 1000;
+
+/// A corrupt record skipped by a lenient [`FastqReader`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseWarning {
+    /// 0 for single-end input or R1, 1 for R2.
+    pub mate: usize,
+    /// 1-based line where the parse error was found.
+    pub line: u64,
+    /// Bytes of the file that were skipped, from the start of the corrupt
+    /// record to the next header line (or the end of the file).
+    pub bytes: Range<u64>,
+    pub reason: String,
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "skipped bytes {}..{} of mate {} (line {}): {}",
+            self.bytes.start,
+            self.bytes.end,
+            self.mate + 1,
+            self.line,
+            self.reason
+        )
+    }
+}
+
+/// Returns the index of the first `\n` in `haystack`, testing sixteen
+/// bytes at a time.
+#[inline]
+fn find_newline(haystack: &[u8]) -> Option<usize> {
+    const LO: u64 = u64::from_ne_bytes([0x01; 8]);
+    const HI: u64 = u64::from_ne_bytes([0x80; 8]);
+    const NEWLINE: u64 = u64::from_ne_bytes([b'\n'; 8]);
+    // 最低的置位字节即第一个换行符 (更高位可能有借位造成的误报)
+    let newlines = |word: &[u8]| {
+        let x = u64::from_le_bytes(word.try_into().unwrap()) ^ NEWLINE;
+        x.wrapping_sub(LO) & !x & HI
+    };
+
+    let mut chunks = haystack.chunks_exact(16);
+    let mut offset = 0;
+    for chunk in &mut chunks {
+        let (lo, hi) = (newlines(&chunk[..8]), newlines(&chunk[8..]));
+        if lo | hi != 0 {
+            let found = if lo != 0 {
+                lo.trailing_zeros()
+            } else {
+                64 + hi.trailing_zeros()
+            };
+            return Some(offset + found as usize / 8);
+        }
+        offset += 16;
+    }
+    chunks
+        .remainder()
+        .iter()
+        .position(|&b| b == b'\n')
+        .map(|i| offset + i)
+}
+
+/// Reads one line, adding its length to `bytes_read`.
+#[inline]
+fn read_counted<R: BufRead>(
+    reader: &mut R,
+    buf: &mut Vec<u8>,
+    bytes_read: &mut u64,
+) -> Result<usize> {
+    let n = reader.read_until(b'\n', buf)?;
+    *bytes_read += n as u64;
+    Ok(n)
 }
 
 impl<R> QReader<R>
 where
     R: Read + Send,
 {
-    pub fn with_capacity(reader: R, capacity: usize, quality_score: i32) -> Self {
+    pub fn with_capacity(reader: R, capacity: usize, quality_policy: QualityPolicy) -> Self {
         assert!(capacity >= 3);
         Self {
             reader: BufReader::with_capacity(capacity, reader),
@@ -26,44 +150,255 @@ where
             seq: Vec::new(),
             plus: Vec::new(),
             quals: Vec::new(),
-            quality_score,
+            quality_policy,
+            bytes_read: 0,
+            reported: 0,
+            line: 0,
+            path: None,
+            lenient: false,
+            pending_header: false,
+            record_start: 0,
+            record_end: 0,
+            record_line: 0,
+            warnings: Vec::new(),
+            phred: PhredEncoding::Phred33,
+            phred_sample: 0,
+            sampled: VecDeque::new(),
+            deferred: Vec::new(),
+        }
+    }
+
+    /// A parse error at `line` of this stream.
+    fn parse_error(&self, line: u64, reason: String) -> SeqKmerError {
+        let err = SeqKmerError::parse(Some(line), reason);
+        match &self.path {
+            Some(path) => err.with_file(path),
+            None => err,
+        }
+    }
+
+    /// Counts a record line just read; end of input inside a record means
+    /// it was truncated.
+    fn record_line(&mut self, n: usize, part: &str) -> Result<()> {
+        self.line += 1;
+        if n == 0 {
+            return Err(self.parse_error(
+                self.line,
+                format!("record truncated before its {} line", part),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Reads the next record, with Phred+33 qualities and the quality policy
+    /// applied. With detection on, the first call samples records to detect
+    /// the encoding.
+    pub fn read_next(&mut self, mate: usize) -> Result<Option<()>> {
+        if self.phred_sample > 0 {
+            self.detect_phred(mate)?;
+        }
+        match self.sampled.pop_front() {
+            Some(mut record) => {
+                self.warnings.append(&mut record.warnings);
+                self.header = record.header;
+                self.seq = record.seq;
+                self.quals = record.quals;
+                self.record_start = record.start;
+                self.record_end = record.end;
+                self.record_line = record.line;
+            }
+            None => {
+                self.warnings.append(&mut self.deferred);
+                if self.read_parsed(mate)?.is_none() {
+                    return Ok(None);
+                }
+            }
+        }
+        self.phred.to_phred33(&mut self.quals);
+        self.quality_policy.apply(&mut self.seq, &mut self.quals);
+        Ok(Some(()))
+    }
+
+    /// Buffers up to `phred_sample` records and detects their encoding;
+    /// an ambiguous sample keeps Phred+33.
+    fn detect_phred(&mut self, mate: usize) -> Result<()> {
+        for _ in 0..std::mem::take(&mut self.phred_sample) {
+            if self.read_parsed(mate)?.is_none() {
+                break;
+            }
+            let warnings = std::mem::take(&mut self.warnings);
+            self.sampled.push_back(Sampled {
+                warnings,
+                header: std::mem::take(&mut self.header),
+                seq: std::mem::take(&mut self.seq),
+                quals: std::mem::take(&mut self.quals),
+                start: self.record_start,
+                end: self.record_end,
+                line: self.record_line,
+            });
+        }
+        self.deferred = std::mem::take(&mut self.warnings);
+        if let Some(phred) =
+            PhredEncoding::detect(self.sampled.iter().map(|record| record.quals.as_slice()))
+        {
+            self.phred = phred;
+        }
+        Ok(())
+    }
+
+    /// Parses the next record; in lenient mode a corrupt record is recorded
+    /// in `warnings` and skipped.
+    fn read_parsed(&mut self, mate: usize) -> Result<Option<()>> {
+        loop {
+            match self.read_record() {
+                Err(SeqKmerError::Parse { line, reason, .. }) if self.lenient => {
+                    let start = self.record_start;
+                    let found = self.resync()?;
+                    self.warnings.push(ParseWarning {
+                        mate,
+                        line: line.unwrap_or(self.line),
+                        bytes: start..self.record_start,
+                        reason,
+                    });
+                    if !found {
+                        return Ok(None);
+                    }
+                }
+                result => return result,
+            }
         }
     }
 
-    pub fn read_next(&mut self) -> Result<Option<()>> {
-        // 读取fastq文件header部分
+    /// Skips lines up to the next one starting with '@' and keeps it as the
+    /// header of the next record. Returns false at end of input.
+    fn resync(&mut self) -> Result<bool> {
+        loop {
+            self.record_start = self.bytes_read;
+            self.header.clear();
+            if read_counted(&mut self.reader, &mut self.header, &mut self.bytes_read)? == 0 {
+                return Ok(false);
+            }
+            self.line += 1;
+            if self.header.starts_with(b"@") {
+                self.pending_header = true;
+                return Ok(true);
+            }
+        }
+    }
+
+    /// Parses a record that lies entirely in the read buffer, copying each
+    /// line once. Returns false, consuming nothing, when the buffer does not
+    /// hold a complete well-formed record; the line-by-line path then reads
+    /// it and reports any error.
+    fn read_buffered(&mut self) -> Result<bool> {
+        let buf = self.reader.fill_buf()?;
+        let mut ends = [0usize; 4];
+        let mut from = 0;
+        for end in ends.iter_mut() {
+            match find_newline(&buf[from..]) {
+                Some(i) => {
+                    *end = from + i;
+                    from = *end + 1;
+                }
+                None => return Ok(false),
+            }
+        }
+        let line = |start: usize, end: usize| {
+            let line = &buf[start..end];
+            line.strip_suffix(b"\r").unwrap_or(line)
+        };
+        let header = &buf[..=ends[0]];
+        let seq = line(ends[0] + 1, ends[1]);
+        let plus = line(ends[1] + 1, ends[2]);
+        let quals = line(ends[2] + 1, ends[3]);
+        if header.first() != Some(&b'@')
+            || !plus.starts_with(b"+")
+            || seq.len() != quals.len()
+            || seq.last().is_some_and(|b| matches!(b, b'>' | b'@' | b'\r'))
+            || quals.last() == Some(&b'\r')
+            || std::str::from_utf8(header).is_err()
+        {
+            return Ok(false);
+        }
+
         self.header.clear();
-        if self.reader.read_until(b'\n', &mut self.header)? == 0 {
-            return Ok(None);
+        self.header.extend_from_slice(header);
+        self.seq.clear();
+        self.seq.extend_from_slice(seq);
+        self.quals.clear();
+        self.quals.extend_from_slice(quals);
+
+        self.record_start = self.bytes_read;
+        self.bytes_read += from as u64;
+        self.line += 4;
+        self.record_end = self.bytes_read;
+        self.record_line = self.line;
+        self.reader.consume(from);
+        Ok(true)
+    }
+
+    fn read_record(&mut self) -> Result<Option<()>> {
+        if !self.pending_header && self.read_buffered()? {
+            return Ok(Some(()));
         }
+        // 读取fastq文件header部分, 跳过记录之间的空行
+        if !std::mem::take(&mut self.pending_header) {
+            loop {
+                self.record_start = self.bytes_read;
+                self.header.clear();
+                if read_counted(&mut self.reader, &mut self.header, &mut self.bytes_read)? == 0 {
+                    return Ok(None);
+                }
+                self.line += 1;
+                if !self.header.iter().all(u8::is_ascii_whitespace) {
+                    break;
+                }
+            }
+        }
+        if self.header[0] != b'@' {
+            return Err(self.parse_error(self.line, "header does not start with '@'".to_string()));
+        }
+        if std::str::from_utf8(&self.header).is_err() {
+            return Err(self.parse_error(self.line, "header is not valid UTF-8".to_string()));
+        }
+
         // 读取fastq文件seq部分
         self.seq.clear();
-        if self.reader.read_until(b'\n', &mut self.seq)? == 0 {
-            return Ok(None);
-        }
+        let n = read_counted(&mut self.reader, &mut self.seq, &mut self.bytes_read)?;
+        self.record_line(n, "sequence")?;
         trim_end(&mut self.seq);
 
         // 读取fastq文件+部分
         self.plus.clear();
-        if self.reader.read_until(b'\n', &mut self.plus)? == 0 {
-            return Ok(None);
+        let n = read_counted(&mut self.reader, &mut self.plus, &mut self.bytes_read)?;
+        self.record_line(n, "'+'")?;
+        if !self.plus.starts_with(b"+") {
+            return Err(self.parse_error(
+                self.line,
+                "separator line does not start with '+'".to_string(),
+            ));
         }
 
-        // 读取fastq文件quals部分
+        // 读取fastq文件quals部分; '@' 是合法的质量值, 只去掉换行
         self.quals.clear();
-        if self.reader.read_until(b'\n', &mut self.quals)? == 0 {
-            return Ok(None);
+        let n = read_counted(&mut self.reader, &mut self.quals, &mut self.bytes_read)?;
+        self.record_line(n, "quality")?;
+        while let Some(b'\n' | b'\r') = self.quals.last() {
+            self.quals.pop();
         }
-        trim_end(&mut self.quals);
-
-        if self.quality_score > 0 {
-            for (base, &qscore) in self.seq.iter_mut().zip(self.quals.iter()) {
-                if (qscore as i32 - '!' as i32) < self.quality_score {
-                    *base = b'x';
-                }
-            }
+        if self.quals.len() != self.seq.len() {
+            return Err(self.parse_error(
+                self.line,
+                format!(
+                    "quality length {} differs from sequence length {}",
+                    self.quals.len(),
+                    self.seq.len()
+                ),
+            ));
         }
 
+        self.record_end = self.bytes_read;
+        self.record_line = self.line;
         Ok(Some(()))
     }
 }
@@ -95,8 +430,25 @@ pub struct FastqReader<R: Read + Send> {
     reads_index: usize,
     // 批量读取
     batch_size: usize,
+    batch_bytes: usize,
+    keep_qual: bool,
+
+    // 双端 id 不一致时的处理
+    id_policy: IdPolicy,
+    id_mismatches: usize,
+    validate_pairs: bool,
+    orphan_policy: OrphanPolicy,
+    orphans: usize,
+    pair_suffixes: PairSuffixes,
+    keep_desc: bool,
+    progress: Option<Arc<dyn Progress>>,
+    warnings: Vec<ParseWarning>,
+    soft_mask: SoftMask,
 }
 
+/// 宽松模式下为找回配对最多丢弃的记录数
+const MAX_RESYNC: usize = 1024;
+
 impl<R> FastqReader<R>
 where
     R: Read + Send,
@@ -141,13 +493,14 @@ where
         batch_size: usize,
     ) -> Self {
         assert!(capacity >= 3);
+        let quality_policy = QualityPolicy::from_score(quality_score);
         let inner = match readers {
             OptionPair::Single(reader) => {
-                OptionPair::Single(QReader::with_capacity(reader, capacity, quality_score))
+                OptionPair::Single(QReader::with_capacity(reader, capacity, quality_policy))
             }
             OptionPair::Pair(reader1, reader2) => OptionPair::Pair(
-                QReader::with_capacity(reader1, capacity, quality_score),
-                QReader::with_capacity(reader2, capacity, quality_score),
+                QReader::with_capacity(reader1, capacity, quality_policy),
+                QReader::with_capacity(reader2, capacity, quality_policy),
             ),
         };
         Self {
@@ -155,24 +508,444 @@ where
             file_index,
             reads_index: 0,
             batch_size,
+            batch_bytes: BATCH_BYTES,
+            keep_qual: false,
+            id_policy: IdPolicy::default(),
+            id_mismatches: 0,
+            validate_pairs: false,
+            orphan_policy: OrphanPolicy::default(),
+            orphans: 0,
+            pair_suffixes: PairSuffixes::default(),
+            keep_desc: true,
+            progress: None,
+            warnings: Vec::new(),
+            soft_mask: SoftMask::Keep,
         }
     }
 
-    fn create_seq_header(reader: &QReader<R>, file_index: usize, reads_index: usize) -> SeqHeader {
-        let seq_id = unsafe {
+    /// Sets the maximum number of records returned per batch.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{FastqReader, OptionPair, Reader};
+    /// use std::path::Path;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let path = Path::new("tests/data/test.fastq");
+    /// let mut reader = FastqReader::from_path(OptionPair::Single(path), 0, 0)?.with_batch_size(1);
+    /// assert_eq!(reader.next()?.unwrap().len(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Sets the sequence byte budget per batch (both mates are counted for
+    /// paired reads). A batch always holds at least one record.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{FastqReader, OptionPair, Reader};
+    /// use std::path::Path;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let path = Path::new("tests/data/test.fastq");
+    /// let mut reader = FastqReader::from_path(OptionPair::Single(path), 0, 0)?.with_batch_bytes(10);
+    /// assert_eq!(reader.next()?.unwrap().len(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_batch_bytes(mut self, batch_bytes: usize) -> Self {
+        self.batch_bytes = batch_bytes;
+        self
+    }
+
+    /// Sets how base qualities are applied, replacing the policy derived
+    /// from the `quality_score` constructor argument.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{FastqReader, OptionPair, QualityPolicy};
+    /// use std::path::Path;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let path = Path::new("tests/data/test.fastq");
+    /// let mut reader = FastqReader::from_path(OptionPair::Single(path), 0, 0)?
+    ///     .with_quality_policy(QualityPolicy::TrimEnds(20));
+    /// let seq = reader.read_next()?.unwrap();
+    /// assert_eq!(seq.header.quality_policy, QualityPolicy::TrimEnds(20));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_quality_policy(mut self, quality_policy: QualityPolicy) -> Self {
+        self.inner
+            .apply_mut(|reader| reader.quality_policy = quality_policy);
+        self
+    }
+
+    /// Detects the quality encoding from the first `records` records
+    /// ([`PHRED_SAMPLE`] is a reasonable choice). Phred+64 qualities are
+    /// then converted to Phred+33, so quality thresholds mean the same for
+    /// both; a sample that fits both encodings is read as Phred+33. Zero,
+    /// the default, turns detection off and reads Phred+33.
+    ///
+    /// Detection is opt-in because it can misfire: Phred+33 reads whose
+    /// qualities all stay at Q26 (`;`) or above with some at Q42 (`K`) or
+    /// above, e.g. PacBio HiFi reads, look like Phred+64. Only enable it for
+    /// input that may come from Illumina 1.3 - 1.7 pipelines.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{FastqReader, OptionPair, PhredEncoding, QualityPolicy, PHRED_SAMPLE};
+    ///
+    /// # fn main() -> Result<(), seqkmer::SeqKmerError> {
+    /// // Illumina 1.5: 'B' 即 Q2
+    /// let input = b"@r1\nACGTAC\n+\nhhhhhB\n@r2\nACGT\n+\nhhhh\n".as_slice();
+    /// let mut reader = FastqReader::new(OptionPair::Single(input), 0, 0)
+    ///     .with_phred_sample(PHRED_SAMPLE)
+    ///     .with_quality_policy(QualityPolicy::MaskBelow(20))
+    ///     .with_qualities(true);
+    /// let seq = reader.read_next()?.unwrap();
+    /// assert_eq!(reader.phred_encoding(), PhredEncoding::Phred64);
+    /// assert_eq!(seq.body.single().unwrap(), b"ACGTAN");
+    /// assert_eq!(seq.qual.unwrap().single().unwrap(), b"IIIII#");
+    ///
+    /// // 默认不检测
+    /// let mut reader = FastqReader::new(OptionPair::Single(input), 0, 0);
+    /// reader.read_next()?;
+    /// assert_eq!(reader.phred_encoding(), PhredEncoding::Phred33);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_phred_sample(mut self, records: usize) -> Self {
+        self.inner.apply_mut(|reader| reader.phred_sample = records);
+        self
+    }
+
+    /// Reads qualities as `phred`, without detection.
+    pub fn with_phred_encoding(mut self, phred: PhredEncoding) -> Self {
+        self.inner.apply_mut(|reader| {
+            reader.phred = phred;
+            reader.phred_sample = 0;
+        });
+        self
+    }
+
+    /// The quality encoding in use (R1's for pairs). Detection runs on the
+    /// first read, so before it this is Phred+33.
+    pub fn phred_encoding(&self) -> PhredEncoding {
+        match &self.inner {
+            OptionPair::Single(reader) | OptionPair::Pair(reader, _) => reader.phred,
+        }
+    }
+
+    /// Keeps base qualities (as Phred+33) on the records in `Base::qual`,
+    /// at the cost of one extra allocation per read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{FastqReader, OptionPair};
+    /// use std::path::Path;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let path = Path::new("tests/data/test.fastq");
+    /// let mut reader = FastqReader::from_path(OptionPair::Single(path), 0, 0)?.with_qualities(true);
+    /// let seq = reader.read_next()?.unwrap();
+    /// assert_eq!(seq.qual.unwrap().single().unwrap().len(), 20);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_qualities(mut self, keep_qual: bool) -> Self {
+        self.keep_qual = keep_qual;
+        self
+    }
+
+    /// Sets how mate ids that differ beyond the pair suffix are handled.
+    /// The default, `IdPolicy::PreferR1`, keeps R1's id.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{FastqReader, IdPolicy, OptionPair};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let r1 = b"@read1/1\nACGT\n+\nIIII\n".as_slice();
+    /// let r2 = b"@read1.trimmed/2\nACGT\n+\nIIII\n".as_slice();
+    ///
+    /// let mut reader = FastqReader::new(OptionPair::Pair(r1, r2), 0, 0);
+    /// assert_eq!(reader.read_next()?.unwrap().header.id, "read1");
+    /// assert_eq!(reader.id_mismatches(), 1);
+    ///
+    /// let mut reader = FastqReader::new(OptionPair::Pair(r1, r2), 0, 0).with_id_policy(IdPolicy::Error);
+    /// assert!(reader.read_next().is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_id_policy(mut self, id_policy: IdPolicy) -> Self {
+        self.id_policy = id_policy;
+        self
+    }
+
+    /// Checks that paired files stay in sync: a pair whose mate ids differ
+    /// (after `trim_pair_info`) or a file that ends before its mate is an
+    /// `InvalidData` error naming the read index, whatever the `IdPolicy`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{FastqReader, OptionPair};
+    ///
+    /// let r1 = b"@a/1\nACGT\n+\nIIII\n@b/1\nACGT\n+\nIIII\n".as_slice();
+    /// let r2 = b"@a/2\nACGT\n+\nIIII\n@c/2\nACGT\n+\nIIII\n".as_slice();
+    /// let mut reader = FastqReader::new(OptionPair::Pair(r1, r2), 0, 0).with_pair_validation(true);
+    /// assert!(reader.read_next().unwrap().is_some());
+    /// let err = reader.read_next().unwrap_err();
+    /// assert!(err.to_string().contains("read 2"));
+    ///
+    /// let r2 = b"@a/2\nACGT\n+\nIIII\n".as_slice();
+    /// let mut reader = FastqReader::new(OptionPair::Pair(r1, r2), 0, 0).with_pair_validation(true);
+    /// assert!(reader.read_next().unwrap().is_some());
+    /// assert!(reader.read_next().is_err());
+    /// ```
+    pub fn with_pair_validation(mut self, validate_pairs: bool) -> Self {
+        self.validate_pairs = validate_pairs;
+        self
+    }
+
+    /// Sets which mate suffixes are stripped from read ids. By default only
+    /// `/1` and `/2` are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{FastqReader, OptionPair, PairSuffixes};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let r1 = b"@SRR001.7_1\nACGT\n+\nIIII\n".as_slice();
+    /// let r2 = b"@SRR001.7_2\nACGT\n+\nIIII\n".as_slice();
+    /// let mut reader = FastqReader::new(OptionPair::Pair(r1, r2), 0, 0)
+    ///     .with_pair_suffixes(PairSuffixes::all())
+    ///     .with_pair_validation(true);
+    /// assert_eq!(reader.read_next()?.unwrap().header.id, "SRR001.7");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_pair_suffixes(mut self, pair_suffixes: PairSuffixes) -> Self {
+        self.pair_suffixes = pair_suffixes;
+        self
+    }
+
+    /// Keeps the header comment after the id in `SeqHeader::desc` (the
+    /// default); for pairs, R1's comment is kept. Turn it off to save one
+    /// allocation per record.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{FastqReader, OptionPair};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let r1 = b"@r1 1:N:0:ACGTAC\nACGT\n+\nIIII\n".as_slice();
+    /// let mut reader = FastqReader::new(OptionPair::Single(r1), 0, 0);
+    /// assert_eq!(reader.read_next()?.unwrap().header.desc.as_deref(), Some("1:N:0:ACGTAC"));
+    ///
+    /// let mut reader = FastqReader::new(OptionPair::Single(r1), 0, 0).with_descriptions(false);
+    /// assert_eq!(reader.read_next()?.unwrap().header.desc, None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_descriptions(mut self, keep_desc: bool) -> Self {
+        self.keep_desc = keep_desc;
+        self
+    }
+
+    /// Reports bytes read (both mates) and records emitted to `progress`
+    /// after every batch.
+    pub fn with_progress(mut self, progress: Arc<dyn Progress>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Skips corrupt records instead of failing: after a parse error the
+    /// reader moves on to the next line starting with '@', and the skipped
+    /// bytes are reported by [`warnings`](Self::warnings). Off by default.
+    ///
+    /// With paired input, the mate of a skipped record is dropped as well:
+    /// the other file is advanced until the ids match again (at most 1024
+    /// records, after which a `PairMismatch` error is returned).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{FastqReader, OptionPair};
+    ///
+    /// # fn main() -> Result<(), seqkmer::SeqKmerError> {
+    /// let input = b"@r1\nACGT\n+\nIIII\n@r2\nACGT\n+\nII\n@r3\nACGT\n+\nIIII\n".as_slice();
+    /// let mut reader = FastqReader::new(OptionPair::Single(input), 0, 0).with_lenient(true);
+    ///
+    /// assert_eq!(reader.read_next()?.unwrap().header.id, "r1");
+    /// assert_eq!(reader.read_next()?.unwrap().header.id, "r3");
+    /// assert!(reader.read_next()?.is_none());
+    ///
+    /// let warnings = reader.warnings();
+    /// assert_eq!(warnings.len(), 1);
+    /// assert_eq!(warnings[0].line, 8);
+    /// assert_eq!(warnings[0].bytes, 16..30);
+    ///
+    /// // 双端: r2 的另一端也被丢弃
+    /// let r2 = b"@r1\nACGT\n+\nIIII\n@r2\nACGT\n+\nIIII\n@r3\nACGT\n+\nIIII\n".as_slice();
+    /// let mut reader = FastqReader::new(OptionPair::Pair(input, r2), 0, 0)
+    ///     .with_lenient(true)
+    ///     .with_pair_validation(true);
+    /// assert_eq!(reader.read_next()?.unwrap().header.id, "r1");
+    /// assert_eq!(reader.read_next()?.unwrap().header.id, "r3");
+    /// assert!(reader.read_next()?.is_none());
+    /// assert_eq!(reader.warnings()[1].mate, 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_lenient(mut self, lenient: bool) -> Self {
+        self.inner.apply_mut(|reader| reader.lenient = lenient);
+        self
+    }
+
+    /// Records skipped so far in lenient mode, in the order they were met.
+    pub fn warnings(&self) -> &[ParseWarning] {
+        &self.warnings
+    }
+
+    /// Sets how lower-case bases are treated (see [`SoftMask`]).
+    pub fn with_soft_mask(mut self, soft_mask: SoftMask) -> Self {
+        self.soft_mask = soft_mask;
+        self
+    }
+
+    /// Number of pairs read so far whose mate ids differed.
+    pub fn id_mismatches(&self) -> usize {
+        self.id_mismatches
+    }
+
+    /// Sets what happens to the records of the longer mate file once the
+    /// other ends. Pair validation, when enabled, takes precedence and
+    /// fails as [`OrphanPolicy::ErrorOut`] does.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{FastqReader, Mate, OptionPair, OrphanPolicy};
+    ///
+    /// # fn main() -> Result<(), seqkmer::SeqKmerError> {
+    /// let r1 = b"@a/1\nACGT\n+\nIIII\n".as_slice();
+    /// let r2 = b"@a/2\nACGT\n+\nIIII\n@b/2\nGG\n+\nII\n@c/2\nTT\n+\nII\n".as_slice();
+    ///
+    /// let mut reader = FastqReader::new(OptionPair::Pair(r1, r2), 0, 0);
+    /// assert!(reader.read_next()?.unwrap().body.is_pair());
+    /// assert!(reader.read_next()?.is_none());
+    /// assert_eq!(reader.orphans(), 2);
+    ///
+    /// let mut reader = FastqReader::new(OptionPair::Pair(r1, r2), 0, 0)
+    ///     .with_orphan_policy(OrphanPolicy::EmitAsSingle);
+    /// reader.read_next()?;
+    /// let orphan = reader.read_next()?.unwrap();
+    /// assert_eq!(orphan.header.id, "b");
+    /// assert_eq!(orphan.body.single().unwrap(), b"GG");
+    /// assert_eq!(orphan.header.mate, Some(Mate::Second));
+    /// assert_eq!(reader.read_next()?.unwrap().header.id, "c");
+    /// assert!(reader.read_next()?.is_none());
+    /// assert_eq!(reader.orphans(), 2);
+    ///
+    /// let mut reader = FastqReader::new(OptionPair::Pair(r1, r2), 0, 0)
+    ///     .with_orphan_policy(OrphanPolicy::ErrorOut);
+    /// reader.read_next()?;
+    /// assert!(reader.read_next().is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_orphan_policy(mut self, orphan_policy: OrphanPolicy) -> Self {
+        self.orphan_policy = orphan_policy;
+        self
+    }
+
+    /// Number of records read so far from one mate file after the other
+    /// ended, whatever the `OrphanPolicy` did with them.
+    pub fn orphans(&self) -> usize {
+        self.orphans
+    }
+
+    fn parse_id(reader: &QReader<R>, pair_suffixes: PairSuffixes) -> (&str, &str) {
+        let (seq_id, rest) = unsafe {
             let s = std::str::from_utf8_unchecked(&reader.header[1..]);
             let first_space_index = s
                 .find(|c: char| c.is_whitespace() || c == '\u{1}')
                 .unwrap_or(s.len());
 
             // 直接从原始切片创建第一个单词的切片
-            &s[..first_space_index]
+            (&s[..first_space_index], &s[first_space_index..])
         };
+        (strip_pair_suffix(seq_id, pair_suffixes), rest)
+    }
+
+    /// After a corrupt record was skipped in one mate only, drops records of
+    /// the other mate until the ids match again. Returns whether each mate
+    /// still has a record.
+    fn resync_pair(
+        reader1: &mut QReader<R>,
+        reader2: &mut QReader<R>,
+        pair_suffixes: PairSuffixes,
+    ) -> Result<(bool, bool)> {
+        let skipped1 = !reader1.warnings.is_empty();
+        let skipped2 = !reader2.warnings.is_empty();
+        if skipped1 == skipped2 {
+            return Ok((true, true));
+        }
+        let (ahead, behind, mate) = if skipped1 {
+            (&*reader1, reader2, 1)
+        } else {
+            (&*reader2, reader1, 0)
+        };
+        let (id, _) = Self::parse_id(ahead, pair_suffixes);
+        for _ in 0..MAX_RESYNC {
+            if Self::parse_id(behind, pair_suffixes).0 == id {
+                return Ok((true, true));
+            }
+            behind.warnings.push(ParseWarning {
+                mate,
+                line: behind.record_line,
+                bytes: behind.record_start..behind.record_end,
+                reason: "mate of a skipped record".to_string(),
+            });
+            if behind.read_next(mate)?.is_none() {
+                return Ok((mate == 1, mate == 0));
+            }
+        }
+        Err(SeqKmerError::PairMismatch(format!(
+            "no mate for {} within {} records after a skipped record",
+            id, MAX_RESYNC
+        )))
+    }
+
+    fn create_seq_header(
+        reader: &QReader<R>,
+        spare: &mut Recycled,
+        file_index: usize,
+        reads_index: usize,
+        pair_suffixes: PairSuffixes,
+        keep_desc: bool,
+    ) -> SeqHeader {
+        let (id, rest) = Self::parse_id(reader, pair_suffixes);
         SeqHeader {
             file_index,
             reads_index,
-            format: SeqFormat::Fastq,
-            id: trim_pair_info(seq_id),
+            quality_policy: reader.quality_policy,
+            desc: spare.desc(rest, keep_desc),
+            ..SeqHeader::new(spare.id(id), SeqFormat::Fastq)
         }
     }
 
@@ -195,37 +968,131 @@ where
     /// # }
     /// ```
     pub fn read_next(&mut self) -> Result<Option<Base<Vec<u8>>>> {
+        self.read_next_reusing(None)
+    }
+
+    /// Reads the next record into the buffers of `spare`, if given.
+    fn read_next_reusing(&mut self, spare: Option<Base<Vec<u8>>>) -> Result<Option<Base<Vec<u8>>>> {
+        let mut seq = self.read_record(Recycled::new(spare))?;
+        if let Some(seq) = seq.as_mut() {
+            self.soft_mask.apply(seq);
+        }
+        Ok(seq)
+    }
+
+    fn read_record(&mut self, mut spare: Recycled) -> Result<Option<Base<Vec<u8>>>> {
         match &mut self.inner {
             OptionPair::Single(reader) => {
-                if reader.read_next()?.is_none() {
+                let more = reader.read_next(0)?.is_some();
+                self.warnings.append(&mut reader.warnings);
+                if !more {
                     return Ok(None);
                 }
 
                 self.reads_index += 1;
 
-                let seq_header =
-                    Self::create_seq_header(&reader, self.file_index, self.reads_index);
-                Ok(Some(Base::new(
-                    seq_header,
-                    OptionPair::Single(reader.seq.to_owned()),
-                )))
+                let seq_header = Self::create_seq_header(
+                    reader,
+                    &mut spare,
+                    self.file_index,
+                    self.reads_index,
+                    self.pair_suffixes,
+                    self.keep_desc,
+                );
+                let seq = Base::new(seq_header, OptionPair::Single(spare.body(0, &reader.seq)));
+                Ok(Some(if self.keep_qual {
+                    seq.with_qual(OptionPair::Single(spare.qual(0, &reader.quals)))
+                } else {
+                    seq
+                }))
             }
             OptionPair::Pair(reader1, reader2) => {
-                if reader1.read_next()?.is_none() {
-                    return Ok(None);
+                let mut more1 = reader1.read_next(0)?.is_some();
+                let mut more2 = reader2.read_next(1)?.is_some();
+                if more1 && more2 {
+                    (more1, more2) = Self::resync_pair(reader1, reader2, self.pair_suffixes)?;
                 }
-                if reader2.read_next()?.is_none() {
+                self.warnings.append(&mut reader1.warnings);
+                self.warnings.append(&mut reader2.warnings);
+                if more1 != more2 {
+                    if self.validate_pairs || self.orphan_policy == OrphanPolicy::ErrorOut {
+                        return Err(SeqKmerError::PairMismatch(format!(
+                            "R{} ended after {} records while its mate file continues",
+                            if more1 { 2 } else { 1 },
+                            self.reads_index
+                        )));
+                    }
+                    self.orphans += 1;
+                    let (mate, reader) = if more1 {
+                        (0, &mut *reader1)
+                    } else {
+                        (1, &mut *reader2)
+                    };
+                    if self.orphan_policy == OrphanPolicy::DropOrphans {
+                        // 读完较长的文件, 只计数
+                        while reader.read_next(mate)?.is_some() {
+                            self.orphans += 1;
+                        }
+                        self.warnings.append(&mut reader.warnings);
+                        return Ok(None);
+                    }
+                    self.reads_index += 1;
+                    let mut seq_header = Self::create_seq_header(
+                        reader,
+                        &mut spare,
+                        self.file_index,
+                        self.reads_index,
+                        self.pair_suffixes,
+                        self.keep_desc,
+                    );
+                    seq_header.mate = Some(if mate == 0 { Mate::First } else { Mate::Second });
+                    let seq = Base::new(
+                        seq_header,
+                        OptionPair::Single(spare.body(mate, &reader.seq)),
+                    );
+                    return Ok(Some(if self.keep_qual {
+                        seq.with_qual(OptionPair::Single(spare.qual(mate, &reader.quals)))
+                    } else {
+                        seq
+                    }));
+                }
+                if !more1 {
                     return Ok(None);
                 }
 
                 self.reads_index += 1;
-                let seq_header =
-                    Self::create_seq_header(&reader1, self.file_index, self.reads_index);
+                let mut seq_header = Self::create_seq_header(
+                    reader1,
+                    &mut spare,
+                    self.file_index,
+                    self.reads_index,
+                    self.pair_suffixes,
+                    self.keep_desc,
+                );
+                let (id2, _) = Self::parse_id(reader2, self.pair_suffixes);
+                if seq_header.id != id2 {
+                    self.id_mismatches += 1;
+                    if self.validate_pairs {
+                        return Err(SeqKmerError::PairMismatch(format!(
+                            "mate ids differ at read {}: {} and {}",
+                            self.reads_index, seq_header.id, id2
+                        )));
+                    }
+                    seq_header.id = self.id_policy.resolve(&seq_header.id, id2)?;
+                }
 
-                Ok(Some(Base::new(
+                let seq = Base::new(
                     seq_header,
-                    OptionPair::Pair(reader1.seq.to_owned(), reader2.seq.to_owned()),
-                )))
+                    OptionPair::Pair(spare.body(0, &reader1.seq), spare.body(1, &reader2.seq)),
+                );
+                Ok(Some(if self.keep_qual {
+                    seq.with_qual(OptionPair::Pair(
+                        spare.qual(0, &reader1.quals),
+                        spare.qual(1, &reader2.quals),
+                    ))
+                } else {
+                    seq
+                }))
             }
         }
     }
@@ -253,7 +1120,42 @@ impl FastqReader<Box<dyn Read + Send>> {
         quality_score: i32,
     ) -> Result<Self> {
         let readers = paths.map(|path| dyn_reader(path))?;
-        Ok(Self::new(readers, file_index, quality_score))
+        let mut reader = Self::new(readers, file_index, quality_score);
+        // 记录路径, 解析错误时报告文件名
+        for (inner, path) in reader.inner.iter_mut().zip(paths.iter()) {
+            inner.path = Some(path.as_ref().to_path_buf());
+        }
+        Ok(reader)
+    }
+
+    /// Reads the records of an uncompressed single-end FASTQ file that start
+    /// in `start..end`; see [`open_byte_range`](crate::open_byte_range).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{FastqReader, Reader};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let path = "tests/data/test.fastq";
+    /// let mut reader = FastqReader::from_byte_range(path, 0, 100, 0, 0)?;
+    /// assert_eq!(reader.next()?.unwrap().len(), 2);
+    /// let mut reader = FastqReader::from_byte_range(path, 100, 204, 0, 0)?;
+    /// assert_eq!(reader.next()?.unwrap()[0].header.id, "seq3");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_byte_range<P: AsRef<Path>>(
+        path: P,
+        start: u64,
+        end: u64,
+        file_index: usize,
+        quality_score: i32,
+    ) -> Result<Self> {
+        let reader = open_byte_range(&path, start, end, SeqFormat::Fastq)?;
+        let mut reader = Self::new(OptionPair::Single(reader), file_index, quality_score);
+        reader.inner.first_mut().path = Some(path.as_ref().to_path_buf());
+        Ok(reader)
     }
 }
 
@@ -262,10 +1164,66 @@ where
     R: Read + Send,
 {
     fn next(&mut self) -> Result<Option<Vec<Base<Vec<u8>>>>> {
-        let seqs: Vec<Base<Vec<u8>>> = (0..self.batch_size)
-            .filter_map(|_| self.read_next().transpose())
-            .collect::<Result<Vec<_>>>()?;
+        let mut seqs = Vec::new();
+        Ok(self.next_into(&mut seqs)?.then_some(seqs))
+    }
+
+    fn next_into(&mut self, seqs: &mut Vec<Base<Vec<u8>>>) -> Result<bool> {
+        // 旧记录的缓冲区用于新记录
+        let mut spare = std::mem::take(seqs);
+        let mut total_bytes = 0;
+
+        for _ in 0..self.batch_size {
+            match self.read_next_reusing(spare.pop())? {
+                Some(seq) => {
+                    total_bytes += seq.body.reduce(0, |acc, s| acc + s.len());
+                    seqs.push(seq);
+                    if total_bytes > self.batch_bytes {
+                        break;
+                    }
+                }
+                None => break,
+            }
+        }
 
-        Ok(Some(seqs).filter(|v| !v.is_empty()))
+        if let Some(progress) = &self.progress {
+            let bytes = self
+                .inner
+                .apply_mut(|reader| {
+                    let bytes = reader.bytes_read - reader.reported;
+                    reader.reported = reader.bytes_read;
+                    bytes
+                })
+                .reduce(0, |acc, &n| acc + n);
+            progress.update(ProgressEvent::BytesRead(bytes));
+            progress.update(ProgressEvent::RecordsEmitted(seqs.len()));
+        }
+        Ok(!seqs.is_empty())
+    }
+
+    fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size.max(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn high_quality_phred33_is_not_read_as_phred64() {
+        // HiFi 式的高质量 Phred+33: 没有低于 ';' 的字符, 且多数高于 'J'
+        let input: String = (0..50)
+            .map(|i| format!("@r{}\nACGTACGT\n+\n~~~~KKL~\n", i))
+            .collect();
+        let mut reader =
+            FastqReader::new(OptionPair::Single(input.as_bytes()), 0, 0).with_qualities(true);
+        let mut records = 0;
+        while let Some(seq) = reader.read_next().unwrap() {
+            assert_eq!(seq.qual.unwrap().single().unwrap(), b"~~~~KKL~");
+            records += 1;
+        }
+        assert_eq!(records, 50);
+        assert_eq!(reader.phred_encoding(), PhredEncoding::Phred33);
     }
 }
@@ -1,9 +1,9 @@
-use crate::fasta::{BufferFastaReader, FastaReader};
+use crate::error::Result;
+use crate::fasta::{BufferFastaReader, FastaPairReader, FastaReader};
 use crate::fastq::FastqReader;
-use crate::reader::{detect_file_format, Reader};
+use crate::reader::{open_sniffed_pair, FormatMismatch, Reader};
 use crate::seq::{Base, SeqFormat};
 use crate::utils::OptionPair;
-use std::io::Result;
 use std::path::Path;
 
 /// A reader for both FASTA and FASTQ files.
@@ -56,10 +56,20 @@ impl<R: Reader> Reader for FastxReader<R> {
     fn next(&mut self) -> Result<Option<Vec<Base<Vec<u8>>>>> {
         self.inner.next()
     }
+
+    fn next_into(&mut self, batch: &mut Vec<Base<Vec<u8>>>) -> Result<bool> {
+        self.inner.next_into(batch)
+    }
+
+    fn set_batch_size(&mut self, batch_size: usize) {
+        self.inner.set_batch_size(batch_size)
+    }
 }
 
 impl FastxReader<Box<dyn Reader + Send>> {
-    /// Creates a new `FastxReader` from file paths.
+    /// Creates a new `FastxReader` from file paths. A pair of FASTA files
+    /// is read with [`FastaPairReader`]; a FASTA file paired with a FASTQ
+    /// file is an `InvalidInput` error carrying a [`FormatMismatch`].
     ///
     /// # Examples
     ///
@@ -70,6 +80,10 @@ impl FastxReader<Box<dyn Reader + Send>> {
     /// # fn main() -> std::io::Result<()> {
     /// let path = Path::new("tests/data/test.fasta");
     /// let reader = FastxReader::from_paths(OptionPair::Single(path), 0, 0)?;
+    ///
+    /// let mut pairs = FastxReader::from_paths(OptionPair::Pair(path, path), 0, 0)?;
+    /// let seqs = pairs.next()?.unwrap();
+    /// assert!(seqs[0].body.is_pair());
     /// # Ok(())
     /// # }
     /// ```
@@ -78,19 +92,24 @@ impl FastxReader<Box<dyn Reader + Send>> {
         file_index: usize,
         quality_score: i32,
     ) -> Result<Self> {
-        let file_format = paths.map(|path: &P| detect_file_format(path));
+        // 每个文件只打开和解压一次
+        let (formats, streams) = open_sniffed_pair(&paths)?;
 
-        match file_format? {
-            OptionPair::Single(SeqFormat::Fasta) => {
-                let reader = FastaReader::from_path(paths.single().unwrap().as_ref(), file_index)?;
+        match (formats, streams) {
+            (OptionPair::Single(SeqFormat::Fasta), OptionPair::Single(stream)) => {
+                let reader = FastaReader::new(stream, file_index);
                 Ok(Self::new(Box::new(reader) as Box<dyn Reader + Send>))
             }
-            OptionPair::Single(SeqFormat::Fastq)
-            | OptionPair::Pair(SeqFormat::Fastq, SeqFormat::Fastq) => {
-                let reader = FastqReader::from_path(paths, file_index, quality_score)?;
+            (OptionPair::Single(SeqFormat::Fastq), streams)
+            | (OptionPair::Pair(SeqFormat::Fastq, SeqFormat::Fastq), streams) => {
+                let reader = FastqReader::new(streams, file_index, quality_score);
                 Ok(Self::new(Box::new(reader) as Box<dyn Reader + Send>))
             }
-            _ => panic!("Unsupported file format combination"),
+            (OptionPair::Pair(SeqFormat::Fasta, SeqFormat::Fasta), OptionPair::Pair(s1, s2)) => {
+                let reader = FastaPairReader::new(s1, s2, file_index);
+                Ok(Self::new(Box::new(reader) as Box<dyn Reader + Send>))
+            }
+            (formats, _) => Err(FormatMismatch { formats }.into()),
         }
     }
 
@@ -113,20 +132,24 @@ impl FastxReader<Box<dyn Reader + Send>> {
         file_index: usize,
         quality_score: i32,
     ) -> Result<Self> {
-        let file_format = paths.map(|path: &P| detect_file_format(path));
+        // 每个文件只打开和解压一次
+        let (formats, streams) = open_sniffed_pair(&paths)?;
 
-        match file_format? {
-            OptionPair::Single(SeqFormat::Fasta) => {
-                let reader =
-                    BufferFastaReader::from_path(paths.single().unwrap().as_ref(), file_index)?;
+        match (formats, streams) {
+            (OptionPair::Single(SeqFormat::Fasta), OptionPair::Single(stream)) => {
+                let reader = BufferFastaReader::new(stream, file_index);
+                Ok(Self::new(Box::new(reader) as Box<dyn Reader + Send>))
+            }
+            (OptionPair::Single(SeqFormat::Fastq), streams)
+            | (OptionPair::Pair(SeqFormat::Fastq, SeqFormat::Fastq), streams) => {
+                let reader = FastqReader::new(streams, file_index, quality_score);
                 Ok(Self::new(Box::new(reader) as Box<dyn Reader + Send>))
             }
-            OptionPair::Single(SeqFormat::Fastq)
-            | OptionPair::Pair(SeqFormat::Fastq, SeqFormat::Fastq) => {
-                let reader = FastqReader::from_path(paths, file_index, quality_score)?;
+            (OptionPair::Pair(SeqFormat::Fasta, SeqFormat::Fasta), OptionPair::Pair(s1, s2)) => {
+                let reader = FastaPairReader::new(s1, s2, file_index);
                 Ok(Self::new(Box::new(reader) as Box<dyn Reader + Send>))
             }
-            _ => panic!("Unsupported file format combination"),
+            (formats, _) => Err(FormatMismatch { formats }.into()),
         }
     }
 }
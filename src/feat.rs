@@ -1,3 +1,6 @@
+use std::borrow::Cow;
+use std::io;
+
 #[cfg(feature = "dna")]
 pub mod constants {
     pub const DEFAULT_KMER_LENGTH: u64 = 35;
@@ -5,6 +8,9 @@ pub mod constants {
     pub const DEFAULT_MINIMIZER_SPACES: u8 = 7;
 
     pub const BITS_PER_CHAR: usize = 2;
+
+    /// Kraken2's defaults, re-exported next to the length constants.
+    pub use super::{DEFAULT_SPACED_SEED_MASK, DEFAULT_TOGGLE_MASK};
 }
 
 #[cfg(feature = "protein")]
@@ -14,11 +20,14 @@ pub mod constants {
     pub const DEFAULT_MINIMIZER_SPACES: u8 = 0;
 
     pub const BITS_PER_CHAR: usize = 4;
+
+    /// Kraken2's defaults, re-exported next to the length constants.
+    pub use super::{DEFAULT_SPACED_SEED_MASK, DEFAULT_TOGGLE_MASK};
 }
 
-#[cfg(feature = "dna")]
+/// Encodes a nucleotide as a 2-bit value.
 #[inline]
-pub fn char_to_value(c: u8) -> Option<u64> {
+pub fn dna_to_value(c: u8) -> Option<u64> {
     match c {
         b'A' | b'a' => Some(0x00),
         b'C' | b'c' => Some(0x01),
@@ -28,9 +37,40 @@ pub fn char_to_value(c: u8) -> Option<u64> {
     }
 }
 
-#[cfg(feature = "protein")]
+/// Encodes an amino acid in the full 20-letter alphabet (plus one code for
+/// stop codons and rare amino acids), 5 bits per residue.
 #[inline]
-pub fn char_to_value(c: u8) -> Option<64> {
+pub fn protein_to_value(c: u8) -> Option<u64> {
+    match c.to_ascii_uppercase() {
+        b'*' | b'U' | b'O' => Some(0x00),
+        b'A' => Some(0x01),
+        b'C' => Some(0x02),
+        b'D' => Some(0x03),
+        b'E' => Some(0x04),
+        b'F' => Some(0x05),
+        b'G' => Some(0x06),
+        b'H' => Some(0x07),
+        b'I' => Some(0x08),
+        b'K' => Some(0x09),
+        b'L' => Some(0x0a),
+        b'M' => Some(0x0b),
+        b'N' => Some(0x0c),
+        b'P' => Some(0x0d),
+        b'Q' => Some(0x0e),
+        b'R' => Some(0x0f),
+        b'S' => Some(0x10),
+        b'T' => Some(0x11),
+        b'V' => Some(0x12),
+        b'W' => Some(0x13),
+        b'Y' => Some(0x14),
+        _ => None,
+    }
+}
+
+/// Encodes an amino acid in Kraken 2's reduced 15-letter alphabet (plus one
+/// code for stop codons and rare amino acids), 4 bits per residue.
+#[inline]
+pub fn reduced15_to_value(c: u8) -> Option<u64> {
     match c {
         // stop codons/rare amino acids
         b'*' | b'U' | b'u' | b'O' | b'o' => Some(0x00),
@@ -68,6 +108,208 @@ pub fn char_to_value(c: u8) -> Option<64> {
     }
 }
 
+/// Encodes an amino acid in a 9-letter alphabet following Murphy et al.
+/// (2000) with alanine and glycine merged (plus one code for stop codons and
+/// rare amino acids), 4 bits per residue.
+#[inline]
+pub fn reduced9_to_value(c: u8) -> Option<u64> {
+    match c.to_ascii_uppercase() {
+        // stop codons/rare amino acids
+        b'*' | b'U' | b'O' => Some(0x00),
+        // hydrophobic
+        b'L' | b'V' | b'I' | b'M' => Some(0x01),
+        b'C' => Some(0x02),
+        // small
+        b'A' | b'G' => Some(0x03),
+        b'S' | b'T' => Some(0x04),
+        b'P' => Some(0x05),
+        // aromatic
+        b'F' | b'Y' | b'W' => Some(0x06),
+        // acidic and amide
+        b'E' | b'D' | b'N' | b'Q' => Some(0x07),
+        // basic
+        b'K' | b'R' => Some(0x08),
+        b'H' => Some(0x09),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "dna")]
+#[inline]
+pub fn char_to_value(c: u8) -> Option<u64> {
+    dna_to_value(c)
+}
+
+#[cfg(feature = "protein")]
+#[inline]
+pub fn char_to_value(c: u8) -> Option<u64> {
+    reduced15_to_value(c)
+}
+
+/// Bases an IUPAC ambiguity code stands for. `N` and non-IUPAC bytes
+/// give `None`.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::iupac_bases;
+///
+/// assert_eq!(iupac_bases(b'R'), Some(b"AG".as_slice()));
+/// assert_eq!(iupac_bases(b'v'), Some(b"ACG".as_slice()));
+/// assert_eq!(iupac_bases(b'N'), None);
+/// ```
+pub fn iupac_bases(c: u8) -> Option<&'static [u8]> {
+    match c.to_ascii_uppercase() {
+        b'R' => Some(b"AG"),
+        b'Y' => Some(b"CT"),
+        b'S' => Some(b"CG"),
+        b'W' => Some(b"AT"),
+        b'K' => Some(b"GT"),
+        b'M' => Some(b"AC"),
+        b'B' => Some(b"CGT"),
+        b'D' => Some(b"AGT"),
+        b'H' => Some(b"ACT"),
+        b'V' => Some(b"ACG"),
+        _ => None,
+    }
+}
+
+/// How DNA scanners treat IUPAC ambiguity codes (`R`, `Y`, `S`, `W`, `K`,
+/// `M`, `B`, `D`, `H`, `V`). `N` and other bytes always reset the scanner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IupacPolicy {
+    /// The code ends the current run of l-mers, like `N`.
+    #[default]
+    Reset,
+    /// The code is dropped and l-mers span it.
+    Skip,
+    /// The code is replaced with one of its bases, chosen from the seed and
+    /// the position, so repeated scans agree.
+    RandomAssign(u64),
+}
+
+/// One residue as seen by a scanner; see [`Meros::encode_residue`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Residue {
+    Code(u64),
+    /// Ignored, the scanner state is kept.
+    Skip,
+    /// The scanner state is cleared.
+    Reset,
+}
+
+/// IUPAC complement table, case preserving; other bytes map to themselves.
+const COMPLEMENT: [u8; 256] = {
+    let mut table = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        table[i] = i as u8;
+        i += 1;
+    }
+    let pairs: [(u8, u8); 10] = [
+        (b'A', b'T'),
+        (b'C', b'G'),
+        (b'R', b'Y'),
+        (b'K', b'M'),
+        (b'B', b'V'),
+        (b'D', b'H'),
+        (b'S', b'S'),
+        (b'W', b'W'),
+        (b'N', b'N'),
+        (b'U', b'A'),
+    ];
+    let mut j = 0;
+    while j < pairs.len() {
+        let (a, b) = pairs[j];
+        table[a as usize] = b;
+        table[a.to_ascii_lowercase() as usize] = b.to_ascii_lowercase();
+        // U 只作为源, 不作为 A 的补
+        if a != b'U' {
+            table[b as usize] = a;
+            table[b.to_ascii_lowercase() as usize] = a.to_ascii_lowercase();
+        }
+        j += 1;
+    }
+    table
+};
+
+/// Complements one nucleotide, IUPAC-aware and case preserving (`U`
+/// complements to `A`). Bytes that are not nucleotide codes are returned
+/// unchanged.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::complement;
+///
+/// assert_eq!(complement(b'A'), b'T');
+/// assert_eq!(complement(b'g'), b'c');
+/// assert_eq!(complement(b'R'), b'Y');
+/// assert_eq!(complement(b'U'), b'A');
+/// assert_eq!(complement(b'-'), b'-');
+/// ```
+#[inline]
+pub fn complement(c: u8) -> u8 {
+    COMPLEMENT[c as usize]
+}
+
+/// Returns the reverse complement of a nucleotide sequence.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::revcomp;
+///
+/// assert_eq!(revcomp(b"ACGTn"), b"nACGT");
+/// assert_eq!(revcomp(b"AARY"), b"RYTT");
+/// ```
+pub fn revcomp(seq: &[u8]) -> Vec<u8> {
+    seq.iter().rev().map(|&c| complement(c)).collect()
+}
+
+/// Reverse-complements a nucleotide sequence in place.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::revcomp_in_place;
+///
+/// let mut seq = b"GATTACA".to_vec();
+/// revcomp_in_place(&mut seq);
+/// assert_eq!(seq, b"TGTAATC");
+/// ```
+pub fn revcomp_in_place(seq: &mut [u8]) {
+    seq.reverse();
+    for c in seq.iter_mut() {
+        *c = complement(*c);
+    }
+}
+
+/// Returns whichever of the sequence and its reverse complement sorts
+/// first, ignoring case; the forward strand is borrowed when it wins.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::canonical;
+///
+/// assert_eq!(&*canonical(b"TTGCA"), b"TGCAA");
+/// assert_eq!(&*canonical(b"ACGTT"), b"AACGT");
+/// assert_eq!(&*canonical(b"AACGT"), b"AACGT");
+/// ```
+pub fn canonical(seq: &[u8]) -> Cow<'_, [u8]> {
+    let forward = seq.iter().map(u8::to_ascii_uppercase);
+    let reverse = seq
+        .iter()
+        .rev()
+        .map(|&c| complement(c).to_ascii_uppercase());
+    if forward.le(reverse) {
+        Cow::Borrowed(seq)
+    } else {
+        Cow::Owned(revcomp(seq))
+    }
+}
+
 #[inline]
 fn reverse_complement(mut kmer: u64, n: usize) -> u64 {
     // Reverse bits while leaving bit pairs (nucleotides) intact.
@@ -85,7 +327,7 @@ fn reverse_complement(mut kmer: u64, n: usize) -> u64 {
     kmer = (kmer >> 16 & 0x0000FFFF0000FFFF) | (kmer << 16 & 0xFFFF0000FFFF0000);
 
     // Swap the two halves of the 64-bit word
-    kmer = (kmer >> 32) | (kmer << 32);
+    kmer = kmer.rotate_right(32);
 
     // Complement the bits, shift to the right length, and mask to get the desired length
     (!kmer >> (64 - n * 2)) & ((1u64 << (n * 2)) - 1)
@@ -99,9 +341,8 @@ fn reverse_complement(mut kmer: u64, n: usize) -> u64 {
     // }
 }
 
-#[cfg(feature = "dna")]
 #[inline]
-pub fn canonical_representation(kmer: u64, n: usize) -> u64 {
+fn dna_canonical(kmer: u64, n: usize) -> u64 {
     let revcom = reverse_complement(kmer, n);
     if kmer < revcom {
         kmer
@@ -110,12 +351,87 @@ pub fn canonical_representation(kmer: u64, n: usize) -> u64 {
     }
 }
 
+#[cfg(feature = "dna")]
+#[inline]
+pub fn canonical_representation(kmer: u64, n: usize) -> u64 {
+    dna_canonical(kmer, n)
+}
+
 #[cfg(feature = "protein")]
 #[inline]
-pub fn canonical_representation(kmer: u64, n: usize, revcom_version: u8) -> u64 {
+pub fn canonical_representation(kmer: u64, _n: usize) -> u64 {
     kmer
 }
 
+/// Residue alphabet used to pack l-mers.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::Alphabet;
+///
+/// assert_eq!(Alphabet::Dna.bits_per_char(), 2);
+/// assert_eq!(Alphabet::Protein.bits_per_char(), 5);
+/// assert_eq!(Alphabet::Reduced15.encode(b'L'), Alphabet::Reduced15.encode(b'I'));
+/// assert_eq!(Alphabet::Dna.encode(b'N'), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alphabet {
+    /// A, C, G, T; l-mers are canonicalized with their reverse complement.
+    Dna,
+    /// The 20 standard amino acids.
+    Protein,
+    /// Kraken 2's reduced 15-letter amino acid alphabet.
+    Reduced15,
+    /// Murphy-style reduced 9-letter amino acid alphabet.
+    Reduced9,
+}
+
+impl Default for Alphabet {
+    #[cfg(feature = "dna")]
+    fn default() -> Self {
+        Alphabet::Dna
+    }
+
+    #[cfg(feature = "protein")]
+    fn default() -> Self {
+        Alphabet::Reduced15
+    }
+}
+
+impl Alphabet {
+    /// Number of bits used to pack one residue.
+    #[inline]
+    pub fn bits_per_char(&self) -> usize {
+        match self {
+            Alphabet::Dna => 2,
+            Alphabet::Protein => 5,
+            Alphabet::Reduced15 | Alphabet::Reduced9 => 4,
+        }
+    }
+
+    /// Encodes one residue, returning None for characters outside the alphabet.
+    #[inline]
+    pub fn encode(&self, c: u8) -> Option<u64> {
+        match self {
+            Alphabet::Dna => dna_to_value(c),
+            Alphabet::Protein => protein_to_value(c),
+            Alphabet::Reduced15 => reduced15_to_value(c),
+            Alphabet::Reduced9 => reduced9_to_value(c),
+        }
+    }
+
+    /// Returns the canonical form of a packed l-mer of length `n`. Only DNA
+    /// has a reverse strand; protein l-mers are returned unchanged.
+    #[inline]
+    pub fn canonical(&self, kmer: u64, n: usize) -> u64 {
+        match self {
+            Alphabet::Dna => dna_canonical(kmer, n),
+            _ => kmer,
+        }
+    }
+}
+
 pub const DEFAULT_TOGGLE_MASK: u64 = 0xe37e28c4271b5a2d;
 pub const DEFAULT_SPACED_SEED_MASK: u64 = 0;
 pub const CURRENT_REVCOM_VERSION: u8 = 1;
@@ -151,9 +467,22 @@ pub struct Meros {
     pub spaced_seed_mask: u64,
     pub toggle_mask: u64,
     pub min_clear_hash_value: Option<u64>,
+    pub alphabet: Alphabet,
+    /// 同聚物压缩 (homopolymer-compressed seeds)
+    pub hpc: bool,
+    /// 低于该香农熵 (bits) 的最小化子被跳过
+    pub min_entropy: Option<f64>,
+    /// IUPAC 简并碱基的处理方式
+    pub iupac: IupacPolicy,
 }
 
 impl Meros {
+    /// Creates a configuration for k-mer length `k_mer` and minimizer
+    /// length `l_mer`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the lengths are invalid; see [`Meros::try_new`].
     pub fn new(
         k_mer: usize,
         l_mer: usize,
@@ -161,6 +490,9 @@ impl Meros {
         toggle_mask: Option<u64>,
         min_clear_hash_value: Option<u64>,
     ) -> Self {
+        if let Err(e) = check_lengths(k_mer, l_mer, constants::BITS_PER_CHAR) {
+            panic!("invalid minimizer parameters: {}", e);
+        }
         let mut mask = 1u64;
         mask <<= l_mer * constants::BITS_PER_CHAR;
         mask -= 1;
@@ -172,6 +504,353 @@ impl Meros {
             spaced_seed_mask: spaced_seed_mask.unwrap_or(DEFAULT_SPACED_SEED_MASK),
             toggle_mask: toggle_mask.unwrap_or(DEFAULT_TOGGLE_MASK) & mask,
             min_clear_hash_value,
+            alphabet: Alphabet::default(),
+            hpc: false,
+            min_entropy: None,
+            iupac: IupacPolicy::Reset,
+        }
+    }
+
+    /// Like [`Meros::new`], but returns an error instead of panicking when
+    /// `l_mer` is 0, `l_mer` is not shorter than `k_mer` (which would leave
+    /// an empty minimizer window), or an l-mer does not fit in 64 bits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::Meros;
+    ///
+    /// assert!(Meros::try_new(35, 31, None, None, None).is_ok());
+    /// let err = Meros::try_new(31, 35, None, None, None).unwrap_err();
+    /// assert!(err.to_string().contains("l_mer (35) must be shorter than k_mer (31)"));
+    /// assert!(Meros::try_new(31, 31, None, None, None).is_err());
+    /// assert!(Meros::try_new(40, 0, None, None, None).is_err());
+    /// ```
+    pub fn try_new(
+        k_mer: usize,
+        l_mer: usize,
+        spaced_seed_mask: Option<u64>,
+        toggle_mask: Option<u64>,
+        min_clear_hash_value: Option<u64>,
+    ) -> io::Result<Self> {
+        check_lengths(k_mer, l_mer, constants::BITS_PER_CHAR)?;
+        Ok(Self::new(
+            k_mer,
+            l_mer,
+            spaced_seed_mask,
+            toggle_mask,
+            min_clear_hash_value,
+        ))
+    }
+
+    /// Checks a configuration built or edited field by field, with the
+    /// same rules as [`Meros::try_new`] for the current alphabet.
+    pub fn validate(&self) -> io::Result<()> {
+        check_lengths(self.k_mer, self.l_mer, self.alphabet.bits_per_char())
+    }
+
+    /// Returns the effective parameters as `name: value` lines, for logs
+    /// and for checking a configuration by eye.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::Meros;
+    ///
+    /// let report = Meros::kraken2_defaults(35, 31).unwrap().describe();
+    /// assert!(report.contains("k: 35\n"));
+    /// assert!(report.contains("l: 31\n"));
+    /// assert!(report.contains("window size: 4\n"));
+    /// assert!(report.contains("spaced seed: weight 24 of 31"));
+    /// println!("{}", report);
+    /// ```
+    pub fn describe(&self) -> String {
+        let bits = self.alphabet.bits_per_char();
+        let spaced = match self.spaced_seed_mask & self.mask {
+            0 => "none".to_string(),
+            mask => format!(
+                "weight {} of {} ({:#x})",
+                mask.count_ones() as usize / bits,
+                self.l_mer,
+                mask
+            ),
+        };
+        let toggle = match self.toggle_mask {
+            0 => "off".to_string(),
+            mask => format!("{:#x}", mask),
+        };
+        let optional = |value: Option<String>| value.unwrap_or_else(|| "none".to_string());
+        let mut report = String::new();
+        report.push_str(&format!("k: {}\n", self.k_mer));
+        report.push_str(&format!("l: {}\n", self.l_mer));
+        report.push_str(&format!(
+            "window size: {}\n",
+            self.k_mer.saturating_sub(self.l_mer)
+        ));
+        report.push_str(&format!(
+            "alphabet: {:?} ({} bits per residue)\n",
+            self.alphabet, bits
+        ));
+        report.push_str(&format!("l-mer mask: {:#x}\n", self.mask));
+        report.push_str(&format!("spaced seed: {}\n", spaced));
+        report.push_str(&format!("toggle mask: {}\n", toggle));
+        report.push_str(&format!(
+            "min clear hash value: {}\n",
+            optional(self.min_clear_hash_value.map(|v| format!("{:#x}", v)))
+        ));
+        report.push_str(&format!(
+            "homopolymer compression: {}\n",
+            if self.hpc { "on" } else { "off" }
+        ));
+        report.push_str(&format!(
+            "min entropy: {}\n",
+            optional(self.min_entropy.map(|v| format!("{} bits", v)))
+        ));
+        report.push_str(&format!("iupac: {:?}\n", self.iupac));
+        report
+    }
+
+    /// Kraken2's configuration for k-mer length `k` and minimizer length
+    /// `l`: the default toggle mask, `DEFAULT_MINIMIZER_SPACES` spaced
+    /// positions laid out as `kraken2-build` does, and no subsampling. Fails
+    /// if the lengths are invalid or `l` is too short for the spaces.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{Meros, DEFAULT_TOGGLE_MASK};
+    ///
+    /// let meros = Meros::kraken2_defaults(35, 31).unwrap();
+    /// assert_eq!(meros.toggle_mask, DEFAULT_TOGGLE_MASK & meros.mask);
+    /// assert_eq!(meros.spaced_seed_mask.count_ones(), 2 * (31 - 7));
+    /// assert_eq!(meros.min_clear_hash_value, None);
+    /// ```
+    pub fn kraken2_defaults(k_mer: usize, l_mer: usize) -> io::Result<Self> {
+        let meros = Self::try_new(k_mer, l_mer, None, None, None)?;
+        let spaces = constants::DEFAULT_MINIMIZER_SPACES as usize;
+        if spaces == 0 {
+            return Ok(meros);
+        }
+        meros.with_spaced_seed(&SpacedSeed::with_spaces(l_mer, spaces)?)
+    }
+
+    /// Disables the toggle mask, so minimizers are ordered by their raw
+    /// l-mer value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{Alphabet, Meros};
+    ///
+    /// let meros = Meros::new(15, 12, None, None, None).without_toggle_mask();
+    /// assert_eq!(meros.toggle_mask, 0);
+    /// assert_eq!(meros.with_alphabet(Alphabet::Protein).toggle_mask, 0);
+    /// ```
+    pub fn without_toggle_mask(mut self) -> Self {
+        self.toggle_mask = 0;
+        self
+    }
+
+    /// Switches the residue alphabet, recomputing the l-mer mask for its
+    /// packing width. Toggle mask bits beyond the previous mask are taken
+    /// from `DEFAULT_TOGGLE_MASK`, unless the toggle mask is disabled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{Alphabet, Meros};
+    ///
+    /// let meros = Meros::new(15, 12, None, None, None).with_alphabet(Alphabet::Protein);
+    /// assert_eq!(meros.mask, (1u64 << 60) - 1);
+    /// ```
+    pub fn with_alphabet(mut self, alphabet: Alphabet) -> Self {
+        let bits = self.l_mer * alphabet.bits_per_char();
+        assert!(bits < 64, "l_mer does not fit in 64 bits for this alphabet");
+        let mask = (1u64 << bits) - 1;
+        // 关闭的 toggle mask 保持关闭
+        if self.toggle_mask != 0 {
+            self.toggle_mask = (self.toggle_mask | (DEFAULT_TOGGLE_MASK & !self.mask)) & mask;
+        }
+        self.mask = mask;
+        self.alphabet = alphabet;
+        self
+    }
+
+    /// Applies a spaced seed, checking that it covers exactly `l_mer`
+    /// positions in the current alphabet's packing. Call it after
+    /// [`Meros::with_alphabet`], since the mask depends on the packing width.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{Meros, SpacedSeed};
+    ///
+    /// let seed = SpacedSeed::from_pattern("11011").unwrap();
+    /// let meros = Meros::new(8, 5, None, None, None).with_spaced_seed(&seed).unwrap();
+    /// assert_eq!(meros.spaced_seed_mask, 0b1111001111);
+    ///
+    /// let meros = Meros::new(8, 4, None, None, None);
+    /// assert!(meros.with_spaced_seed(&seed).is_err());
+    /// ```
+    pub fn with_spaced_seed(mut self, seed: &SpacedSeed) -> io::Result<Self> {
+        if seed.len() != self.l_mer {
+            return Err(invalid_seed(format!(
+                "spaced seed has {} positions but l_mer is {}",
+                seed.len(),
+                self.l_mer
+            )));
+        }
+        self.spaced_seed_mask = seed.mask_for(self.alphabet.bits_per_char())?;
+        Ok(self)
+    }
+
+    /// Validates several spaced seeds against this configuration and returns
+    /// their masks, ready for `MultiSeedIterator`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{Meros, SpacedSeed};
+    ///
+    /// let seeds = [
+    ///     SpacedSeed::from_pattern("11111").unwrap(),
+    ///     SpacedSeed::from_pattern("11011").unwrap(),
+    /// ];
+    /// let meros = Meros::new(8, 5, None, None, None);
+    /// assert_eq!(meros.spaced_seed_masks(&seeds).unwrap(), vec![0b1111111111, 0b1111001111]);
+    /// ```
+    pub fn spaced_seed_masks(&self, seeds: &[SpacedSeed]) -> io::Result<Vec<u64>> {
+        seeds
+            .iter()
+            .map(|seed| self.with_spaced_seed(seed).map(|m| m.spaced_seed_mask))
+            .collect()
+    }
+
+    /// Collapses homopolymer runs before l-mers are packed, like minimap2's
+    /// HPC seeds, so `AAAC` and `AC` give the same l-mers. Useful for
+    /// nanopore reads, whose errors concentrate in homopolymer lengths.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{for_each_minimizer, Meros};
+    ///
+    /// let meros = Meros::new(9, 5, None, None, None).with_hpc(true);
+    /// let scan = |seq: &[u8]| {
+    ///     let mut hashes = Vec::new();
+    ///     for_each_minimizer(seq, &meros, |_, hash| hashes.push(hash));
+    ///     hashes
+    /// };
+    /// assert_eq!(scan(b"ACGTTTTGCAAACGTAGC"), scan(b"ACGTGCACGTAGC"));
+    /// ```
+    pub fn with_hpc(mut self, hpc: bool) -> Self {
+        self.hpc = hpc;
+        self
+    }
+
+    /// Skips minimizers whose l-mer has a Shannon entropy below
+    /// `min_entropy` bits, so homopolymer and dinucleotide-repeat seeds are
+    /// never emitted. A 2-base repeat has 1 bit, a uniform DNA l-mer 2 bits.
+    /// The window still selects them; they are dropped on output.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{for_each_minimizer, Meros};
+    ///
+    /// let meros = Meros::new(15, 11, Some(0), None, None);
+    /// assert!(meros.lmer_entropy(0) < 0.01); // AAAAAAAAAAA
+    ///
+    /// let count = |meros: &Meros, seq: &[u8]| {
+    ///     let mut n = 0;
+    ///     for_each_minimizer(seq, meros, |_, _| n += 1);
+    ///     n
+    /// };
+    /// let repeat = b"ACACACACACACACACACACACACACACAC";
+    /// assert!(count(&meros, repeat) > 0);
+    /// assert_eq!(count(&meros.with_min_entropy(1.5), repeat), 0);
+    /// assert!(count(&meros.with_min_entropy(1.5), b"ACGTTGCAACGTGGCATTACGATCGA") > 0);
+    /// ```
+    pub fn with_min_entropy(mut self, min_entropy: f64) -> Self {
+        self.min_entropy = Some(min_entropy);
+        self
+    }
+
+    /// Shannon entropy, in bits, of the residues of a packed l-mer. Positions
+    /// cleared by the spaced seed are not counted.
+    pub fn lmer_entropy(&self, lmer: u64) -> f64 {
+        let bits = self.alphabet.bits_per_char();
+        let symbol = (1u64 << bits) - 1;
+        let mut counts = [0u32; 32];
+        let mut total = 0;
+        for pos in 0..self.l_mer {
+            let shift = pos * bits;
+            if self.spaced_seed_mask > 0 && (self.spaced_seed_mask >> shift) & symbol == 0 {
+                continue;
+            }
+            counts[((lmer >> shift) & symbol) as usize] += 1;
+            total += 1;
+        }
+        counts
+            .iter()
+            .filter(|&&n| n > 0)
+            .map(|&n| {
+                let p = n as f64 / total as f64;
+                -p * p.log2()
+            })
+            .sum()
+    }
+
+    /// Whether a minimizer (a candidate l-mer, toggle mask applied) passes
+    /// the entropy filter.
+    #[inline]
+    pub(crate) fn passes_entropy(&self, minimizer: u64) -> bool {
+        self.min_entropy
+            .is_none_or(|min| self.lmer_entropy(minimizer ^ self.toggle_mask) >= min)
+    }
+
+    /// Sets how DNA scanners treat IUPAC ambiguity codes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{for_each_minimizer, IupacPolicy, Meros};
+    ///
+    /// let seq = b"ACGTTGCAACGTGGCARTACGATCGATCGGGATCGA";
+    /// let count = |meros: &Meros| {
+    ///     let mut n = 0;
+    ///     for_each_minimizer(seq, meros, |_, _| n += 1);
+    ///     n
+    /// };
+    /// let meros = Meros::new(15, 11, Some(0), None, None);
+    /// let reset = count(&meros);
+    /// assert!(count(&meros.with_iupac_policy(IupacPolicy::Skip)) > reset);
+    /// assert!(count(&meros.with_iupac_policy(IupacPolicy::RandomAssign(7))) > reset);
+    /// ```
+    pub fn with_iupac_policy(mut self, iupac: IupacPolicy) -> Self {
+        self.iupac = iupac;
+        self
+    }
+
+    /// Encodes the residue `c` found at offset `pos` of the sequence,
+    /// applying the IUPAC policy to DNA ambiguity codes.
+    #[inline]
+    pub fn encode_residue(&self, c: u8, pos: usize) -> Residue {
+        if let Some(code) = self.alphabet.encode(c) {
+            return Residue::Code(code);
+        }
+        if self.alphabet != Alphabet::Dna {
+            return Residue::Reset;
+        }
+        match (self.iupac, iupac_bases(c)) {
+            (IupacPolicy::Skip, Some(_)) => Residue::Skip,
+            (IupacPolicy::RandomAssign(seed), Some(bases)) => {
+                // 由种子和位置决定, 结果可复现
+                let hash = fmix64(seed ^ (pos as u64).wrapping_mul(0x9e3779b97f4a7c15));
+                let base = bases[(hash % bases.len() as u64) as usize];
+                dna_to_value(base).map_or(Residue::Reset, Residue::Code)
+            }
+            _ => Residue::Reset,
         }
     }
 
@@ -195,6 +874,148 @@ impl Default for Meros {
             spaced_seed_mask: DEFAULT_SPACED_SEED_MASK,
             toggle_mask: DEFAULT_TOGGLE_MASK & mask,
             min_clear_hash_value: None,
+            alphabet: Alphabet::default(),
+            hpc: false,
+            min_entropy: None,
+            iupac: IupacPolicy::Reset,
+        }
+    }
+}
+
+/// A spaced seed pattern over the positions of an l-mer, written from the
+/// first (5') to the last position: `1` keeps a position, `0` masks it.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::SpacedSeed;
+///
+/// let seed = SpacedSeed::from_pattern("111010011").unwrap();
+/// assert_eq!(seed.len(), 9);
+/// assert_eq!(seed.weight(), 6);
+/// assert_eq!(seed.mask_for(2).unwrap(), 0b111111001100001111);
+/// assert_eq!(seed.popcount(2).unwrap(), 12);
+///
+/// assert!(SpacedSeed::from_pattern("11x1").is_err());
+/// assert!(SpacedSeed::from_pattern("000").is_err());
+/// assert!(SpacedSeed::from_pattern(&"1".repeat(32)).unwrap().mask_for(2).is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpacedSeed {
+    // 每个位置 1 bit, 最低位对应最后一个位置
+    positions: u64,
+    len: usize,
+}
+
+impl SpacedSeed {
+    /// Parses a pattern of `0` and `1`. The pattern must keep at least one
+    /// position and be at most 64 positions long.
+    pub fn from_pattern(pattern: &str) -> io::Result<Self> {
+        if pattern.is_empty() || pattern.len() > 64 {
+            return Err(invalid_seed(format!(
+                "spaced seed pattern must have 1 to 64 positions, got {}",
+                pattern.len()
+            )));
+        }
+        let positions = pattern.bytes().try_fold(0u64, |acc, c| match c {
+            b'1' => Ok((acc << 1) | 1),
+            b'0' => Ok(acc << 1),
+            _ => Err(invalid_seed(format!(
+                "invalid character {:?} in spaced seed pattern",
+                c as char
+            ))),
+        })?;
+        if positions == 0 {
+            return Err(invalid_seed("spaced seed pattern masks every position"));
         }
+        Ok(Self {
+            positions,
+            len: pattern.len(),
+        })
     }
+
+    /// Builds Kraken 2's default seed for `--minimizer-spaces`: a solid core
+    /// followed by `spaces` alternating masked positions at the 3' end.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::SpacedSeed;
+    ///
+    /// let seed = SpacedSeed::with_spaces(9, 2).unwrap();
+    /// assert_eq!(seed, SpacedSeed::from_pattern("111110101").unwrap());
+    /// ```
+    pub fn with_spaces(l_mer: usize, spaces: usize) -> io::Result<Self> {
+        if spaces * 4 > l_mer {
+            return Err(invalid_seed(format!(
+                "{} spaces is too many for an l-mer of length {}",
+                spaces, l_mer
+            )));
+        }
+        let pattern = "1".repeat(l_mer - 2 * spaces) + &"01".repeat(spaces);
+        Self::from_pattern(&pattern)
+    }
+
+    /// Number of positions in the pattern.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of positions kept by the pattern.
+    pub fn weight(&self) -> usize {
+        self.positions.count_ones() as usize
+    }
+
+    /// Expands the pattern to an l-mer mask with `bits_per_char` bits per
+    /// position. Fails if the packed l-mer does not fit in 64 bits.
+    pub fn mask_for(&self, bits_per_char: usize) -> io::Result<u64> {
+        if self.len * bits_per_char >= 64 {
+            return Err(invalid_seed(format!(
+                "a {}-position seed does not fit in 64 bits at {} bits per character",
+                self.len, bits_per_char
+            )));
+        }
+        let char_mask = (1u64 << bits_per_char) - 1;
+        Ok((0..self.len)
+            .filter(|i| self.positions >> i & 1 == 1)
+            .fold(0u64, |mask, i| mask | (char_mask << (i * bits_per_char))))
+    }
+
+    /// Expands the pattern with the crate's `BITS_PER_CHAR` packing.
+    pub fn mask(&self) -> io::Result<u64> {
+        self.mask_for(constants::BITS_PER_CHAR)
+    }
+
+    /// Number of bits set in the expanded mask.
+    pub fn popcount(&self, bits_per_char: usize) -> io::Result<u32> {
+        self.mask_for(bits_per_char).map(u64::count_ones)
+    }
+}
+
+/// 检查 k/l 长度: 窗口不能为空, l-mer 必须放得进 u64
+fn check_lengths(k_mer: usize, l_mer: usize, bits_per_char: usize) -> io::Result<()> {
+    if l_mer == 0 {
+        return Err(invalid_seed("l_mer must be at least 1"));
+    }
+    if l_mer >= k_mer {
+        return Err(invalid_seed(format!(
+            "l_mer ({}) must be shorter than k_mer ({}), or the minimizer window is empty",
+            l_mer, k_mer
+        )));
+    }
+    if l_mer * bits_per_char >= 64 {
+        return Err(invalid_seed(format!(
+            "l_mer ({}) at {} bits per residue does not fit in 64 bits",
+            l_mer, bits_per_char
+        )));
+    }
+    Ok(())
+}
+
+fn invalid_seed(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg.into())
 }
@@ -0,0 +1,111 @@
+use crate::seq::Base;
+
+/// Decides whether a record is scanned. Closures taking `&Base<Vec<u8>>`
+/// implement it.
+pub trait Filter: Send + Sync {
+    fn accept(&self, seq: &Base<Vec<u8>>) -> bool;
+}
+
+impl<F> Filter for F
+where
+    F: Fn(&Base<Vec<u8>>) -> bool + Send + Sync,
+{
+    fn accept(&self, seq: &Base<Vec<u8>>) -> bool {
+        self(seq)
+    }
+}
+
+/// Common read-level criteria. A paired record passes only if both mates do.
+///
+/// The mean quality criterion needs qualities on the record (see
+/// `FastqReader::with_qualities`); records without them pass it.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{Base, Filter, OptionPair, ReadFilter, SeqFormat, SeqHeader};
+///
+/// let header = SeqHeader {
+///     reads_index: 1,
+///     ..SeqHeader::new("r1", SeqFormat::Fastq)
+/// };
+/// let read = Base::new(header, OptionPair::Single(b"ACGTNNACGT".to_vec()))
+///     .with_qual(OptionPair::Single(b"IIIIIIIIII".to_vec()));
+///
+/// assert!(ReadFilter::new().with_min_length(10).accept(&read));
+/// assert!(!ReadFilter::new().with_min_length(11).accept(&read));
+/// assert!(!ReadFilter::new().with_max_n_fraction(0.1).accept(&read));
+/// assert!(ReadFilter::new().with_min_mean_quality(40.0).accept(&read));
+/// assert!(!ReadFilter::new().with_min_mean_quality(41.0).accept(&read));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReadFilter {
+    pub min_length: usize,
+    pub max_n_fraction: f64,
+    pub min_mean_quality: Option<f64>,
+}
+
+impl Default for ReadFilter {
+    fn default() -> Self {
+        Self {
+            min_length: 0,
+            max_n_fraction: 1.0,
+            min_mean_quality: None,
+        }
+    }
+}
+
+impl ReadFilter {
+    /// Creates a filter that accepts every read.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_min_length(mut self, min_length: usize) -> Self {
+        self.min_length = min_length;
+        self
+    }
+
+    /// Sets the largest tolerated fraction of bases outside `ACGT`.
+    pub fn with_max_n_fraction(mut self, max_n_fraction: f64) -> Self {
+        self.max_n_fraction = max_n_fraction;
+        self
+    }
+
+    /// Sets the lowest tolerated mean Phred score.
+    pub fn with_min_mean_quality(mut self, min_mean_quality: f64) -> Self {
+        self.min_mean_quality = Some(min_mean_quality);
+        self
+    }
+
+    fn accept_seq(&self, seq: &[u8]) -> bool {
+        let (len, ambiguous) = seq.iter().filter(|&&c| c != b'\n' && c != b'\r').fold(
+            (0usize, 0usize),
+            |(len, n), &c| {
+                let is_acgt = matches!(c.to_ascii_uppercase(), b'A' | b'C' | b'G' | b'T');
+                (len + 1, n + usize::from(!is_acgt))
+            },
+        );
+        len >= self.min_length && (len == 0 || ambiguous as f64 / len as f64 <= self.max_n_fraction)
+    }
+
+    fn accept_qual(&self, qual: &[u8]) -> bool {
+        match self.min_mean_quality {
+            Some(min) if !qual.is_empty() => {
+                let total: u64 = qual.iter().map(|&q| q.saturating_sub(b'!') as u64).sum();
+                total as f64 / qual.len() as f64 >= min
+            }
+            _ => true,
+        }
+    }
+}
+
+impl Filter for ReadFilter {
+    fn accept(&self, seq: &Base<Vec<u8>>) -> bool {
+        seq.body.reduce(true, |ok, s| ok && self.accept_seq(s))
+            && seq
+                .qual
+                .as_ref()
+                .is_none_or(|qual| qual.reduce(true, |ok, q| ok && self.accept_qual(q)))
+    }
+}
@@ -0,0 +1,548 @@
+use crate::error::{Result, SeqKmerError};
+use crate::mmscanner::Mate;
+use crate::reader::Reader;
+use crate::seq::{Base, QualityPolicy, ReadTags, SeqChunk, SeqFormat, SeqHeader};
+use crate::tee::BatchSink;
+use crate::utils::OptionPair;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use std::io::{self, BufReader, Read, Write};
+
+/// Signature at the start of a framed batch stream.
+pub const FRAME_MAGIC: [u8; 4] = *b"SKBF";
+
+/// Version of the record layout written by [`FrameWriter`].
+pub const FRAME_VERSION: u8 = 1;
+
+/// Compression of the frame payloads, recorded in the stream header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FrameCompression {
+    #[default]
+    None,
+    /// Raw deflate at the given level (0-9).
+    Deflate(u32),
+}
+
+impl FrameCompression {
+    fn code(&self) -> u8 {
+        match self {
+            FrameCompression::None => 0,
+            FrameCompression::Deflate(_) => 1,
+        }
+    }
+}
+
+// 记录中可选字段的标志位
+const HAS_CHUNK: u8 = 1;
+const HAS_DESC: u8 = 1 << 1;
+const HAS_TAGS: u8 = 1 << 2;
+const HAS_SOFT_MASKED: u8 = 1 << 3;
+const IS_PAIR: u8 = 1 << 4;
+const HAS_QUAL: u8 = 1 << 5;
+const HAS_BARCODE: u8 = 1 << 6;
+const HAS_UMI: u8 = 1 << 7;
+
+/// Writes batches as a self-describing binary stream that a [`FrameReader`]
+/// turns back into identical batches, so reading and scanning can run in
+/// different processes connected by a pipe or socket.
+///
+/// The stream starts with [`FRAME_MAGIC`], the version and the compression
+/// code. Each batch is one frame: a little-endian `u32` payload length and
+/// the payload, optionally compressed, holding the record count and the
+/// records with every header field, both mates and the qualities. Lengths
+/// and indices are LEB128 varints.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{BatchSink, FastqReader, FrameCompression, FrameReader, FrameWriter};
+/// use seqkmer::{OptionPair, Reader};
+/// use std::path::Path;
+///
+/// # fn main() -> Result<(), seqkmer::SeqKmerError> {
+/// let path = Path::new("tests/data/test.fastq");
+/// let mut reader = FastqReader::from_path(OptionPair::Single(path), 0, 0)?.with_qualities(true);
+/// let batch = reader.next()?.unwrap();
+///
+/// let mut writer = FrameWriter::new(Vec::new()).with_compression(FrameCompression::Deflate(6));
+/// writer.write_batch(&batch)?;
+/// writer.finish()?;
+/// let stream = writer.into_inner();
+///
+/// let mut reader = FrameReader::new(stream.as_slice())?;
+/// assert_eq!(reader.compression(), FrameCompression::Deflate(6));
+/// let decoded = reader.next()?.unwrap();
+/// assert_eq!(decoded.len(), batch.len());
+/// for (copy, original) in decoded.iter().zip(&batch) {
+///     assert_eq!(copy.header, original.header);
+///     assert_eq!(copy.body.single(), original.body.single());
+///     assert_eq!(copy.qual.as_ref().unwrap().single(), original.qual.as_ref().unwrap().single());
+/// }
+/// assert!(reader.next()?.is_none());
+/// # Ok(())
+/// # }
+/// ```
+pub struct FrameWriter<W: Write + Send> {
+    writer: W,
+    compression: FrameCompression,
+    started: bool,
+    payload: Vec<u8>,
+}
+
+impl<W: Write + Send> FrameWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            compression: FrameCompression::None,
+            started: false,
+            payload: Vec::new(),
+        }
+    }
+
+    /// Sets the payload compression. It applies to the whole stream, so set
+    /// it before the first batch.
+    pub fn with_compression(mut self, compression: FrameCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_stream_header(&mut self) -> io::Result<()> {
+        if !self.started {
+            self.started = true;
+            self.writer.write_all(&FRAME_MAGIC)?;
+            self.writer
+                .write_all(&[FRAME_VERSION, self.compression.code()])?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write + Send> BatchSink for FrameWriter<W> {
+    fn write_batch(&mut self, batch: &[Base<Vec<u8>>]) -> Result<()> {
+        self.write_stream_header()?;
+        self.payload.clear();
+        put_varint(&mut self.payload, batch.len() as u64);
+        for record in batch {
+            encode_record(&mut self.payload, record);
+        }
+        let compressed;
+        let payload = match self.compression {
+            FrameCompression::None => &self.payload,
+            FrameCompression::Deflate(level) => {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::new(level));
+                encoder.write_all(&self.payload)?;
+                compressed = encoder.finish()?;
+                &compressed
+            }
+        };
+        let len = u32::try_from(payload.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame exceeds 4 GiB"))?;
+        self.writer.write_all(&len.to_le_bytes())?;
+        self.writer.write_all(payload)?;
+        Ok(())
+    }
+
+    /// Writes the stream header if no batch was written, so an empty input
+    /// still yields a valid stream, and flushes.
+    fn finish(&mut self) -> Result<()> {
+        self.write_stream_header()?;
+        Ok(self.writer.flush()?)
+    }
+}
+
+/// Reads a stream written by [`FrameWriter`]; each frame is returned as one
+/// batch, so the batch size is the writer's and `set_batch_size` has no
+/// effect.
+pub struct FrameReader<R: Read + Send> {
+    reader: BufReader<R>,
+    compression: FrameCompression,
+    payload: Vec<u8>,
+    frames: u64,
+}
+
+impl<R: Read + Send> FrameReader<R> {
+    /// Checks the stream header.
+    pub fn new(reader: R) -> Result<Self> {
+        let mut reader = BufReader::new(reader);
+        let mut header = [0u8; 6];
+        reader
+            .read_exact(&mut header)
+            .map_err(|_| SeqKmerError::parse(None, "missing frame stream header"))?;
+        if header[..4] != FRAME_MAGIC {
+            return Err(SeqKmerError::parse(None, "not a framed batch stream"));
+        }
+        if header[4] != FRAME_VERSION {
+            return Err(SeqKmerError::parse(
+                None,
+                format!("unsupported frame version {}", header[4]),
+            ));
+        }
+        let compression = match header[5] {
+            0 => FrameCompression::None,
+            // 级别只影响写入端
+            1 => FrameCompression::Deflate(Compression::default().level()),
+            code => {
+                return Err(SeqKmerError::CompressionError(format!(
+                    "unknown frame compression {}",
+                    code
+                )))
+            }
+        };
+        Ok(Self {
+            reader,
+            compression,
+            payload: Vec::new(),
+            frames: 0,
+        })
+    }
+
+    /// The compression recorded in the stream header. The deflate level is
+    /// not recorded and is reported as the default.
+    pub fn compression(&self) -> FrameCompression {
+        self.compression
+    }
+
+    /// Reads the next frame payload into `self.payload`, decompressed.
+    /// Returns false at a clean end of stream.
+    fn read_frame(&mut self) -> Result<bool> {
+        let mut len = [0u8; 4];
+        let mut filled = 0;
+        while filled < len.len() {
+            match self.reader.read(&mut len[filled..])? {
+                0 if filled == 0 => return Ok(false),
+                0 => return Err(self.truncated()),
+                n => filled += n,
+            }
+        }
+        let len = u32::from_le_bytes(len) as u64;
+        let mut raw = Vec::new();
+        (&mut self.reader).take(len).read_to_end(&mut raw)?;
+        if raw.len() as u64 != len {
+            return Err(self.truncated());
+        }
+        self.payload.clear();
+        match self.compression {
+            FrameCompression::None => self.payload = raw,
+            FrameCompression::Deflate(_) => {
+                DeflateDecoder::new(raw.as_slice())
+                    .read_to_end(&mut self.payload)
+                    .map_err(|e| {
+                        SeqKmerError::CompressionError(format!("frame {}: {}", self.frames, e))
+                    })?;
+            }
+        }
+        self.frames += 1;
+        Ok(true)
+    }
+
+    fn truncated(&self) -> SeqKmerError {
+        SeqKmerError::parse(None, format!("frame {} is truncated", self.frames))
+    }
+}
+
+impl<R: Read + Send> Reader for FrameReader<R> {
+    fn next(&mut self) -> Result<Option<Vec<Base<Vec<u8>>>>> {
+        if !self.read_frame()? {
+            return Ok(None);
+        }
+        let frame = self.frames - 1;
+        let invalid =
+            |reason: &str| SeqKmerError::parse(None, format!("frame {}: {}", frame, reason));
+        let mut input = self.payload.as_slice();
+        let count = get_varint(&mut input).ok_or_else(|| invalid("missing record count"))?;
+        let mut seqs = Vec::with_capacity(count.min(1 << 16) as usize);
+        for _ in 0..count {
+            seqs.push(decode_record(&mut input).ok_or_else(|| invalid("malformed record"))?);
+        }
+        if !input.is_empty() {
+            return Err(invalid("trailing bytes after the last record"));
+        }
+        Ok(Some(seqs))
+    }
+}
+
+fn encode_record(out: &mut Vec<u8>, record: &Base<Vec<u8>>) {
+    let header = &record.header;
+    let tags = header.tags.as_ref();
+    let mut flags = 0;
+    for (present, flag) in [
+        (header.chunk.is_some(), HAS_CHUNK),
+        (header.desc.is_some(), HAS_DESC),
+        (tags.is_some(), HAS_TAGS),
+        (header.soft_masked.is_some(), HAS_SOFT_MASKED),
+        (matches!(record.body, OptionPair::Pair(..)), IS_PAIR),
+        (record.qual.is_some(), HAS_QUAL),
+        (tags.is_some_and(|t| t.barcode.is_some()), HAS_BARCODE),
+        (tags.is_some_and(|t| t.umi.is_some()), HAS_UMI),
+    ] {
+        if present {
+            flags |= flag;
+        }
+    }
+    out.push(flags);
+    put_bytes(out, header.id.as_bytes());
+    put_varint(out, header.file_index as u64);
+    put_varint(out, header.reads_index as u64);
+    // 低位是格式, 其上两位是拆分出的单端记录所属的 mate
+    let format = match header.format {
+        SeqFormat::Fasta => 0,
+        SeqFormat::Fastq => 1,
+    };
+    let mate = match header.mate {
+        None => 0,
+        Some(Mate::First) => 1,
+        Some(Mate::Second) => 2,
+    };
+    out.push(format | mate << 1);
+    let (policy, threshold) = match header.quality_policy {
+        QualityPolicy::NoMask => (0, 0),
+        QualityPolicy::MaskBelow(q) => (1, q),
+        QualityPolicy::TrimEnds(q) => (2, q),
+    };
+    out.extend_from_slice(&[policy, threshold]);
+    if let Some(chunk) = header.chunk {
+        for value in [chunk.index, chunk.count, chunk.offset] {
+            put_varint(out, value as u64);
+        }
+    }
+    if let Some(desc) = &header.desc {
+        put_bytes(out, desc.as_bytes());
+    }
+    if let Some(tags) = tags {
+        for value in [&tags.barcode, &tags.umi].into_iter().flatten() {
+            put_bytes(out, value.as_bytes());
+        }
+    }
+    if let Some(count) = header.soft_masked {
+        put_varint(out, count as u64);
+    }
+    for seq in record.body.iter() {
+        put_bytes(out, seq);
+    }
+    if let Some(qual) = &record.qual {
+        for q in qual.iter() {
+            put_bytes(out, q);
+        }
+    }
+}
+
+fn decode_record(input: &mut &[u8]) -> Option<Base<Vec<u8>>> {
+    let flags = get_u8(input)?;
+    let id = get_string(input)?;
+    let file_index = get_varint(input)? as usize;
+    let reads_index = get_varint(input)? as usize;
+    let format_byte = get_u8(input)?;
+    let format = match format_byte & 1 {
+        0 => SeqFormat::Fasta,
+        _ => SeqFormat::Fastq,
+    };
+    let mate = match format_byte >> 1 {
+        0 => None,
+        1 => Some(Mate::First),
+        2 => Some(Mate::Second),
+        _ => return None,
+    };
+    let (policy, threshold) = (get_u8(input)?, get_u8(input)?);
+    let quality_policy = match policy {
+        0 => QualityPolicy::NoMask,
+        1 => QualityPolicy::MaskBelow(threshold),
+        2 => QualityPolicy::TrimEnds(threshold),
+        _ => return None,
+    };
+    let chunk = if flags & HAS_CHUNK != 0 {
+        Some(SeqChunk {
+            index: get_varint(input)? as usize,
+            count: get_varint(input)? as usize,
+            offset: get_varint(input)? as usize,
+        })
+    } else {
+        None
+    };
+    let desc = optional(flags, HAS_DESC, || get_string(input))?;
+    let tags = if flags & HAS_TAGS != 0 {
+        Some(ReadTags {
+            barcode: optional(flags, HAS_BARCODE, || get_string(input))?,
+            umi: optional(flags, HAS_UMI, || get_string(input))?,
+        })
+    } else {
+        None
+    };
+    let soft_masked = optional(flags, HAS_SOFT_MASKED, || {
+        get_varint(input).map(|n| n as usize)
+    })?;
+    let pair = |input: &mut &[u8]| -> Option<OptionPair<Vec<u8>>> {
+        let first = get_bytes(input)?;
+        Some(if flags & IS_PAIR != 0 {
+            OptionPair::Pair(first, get_bytes(input)?)
+        } else {
+            OptionPair::Single(first)
+        })
+    };
+    let body = pair(input)?;
+    let qual = if flags & HAS_QUAL != 0 {
+        Some(pair(input)?)
+    } else {
+        None
+    };
+    Some(Base {
+        header: SeqHeader {
+            id,
+            file_index,
+            reads_index,
+            format,
+            chunk,
+            quality_policy,
+            desc,
+            tags,
+            soft_masked,
+            mate,
+        },
+        body,
+        qual,
+    })
+}
+
+/// Reads an optional field: `Some(None)` if the flag is clear, `None` if
+/// the field is malformed.
+fn optional<T>(flags: u8, flag: u8, read: impl FnOnce() -> Option<T>) -> Option<Option<T>> {
+    if flags & flag != 0 {
+        read().map(Some)
+    } else {
+        Some(None)
+    }
+}
+
+fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn put_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    put_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+fn get_u8(input: &mut &[u8]) -> Option<u8> {
+    let (&byte, rest) = input.split_first()?;
+    *input = rest;
+    Some(byte)
+}
+
+fn get_varint(input: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = get_u8(input)?;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+fn get_bytes(input: &mut &[u8]) -> Option<Vec<u8>> {
+    let len = usize::try_from(get_varint(input)?).ok()?;
+    if len > input.len() {
+        return None;
+    }
+    let (bytes, rest) = input.split_at(len);
+    *input = rest;
+    Some(bytes.to_vec())
+}
+
+fn get_string(input: &mut &[u8]) -> Option<String> {
+    String::from_utf8(get_bytes(input)?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(id: &str, body: OptionPair<Vec<u8>>) -> Base<Vec<u8>> {
+        Base {
+            header: SeqHeader {
+                file_index: 3,
+                reads_index: 300,
+                ..SeqHeader::new(id, SeqFormat::Fastq)
+            },
+            body,
+            qual: None,
+        }
+    }
+
+    type Parts = (SeqHeader, Vec<Vec<u8>>, Option<Vec<Vec<u8>>>);
+
+    fn parts(batch: &[Base<Vec<u8>>]) -> Vec<Parts> {
+        let mates = |pair: &OptionPair<Vec<u8>>| pair.iter().cloned().collect::<Vec<_>>();
+        batch
+            .iter()
+            .map(|r| (r.header.clone(), mates(&r.body), r.qual.as_ref().map(mates)))
+            .collect()
+    }
+
+    #[test]
+    fn test_round_trip_keeps_every_field() {
+        let plain = record("r1", OptionPair::Single(b"ACGT".to_vec()));
+        let mut full = record("r2", OptionPair::Pair(b"ACGTN".to_vec(), b"".to_vec()));
+        full.header.chunk = Some(SeqChunk {
+            index: 1,
+            count: 4,
+            offset: 1 << 40,
+        });
+        full.header.quality_policy = QualityPolicy::TrimEnds(20);
+        full.header.desc = Some("taxid|562".to_string());
+        full.header.tags = Some(ReadTags {
+            barcode: None,
+            umi: Some("ACGTACGT".to_string()),
+        });
+        full.header.soft_masked = Some(2);
+        full.qual = Some(OptionPair::Pair(b"IIIII".to_vec(), b"".to_vec()));
+        let mut orphan = record("r3", OptionPair::Single(b"GG".to_vec()));
+        orphan.header.mate = Some(Mate::Second);
+        let batches = vec![vec![plain, full, orphan], Vec::new()];
+
+        for compression in [FrameCompression::None, FrameCompression::Deflate(1)] {
+            let mut writer = FrameWriter::new(Vec::new()).with_compression(compression);
+            for batch in &batches {
+                writer.write_batch(batch).unwrap();
+            }
+            let stream = writer.into_inner();
+            let mut reader = FrameReader::new(stream.as_slice()).unwrap();
+            for batch in &batches {
+                assert_eq!(parts(&reader.next().unwrap().unwrap()), parts(batch));
+            }
+            assert!(reader.next().unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn test_corrupt_streams_are_errors() {
+        assert!(FrameReader::new(b"FASTA\n".as_slice()).is_err());
+
+        let mut writer = FrameWriter::new(Vec::new());
+        writer.finish().unwrap();
+        let empty = writer.into_inner();
+        assert!(FrameReader::new(empty.as_slice())
+            .unwrap()
+            .next()
+            .unwrap()
+            .is_none());
+
+        let mut writer = FrameWriter::new(Vec::new());
+        let batch = vec![record("r1", OptionPair::Single(b"ACGT".to_vec()))];
+        writer.write_batch(&batch).unwrap();
+        let stream = writer.into_inner();
+        for cut in [stream.len() - 1, 8] {
+            let mut reader = FrameReader::new(&stream[..cut]).unwrap();
+            assert!(matches!(reader.next(), Err(SeqKmerError::Parse { .. })));
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use crate::feat::Meros;
+use crate::mmscanner::{for_each_minimizer, scan_sequence, MinimizerIterator};
+use crate::seq::Base;
+use crate::utils::OptionPair;
+use std::collections::HashMap;
+
+/// Minimizer occurrence counts with a threshold above which a minimizer is
+/// considered over-represented (repeats, adapters, low-complexity regions).
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::MinimizerFrequency;
+///
+/// let mut freq = MinimizerFrequency::new(2);
+/// freq.add(7, 1);
+/// freq.add(9, 8);
+/// assert!(!freq.is_frequent(7));
+/// assert!(freq.is_frequent(9));
+/// assert_eq!(freq.weight(7), 1.0);
+/// assert_eq!(freq.weight(9), 0.25);
+///
+/// let set = MinimizerFrequency::from_hashes([9]);
+/// assert!(set.is_frequent(9) && !set.is_frequent(7));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MinimizerFrequency {
+    pub counts: HashMap<u64, u64>,
+    pub threshold: u64,
+}
+
+impl MinimizerFrequency {
+    /// Creates an empty table; minimizers seen more than `threshold` times
+    /// are frequent.
+    pub fn new(threshold: u64) -> Self {
+        Self {
+            counts: HashMap::new(),
+            threshold,
+        }
+    }
+
+    /// Builds a table from a precomputed set of frequent minimizer hashes.
+    pub fn from_hashes<I: IntoIterator<Item = u64>>(hashes: I) -> Self {
+        let mut freq = Self::new(0);
+        for hash in hashes {
+            freq.add(hash, 1);
+        }
+        freq
+    }
+
+    /// Adds `count` occurrences of a minimizer hash.
+    #[inline]
+    pub fn add(&mut self, hash: u64, count: u64) {
+        *self.counts.entry(hash).or_insert(0) += count;
+    }
+
+    /// Counts every minimizer of a sequence, e.g. of a reference or a
+    /// sample of reads.
+    pub fn add_sequence(&mut self, seq: &[u8], meros: &Meros) {
+        for_each_minimizer(seq, meros, |_, hash| self.add(hash, 1));
+    }
+
+    #[inline]
+    pub fn count(&self, hash: u64) -> u64 {
+        self.counts.get(&hash).copied().unwrap_or(0)
+    }
+
+    #[inline]
+    pub fn is_frequent(&self, hash: u64) -> bool {
+        self.count(hash) > self.threshold
+    }
+
+    /// Weight for down-weighting instead of skipping: 1 up to the
+    /// threshold, then `threshold / count`.
+    #[inline]
+    pub fn weight(&self, hash: u64) -> f64 {
+        let count = self.count(hash);
+        if count <= self.threshold {
+            1.0
+        } else {
+            self.threshold.max(1) as f64 / count as f64
+        }
+    }
+}
+
+/// A `MinimizerIterator` that skips frequent minimizers. Positions are
+/// those of the underlying iterator, so skipped minimizers leave gaps.
+pub struct FrequencyFiltered<'a> {
+    inner: MinimizerIterator<'a>,
+    freq: &'a MinimizerFrequency,
+    /// 被跳过的 minimizer 数
+    pub skipped: usize,
+}
+
+impl FrequencyFiltered<'_> {
+    /// Returns the size of the sequence being processed.
+    pub fn seq_size(&self) -> usize {
+        self.inner.seq_size()
+    }
+}
+
+impl Iterator for FrequencyFiltered<'_> {
+    type Item = (usize, u64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for (pos, hash) in self.inner.by_ref() {
+            if !self.freq.is_frequent(hash) {
+                return Some((pos, hash));
+            }
+            self.skipped += 1;
+        }
+        None
+    }
+}
+
+/// Like `scan_sequence`, but drops minimizers that `freq` marks as
+/// frequent.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{
+///     scan_sequence_filtered, Base, Meros, MinimizerFrequency, OptionPair, SeqFormat, SeqHeader,
+/// };
+///
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+/// let mut freq = MinimizerFrequency::new(3);
+/// // a repeat seen many times
+/// for _ in 0..10 {
+///     freq.add_sequence(b"ATATATATATATATATATAT", &meros);
+/// }
+///
+/// let header = SeqHeader::new("read", SeqFormat::Fasta);
+/// let seq = Base::new(header, OptionPair::Single(b"ATATATATATATATATATAT".to_vec()));
+/// let mut scanned = scan_sequence_filtered(&seq, &meros, &freq);
+/// let OptionPair::Single(iter) = &mut scanned.body else { unreachable!() };
+/// assert_eq!(iter.count(), 0);
+/// ```
+pub fn scan_sequence_filtered<'a>(
+    sequence: &'a Base<Vec<u8>>,
+    meros: &'a Meros,
+    freq: &'a MinimizerFrequency,
+) -> Base<FrequencyFiltered<'a>> {
+    let filtered = |inner| FrequencyFiltered {
+        inner,
+        freq,
+        skipped: 0,
+    };
+    let scanned = scan_sequence(sequence, meros);
+    let body = match scanned.body {
+        OptionPair::Single(inner) => OptionPair::Single(filtered(inner)),
+        OptionPair::Pair(inner1, inner2) => OptionPair::Pair(filtered(inner1), filtered(inner2)),
+    };
+    Base::new(scanned.header, body)
+}
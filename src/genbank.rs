@@ -0,0 +1,442 @@
+use crate::error::{Result, SeqKmerError};
+use crate::feat::revcomp;
+use crate::reader::{dyn_reader, Reader, BUFSIZE};
+use crate::seq::{Base, SeqFormat, SeqHeader};
+use crate::utils::OptionPair;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Definition,
+    Features,
+    Sequence,
+    Other,
+}
+
+/// One feature of the feature table, with the qualifiers used for naming.
+#[derive(Debug, Default)]
+struct Feature {
+    key: String,
+    location: String,
+    in_qualifiers: bool,
+    protein_id: Option<String>,
+    locus_tag: Option<String>,
+}
+
+impl Feature {
+    /// Feeds one feature-table line, minus its five-column prefix.
+    fn push_value(&mut self, value: &str) {
+        if let Some(qualifier) = value.strip_prefix('/') {
+            self.in_qualifiers = true;
+            let (name, text) = qualifier.split_once('=').unwrap_or((qualifier, ""));
+            let text = Some(text.trim_matches('"').to_string());
+            match name {
+                "protein_id" => self.protein_id = text,
+                "locus_tag" => self.locus_tag = text,
+                _ => {}
+            }
+        } else if !self.in_qualifiers {
+            // 位置可能跨多行
+            self.location.push_str(value);
+        }
+    }
+}
+
+/// A parsed flat-file entry.
+#[derive(Debug, Default)]
+struct Entry {
+    name: String,
+    accession: Option<String>,
+    version: Option<String>,
+    desc: Vec<String>,
+    features: Vec<Feature>,
+    seq: Vec<u8>,
+}
+
+impl Entry {
+    fn id(&self) -> &str {
+        self.version
+            .as_deref()
+            .or(self.accession.as_deref())
+            .unwrap_or(&self.name)
+    }
+
+    fn feature_line(&mut self, rest: &str) {
+        if rest.starts_with(|c: char| !c.is_whitespace()) {
+            let (key, value) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            self.features.push(Feature {
+                key: key.to_string(),
+                ..Default::default()
+            });
+            self.features.last_mut().unwrap().push_value(value.trim());
+        } else if let Some(feature) = self.features.last_mut() {
+            feature.push_value(rest.trim());
+        }
+    }
+
+    fn sequence_line(&mut self, line: &str) {
+        self.seq.extend(
+            line.bytes()
+                .filter(u8::is_ascii_alphabetic)
+                .map(|c| c.to_ascii_uppercase()),
+        );
+    }
+}
+
+/// Strips `name(` and the matching `)` from a location.
+fn strip_call<'a>(location: &'a str, name: &str) -> Option<&'a str> {
+    location
+        .strip_prefix(name)?
+        .trim_start()
+        .strip_prefix('(')?
+        .strip_suffix(')')
+}
+
+/// Splits on the commas that are not nested in parentheses.
+fn split_top_level(location: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0usize, 0);
+    for (i, c) in location.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(&location[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&location[start..]);
+    parts
+}
+
+/// Extracts the bases of an INSDC feature location such as `12..45`,
+/// `<1..>200`, `complement(join(1..5,9..20))` or `order(...)`.
+///
+/// Returns `None` for locations this reader cannot resolve from the entry
+/// alone: references to other entries (`J00194.1:100..202`), between-base
+/// sites (`12^13`), or ranges outside the sequence.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::extract_location;
+///
+/// let seq = b"AACCGGTTAC";
+/// assert_eq!(extract_location("3..6", seq).unwrap(), b"CCGG");
+/// assert_eq!(extract_location("complement(1..3)", seq).unwrap(), b"GTT");
+/// assert_eq!(extract_location("join(1..2,<9..>10)", seq).unwrap(), b"AAAC");
+/// assert_eq!(extract_location("X00001.1:1..3", seq), None);
+/// ```
+pub fn extract_location(location: &str, seq: &[u8]) -> Option<Vec<u8>> {
+    let location = location.trim();
+    if let Some(inner) = strip_call(location, "complement") {
+        return extract_location(inner, seq).map(|bases| revcomp(&bases));
+    }
+    if let Some(inner) = strip_call(location, "join").or_else(|| strip_call(location, "order")) {
+        let mut bases = Vec::new();
+        for part in split_top_level(inner) {
+            bases.extend(extract_location(part, seq)?);
+        }
+        return Some(bases);
+    }
+    if location.contains([':', '^']) {
+        return None;
+    }
+
+    // 单个区间: a..b, <a..>b 或单个碱基 a
+    let (start, end) = location.split_once("..").unwrap_or((location, location));
+    let parse = |s: &str| s.trim_matches(['<', '>']).parse::<usize>().ok();
+    let (start, end) = (parse(start)?, parse(end)?);
+    (1 <= start && start <= end && end <= seq.len()).then(|| seq[start - 1..end].to_vec())
+}
+
+/// Reads GenBank and EMBL flat files, as downloaded from NCBI or ENA, and
+/// returns the sequence of each entry (GenBank `ORIGIN`, EMBL `SQ`) as a
+/// FASTA-like record, so reference records can be scanned without first
+/// converting them to FASTA.
+///
+/// The record id is the `VERSION` (accession.version) when present, then the
+/// `ACCESSION`, then the `LOCUS` name; for EMBL it is the `ID` with its `SV`
+/// suffix. The `DEFINITION` / `DE` text becomes `SeqHeader::desc`. Bases
+/// are upper-cased, and entries without sequence (e.g. `CON` records) are
+/// skipped.
+///
+/// With [`with_cds`](Self::with_cds) each entry is followed by one record
+/// per `CDS` feature, see there.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{GenbankReader, Reader};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut reader = GenbankReader::from_path("tests/data/test.gb", 0)?;
+/// let seqs = reader.next()?.unwrap();
+///
+/// assert_eq!(seqs.len(), 2);
+/// assert_eq!(seqs[0].header.id, "TEST0001.1");
+/// assert_eq!(
+///     seqs[0].header.desc.as_deref(),
+///     Some("Test organism chromosome, partial sequence.")
+/// );
+/// assert_eq!(seqs[0].body.single().unwrap().len(), 40);
+/// assert_eq!(seqs[1].header.id, "TEST0002");
+/// assert_eq!(seqs[1].body.single().unwrap(), b"GGGGCCCCAATT");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// EMBL entries are read by the same reader:
+///
+/// ```
+/// use seqkmer::{GenbankReader, Reader};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let embl = "\
+/// ID   X56734; SV 1; linear; mRNA; STD; PLN; 12 BP.
+/// DE   Test mRNA
+/// FT   CDS             4..9
+/// FT                   /protein_id=\"CAA39998.1\"
+/// SQ   Sequence 12 BP;
+///      acgatggcct ag                                                      12
+/// //
+/// ";
+/// let mut reader = GenbankReader::new(embl.as_bytes(), 0).with_cds(true);
+/// let seqs = reader.next()?.unwrap();
+///
+/// assert_eq!(seqs[0].header.id, "X56734.1");
+/// assert_eq!(seqs[0].body.single().unwrap(), b"ACGATGGCCTAG");
+/// assert_eq!(seqs[1].header.id, "CAA39998.1");
+/// assert_eq!(seqs[1].body.single().unwrap(), b"ATGGCC");
+/// # Ok(())
+/// # }
+/// ```
+pub struct GenbankReader<R>
+where
+    R: Read + Send,
+{
+    reader: BufReader<R>,
+    file_index: usize,
+    reads_index: usize,
+    line: String,
+    batch_size: usize,
+    cds: bool,
+    pending: VecDeque<Base<Vec<u8>>>,
+}
+
+impl<R> GenbankReader<R>
+where
+    R: Read + Send,
+{
+    /// Creates a new GenbankReader with the default buffer and batch size.
+    pub fn new(reader: R, file_index: usize) -> Self {
+        Self {
+            reader: BufReader::with_capacity(BUFSIZE, reader),
+            file_index,
+            reads_index: 0,
+            line: String::new(),
+            batch_size: 30,
+            cds: false,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Sets the maximum number of records returned per batch.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Also emits the spliced, strand-corrected sequence of every `CDS`
+    /// feature after its entry. A CDS record is named by its `/protein_id`,
+    /// else its `/locus_tag`, else `<entry id>_cds<n>`; its description is
+    /// the entry id and the location. CDS whose location cannot be resolved
+    /// (see [`extract_location`]) are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{GenbankReader, Reader};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut reader = GenbankReader::from_path("tests/data/test.gb", 0)?.with_cds(true);
+    /// let seqs = reader.next()?.unwrap();
+    ///
+    /// let ids: Vec<_> = seqs.iter().map(|s| s.header.id.as_str()).collect();
+    /// assert_eq!(ids, ["TEST0001.1", "TP_0001.1", "TP_0002.1", "TEST0002"]);
+    /// assert_eq!(seqs[1].body.single().unwrap(), b"GATGCCCGG");
+    /// // complement(join(20..25,31..36))
+    /// assert_eq!(seqs[2].body.single().unwrap(), b"GGGTTTTACGTA");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_cds(mut self, cds: bool) -> Self {
+        self.cds = cds;
+        self
+    }
+
+    /// Parses the next entry, up to its `//` terminator or the end of input.
+    fn read_entry(&mut self) -> Result<Option<Entry>> {
+        let mut entry = Entry::default();
+        let mut section = Section::Other;
+        let mut started = false;
+        let mut embl = false;
+
+        loop {
+            self.line.clear();
+            if self.reader.read_line(&mut self.line)? == 0 {
+                return Ok(started.then_some(entry));
+            }
+            let line = self.line.trim_end_matches(['\n', '\r']);
+            if line.starts_with("//") {
+                if started {
+                    return Ok(Some(entry));
+                }
+                continue;
+            }
+            if !started {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                embl = line.starts_with("ID ");
+                if !embl && !line.starts_with("LOCUS") {
+                    return Err(SeqKmerError::parse(
+                        None,
+                        format!("expected a LOCUS or ID line, found: {}", line),
+                    ));
+                }
+                started = true;
+            }
+
+            if embl {
+                // EMBL: 两字母行代码, 内容从第 6 列开始
+                let code = line.get(..2).unwrap_or(line);
+                let body = line.get(5..).unwrap_or("");
+                match code {
+                    "ID" => {
+                        let mut fields = body.split(';').map(str::trim);
+                        let name = fields.next().unwrap_or("").to_string();
+                        entry.version = fields
+                            .next()
+                            .and_then(|f| f.strip_prefix("SV "))
+                            .map(|sv| format!("{}.{}", name, sv.trim()));
+                        entry.name = name;
+                    }
+                    "AC" if entry.accession.is_none() => {
+                        entry.accession = body.split(';').next().map(|a| a.trim().to_string());
+                    }
+                    "DE" => entry.desc.push(body.trim().to_string()),
+                    "FT" => entry.feature_line(body),
+                    "SQ" => section = Section::Sequence,
+                    "  " if section == Section::Sequence => entry.sequence_line(line),
+                    _ => {}
+                }
+                continue;
+            }
+
+            if line.starts_with(char::is_whitespace) {
+                match section {
+                    Section::Definition => entry.desc.push(line.trim().to_string()),
+                    Section::Features => entry.feature_line(line.get(5..).unwrap_or("")),
+                    Section::Sequence => entry.sequence_line(line),
+                    Section::Other => {}
+                }
+                continue;
+            }
+            let (keyword, body) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let first_word = body.split_whitespace().next().map(str::to_string);
+            section = Section::Other;
+            match keyword {
+                "LOCUS" => entry.name = first_word.unwrap_or_default(),
+                "DEFINITION" => {
+                    entry.desc.push(body.trim().to_string());
+                    section = Section::Definition;
+                }
+                "ACCESSION" => entry.accession = first_word,
+                "VERSION" => entry.version = first_word,
+                "FEATURES" => section = Section::Features,
+                "ORIGIN" => section = Section::Sequence,
+                _ => {}
+            }
+        }
+    }
+
+    fn header(&mut self, id: String, desc: Option<String>) -> SeqHeader {
+        self.reads_index += 1;
+        SeqHeader {
+            file_index: self.file_index,
+            reads_index: self.reads_index,
+            desc,
+            ..SeqHeader::new(id, SeqFormat::Fasta)
+        }
+    }
+
+    /// Queues the records of the next entry that has a sequence.
+    fn fill_pending(&mut self) -> Result<bool> {
+        while let Some(entry) = self.read_entry()? {
+            if entry.seq.is_empty() {
+                continue;
+            }
+            let id = entry.id().to_string();
+            let desc = entry.desc.join(" ");
+            let desc = (!desc.is_empty()).then_some(desc);
+
+            let header = self.header(id.clone(), desc);
+            let mut records = Vec::new();
+            if self.cds {
+                let cds = entry.features.iter().filter(|f| f.key == "CDS");
+                for (cds_index, feature) in cds.enumerate() {
+                    let Some(bases) = extract_location(&feature.location, &entry.seq) else {
+                        continue;
+                    };
+                    let cds_id = feature
+                        .protein_id
+                        .clone()
+                        .or_else(|| feature.locus_tag.clone())
+                        .unwrap_or_else(|| format!("{}_cds{}", id, cds_index + 1));
+                    let cds_desc = format!("{} {}", id, feature.location);
+                    let cds_header = self.header(cds_id, Some(cds_desc));
+                    records.push(Base::new(cds_header, OptionPair::Single(bases)));
+                }
+            }
+            // 整条序列排在它的 CDS 之前
+            self.pending
+                .push_back(Base::new(header, OptionPair::Single(entry.seq)));
+            self.pending.extend(records);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+}
+
+impl GenbankReader<Box<dyn Read + Send>> {
+    /// Creates a new GenbankReader from a file path; gzipped files are
+    /// decompressed.
+    #[inline]
+    pub fn from_path<P: AsRef<Path>>(path: P, file_index: usize) -> Result<Self> {
+        let reader = dyn_reader(path)?;
+        Ok(Self::new(reader, file_index))
+    }
+}
+
+impl<R: Read + Send> Reader for GenbankReader<R> {
+    fn next(&mut self) -> Result<Option<Vec<Base<Vec<u8>>>>> {
+        let mut seqs = Vec::new();
+        while seqs.len() < self.batch_size {
+            match self.pending.pop_front() {
+                Some(seq) => seqs.push(seq),
+                None if self.fill_pending()? => {}
+                None => break,
+            }
+        }
+        Ok(if seqs.is_empty() { None } else { Some(seqs) })
+    }
+
+    fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size.max(1);
+    }
+}
@@ -0,0 +1,535 @@
+use crate::export::invalid_data;
+use crate::feat::{Alphabet, IupacPolicy, Meros};
+use crate::lookup::MinimizerLookup;
+use crate::reader::open_file;
+use std::io::{BufReader, Read, Result, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+// 以下仅 build_index 使用, wasm32 下没有并行驱动
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+    fasta::FastaReader,
+    parallel::{read_parallel, ParallelResult},
+    seq::{Base, SeqHeader},
+    MinimizerIterator,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{io, path::PathBuf, sync::atomic::AtomicBool};
+
+/// Magic bytes opening an index file written by [`CompactHashTable::write_to`].
+pub const INDEX_MAGIC: &[u8; 4] = b"SKIX";
+/// Current version of the index file layout.
+pub const INDEX_VERSION: u32 = 2;
+
+/// Open-addressing hash table of 32-bit cells, laid out like Kraken2's
+/// compact hash table: each cell holds the high bits of the minimizer hash
+/// above a `value_bits`-wide value, and 0 marks an empty cell. Values must
+/// be non-zero and fit in `value_bits`.
+///
+/// Keys are the hashes yielded by `MinimizerIterator`; the slot is
+/// `hash % capacity`, probed linearly. Insertion is lock-free, so worker
+/// threads can fill one table concurrently.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{CompactHashTable, MinimizerLookup};
+///
+/// let table = CompactHashTable::new(64, 16);
+/// let keep_first = |old: u32, _new: u32| old;
+/// assert!(table.upsert(0xdead_beef_0000_1234, 562, keep_first));
+/// assert!(table.upsert(0xdead_beef_0000_1234, 620, keep_first));
+///
+/// assert_eq!(table.get(0xdead_beef_0000_1234), Some(562));
+/// assert_eq!(table.get(42), None);
+/// assert_eq!(table.len(), 1);
+/// ```
+#[derive(Debug)]
+pub struct CompactHashTable {
+    cells: Vec<AtomicU32>,
+    value_bits: u32,
+    size: AtomicUsize,
+}
+
+impl CompactHashTable {
+    /// Creates an empty table of `capacity` cells.
+    pub fn new(capacity: usize, value_bits: u32) -> Self {
+        assert!(capacity > 0, "capacity must be positive");
+        assert!(
+            value_bits > 0 && value_bits < 32,
+            "value_bits must be in 1..32"
+        );
+        Self {
+            cells: (0..capacity).map(|_| AtomicU32::new(0)).collect(),
+            value_bits,
+            size: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn from_cells(cells: Vec<u32>, value_bits: u32) -> Self {
+        let size = cells.iter().filter(|&&cell| cell != 0).count();
+        Self {
+            cells: cells.into_iter().map(AtomicU32::new).collect(),
+            value_bits,
+            size: AtomicUsize::new(size),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Number of occupied cells.
+    pub fn len(&self) -> usize {
+        self.size.load(Ordering::Relaxed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn value_bits(&self) -> u32 {
+        self.value_bits
+    }
+
+    pub fn key_bits(&self) -> u32 {
+        32 - self.value_bits
+    }
+
+    #[inline]
+    fn value_mask(&self) -> u32 {
+        (1u32 << self.value_bits) - 1
+    }
+
+    /// 哈希值的高位, 存在单元的 key 部分
+    #[inline]
+    fn compacted_key(&self, hash: u64) -> u32 {
+        (hash >> (32 + self.value_bits)) as u32
+    }
+
+    /// Returns the value stored for `hash`, or 0 if there is none.
+    #[inline]
+    pub fn get_value(&self, hash: u64) -> u32 {
+        let capacity = self.cells.len();
+        let key = self.compacted_key(hash);
+        let first = (hash % capacity as u64) as usize;
+        let mut idx = first;
+        loop {
+            let cell = self.cells[idx].load(Ordering::Relaxed);
+            if cell == 0 {
+                return 0;
+            }
+            if cell >> self.value_bits == key {
+                return cell & self.value_mask();
+            }
+            idx = (idx + 1) % capacity;
+            if idx == first {
+                return 0;
+            }
+        }
+    }
+
+    /// Inserts `value` for `hash`, or replaces the stored value with
+    /// `merge(old, value)` (e.g. the LCA of two taxids). Returns false if
+    /// the table is full.
+    pub fn upsert<M>(&self, hash: u64, value: u32, merge: M) -> bool
+    where
+        M: Fn(u32, u32) -> u32,
+    {
+        let mask = self.value_mask();
+        assert!(value != 0 && value <= mask, "value out of range");
+        let capacity = self.cells.len();
+        let key = self.compacted_key(hash);
+        let first = (hash % capacity as u64) as usize;
+        let mut idx = first;
+        loop {
+            let cell = self.cells[idx].load(Ordering::Relaxed);
+            if cell == 0 {
+                let new_cell = (key << self.value_bits) | value;
+                if self.cells[idx]
+                    .compare_exchange(0, new_cell, Ordering::Relaxed, Ordering::Relaxed)
+                    .is_ok()
+                {
+                    self.size.fetch_add(1, Ordering::Relaxed);
+                    return true;
+                }
+                // 被其他线程抢先写入, 重新检查这个单元
+                continue;
+            }
+            if cell >> self.value_bits == key {
+                let merged = merge(cell & mask, value) & mask;
+                let new_cell = (key << self.value_bits) | merged;
+                if new_cell == cell
+                    || self.cells[idx]
+                        .compare_exchange(cell, new_cell, Ordering::Relaxed, Ordering::Relaxed)
+                        .is_ok()
+                {
+                    return true;
+                }
+                continue;
+            }
+            idx = (idx + 1) % capacity;
+            if idx == first {
+                return false;
+            }
+        }
+    }
+
+    pub(crate) fn cells(&self) -> impl Iterator<Item = u32> + '_ {
+        self.cells.iter().map(|cell| cell.load(Ordering::Relaxed))
+    }
+
+    /// Serializes the table with a versioned header: `INDEX_MAGIC`,
+    /// `INDEX_VERSION` as a little-endian u32, then as little-endian u64s
+    /// the k-mer and l-mer lengths, spaced seed mask, toggle mask, alphabet,
+    /// homopolymer compression flag, minimum entropy (f64 bits, `u64::MAX`
+    /// when unset) and IUPAC policy (tag and seed) of `meros`, the capacity,
+    /// size and value bits, followed by the cells as little-endian u32s.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{CompactHashTable, Meros};
+    ///
+    /// let meros = Meros::new(35, 31, None, None, None);
+    /// let table = CompactHashTable::new(16, 20);
+    /// table.upsert(12345, 9606, |old, _| old);
+    ///
+    /// let mut out = Vec::new();
+    /// table.write_to(&mut out, &meros).unwrap();
+    /// let (loaded, header) = CompactHashTable::read_from(out.as_slice()).unwrap();
+    /// assert_eq!(loaded.get_value(12345), 9606);
+    /// assert_eq!(header.k_mer, 35);
+    /// assert_eq!(header.toggle_mask, meros.toggle_mask);
+    /// assert!(header.matches(&meros));
+    /// assert!(!header.matches(&meros.with_hpc(true)));
+    /// ```
+    pub fn write_to<W: Write>(&self, mut out: W, meros: &Meros) -> Result<()> {
+        out.write_all(INDEX_MAGIC)?;
+        out.write_all(&INDEX_VERSION.to_le_bytes())?;
+        let (iupac_tag, iupac_seed) = iupac_to_words(meros.iupac);
+        for word in [
+            meros.k_mer as u64,
+            meros.l_mer as u64,
+            meros.spaced_seed_mask,
+            meros.toggle_mask,
+            alphabet_to_word(meros.alphabet),
+            meros.hpc as u64,
+            meros.min_entropy.map_or(NO_ENTROPY, f64::to_bits),
+            iupac_tag,
+            iupac_seed,
+            self.capacity() as u64,
+            self.len() as u64,
+            self.value_bits as u64,
+        ] {
+            out.write_all(&word.to_le_bytes())?;
+        }
+        for cell in self.cells() {
+            out.write_all(&cell.to_le_bytes())?;
+        }
+        out.flush()
+    }
+
+    /// Loads a table written by [`write_to`](Self::write_to), returning it
+    /// with the scanner settings it was built with. The whole table is read
+    /// into memory.
+    pub fn read_from<R: Read>(input: R) -> Result<(Self, IndexHeader)> {
+        let mut input = BufReader::new(input);
+        let mut magic = [0u8; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != INDEX_MAGIC {
+            return Err(invalid_data("not a seqkmer index file".to_string()));
+        }
+        let mut version = [0u8; 4];
+        input.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version != INDEX_VERSION {
+            return Err(invalid_data(format!(
+                "unsupported index version {}",
+                version
+            )));
+        }
+        let mut words = [0u64; 12];
+        for word in words.iter_mut() {
+            *word = read_u64(&mut input)?;
+        }
+        let [k_mer, l_mer, spaced_seed_mask, toggle_mask, alphabet, hpc, min_entropy, iupac_tag, iupac_seed, capacity, _size, value_bits] =
+            words;
+        if capacity == 0 || value_bits == 0 || value_bits >= 32 || hpc > 1 {
+            return Err(invalid_data("corrupt index header".to_string()));
+        }
+        let (Some(alphabet), Some(iupac)) = (
+            alphabet_from_word(alphabet),
+            iupac_from_words(iupac_tag, iupac_seed),
+        ) else {
+            return Err(invalid_data("corrupt index header".to_string()));
+        };
+        let cells = read_cells(&mut input, capacity as usize)?;
+        let header = IndexHeader {
+            k_mer: k_mer as usize,
+            l_mer: l_mer as usize,
+            spaced_seed_mask,
+            toggle_mask,
+            alphabet,
+            hpc: hpc == 1,
+            min_entropy: Some(min_entropy)
+                .filter(|&bits| bits != NO_ENTROPY)
+                .map(f64::from_bits),
+            iupac,
+        };
+        Ok((Self::from_cells(cells, value_bits as u32), header))
+    }
+}
+
+impl CompactHashTable {
+    /// Writes the table in Kraken2's `hash.k2d` layout: capacity, size,
+    /// key bits and value bits as little-endian u64s, then the cells.
+    ///
+    /// Kraken2 stores its internal taxon ids (the node order of
+    /// `taxo.k2d`) as values, not NCBI taxids; a table meant for Kraken2
+    /// must be filled accordingly.
+    ///
+    /// Interoperability is unverified: the layout follows Kraken2's
+    /// `CompactHashTable` source, but no `hash.k2d` written by a Kraken2 build
+    /// is checked in as test data, and no file written here has been loaded
+    /// by Kraken2. The tests only round-trip through this crate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::CompactHashTable;
+    ///
+    /// let table = CompactHashTable::new(8, 22);
+    /// table.upsert(0x0123_4567_89ab_cdef, 17, |old, _| old);
+    ///
+    /// let mut out = Vec::new();
+    /// table.write_k2d(&mut out).unwrap();
+    /// assert_eq!(out.len(), 4 * 8 + 8 * 4);
+    /// assert_eq!(&out[16..24], &10u64.to_le_bytes()); // key bits
+    ///
+    /// let loaded = CompactHashTable::read_k2d(out.as_slice()).unwrap();
+    /// assert_eq!(loaded.get_value(0x0123_4567_89ab_cdef), 17);
+    /// assert_eq!(loaded.len(), 1);
+    /// ```
+    pub fn write_k2d<W: Write>(&self, mut out: W) -> Result<()> {
+        for word in [
+            self.capacity() as u64,
+            self.len() as u64,
+            self.key_bits() as u64,
+            self.value_bits as u64,
+        ] {
+            out.write_all(&word.to_le_bytes())?;
+        }
+        for cell in self.cells() {
+            out.write_all(&cell.to_le_bytes())?;
+        }
+        out.flush()
+    }
+
+    /// Reads a Kraken2 `hash.k2d` table. Its values can be looked up with
+    /// the hashes `scan_sequence` yields for a `Meros` configured like the
+    /// Kraken2 database (see `opts.k2d`). Reading real Kraken2 output is
+    /// untested; see [`write_k2d`](Self::write_k2d).
+    pub fn read_k2d<R: Read>(input: R) -> Result<Self> {
+        let mut input = BufReader::new(input);
+        let capacity = read_u64(&mut input)?;
+        let size = read_u64(&mut input)?;
+        let key_bits = read_u64(&mut input)?;
+        let value_bits = read_u64(&mut input)?;
+        if capacity == 0 || value_bits == 0 || key_bits + value_bits != 32 {
+            return Err(invalid_data(format!(
+                "invalid hash.k2d header: capacity {}, key bits {}, value bits {}",
+                capacity, key_bits, value_bits
+            )));
+        }
+        let cells = read_cells(&mut input, capacity as usize)?;
+        let table = Self::from_cells(cells, value_bits as u32);
+        if table.len() as u64 != size {
+            return Err(invalid_data(format!(
+                "hash.k2d declares {} entries but holds {}",
+                size,
+                table.len()
+            )));
+        }
+        Ok(table)
+    }
+
+    /// Opens a Kraken2 `hash.k2d` file.
+    pub fn from_k2d_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::read_k2d(open_file(path)?)
+    }
+}
+
+pub(crate) fn read_u64<R: Read>(input: &mut R) -> Result<u64> {
+    let mut word = [0u8; 8];
+    input.read_exact(&mut word)?;
+    Ok(u64::from_le_bytes(word))
+}
+
+/// Reads `capacity` little-endian u32 cells, decoding them through a
+/// fixed-size buffer so peak memory stays at the table itself.
+///
+/// Tables are always copied into memory rather than mapped: the cells are
+/// `AtomicU32`s that `upsert` writes to, so they need owned, writable memory,
+/// and a read-only mapping of the file could not back them.
+pub(crate) fn read_cells<R: Read>(input: &mut R, capacity: usize) -> Result<Vec<u32>> {
+    const CHUNK_CELLS: usize = 16 * 1024;
+    let mut cells = Vec::with_capacity(capacity);
+    let mut buf = [0u8; CHUNK_CELLS * 4];
+    while cells.len() < capacity {
+        let n = (capacity - cells.len()).min(CHUNK_CELLS);
+        let bytes = &mut buf[..n * 4];
+        input.read_exact(bytes)?;
+        cells.extend(
+            bytes
+                .chunks_exact(4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+        );
+    }
+    Ok(cells)
+}
+
+impl MinimizerLookup for CompactHashTable {
+    type Value = u64;
+
+    #[inline]
+    fn get(&self, hash: u64) -> Option<u64> {
+        Some(self.get_value(hash) as u64).filter(|&value| value != 0)
+    }
+}
+
+/// Scanner settings stored in an index file header.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IndexHeader {
+    pub k_mer: usize,
+    pub l_mer: usize,
+    pub spaced_seed_mask: u64,
+    pub toggle_mask: u64,
+    pub alphabet: Alphabet,
+    pub hpc: bool,
+    pub min_entropy: Option<f64>,
+    pub iupac: IupacPolicy,
+}
+
+impl IndexHeader {
+    /// Returns true if `meros` produces the minimizers the index was built
+    /// with.
+    pub fn matches(&self, meros: &Meros) -> bool {
+        self.k_mer == meros.k_mer
+            && self.l_mer == meros.l_mer
+            && self.spaced_seed_mask == meros.spaced_seed_mask
+            && self.toggle_mask == meros.toggle_mask
+            && self.alphabet == meros.alphabet
+            && self.hpc == meros.hpc
+            && self.min_entropy == meros.min_entropy
+            && self.iupac == meros.iupac
+    }
+}
+
+// 未设置最小熵时写入的值, 不是任何合法熵值的位模式
+const NO_ENTROPY: u64 = u64::MAX;
+
+fn alphabet_to_word(alphabet: Alphabet) -> u64 {
+    match alphabet {
+        Alphabet::Dna => 0,
+        Alphabet::Protein => 1,
+        Alphabet::Reduced15 => 2,
+        Alphabet::Reduced9 => 3,
+    }
+}
+
+fn alphabet_from_word(word: u64) -> Option<Alphabet> {
+    match word {
+        0 => Some(Alphabet::Dna),
+        1 => Some(Alphabet::Protein),
+        2 => Some(Alphabet::Reduced15),
+        3 => Some(Alphabet::Reduced9),
+        _ => None,
+    }
+}
+
+fn iupac_to_words(policy: IupacPolicy) -> (u64, u64) {
+    match policy {
+        IupacPolicy::Reset => (0, 0),
+        IupacPolicy::Skip => (1, 0),
+        IupacPolicy::RandomAssign(seed) => (2, seed),
+    }
+}
+
+fn iupac_from_words(tag: u64, seed: u64) -> Option<IupacPolicy> {
+    match tag {
+        0 => Some(IupacPolicy::Reset),
+        1 => Some(IupacPolicy::Skip),
+        2 => Some(IupacPolicy::RandomAssign(seed)),
+        _ => None,
+    }
+}
+
+/// Scans reference FASTA files with `read_parallel` and inserts every
+/// minimizer into `table`. The value of a record comes from `value_of`
+/// (a reference id derived from `file_index`, a taxid looked up from the
+/// header, ...); records mapped to 0 are skipped. When a minimizer is
+/// already present its value becomes `merge(old, new)`.
+///
+/// Fails with `OutOfMemory` if the table fills up.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{build_index, CompactHashTable, Meros, MinimizerLookup};
+/// use std::path::PathBuf;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+/// let table = CompactHashTable::new(1 << 12, 16);
+/// let files = [PathBuf::from("tests/data/test.fasta")];
+///
+/// // 每个参考文件的编号 + 1 作为值
+/// let value_of = |header: &seqkmer::SeqHeader| header.file_index as u32 + 1;
+/// build_index(&files, 4, &meros, &table, value_of, |old, _| old)?;
+/// assert!(!table.is_empty());
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(not(target_arch = "wasm32"))]
+pub fn build_index<V, M>(
+    files: &[PathBuf],
+    n_threads: usize,
+    meros: &Meros,
+    table: &CompactHashTable,
+    value_of: V,
+    merge: M,
+) -> Result<()>
+where
+    V: Fn(&SeqHeader) -> u32 + Send + Sync,
+    M: Fn(u32, u32) -> u32 + Send + Sync,
+{
+    let full = AtomicBool::new(false);
+    for (file_index, path) in files.iter().enumerate() {
+        let mut reader = FastaReader::from_path(path, file_index)?;
+        let work = |seqs: &mut Vec<Base<MinimizerIterator>>| {
+            for seq in seqs.iter_mut() {
+                let value = value_of(&seq.header);
+                if value == 0 {
+                    continue;
+                }
+                for m_iter in seq.body.iter_mut() {
+                    for (_, hash) in m_iter {
+                        if !table.upsert(hash, value, &merge) {
+                            full.store(true, Ordering::Relaxed);
+                        }
+                    }
+                }
+            }
+        };
+        let func = |result: &mut ParallelResult<()>| while result.next().is_some() {};
+        read_parallel(&mut reader, n_threads, meros, work, func)?;
+        if full.load(Ordering::Relaxed) {
+            return Err(io::Error::new(
+                io::ErrorKind::OutOfMemory,
+                "compact hash table is full",
+            ));
+        }
+    }
+    Ok(())
+}
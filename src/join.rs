@@ -0,0 +1,282 @@
+use crate::error::{Result, SeqKmerError};
+use crate::mmscanner::Mate;
+use crate::reader::{trim_pair_info_with, PairSuffixes, Reader};
+use crate::seq::Base;
+use crate::utils::OptionPair;
+use std::collections::{HashMap, VecDeque};
+
+/// Records of one mate file still waiting for their mate.
+#[derive(Default)]
+struct Pending {
+    records: HashMap<String, Base<Vec<u8>>>,
+    /// 按到达顺序排列的名字, 用于淘汰最早的记录; 可能含已配对的名字
+    order: VecDeque<String>,
+}
+
+impl Pending {
+    /// Drops names of records that were joined, once they dominate `order`.
+    fn compact(&mut self) {
+        if self.order.len() > 2 * self.records.len() + 64 {
+            let records = &self.records;
+            self.order.retain(|name| records.contains_key(name));
+        }
+    }
+}
+
+/// Pairs the records of two single-end readers by read name, for mate files
+/// that are out of order or lost records independently, e.g. after separate
+/// filtering. Records wait in memory until their mate arrives; a record
+/// whose mate never arrives, or that is evicted by
+/// [`with_max_pending`](Self::with_max_pending), becomes an orphan.
+///
+/// Names are compared after stripping the mate suffixes. Joined records
+/// take the header of the first mate and are numbered in the order they
+/// are completed, so the output order follows the slower file.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{FastqReader, Mate, OptionPair, PairJoiner, Reader};
+///
+/// # fn main() -> Result<(), seqkmer::SeqKmerError> {
+/// let r1 = b"@a/1\nAAAA\n+\nIIII\n@b/1\nCCCC\n+\nIIII\n@c/1\nGGGG\n+\nIIII\n".as_slice();
+/// let r2 = b"@c/2\nTTGG\n+\nIIII\n@a/2\nTTAA\n+\nIIII\n@d/2\nACAC\n+\nIIII\n".as_slice();
+/// let mut joiner = PairJoiner::new(
+///     FastqReader::new(OptionPair::Single(r1), 0, 0),
+///     FastqReader::new(OptionPair::Single(r2), 0, 0),
+/// );
+///
+/// let mut ids = Vec::new();
+/// while let Some(pairs) = joiner.next()? {
+///     for pair in pairs {
+///         assert!(pair.body.is_pair());
+///         ids.push(pair.header.id);
+///     }
+/// }
+/// ids.sort();
+/// assert_eq!(ids, ["a", "c"]);
+///
+/// let orphans = joiner.take_orphans();
+/// let names: Vec<_> = orphans.iter().map(|(mate, r)| (*mate, r.header.id.as_str())).collect();
+/// assert_eq!(names, [(Mate::First, "b"), (Mate::Second, "d")]);
+/// assert_eq!(joiner.orphan_count(), 2);
+/// # Ok(())
+/// # }
+/// ```
+pub struct PairJoiner<R1: Reader, R2: Reader> {
+    reader1: Option<R1>,
+    reader2: Option<R2>,
+    pending: [Pending; 2],
+    max_pending: Option<usize>,
+    pair_suffixes: PairSuffixes,
+    orphans: Vec<(Mate, Base<Vec<u8>>)>,
+    orphan_count: usize,
+    ready: Vec<Base<Vec<u8>>>,
+    reads_index: usize,
+    batch_size: usize,
+}
+
+impl<R1: Reader, R2: Reader> PairJoiner<R1, R2> {
+    pub fn new(reader1: R1, reader2: R2) -> Self {
+        Self {
+            reader1: Some(reader1),
+            reader2: Some(reader2),
+            pending: Default::default(),
+            max_pending: None,
+            pair_suffixes: PairSuffixes::default(),
+            orphans: Vec::new(),
+            orphan_count: 0,
+            ready: Vec::new(),
+            reads_index: 0,
+            batch_size: 30,
+        }
+    }
+
+    /// Bounds the records waiting for a mate, per file. When the bound is
+    /// exceeded the oldest waiting record becomes an orphan, so a mate
+    /// further away than this is not joined. Unbounded by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{FastaReader, PairJoiner, Reader};
+    ///
+    /// # fn main() -> Result<(), seqkmer::SeqKmerError> {
+    /// let r1 = b">a/1\nAC\n>b/1\nAC\n>c/1\nAC\n".as_slice();
+    /// let r2 = b">c/2\nGT\n>b/2\nGT\n>a/2\nGT\n".as_slice();
+    /// let mut joiner = PairJoiner::new(FastaReader::new(r1, 0), FastaReader::new(r2, 0))
+    ///     .with_max_pending(1);
+    /// let pairs = joiner.next()?.unwrap();
+    /// // a 和 b 等待时被挤出, 只有 c 配对成功
+    /// assert_eq!(pairs.len(), 1);
+    /// assert_eq!(pairs[0].header.id, "c");
+    /// assert_eq!(joiner.orphan_count(), 4);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_max_pending(mut self, max_pending: usize) -> Self {
+        self.max_pending = Some(max_pending.max(1));
+        self
+    }
+
+    /// Sets which mate suffixes are stripped before comparing names.
+    pub fn with_pair_suffixes(mut self, pair_suffixes: PairSuffixes) -> Self {
+        self.pair_suffixes = pair_suffixes;
+        self
+    }
+
+    /// Returns the orphans found so far, in the order they were found, and
+    /// clears them. All remaining unmatched records are orphaned once both
+    /// readers are exhausted.
+    pub fn take_orphans(&mut self) -> Vec<(Mate, Base<Vec<u8>>)> {
+        std::mem::take(&mut self.orphans)
+    }
+
+    /// Total number of orphans found, including those already taken.
+    pub fn orphan_count(&self) -> usize {
+        self.orphan_count
+    }
+
+    /// Records currently waiting for their mate, per file.
+    pub fn pending(&self) -> (usize, usize) {
+        (self.pending[0].records.len(), self.pending[1].records.len())
+    }
+
+    fn orphan(&mut self, mate: Mate, record: Base<Vec<u8>>) {
+        self.orphan_count += 1;
+        self.orphans.push((mate, record));
+    }
+
+    /// Joins `record` of `mate` with its waiting mate, or queues it.
+    fn add(&mut self, mate: Mate, record: Base<Vec<u8>>) -> Result<()> {
+        if record.body.is_pair() {
+            return Err(SeqKmerError::PairMismatch(format!(
+                "{} is already paired; PairJoiner takes single-end readers",
+                record.header.id
+            )));
+        }
+        let (own, other) = match mate {
+            Mate::First => (0, 1),
+            Mate::Second => (1, 0),
+        };
+        let name = trim_pair_info_with(&record.header.id, self.pair_suffixes);
+        if let Some(waiting) = self.pending[other].records.remove(&name) {
+            let (first, second) = match mate {
+                Mate::First => (record, waiting),
+                Mate::Second => (waiting, record),
+            };
+            self.join(name, first, second);
+            self.pending[other].compact();
+            return Ok(());
+        }
+        // 另一个文件已读完, 不会再有配对
+        let other_done = match mate {
+            Mate::First => self.reader2.is_none(),
+            Mate::Second => self.reader1.is_none(),
+        };
+        if other_done {
+            self.orphan(mate, record);
+            return Ok(());
+        }
+        // 同一文件内的重名记录: 先到的成为孤儿
+        if let Some(previous) = self.pending[own].records.insert(name.clone(), record) {
+            self.orphan(mate, previous);
+        } else {
+            self.pending[own].order.push_back(name);
+        }
+        if self
+            .max_pending
+            .is_some_and(|max| self.pending[own].records.len() > max)
+        {
+            self.evict_oldest(mate, own);
+        }
+        Ok(())
+    }
+
+    fn evict_oldest(&mut self, mate: Mate, index: usize) {
+        while let Some(name) = self.pending[index].order.pop_front() {
+            if let Some(record) = self.pending[index].records.remove(&name) {
+                self.orphan(mate, record);
+                return;
+            }
+        }
+    }
+
+    fn join(&mut self, name: String, first: Base<Vec<u8>>, second: Base<Vec<u8>>) {
+        self.reads_index += 1;
+        let mut header = first.header;
+        header.id = name;
+        header.reads_index = self.reads_index;
+        let qual = match (first.qual, second.qual) {
+            (Some(OptionPair::Single(q1)), Some(OptionPair::Single(q2))) => {
+                Some(OptionPair::Pair(q1, q2))
+            }
+            _ => None,
+        };
+        let body = OptionPair::Pair(
+            first.body.into_iter().next().unwrap_or_default(),
+            second.body.into_iter().next().unwrap_or_default(),
+        );
+        let mut pair = Base::new(header, body);
+        pair.qual = qual;
+        self.ready.push(pair);
+    }
+
+    /// Orphans every waiting record, file 1 first, in arrival order.
+    fn flush_pending(&mut self) {
+        for (index, mate) in [(0, Mate::First), (1, Mate::Second)] {
+            while let Some(name) = self.pending[index].order.pop_front() {
+                if let Some(record) = self.pending[index].records.remove(&name) {
+                    self.orphan(mate, record);
+                }
+            }
+        }
+    }
+}
+
+impl<R1: Reader, R2: Reader> Reader for PairJoiner<R1, R2> {
+    fn next(&mut self) -> Result<Option<Vec<Base<Vec<u8>>>>> {
+        // 两个文件交替读取, 让等待配对的记录尽量少
+        while self.ready.len() < self.batch_size
+            && (self.reader1.is_some() || self.reader2.is_some())
+        {
+            if let Some(reader) = self.reader1.as_mut() {
+                match reader.next()? {
+                    Some(seqs) => {
+                        for record in seqs {
+                            self.add(Mate::First, record)?;
+                        }
+                    }
+                    None => self.reader1 = None,
+                }
+            }
+            if let Some(reader) = self.reader2.as_mut() {
+                match reader.next()? {
+                    Some(seqs) => {
+                        for record in seqs {
+                            self.add(Mate::Second, record)?;
+                        }
+                    }
+                    None => self.reader2 = None,
+                }
+            }
+            if self.reader1.is_none() && self.reader2.is_none() {
+                self.flush_pending();
+            }
+        }
+        if self.ready.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(std::mem::take(&mut self.ready)))
+    }
+
+    fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size.max(1);
+        if let Some(reader) = self.reader1.as_mut() {
+            reader.set_batch_size(batch_size);
+        }
+        if let Some(reader) = self.reader2.as_mut() {
+            reader.set_batch_size(batch_size);
+        }
+    }
+}
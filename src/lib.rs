@@ -1,25 +1,131 @@
 // Modules and public exports
+pub mod affinity;
+pub mod anchors;
+pub mod audit;
+pub mod bytes;
+pub mod chain;
+pub mod compat;
+pub mod count;
+pub mod dedup;
+pub mod demux;
+pub mod dust;
+pub mod error;
+pub mod export;
 pub mod fasta;
 pub mod fastq;
 pub mod fastx;
 pub mod feat;
+pub mod filter;
+pub mod frame;
+pub mod frequency;
+pub mod genbank;
+pub mod index;
+pub mod join;
+pub mod lookup;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod metrics;
 pub mod mmscanner;
+#[cfg(feature = "offload")]
+pub mod offload;
+pub mod output;
+pub mod packed;
+// wasm32 没有线程, 不提供并行驱动
+#[cfg(not(target_arch = "wasm32"))]
 pub mod parallel;
+pub mod preview;
+pub mod progress;
+pub mod qual;
 pub mod reader;
+#[cfg(feature = "remote")]
+pub mod remote;
+pub mod report;
 pub mod seq;
+pub mod slice;
+pub mod spectrum;
+pub mod stats;
+pub mod strobemers;
+pub mod subsample;
+pub mod taxonomy;
+pub mod tee;
+pub mod timing;
+pub mod translate;
+pub mod trim;
+pub mod twobit;
 pub mod utils;
 
+pub use affinity::{numa_node_cores, parse_cpu_list, pin_current_thread, Affinity};
+pub use anchors::{anchors, minimizer_positions, Anchor, PositionIndex};
+pub use audit::{find_divergence, naive_minimizers};
+pub use bytes::{reader_from_bytes, sketch_from_bytes, stats_from_bytes};
+pub use chain::ChainedReader;
+pub use count::{read_jellyfish_dump, read_kmc_dump, KmerCounts};
+pub use dedup::{Decision, Deduplicator, DuplicateKey};
+pub use demux::{BarcodeWhitelist, Demultiplexer, TagExtractor, TagSource};
+pub use dust::{sdust_mask, Sdust, SDUST_THRESHOLD, SDUST_WINDOW};
+pub use error::SeqKmerError;
+pub use export::{
+    collect_kmers, kmer_set, read_kmer_set, write_kmer_set, KmerFormat, KMER_SET_MAGIC,
+    MAX_EXPORT_K,
+};
 pub use fasta::BufferFastaReader;
+pub use fasta::FastaPairReader;
 pub use fasta::FastaReader;
-pub use fastq::FastqReader;
+pub use fastq::{FastqReader, ParseWarning, PHRED_SAMPLE};
 pub use fastx::FastxReader;
 pub use feat::constants::*;
 pub use feat::*;
-pub use mmscanner::{scan_sequence, Cursor, MinimizerData, MinimizerIterator, MinimizerWindow};
+pub use filter::{Filter, ReadFilter};
+pub use frame::{FrameCompression, FrameReader, FrameWriter, FRAME_MAGIC, FRAME_VERSION};
+pub use frequency::{scan_sequence_filtered, FrequencyFiltered, MinimizerFrequency};
+pub use genbank::{extract_location, GenbankReader};
+#[cfg(not(target_arch = "wasm32"))]
+pub use index::build_index;
+pub use index::{CompactHashTable, IndexHeader, INDEX_MAGIC, INDEX_VERSION};
+pub use join::PairJoiner;
+pub use lookup::{lookup_hits, MinimizerLookup, SortedIndex};
+#[cfg(not(target_arch = "wasm32"))]
+pub use metrics::PipelineMetrics;
+pub use mmscanner::{
+    for_each_minimizer, scan_sequence, scan_sequence_multi, scan_slice, scan_subrange, Cursor,
+    FixedCursor, LmerCursor, Mate, MinimizerColumns, MinimizerData, MinimizerIterator,
+    MinimizerScanner, MinimizerWindow, MultiSeedIterator, PairedMinimizers,
+};
+pub use output::{write_kraken_output, write_kraken_report, TaxonTree};
+pub use packed::{PackedMinimizerIterator, PackedSeq};
+#[cfg(not(target_arch = "wasm32"))]
 pub use parallel::create_reader;
+#[cfg(not(target_arch = "wasm32"))]
 pub use parallel::{
-    buffer_map_parallel, buffer_read_parallel, read_parallel, ParallelItem, ParallelResult,
+    buffer_map_parallel, buffer_map_parallel_with_config, buffer_read_parallel, demux_parallel,
+    demux_parallel_with_config, read_parallel, read_parallel_adaptive,
+    read_parallel_adaptive_with_config, read_parallel_cancellable,
+    read_parallel_cancellable_with_config, read_parallel_filtered,
+    read_parallel_filtered_with_config, read_parallel_foreach, read_parallel_foreach_with_config,
+    read_parallel_in, read_parallel_in_with_config, read_parallel_multi,
+    read_parallel_multi_with_config, read_parallel_progress, read_parallel_progress_with_config,
+    read_parallel_transform, read_parallel_transform_with_config, read_parallel_with_config,
+    read_parallel_with_state, read_parallel_with_state_with_config, read_streaming,
+    read_streaming_with_config, BatchController, CancellationToken, ParallelConfig, ParallelItem,
+    ParallelResult,
 };
+pub use preview::{preview_inputs, preview_inputs_with, preview_reader, PreviewReport};
+pub use progress::{Progress, ProgressCounter, ProgressEvent};
+pub use qual::FastaQualReader;
 pub use reader::*;
-pub use seq::{Base, SeqFormat, SeqHeader};
+pub use report::{Column, TableFormat, TableOutput, TableWriter};
+pub use seq::{
+    Base, IdPolicy, OrphanPolicy, PhredEncoding, QualityPolicy, ReadTags, SeqChunk, SeqFormat,
+    SeqHeader, SoftMask,
+};
+pub use slice::{Shard, TakeRecords};
+pub use spectrum::{CountMinSketch, Spectrum};
+pub use stats::{CycleCounts, PositionProfile, SeqStats, StatsSummary};
+pub use strobemers::{StrobeConfig, StrobeMethod, Strobemer};
+pub use subsample::SubsampleReader;
+pub use taxonomy::{resolve, Taxonomy, TAXONOMY_MAGIC};
+pub use tee::{BatchSink, FastxSink, TeeReader};
+pub use timing::{ReadTiming, SlowReads};
+pub use translate::{scan_sequence_translated, translate_six_frames, GeneticCode};
+pub use trim::{quality_trim, AdapterTrimmer, PolyTailTrimmer, QualityTrim, Transform};
+pub use twobit::{TwoBitReader, TWOBIT_SIGNATURE};
 pub use utils::OptionPair;
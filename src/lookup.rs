@@ -0,0 +1,165 @@
+use crate::frequency::MinimizerFrequency;
+use crate::mmscanner::MinimizerIterator;
+use crate::reader::SpaceDist;
+use crate::seq::Base;
+use crate::utils::OptionPair;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::BuildHasher;
+
+/// A store mapping minimizer hashes to values (taxon ids, counts, ...).
+///
+/// Implement it to plug a custom backend into [`lookup_hits`]; `get_batch`
+/// can be overridden by stores that benefit from batched queries, such as
+/// remote services.
+pub trait MinimizerLookup: Send + Sync {
+    type Value;
+
+    fn get(&self, hash: u64) -> Option<Self::Value>;
+
+    /// Looks up `hashes` in order, appending one result per hash to `out`.
+    fn get_batch(&self, hashes: &[u64], out: &mut Vec<Option<Self::Value>>) {
+        out.extend(hashes.iter().map(|&hash| self.get(hash)));
+    }
+}
+
+impl<V, S> MinimizerLookup for HashMap<u64, V, S>
+where
+    V: Clone + Send + Sync,
+    S: BuildHasher + Send + Sync,
+{
+    type Value = V;
+
+    #[inline]
+    fn get(&self, hash: u64) -> Option<V> {
+        HashMap::get(self, &hash).cloned()
+    }
+}
+
+impl<V: Clone + Send + Sync> MinimizerLookup for BTreeMap<u64, V> {
+    type Value = V;
+
+    #[inline]
+    fn get(&self, hash: u64) -> Option<V> {
+        BTreeMap::get(self, &hash).cloned()
+    }
+}
+
+impl MinimizerLookup for MinimizerFrequency {
+    type Value = u64;
+
+    /// Returns the count of a minimizer that has been seen.
+    #[inline]
+    fn get(&self, hash: u64) -> Option<u64> {
+        self.counts.get(&hash).copied()
+    }
+}
+
+/// A read-only index of `(hash, value)` pairs kept sorted by hash and
+/// searched by bisection; compact for large, static tables.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{MinimizerLookup, SortedIndex};
+///
+/// let index: SortedIndex<u32> = [(30, 3), (10, 1), (20, 2)].into_iter().collect();
+/// assert_eq!(index.get(20), Some(2));
+/// assert_eq!(index.get(25), None);
+///
+/// let mut out = Vec::new();
+/// index.get_batch(&[10, 11, 30], &mut out);
+/// assert_eq!(out, vec![Some(1), None, Some(3)]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SortedIndex<V> {
+    entries: Vec<(u64, V)>,
+}
+
+impl<V> SortedIndex<V> {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl<V> FromIterator<(u64, V)> for SortedIndex<V> {
+    /// Builds the index; for duplicate hashes the last value wins.
+    fn from_iter<I: IntoIterator<Item = (u64, V)>>(iter: I) -> Self {
+        let mut entries: Vec<(u64, V)> = iter.into_iter().collect();
+        // 稳定排序后去重, 保留最后一个
+        entries.sort_by_key(|&(hash, _)| hash);
+        entries.reverse();
+        entries.dedup_by_key(|&mut (hash, _)| hash);
+        entries.reverse();
+        Self { entries }
+    }
+}
+
+impl<V: Clone + Send + Sync> MinimizerLookup for SortedIndex<V> {
+    type Value = V;
+
+    #[inline]
+    fn get(&self, hash: u64) -> Option<V> {
+        self.entries
+            .binary_search_by_key(&hash, |&(h, _)| h)
+            .ok()
+            .map(|i| self.entries[i].1.clone())
+    }
+}
+
+/// Scans a record and looks every minimizer up, returning the hit
+/// distribution per mate as `SpaceDist` runs of `value:count`, with 0 for
+/// minimizers the store does not know.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{lookup_hits, scan_sequence, Base, Meros, OptionPair, SeqFormat, SeqHeader};
+/// use std::collections::HashMap;
+///
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+/// let header = SeqHeader::new("read", SeqFormat::Fasta);
+/// let seq = Base::new(header, OptionPair::Single(b"ATCGATCGATCGATCGATCG".to_vec()));
+///
+/// // every minimizer of the read maps to taxon 562
+/// let mut store = HashMap::new();
+/// let mut all = scan_sequence(&seq, &meros);
+/// if let OptionPair::Single(m_iter) = &mut all.body {
+///     for (_, hash) in m_iter {
+///         store.insert(hash, 562u64);
+///     }
+/// }
+///
+/// let mut record = scan_sequence(&seq, &meros);
+/// let hits = lookup_hits(&mut record, &store);
+/// let dist = hits.single().unwrap();
+/// assert_eq!(dist.value.len(), 1);
+/// assert_eq!(dist.value[0].ext_code, 562);
+/// ```
+pub fn lookup_hits<L>(record: &mut Base<MinimizerIterator>, lookup: &L) -> OptionPair<SpaceDist>
+where
+    L: MinimizerLookup<Value = u64>,
+{
+    let hits = record.fold(|hits, m_iter, offset| {
+        let hashes: Vec<(usize, u64)> = m_iter.by_ref().collect();
+        let keys: Vec<u64> = hashes.iter().map(|&(_, hash)| hash).collect();
+        let mut values = Vec::with_capacity(keys.len());
+        lookup.get_batch(&keys, &mut values);
+        for (&(pos, _), value) in hashes.iter().zip(values) {
+            if let Some(value) = value {
+                hits.push((offset + pos, value));
+            }
+        }
+        offset + m_iter.size
+    });
+
+    let mut dist = record.range().apply(|&range| SpaceDist::new(range));
+    for (pos, value) in hits {
+        dist.add(value, pos);
+    }
+    dist.fill_tail_with_zeros();
+    dist
+}
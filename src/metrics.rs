@@ -0,0 +1,143 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// Throughput and stall counters of a parallel pipeline, readable from any
+/// thread while it runs.
+///
+/// Attach one with [`ParallelConfig::with_metrics`](crate::ParallelConfig::with_metrics).
+/// Low queue occupancy with low worker utilization points at a slow reader;
+/// a full queue with busy workers means `work` is the bottleneck.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{read_parallel_with_config, FastaReader, Meros, ParallelConfig};
+/// use seqkmer::{Base, MinimizerIterator, ParallelResult, PipelineMetrics};
+/// use std::path::Path;
+/// use std::sync::Arc;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut reader = FastaReader::from_path(Path::new("tests/data/test.fasta"), 0)?;
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+/// let metrics = Arc::new(PipelineMetrics::default());
+/// let config = ParallelConfig::new(4).with_metrics(metrics.clone());
+///
+/// let work = |seqs: &mut Vec<Base<MinimizerIterator>>| seqs.len();
+/// let func = |result: &mut ParallelResult<usize>| while result.next().is_some() {};
+/// read_parallel_with_config(&mut reader, &config, &meros, work, func)?;
+///
+/// assert_eq!(metrics.records(), 3);
+/// assert!(metrics.bytes() > 0);
+/// assert!(metrics.batches() >= 1);
+/// assert!((0.0..=1.0).contains(&metrics.worker_utilization()));
+/// assert!((0.0..=1.0).contains(&metrics.queue_occupancy()));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct PipelineMetrics {
+    started: OnceLock<Instant>,
+    // 流水线结束时的耗时, 0 表示仍在运行
+    finished_nanos: AtomicU64,
+    records: AtomicU64,
+    bytes: AtomicU64,
+    batches: AtomicU64,
+    // 每次入队后的队列长度与容量之和, 用于求平均占用率
+    queue_len_sum: AtomicU64,
+    queue_cap_sum: AtomicU64,
+    busy_nanos: AtomicU64,
+    idle_nanos: AtomicU64,
+}
+
+impl PipelineMetrics {
+    /// Records processed by the workers.
+    pub fn records(&self) -> u64 {
+        self.records.load(Ordering::Relaxed)
+    }
+
+    /// Sequence and quality bytes processed by the workers.
+    pub fn bytes(&self) -> u64 {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    /// Batches processed by the workers; split batches count once per part.
+    pub fn batches(&self) -> u64 {
+        self.batches.load(Ordering::Relaxed)
+    }
+
+    /// Time since the pipeline started, frozen once it finishes.
+    pub fn elapsed(&self) -> Duration {
+        match self.finished_nanos.load(Ordering::Relaxed) {
+            0 => self.started.get().map_or(Duration::ZERO, Instant::elapsed),
+            nanos => Duration::from_nanos(nanos),
+        }
+    }
+
+    pub fn records_per_sec(&self) -> f64 {
+        rate(self.records(), self.elapsed())
+    }
+
+    pub fn bytes_per_sec(&self) -> f64 {
+        rate(self.bytes(), self.elapsed())
+    }
+
+    /// Mean fill of the input queue, from 0 (workers always waiting) to 1
+    /// (reader always blocked), sampled each time a batch is queued.
+    pub fn queue_occupancy(&self) -> f64 {
+        ratio(
+            self.queue_len_sum.load(Ordering::Relaxed),
+            self.queue_cap_sum.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Share of worker time spent in scanning and `work` rather than
+    /// waiting for batches.
+    pub fn worker_utilization(&self) -> f64 {
+        let busy = self.busy_nanos.load(Ordering::Relaxed);
+        ratio(busy, busy + self.idle_nanos.load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn start(&self) {
+        let _ = self.started.set(Instant::now());
+    }
+
+    pub(crate) fn finish(&self) {
+        let nanos = self.elapsed().as_nanos() as u64;
+        self.finished_nanos.store(nanos.max(1), Ordering::Relaxed);
+    }
+
+    pub(crate) fn queued(&self, len: usize, capacity: usize) {
+        self.queue_len_sum.fetch_add(len as u64, Ordering::Relaxed);
+        self.queue_cap_sum
+            .fetch_add(capacity as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn idle(&self, waited: Duration) {
+        self.idle_nanos
+            .fetch_add(waited.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn processed(&self, records: usize, bytes: usize, took: Duration) {
+        self.records.fetch_add(records as u64, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.batches.fetch_add(1, Ordering::Relaxed);
+        self.busy_nanos
+            .fetch_add(took.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+fn rate(count: u64, elapsed: Duration) -> f64 {
+    match elapsed.as_secs_f64() {
+        secs if secs > 0.0 => count as f64 / secs,
+        _ => 0.0,
+    }
+}
+
+fn ratio(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 / whole as f64
+    }
+}
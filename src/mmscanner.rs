@@ -1,15 +1,15 @@
 // kraken 2 使用的是murmur_hash3 算法的 fmix64作为 hash
-use crate::feat::Meros;
-use crate::feat::{canonical_representation, char_to_value, fmix64 as murmur_hash3};
+use crate::feat::fmix64 as murmur_hash3;
+use crate::feat::{Meros, Residue};
 use crate::Base;
 use crate::OptionPair;
-use crate::BITS_PER_CHAR;
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
+use std::ops::Range;
 
 /// Converts an l-mer to a candidate l-mer using the given Meros configuration.
 #[inline]
-fn to_candidate_lmer(meros: &Meros, lmer: u64) -> u64 {
-    let mut canonical_lmer = canonical_representation(lmer, meros.l_mer);
+pub(crate) fn to_candidate_lmer(meros: &Meros, lmer: u64) -> u64 {
+    let mut canonical_lmer = meros.alphabet.canonical(lmer, meros.l_mer);
     if meros.spaced_seed_mask > 0 {
         canonical_lmer &= meros.spaced_seed_mask;
     }
@@ -19,6 +19,7 @@ fn to_candidate_lmer(meros: &Meros, lmer: u64) -> u64 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Alphabet;
 
     #[test]
     fn test_to_candidate_lmer() {
@@ -29,6 +30,158 @@ mod tests {
         // 在这里添加断言来验证结果
         assert_eq!(candidate, 0b11110u64);
     }
+
+    #[test]
+    fn test_scanner_reuse_matches_fresh_scans() {
+        let meros = Meros::new(11, 3, Some(0), None, None);
+        let mut scanner = MinimizerScanner::new(&meros);
+        let mut state = 0x9e3779b97f4a7c15u64;
+        for len in [0, 5, 11, 40, 150, 3, 90] {
+            let read: Vec<u8> = (0..len)
+                .map(|_| {
+                    state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+                    b"ACGTN"[(state >> 33) as usize % 5]
+                })
+                .collect();
+            // 只消费一半就丢弃, 下一次扫描不能受残留状态影响
+            scanner.scan(&read).take(2).for_each(drop);
+            let reused: Vec<_> = scanner.scan(&read).collect();
+            assert_eq!(reused, scan_slice(&read, &meros).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn test_window_matches_naive_minimum() {
+        // 小取值范围制造大量重复值, 模拟重复序列
+        let mut state = 0x2545f4914f6cdd1du64;
+        let candidates: Vec<u64> = (0..5000)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+                (state >> 33) % 7
+            })
+            .collect();
+
+        for capacity in [2, 3, 5, 16] {
+            let mut window = MinimizerWindow::new(capacity);
+            let mut current = None;
+            for (t, &candidate) in candidates.iter().enumerate() {
+                if let Some(minimizer) = window.next(candidate) {
+                    current = Some(minimizer);
+                }
+                if t >= capacity {
+                    let naive = candidates[t - capacity..=t].iter().min().copied();
+                    assert_eq!(current, naive, "capacity {} position {}", capacity, t);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_fixed_cursor_matches_generic() {
+        let mut state = 0x9e3779b97f4a7c15u64;
+        let seq: Vec<u8> = (0..2000)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+                b"ACGTN"[((state >> 33) % 5) as usize]
+            })
+            .collect();
+
+        for (k, l) in [(35, 31), (31, 21), (25, 15), (20, 11)] {
+            let meros = Meros::new(k, l, None, None, None);
+            let cursor = Cursor::new(&meros);
+            let window = MinimizerWindow::new(meros.window_size());
+            let expected: Vec<_> = MinimizerIterator::new(&seq, cursor, window, &meros).collect();
+            let mut actual = Vec::new();
+            for_each_minimizer(&seq, &meros, |pos, hash| actual.push((pos, hash)));
+            assert_eq!(actual, expected, "k={} l={}", k, l);
+        }
+    }
+
+    #[test]
+    fn test_protein_alphabet_scan() {
+        let seq = b"MKVLAAGIVGLLLAWQ";
+        let count = |meros: &Meros| {
+            let cursor = Cursor::new(meros);
+            let window = MinimizerWindow::new(meros.window_size());
+            MinimizerIterator::new(seq, cursor, window, meros).count()
+        };
+        let dna = Meros::new(8, 4, Some(0), None, None).with_alphabet(Alphabet::Dna);
+        let protein = Meros::new(8, 4, Some(0), None, None).with_alphabet(Alphabet::Protein);
+        let reduced = Meros::new(8, 4, Some(0), None, None).with_alphabet(Alphabet::Reduced9);
+        assert_eq!(count(&dna), 0);
+        assert!(count(&protein) > 0);
+        assert!(count(&reduced) > 0);
+    }
+
+    fn pair_record(r1: &[u8], r2: &[u8]) -> Base<Vec<u8>> {
+        let header = crate::SeqHeader::new("pair", crate::SeqFormat::Fastq);
+        Base::new(header, OptionPair::Pair(r1.to_vec(), r2.to_vec()))
+    }
+
+    #[test]
+    fn test_paired_positions_follow_range() {
+        let meros = Meros::new(11, 3, Some(0), None, None);
+        let record = pair_record(b"ATCGATCGATCGTTAGGCAACGT", b"GGCATCAACGTTGCAACGTGGCATTACG");
+        let mut scanned = scan_sequence(&record, &meros);
+        let hits: Vec<_> = scanned.paired_minimizers().collect();
+        let OptionPair::Pair((start1, end1), (start2, end2)) = scanned.range() else {
+            unreachable!()
+        };
+        assert_eq!((start1, end1), (0, start2));
+
+        // 每条 mate 的位置在 (start, end] 内连续递增, R2 紧接 R1
+        let r1: Vec<usize> = hits
+            .iter()
+            .filter(|h| h.0 == Mate::First)
+            .map(|h| h.1)
+            .collect();
+        let r2: Vec<usize> = hits
+            .iter()
+            .filter(|h| h.0 == Mate::Second)
+            .map(|h| h.1)
+            .collect();
+        assert_eq!(r1, (start1 + 1..=end1).collect::<Vec<_>>());
+        assert_eq!(r2, (start2 + 1..=end2).collect::<Vec<_>>());
+        assert!(!r1.is_empty() && !r2.is_empty());
+
+        // 与 fold 的 offset 约定一致
+        let folded = scan_sequence(&record, &meros).fold(|acc, m_iter, offset| {
+            acc.extend(m_iter.by_ref().map(|(pos, hash)| (offset + pos, hash)));
+            offset + m_iter.size
+        });
+        let paired: Vec<_> = hits.iter().map(|&(_, pos, hash)| (pos, hash)).collect();
+        assert_eq!(paired, folded);
+    }
+
+    #[test]
+    fn test_paired_boundary_with_short_mates() {
+        let meros = Meros::new(11, 3, Some(0), None, None);
+        // R1 短于 k, 没有 minimizer: R2 从 1 开始
+        let record = pair_record(b"ACGT", b"ATCGATCGATCGTTAGGCA");
+        let mut scanned = scan_sequence(&record, &meros);
+        let hits: Vec<_> = scanned.paired_minimizers().collect();
+        assert!(hits.iter().all(|h| h.0 == Mate::Second));
+        assert_eq!(hits.first().map(|h| h.1), Some(1));
+
+        // R2 为空: 只有 R1
+        let record = pair_record(b"ATCGATCGATCGTTAGGCA", b"");
+        let mut scanned = scan_sequence(&record, &meros);
+        let hits: Vec<_> = scanned.paired_minimizers().collect();
+        assert!(!hits.is_empty());
+        assert!(hits.iter().all(|h| h.0 == Mate::First));
+        assert_eq!(hits.last().map(|h| h.1), Some(scanned.range().first().1));
+
+        // R1 部分消费后, R2 的偏移仍以 R1 的全部数量计
+        let record = pair_record(b"ATCGATCGATCGTTAGGCA", b"ATCGATCGATCGTTAGGCA");
+        let mut scanned = scan_sequence(&record, &meros);
+        if let OptionPair::Pair(m1, _) = &mut scanned.body {
+            m1.next();
+        }
+        let hits: Vec<_> = scanned.paired_minimizers().collect();
+        let size1 = scanned.range().first().1;
+        let first_r2 = hits.iter().find(|h| h.0 == Mate::Second).map(|h| h.1);
+        assert_eq!(first_r2, Some(size1 + 1));
+    }
 }
 
 /// Represents data for a minimizer.
@@ -98,13 +251,23 @@ impl MinimizerWindow {
 
         let data = MinimizerData::new(candidate_lmer, self.count);
 
-        // 移除队列中所有比当前元素大的元素的索引
-        // 因为它们不可能是当前窗口的最小值
-        while let Some(m_data) = self.queue.back() {
-            if m_data.candidate_lmer > candidate_lmer {
-                self.queue.pop_back();
-            } else {
-                break;
+        // 新元素比窗口最小值还小时, 队列中所有元素都不可能再成为最小值,
+        // 直接清空, 跳过逐个比较
+        if self
+            .queue
+            .front()
+            .is_some_and(|front| front.candidate_lmer > candidate_lmer)
+        {
+            self.queue.clear();
+        } else {
+            // 移除队列中所有比当前元素大的元素的索引
+            // 因为它们不可能是当前窗口的最小值
+            while let Some(m_data) = self.queue.back() {
+                if m_data.candidate_lmer > candidate_lmer {
+                    self.queue.pop_back();
+                } else {
+                    break;
+                }
             }
         }
         let mut changed = false;
@@ -115,10 +278,12 @@ impl MinimizerWindow {
         // 将当前元素的索引添加到队列
         self.queue.push_back(data);
 
-        while !self.queue.is_empty()
-            && self.queue.front().map_or(false, |front| {
-                self.count >= self.capacity && front.pos < self.count - self.capacity
-            })
+        // 每次只加入一个元素, 队首最多只有一个元素过期
+        if self.count >= self.capacity
+            && self
+                .queue
+                .front()
+                .is_some_and(|front| front.pos < self.count - self.capacity)
         {
             self.queue.pop_front();
             changed = true;
@@ -133,13 +298,22 @@ impl MinimizerWindow {
     }
 
     /// Clears the window.
-    fn clear(&mut self) {
+    pub(crate) fn clear(&mut self) {
         self.count = 0;
         self.queue_pos = 0;
         self.queue.clear();
     }
 }
 
+/// Packs encoded residues into l-mers for `MinimizerIterator`.
+pub trait LmerCursor: Copy {
+    /// Processes the next item and returns the l-mer if the capacity is reached.
+    fn next_lmer(&mut self, item: u64) -> Option<u64>;
+
+    /// Clears the cursor.
+    fn clear(&mut self);
+}
+
 /// Represents a cursor for processing l-mers.
 ///
 /// # Examples
@@ -156,6 +330,7 @@ pub struct Cursor {
     capacity: usize,
     value: u64,
     mask: u64,
+    bits: usize,
 }
 
 impl Cursor {
@@ -166,12 +341,15 @@ impl Cursor {
             value: 0,
             capacity: meros.l_mer,
             mask: meros.mask,
+            bits: meros.alphabet.bits_per_char(),
         }
     }
+}
 
-    /// Processes the next item and returns the l-mer if the capacity is reached.
+impl LmerCursor for Cursor {
+    #[inline]
     fn next_lmer(&mut self, item: u64) -> Option<u64> {
-        self.value = ((self.value << BITS_PER_CHAR) | item) & self.mask;
+        self.value = ((self.value << self.bits) | item) & self.mask;
         // 更新当前位置
         self.pos += 1;
         // 检查是否达到了容量
@@ -181,7 +359,6 @@ impl Cursor {
         None
     }
 
-    /// Clears the cursor.
     #[inline]
     fn clear(&mut self) {
         self.pos = 0;
@@ -189,6 +366,120 @@ impl Cursor {
     }
 }
 
+/// A cursor whose l-mer length `L` and bits per residue `BITS` are fixed at
+/// compile time, so the mask and warm-up length are constants.
+///
+/// Only [`for_each_minimizer`] uses it; `scan_sequence`, `MinimizerIterator`
+/// and the parallel readers always run on the generic [`Cursor`].
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{FixedCursor, LmerCursor};
+///
+/// let mut cursor = FixedCursor::<3, 2>::new();
+/// assert_eq!(cursor.next_lmer(0b00), None);
+/// assert_eq!(cursor.next_lmer(0b01), None);
+/// assert_eq!(cursor.next_lmer(0b10), Some(0b000110));
+/// assert_eq!(cursor.next_lmer(0b11), Some(0b011011));
+/// ```
+#[derive(Clone, Copy, Default)]
+pub struct FixedCursor<const L: usize, const BITS: usize> {
+    pos: usize,
+    value: u64,
+}
+
+impl<const L: usize, const BITS: usize> FixedCursor<L, BITS> {
+    const MASK: u64 = (1u64 << (L * BITS)) - 1;
+
+    pub fn new() -> Self {
+        Self { pos: 0, value: 0 }
+    }
+}
+
+impl<const L: usize, const BITS: usize> LmerCursor for FixedCursor<L, BITS> {
+    #[inline(always)]
+    fn next_lmer(&mut self, item: u64) -> Option<u64> {
+        self.value = ((self.value << BITS) | item) & Self::MASK;
+        if self.pos + 1 >= L {
+            return Some(self.value);
+        }
+        self.pos += 1;
+        None
+    }
+
+    #[inline(always)]
+    fn clear(&mut self) {
+        self.pos = 0;
+        self.value = 0;
+    }
+}
+
+/// 迭代器自有的窗口, 或借自 `MinimizerScanner` 的窗口
+enum WindowSlot<'a> {
+    Owned(MinimizerWindow),
+    Borrowed(&'a mut MinimizerWindow),
+}
+
+impl WindowSlot<'_> {
+    #[inline]
+    fn get(&mut self) -> &mut MinimizerWindow {
+        match self {
+            WindowSlot::Owned(window) => window,
+            WindowSlot::Borrowed(window) => window,
+        }
+    }
+}
+
+/// Reusable scanning state for one set of minimizer parameters. Each
+/// [`MinimizerScanner::scan`] borrows the scanner's window instead of
+/// allocating a new one, which matters in tight loops over many short reads.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{scan_slice, Meros, MinimizerScanner};
+///
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+/// let mut scanner = MinimizerScanner::new(&meros);
+/// for read in [&b"ATCGATCGATCGTTAGCAGG"[..], b"GGCATCNNACGTTGCAACGTGG", b"AC"] {
+///     let hits: Vec<_> = scanner.scan(read).collect();
+///     assert_eq!(hits, scan_slice(read, &meros).collect::<Vec<_>>());
+/// }
+/// ```
+pub struct MinimizerScanner {
+    meros: Meros,
+    cursor: Cursor,
+    window: MinimizerWindow,
+}
+
+impl MinimizerScanner {
+    pub fn new(meros: &Meros) -> Self {
+        Self {
+            meros: *meros,
+            cursor: Cursor::new(meros),
+            window: MinimizerWindow::new(meros.window_size()),
+        }
+    }
+
+    pub fn meros(&self) -> &Meros {
+        &self.meros
+    }
+
+    /// Returns the minimizer iterator over `seq`, reusing the scanner's
+    /// window. The iterator yields the same values as [`scan_slice`].
+    pub fn scan<'a>(&'a mut self, seq: &'a [u8]) -> MinimizerIterator<'a> {
+        self.cursor.clear();
+        self.window.clear();
+        MinimizerIterator::with_slot(
+            seq,
+            self.cursor,
+            WindowSlot::Borrowed(&mut self.window),
+            &self.meros,
+        )
+    }
+}
+
 /// Iterator for finding minimizers in a sequence.
 ///
 /// # Examples
@@ -205,19 +496,27 @@ impl Cursor {
 ///     println!("Position: {}, Minimizer: {:b}", pos, minimizer);
 /// }
 /// ```
-pub struct MinimizerIterator<'a> {
-    cursor: Cursor,
-    window: MinimizerWindow,
+pub struct MinimizerIterator<'a, C: LmerCursor = Cursor> {
+    cursor: C,
+    window: WindowSlot<'a>,
     seq: &'a [u8],
     meros: &'a Meros,
     pos: usize,
     end: usize,
     pub size: usize,
+    /// 上一个残基编码, 用于同聚物压缩
+    last_code: Option<u64>,
+    #[cfg(feature = "audit")]
+    emitted: Option<Vec<u64>>,
 }
 
-impl<'a> MinimizerIterator<'a> {
+impl<'a, C: LmerCursor> MinimizerIterator<'a, C> {
     /// Creates a new MinimizerIterator.
-    pub fn new(seq: &'a [u8], cursor: Cursor, window: MinimizerWindow, meros: &'a Meros) -> Self {
+    pub fn new(seq: &'a [u8], cursor: C, window: MinimizerWindow, meros: &'a Meros) -> Self {
+        Self::with_slot(seq, cursor, WindowSlot::Owned(window), meros)
+    }
+
+    fn with_slot(seq: &'a [u8], cursor: C, window: WindowSlot<'a>, meros: &'a Meros) -> Self {
         MinimizerIterator {
             cursor,
             window,
@@ -226,22 +525,51 @@ impl<'a> MinimizerIterator<'a> {
             pos: 0,
             size: 0,
             end: seq.len(),
+            last_code: None,
+            #[cfg(feature = "audit")]
+            emitted: Some(Vec::new()),
         }
     }
 
     /// Clears the internal state of the iterator.
     fn clear_state(&mut self) {
         self.cursor.clear();
-        self.window.clear();
+        self.window.get().clear();
+        self.last_code = None;
     }
 
     /// Returns the size of the sequence being processed.
     pub fn seq_size(&self) -> usize {
         self.end
     }
+
+    /// Returns the sequence being scanned.
+    pub fn seq(&self) -> &'a [u8] {
+        self.seq
+    }
+
+    /// Returns the offset in the original sequence just past the residue
+    /// that completed the last yielded minimizer. With homopolymer
+    /// compression this maps compressed positions back to the input.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{Cursor, Meros, MinimizerIterator, MinimizerWindow};
+    ///
+    /// let meros = Meros::new(4, 3, None, None, None).with_hpc(true);
+    /// let seq = b"AAAACCCGGGGT";
+    /// let mut iter = MinimizerIterator::new(seq, Cursor::new(&meros), MinimizerWindow::new(1), &meros);
+    /// // the compressed sequence is ACGT, its first 3-mer ends at the first G
+    /// assert!(iter.next().is_some());
+    /// assert_eq!(iter.seq_pos(), 8);
+    /// ```
+    pub fn seq_pos(&self) -> usize {
+        self.pos
+    }
 }
 
-impl<'a> Iterator for MinimizerIterator<'a> {
+impl<C: LmerCursor> Iterator for MinimizerIterator<'_, C> {
     type Item = (usize, u64);
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -251,24 +579,41 @@ impl<'a> Iterator for MinimizerIterator<'a> {
             if ch == b'\n' || ch == b'\r' {
                 continue;
             } else {
-                let data = match char_to_value(ch) {
-                    Some(code) => self.cursor.next_lmer(code).and_then(|lmer| {
-                        let candidate_lmer = to_candidate_lmer(&self.meros, lmer);
-                        self.window
-                            .next(candidate_lmer)
-                            .map(|minimizer| murmur_hash3(minimizer ^ self.meros.toggle_mask))
-                    }),
-                    None => {
+                let data = match self.meros.encode_residue(ch, self.pos - 1) {
+                    // 同聚物压缩: 连续相同的残基只保留一个
+                    Residue::Code(code) if self.meros.hpc && self.last_code == Some(code) => None,
+                    Residue::Code(code) => {
+                        self.last_code = Some(code);
+                        self.cursor.next_lmer(code).and_then(|lmer| {
+                            let candidate_lmer = to_candidate_lmer(self.meros, lmer);
+                            self.window
+                                .get()
+                                .next(candidate_lmer)
+                                .filter(|&minimizer| self.meros.passes_entropy(minimizer))
+                                .map(|minimizer| murmur_hash3(minimizer ^ self.meros.toggle_mask))
+                        })
+                    }
+                    Residue::Skip => None,
+                    Residue::Reset => {
                         self.clear_state();
                         None
                     }
                 };
-                if data.is_some() {
+                if let Some(data) = data {
                     self.size += 1;
-                    return Some((self.size, data.unwrap()));
+                    #[cfg(feature = "audit")]
+                    if let Some(emitted) = self.emitted.as_mut() {
+                        emitted.push(data);
+                    }
+                    return Some((self.size, data));
                 }
             }
         }
+        // 审计模式下, 迭代结束时与朴素实现对比一次
+        #[cfg(feature = "audit")]
+        if let Some(emitted) = self.emitted.take() {
+            crate::audit::assert_matches_oracle(self.seq, self.meros, &emitted);
+        }
         None
     }
 }
@@ -290,7 +635,117 @@ impl<'a> Base<MinimizerIterator<'a>> {
         self.body.reduce_str("|", |m_iter| m_iter.size.to_string())
     }
 
+    /// Consumes the remaining minimizers and returns a stable 64-bit digest
+    /// of their order and values, mate by mate. Reads with the same
+    /// minimizer sequence share a digest, which makes it usable for
+    /// regression tests and minimizer-level deduplication.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{scan_sequence, Base, Meros, OptionPair, SeqFormat, SeqHeader};
+    ///
+    /// let meros = Meros::new(11, 3, Some(0), None, None);
+    /// let record = |id: &str, seq: &[u8]| {
+    ///     let header = SeqHeader::new(id, SeqFormat::Fasta);
+    ///     Base::new(header, OptionPair::Single(seq.to_vec()))
+    /// };
+    /// let a = record("a", b"ATCGATCGATCGTTAGGCA");
+    /// let b = record("b", b"ATCGATCGATCGTTAGGCA");
+    /// let c = record("c", b"TTAGGCAATCGATCGATCG");
+    ///
+    /// let digest_a = scan_sequence(&a, &meros).digest();
+    /// assert_eq!(digest_a, scan_sequence(&b, &meros).digest());
+    /// assert_ne!(digest_a, scan_sequence(&c, &meros).digest());
+    /// // 固定值, 版本间回归用
+    /// assert_eq!(digest_a, 3836701432265632153);
+    /// ```
+    pub fn digest(&mut self) -> u64 {
+        let mut digest = 0u64;
+        for m_iter in self.body.iter_mut() {
+            for (_, hash) in m_iter.by_ref() {
+                digest = murmur_hash3(digest.rotate_left(5) ^ hash);
+            }
+            // 每个 mate 结束时混入其 minimizer 数量, 区分 mate 边界
+            digest = murmur_hash3(digest ^ m_iter.size as u64);
+        }
+        digest
+    }
+
+    /// Consumes the remaining minimizers into one `(pos, hash)` vector per
+    /// mate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{scan_sequence, Base, Meros, OptionPair, SeqFormat, SeqHeader};
+    ///
+    /// let header = SeqHeader::new("pair", SeqFormat::Fasta);
+    /// let mate = b"ATCGATCGATCGTTAGGCA".to_vec();
+    /// let seq = Base::new(header, OptionPair::Pair(mate.clone(), mate));
+    /// let meros = Meros::new(11, 3, Some(0), None, None);
+    ///
+    /// let OptionPair::Pair(m1, m2) = scan_sequence(&seq, &meros).collect_minimizers() else {
+    ///     unreachable!()
+    /// };
+    /// assert!(!m1.is_empty());
+    /// assert_eq!(m1, m2);
+    ///
+    /// // 两条 mate 相同, 去重后不超过单条 mate 的数量
+    /// let unique = scan_sequence(&seq, &meros).unique_minimizers();
+    /// assert!(unique.len() <= m1.len());
+    /// assert!(m1.iter().all(|(_, hash)| unique.contains(hash)));
+    /// ```
+    pub fn collect_minimizers(&mut self) -> OptionPair<Vec<(usize, u64)>> {
+        self.body.apply_mut(|m_iter| m_iter.by_ref().collect())
+    }
+
+    /// Consumes the remaining minimizers of both mates into a set of
+    /// distinct hashes.
+    pub fn unique_minimizers(&mut self) -> HashSet<u64> {
+        self.body
+            .iter_mut()
+            .flat_map(|m_iter| m_iter.by_ref().map(|(_, hash)| hash))
+            .collect()
+    }
+
+    /// Returns the remaining minimizers as `(mate, pos, hash)`, mate 1
+    /// first. Positions are global across the pair and agree with
+    /// [`range`](Self::range): mate 1 yields `1..=size1` and mate 2 continues
+    /// at `size1 + 1`, where `size1` counts every minimizer of mate 1,
+    /// including any consumed before this call.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{scan_sequence, Base, Mate, Meros, OptionPair, SeqFormat, SeqHeader};
+    ///
+    /// let header = SeqHeader::new("pair", SeqFormat::Fastq);
+    /// let mate = b"ATCGATCGATCGTTAGGCA".to_vec();
+    /// let seq = Base::new(header, OptionPair::Pair(mate.clone(), mate));
+    /// let meros = Meros::new(11, 3, Some(0), None, None);
+    ///
+    /// let mut scanned = scan_sequence(&seq, &meros);
+    /// let hits: Vec<(Mate, usize, u64)> = scanned.paired_minimizers().collect();
+    /// let OptionPair::Pair(_, (start2, _)) = scanned.range() else { unreachable!() };
+    /// let first_r2 = hits.iter().find(|(mate, _, _)| *mate == Mate::Second).unwrap();
+    /// assert_eq!(first_r2.1, start2 + 1);
+    /// ```
+    pub fn paired_minimizers(&mut self) -> PairedMinimizers<'_, 'a> {
+        PairedMinimizers {
+            body: &mut self.body,
+            mate: Mate::First,
+        }
+    }
+
     /// Folds the minimizer iterator into a vector of a specified type.
+    ///
+    /// `f` is called once per mate with the accumulator, the mate's
+    /// iterator and an offset, and returns the offset for the next mate:
+    /// 0 for mate 1, then whatever the call for mate 1 returned. Returning
+    /// `offset + m_iter.size` after draining the iterator gives the global
+    /// positions of [`range`](Self::range); prefer
+    /// [`paired_minimizers`](Self::paired_minimizers) for that case.
     pub fn fold<F, T>(&mut self, mut f: F) -> Vec<T>
     where
         F: FnMut(&mut Vec<T>, &mut MinimizerIterator<'a>, usize) -> usize,
@@ -321,19 +776,280 @@ impl<'a> Base<MinimizerIterator<'a>> {
     }
 }
 
-/// Scans a sequence and returns a MinimizerIterator.
+/// Which read of a pair a minimizer came from; single reads are `First`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mate {
+    First,
+    Second,
+}
+
+/// Iterator returned by [`Base::paired_minimizers`].
+pub struct PairedMinimizers<'s, 'a> {
+    body: &'s mut OptionPair<MinimizerIterator<'a>>,
+    mate: Mate,
+}
+
+impl Iterator for PairedMinimizers<'_, '_> {
+    type Item = (Mate, usize, u64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.body {
+            OptionPair::Single(m_iter) => m_iter.next().map(|(pos, hash)| (Mate::First, pos, hash)),
+            OptionPair::Pair(m_iter1, m_iter2) => {
+                if self.mate == Mate::First {
+                    if let Some((pos, hash)) = m_iter1.next() {
+                        return Some((Mate::First, pos, hash));
+                    }
+                    self.mate = Mate::Second;
+                }
+                // mate 1 已耗尽, size 即其 minimizer 总数
+                let offset = m_iter1.size;
+                m_iter2
+                    .next()
+                    .map(|(pos, hash)| (Mate::Second, offset + pos, hash))
+            }
+        }
+    }
+}
+
+/// The minimizers of a batch of records as parallel columns, one row per
+/// minimizer, ready to hand to a foreign-language binding or a dataframe
+/// as flat arrays without per-record objects. `pos` is the global position
+/// of [`Base::paired_minimizers`] and `mate` is 0 or 1.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{FastaReader, Meros, MinimizerColumns, Reader};
+/// use std::path::Path;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut reader = FastaReader::from_path(Path::new("tests/data/test.fasta"), 0)?;
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+/// let columns = MinimizerColumns::from_records(&reader.next()?.unwrap(), &meros);
+///
+/// assert_eq!(columns.len(), columns.hash.len());
+/// assert_eq!(columns.record.first(), Some(&0));
+/// assert_eq!(columns.record.last(), Some(&2));
+/// assert!(columns.mate.iter().all(|&mate| mate == 0));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MinimizerColumns {
+    /// 记录在批次中的下标
+    pub record: Vec<u32>,
+    pub mate: Vec<u8>,
+    pub pos: Vec<u64>,
+    pub hash: Vec<u64>,
+}
+
+impl MinimizerColumns {
+    pub fn from_records<B: AsRef<[u8]>>(records: &[Base<B>], meros: &Meros) -> Self {
+        let mut columns = Self::default();
+        for (index, record) in records.iter().enumerate() {
+            columns.push(index as u32, record, meros);
+        }
+        columns
+    }
+
+    /// Appends the minimizers of `record` as rows for record `index`.
+    pub fn push<B: AsRef<[u8]>>(&mut self, index: u32, record: &Base<B>, meros: &Meros) {
+        for (mate, pos, hash) in scan_sequence(record, meros).paired_minimizers() {
+            self.record.push(index);
+            self.mate.push(mate as u8);
+            self.pos.push(pos as u64);
+            self.hash.push(hash);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.hash.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hash.is_empty()
+    }
+}
+
+/// Scans a sequence once for several spaced seeds, yielding
+/// `(seed_id, hash)` pairs where `seed_id` indexes `masks`. Each seed keeps
+/// its own window, so the hashes for one seed are exactly those a
+/// `MinimizerIterator` yields with `spaced_seed_mask` set to that mask,
+/// entropy filter included. A mask of 0 leaves the l-mer unspaced;
+/// `meros.spaced_seed_mask` is ignored.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{Cursor, Meros, MinimizerIterator, MinimizerWindow, MultiSeedIterator, SpacedSeed};
+///
+/// let seq = b"ACGTTGCAACGTGGCATTACGATCGATCGGGATCGACTAGCACACACACACACACACACAC";
+/// let meros = Meros::new(15, 9, None, None, None).with_min_entropy(1.5);
+/// let masks = [0, SpacedSeed::from_pattern("111011011").unwrap().mask().unwrap()];
+///
+/// let pairs: Vec<(usize, u64)> = MultiSeedIterator::new(seq, &meros, &masks).collect();
+/// for (seed_id, &mask) in masks.iter().enumerate() {
+///     let single = Meros { spaced_seed_mask: mask, ..meros };
+///     let window = MinimizerWindow::new(single.window_size());
+///     let expected: Vec<u64> = MinimizerIterator::new(seq, Cursor::new(&single), window, &single)
+///         .map(|(_, hash)| hash)
+///         .collect();
+///     let got: Vec<u64> = pairs.iter().filter(|p| p.0 == seed_id).map(|p| p.1).collect();
+///     assert_eq!(got, expected);
+/// }
+/// ```
+pub struct MultiSeedIterator<'a> {
+    cursor: Cursor,
+    windows: Vec<MinimizerWindow>,
+    /// 每个种子各自的参数, 只有 spaced_seed_mask 不同
+    seeds: Vec<Meros>,
+    seq: &'a [u8],
+    meros: &'a Meros,
+    pos: usize,
+    last_code: Option<u64>,
+    // 同一个 l-mer 可能为多个种子产生 minimizer
+    pending: VecDeque<(usize, u64)>,
+    #[cfg(feature = "audit")]
+    emitted: Option<Vec<Vec<u64>>>,
+}
+
+impl<'a> MultiSeedIterator<'a> {
+    /// Creates an iterator over `seq` for every mask in `masks`.
+    pub fn new(seq: &'a [u8], meros: &'a Meros, masks: &[u64]) -> Self {
+        Self {
+            cursor: Cursor::new(meros),
+            windows: masks
+                .iter()
+                .map(|_| MinimizerWindow::new(meros.window_size()))
+                .collect(),
+            seeds: masks
+                .iter()
+                .map(|&spaced_seed_mask| Meros {
+                    spaced_seed_mask,
+                    ..*meros
+                })
+                .collect(),
+            seq,
+            meros,
+            pos: 0,
+            last_code: None,
+            pending: VecDeque::with_capacity(masks.len()),
+            #[cfg(feature = "audit")]
+            emitted: Some(vec![Vec::new(); masks.len()]),
+        }
+    }
+}
+
+impl Iterator for MultiSeedIterator<'_> {
+    type Item = (usize, u64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() && self.pos < self.seq.len() {
+            let ch = self.seq[self.pos];
+            self.pos += 1;
+            if ch == b'\n' || ch == b'\r' {
+                continue;
+            }
+            match self.meros.encode_residue(ch, self.pos - 1) {
+                Residue::Code(code) if self.meros.hpc && self.last_code == Some(code) => {}
+                Residue::Code(code) => {
+                    self.last_code = Some(code);
+                    if let Some(lmer) = self.cursor.next_lmer(code) {
+                        for (seed_id, (seed, window)) in
+                            self.seeds.iter().zip(self.windows.iter_mut()).enumerate()
+                        {
+                            if let Some(minimizer) = window
+                                .next(to_candidate_lmer(seed, lmer))
+                                .filter(|&minimizer| seed.passes_entropy(minimizer))
+                            {
+                                let hash = murmur_hash3(minimizer ^ seed.toggle_mask);
+                                self.pending.push_back((seed_id, hash));
+                            }
+                        }
+                    }
+                }
+                Residue::Skip => {}
+                Residue::Reset => {
+                    self.cursor.clear();
+                    self.windows.iter_mut().for_each(MinimizerWindow::clear);
+                    self.last_code = None;
+                }
+            }
+        }
+        #[cfg(feature = "audit")]
+        match self.pending.front() {
+            Some(&(seed_id, hash)) => {
+                if let Some(emitted) = self.emitted.as_mut() {
+                    emitted[seed_id].push(hash);
+                }
+            }
+            // 审计模式下, 迭代结束时逐个种子与朴素实现对比
+            None => {
+                for (seed, hashes) in self
+                    .seeds
+                    .iter()
+                    .zip(self.emitted.take().unwrap_or_default())
+                {
+                    crate::audit::assert_matches_oracle(self.seq, seed, &hashes);
+                }
+            }
+        }
+        self.pending.pop_front()
+    }
+}
+
+fn for_each_with<C: LmerCursor, F: FnMut(usize, u64)>(
+    seq: &[u8],
+    cursor: C,
+    meros: &Meros,
+    mut f: F,
+) {
+    let window = MinimizerWindow::new(meros.window_size());
+    for (pos, hash) in MinimizerIterator::new(seq, cursor, window, meros) {
+        f(pos, hash);
+    }
+}
+
+/// Calls `f` with every `(pos, hash)` minimizer of a sequence, the same
+/// values `MinimizerIterator` yields. Common l-mer lengths (31, 21 and 15 for
+/// DNA, 12 for 4-bit protein alphabets) run on a `FixedCursor`
+/// specialization; other parameters use the generic `Cursor`. This is the
+/// only entry point with that specialization, and `benches/minimizer.rs`
+/// measures it at about the same throughput as the generic iterator.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{for_each_minimizer, Meros};
+///
+/// let meros = Meros::default();
+/// let mut hashes = Vec::new();
+/// for_each_minimizer(b"ACGTTGCAACGTGGCATTACGATCGATCGGGATCGACTAGC", &meros, |_, hash| {
+///     hashes.push(hash)
+/// });
+/// assert!(!hashes.is_empty());
+/// ```
+pub fn for_each_minimizer<F: FnMut(usize, u64)>(seq: &[u8], meros: &Meros, f: F) {
+    match (meros.l_mer, meros.alphabet.bits_per_char()) {
+        (31, 2) => for_each_with(seq, FixedCursor::<31, 2>::new(), meros, f),
+        (21, 2) => for_each_with(seq, FixedCursor::<21, 2>::new(), meros, f),
+        (15, 2) => for_each_with(seq, FixedCursor::<15, 2>::new(), meros, f),
+        (12, 4) => for_each_with(seq, FixedCursor::<12, 4>::new(), meros, f),
+        _ => for_each_with(seq, Cursor::new(meros), meros, f),
+    }
+}
+
+/// Scans a sequence and returns a MinimizerIterator. Qualities kept by the
+/// reader are carried over to the result. The body can be any byte buffer,
+/// e.g. `Vec<u8>` from a reader or `&[u8]` borrowed from a larger buffer.
 ///
 /// # Examples
 ///
 /// ```
 /// use seqkmer::{scan_sequence, Base, Meros, OptionPair, SeqHeader, SeqFormat};
 ///
-/// let header = SeqHeader {
-///     id: "test".to_string(),
-///     file_index: 0,
-///     reads_index: 0,
-///     format: SeqFormat::Fasta,
-/// };
+/// let header = SeqHeader::new("test", SeqFormat::Fasta);
 /// let seq = Base::new(header, OptionPair::Single(b"ATCGATCGATCG".to_vec()));
 /// let meros = Meros::new(11, 3, Some(0), None, None);
 /// let mut minimizer_iter = scan_sequence(&seq, &meros);
@@ -343,24 +1059,179 @@ impl<'a> Base<MinimizerIterator<'a>> {
 ///         println!("Minimizer: {:?}", minimizer);
 ///     }
 /// }
+///
+/// // 借用的子切片, 不复制
+/// let reference = b"NNNNATCGATCGATCGNNNN";
+/// let borrowed = Base::new(seq.header.clone(), OptionPair::Single(&reference[4..16]));
+/// let (OptionPair::Single(hits), OptionPair::Single(owned)) =
+///     (scan_sequence(&borrowed, &meros).body, scan_sequence(&seq, &meros).body)
+/// else {
+///     unreachable!()
+/// };
+/// assert_eq!(hits.collect::<Vec<_>>(), owned.collect::<Vec<_>>());
 /// ```
-pub fn scan_sequence<'a>(
-    sequence: &'a Base<Vec<u8>>,
+pub fn scan_sequence<'a, B: AsRef<[u8]>>(
+    sequence: &'a Base<B>,
     meros: &'a Meros,
 ) -> Base<MinimizerIterator<'a>> {
-    let func = |seq: &'a Vec<u8>| {
+    let body = match &sequence.body {
+        OptionPair::Pair(seq1, seq2) => OptionPair::Pair(
+            scan_slice(seq1.as_ref(), meros),
+            scan_slice(seq2.as_ref(), meros),
+        ),
+        OptionPair::Single(seq1) => OptionPair::Single(scan_slice(seq1.as_ref(), meros)),
+    };
+    // 保留读取时要求保存的质量值
+    Base {
+        header: sequence.header.clone(),
+        body,
+        qual: sequence.qual.clone(),
+    }
+}
+
+/// Returns the minimizer iterator over a plain byte slice, e.g. a region of
+/// a memory-mapped reference.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{scan_slice, Meros};
+///
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+/// let genome = b"ATCGATCGATCGTTAGCAGGCATC";
+/// assert!(scan_slice(&genome[..12], &meros).count() > 0);
+/// ```
+pub fn scan_slice<'a>(seq: &'a [u8], meros: &'a Meros) -> MinimizerIterator<'a> {
+    let cursor = Cursor::new(meros);
+    let window = MinimizerWindow::new(meros.window_size());
+    MinimizerIterator::new(seq, cursor, window, meros)
+}
+
+/// Scans only `range` of each mate of a record, returning `(pos, hash)`
+/// pairs where `pos` is the offset just past the residue that completed the
+/// minimizer (see [`MinimizerIterator::seq_pos`]) in full-record
+/// coordinates. The range is clamped to each mate's length; nothing is
+/// copied.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{scan_subrange, Base, Meros, OptionPair, SeqFormat, SeqHeader};
+///
+/// let header = SeqHeader::new("contig", SeqFormat::Fasta);
+/// let seq = Base::new(header, OptionPair::Single(b"NNNNNNNNNNACGTTGCAACGTNNNN".to_vec()));
+/// let meros = Meros::new(5, 4, None, None, None);
+///
+/// let hits = scan_subrange(&seq, 8..24, &meros);
+/// let hits = hits.body.single().unwrap();
+/// assert!(hits.iter().all(|&(pos, _)| pos >= 14 && pos <= 22));
+/// assert_eq!(hits.first().unwrap().0, 14);
+/// ```
+pub fn scan_subrange<B: AsRef<[u8]>>(
+    sequence: &Base<B>,
+    range: Range<usize>,
+    meros: &Meros,
+) -> Base<Vec<(usize, u64)>> {
+    let body = sequence.body.apply(|seq| {
+        let seq = seq.as_ref();
+        let end = range.end.min(seq.len());
+        let start = range.start.min(end);
         let cursor = Cursor::new(meros);
         let window = MinimizerWindow::new(meros.window_size());
-        MinimizerIterator::new(seq, cursor, window, meros)
+        let mut iter = MinimizerIterator::new(&seq[start..end], cursor, window, meros);
+        let mut hits = Vec::new();
+        while let Some((_, hash)) = iter.next() {
+            hits.push((start + iter.seq_pos(), hash));
+        }
+        hits
+    });
+    Base::new(sequence.header.clone(), body)
+}
+
+/// Scans each mate of a record with several parameter sets in one pass:
+/// every residue is decoded once and fed to one cursor and window per
+/// `Meros`. Returns, per mate, one `(pos, hash)` list per parameter set in
+/// the order of `meros`, matching what `scan_sequence` would yield for it.
+///
+/// All parameter sets must share the alphabet, the homopolymer compression
+/// and the IUPAC policy, since those decide how residues are decoded.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{scan_sequence, scan_sequence_multi, Base, Meros, OptionPair, SeqFormat, SeqHeader};
+///
+/// let header = SeqHeader::new("read", SeqFormat::Fasta);
+/// let seq = Base::new(
+///     header,
+///     OptionPair::Single(b"ACGTTGCAACGTGGCATTACGATCGATCGGGATCGACTAGCNACGTTGCAACGTGGCATTACGA".to_vec()),
+/// );
+/// let params = [Meros::new(21, 15, None, None, None), Meros::new(31, 21, None, None, None)];
+///
+/// let multi = scan_sequence_multi(&seq, &params);
+/// let multi = multi.body.single().unwrap();
+/// for (meros, hits) in params.iter().zip(multi) {
+///     let mut single = scan_sequence(&seq, meros);
+///     let OptionPair::Single(iter) = &mut single.body else { unreachable!() };
+///     assert_eq!(&iter.collect::<Vec<_>>(), hits);
+/// }
+/// ```
+pub fn scan_sequence_multi<B: AsRef<[u8]>>(
+    sequence: &Base<B>,
+    meros: &[Meros],
+) -> Base<Vec<Vec<(usize, u64)>>> {
+    let Some(first) = meros.first() else {
+        return Base::new(sequence.header.clone(), sequence.body.apply(|_| Vec::new()));
     };
+    assert!(
+        meros
+            .iter()
+            .all(|m| m.alphabet == first.alphabet && m.hpc == first.hpc && m.iupac == first.iupac),
+        "multi-k scanning needs a shared alphabet, hpc and IUPAC setting"
+    );
 
-    match &sequence.body {
-        OptionPair::Pair(seq1, seq2) => Base::new(
-            sequence.header.clone(),
-            OptionPair::Pair(func(&seq1), func(&seq2)),
-        ),
-        OptionPair::Single(seq1) => {
-            Base::new(sequence.header.clone(), OptionPair::Single(func(&seq1)))
+    let body = sequence.body.apply(|seq| {
+        let mut cursors: Vec<Cursor> = meros.iter().map(Cursor::new).collect();
+        let mut windows: Vec<MinimizerWindow> = meros
+            .iter()
+            .map(|m| MinimizerWindow::new(m.window_size()))
+            .collect();
+        let mut hits: Vec<Vec<(usize, u64)>> = vec![Vec::new(); meros.len()];
+        let mut last_code = None;
+
+        for (pos, &ch) in seq
+            .as_ref()
+            .iter()
+            .enumerate()
+            .filter(|&(_, &c)| c != b'\n' && c != b'\r')
+        {
+            match first.encode_residue(ch, pos) {
+                Residue::Code(code) if first.hpc && last_code == Some(code) => {}
+                Residue::Code(code) => {
+                    last_code = Some(code);
+                    // 每个碱基只解码一次, 分发给各组参数
+                    for (i, m) in meros.iter().enumerate() {
+                        let Some(lmer) = cursors[i].next_lmer(code) else {
+                            continue;
+                        };
+                        if let Some(minimizer) = windows[i]
+                            .next(to_candidate_lmer(m, lmer))
+                            .filter(|&minimizer| m.passes_entropy(minimizer))
+                        {
+                            let pos = hits[i].len() + 1;
+                            hits[i].push((pos, murmur_hash3(minimizer ^ m.toggle_mask)));
+                        }
+                    }
+                }
+                Residue::Skip => {}
+                Residue::Reset => {
+                    cursors.iter_mut().for_each(LmerCursor::clear);
+                    windows.iter_mut().for_each(MinimizerWindow::clear);
+                    last_code = None;
+                }
+            }
         }
-    }
+        hits
+    });
+    Base::new(sequence.header.clone(), body)
 }
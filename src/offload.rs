@@ -0,0 +1,152 @@
+//! Two-stage minimizer scanning for batch offload.
+//!
+//! Stage one computes the candidate l-mer ending at every residue of a
+//! packed batch. Each position only looks back `l_mer` residues, so the
+//! stage is data-parallel and is what a device kernel would run. Stage two
+//! slides the minimizer window over the candidates and hashes the
+//! minimizers on the CPU. Only a CPU kernel is provided; a GPU backend
+//! implements [`CandidateKernel`].
+
+use crate::feat::fmix64 as murmur_hash3;
+use crate::feat::{IupacPolicy, Meros, Residue};
+use crate::mmscanner::{to_candidate_lmer, MinimizerWindow};
+use std::io;
+
+/// Sequences concatenated into one buffer, the layout copied to a device.
+/// Line breaks are removed while packing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackedBatch {
+    pub residues: Vec<u8>,
+    /// 每条序列在 `residues` 中的起点, 末尾多一个总长度
+    pub offsets: Vec<usize>,
+}
+
+impl PackedBatch {
+    pub fn new<S: AsRef<[u8]>>(seqs: &[S]) -> Self {
+        let mut batch = Self {
+            residues: Vec::new(),
+            offsets: vec![0],
+        };
+        for seq in seqs {
+            batch
+                .residues
+                .extend(seq.as_ref().iter().filter(|&&c| c != b'\n' && c != b'\r'));
+            batch.offsets.push(batch.residues.len());
+        }
+        batch
+    }
+
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Residues of sequence `index`.
+    pub fn seq(&self, index: usize) -> &[u8] {
+        &self.residues[self.offsets[index]..self.offsets[index + 1]]
+    }
+}
+
+/// Stage one: writes, for every residue of `batch`, the candidate l-mer
+/// ending there, or None when fewer than `l_mer` residues precede it since
+/// the sequence start or the last ambiguous base. `out` gets exactly
+/// `batch.residues.len()` entries.
+pub trait CandidateKernel {
+    fn candidates(&self, batch: &PackedBatch, meros: &Meros, out: &mut Vec<Option<u64>>);
+}
+
+/// Runs stage one on the calling thread.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CpuKernel;
+
+impl CandidateKernel for CpuKernel {
+    fn candidates(&self, batch: &PackedBatch, meros: &Meros, out: &mut Vec<Option<u64>>) {
+        out.clear();
+        out.reserve(batch.residues.len());
+        let bits = meros.alphabet.bits_per_char();
+        for index in 0..batch.len() {
+            let (mut value, mut run) = (0u64, 0usize);
+            for &c in batch.seq(index) {
+                match meros.alphabet.encode(c) {
+                    Some(code) => {
+                        value = ((value << bits) | code) & meros.mask;
+                        run += 1;
+                    }
+                    None => (value, run) = (0, 0),
+                }
+                out.push((run >= meros.l_mer).then(|| to_candidate_lmer(meros, value)));
+            }
+        }
+    }
+}
+
+/// Scans a batch in two stages with `kernel`, returning the `(pos, hash)`
+/// minimizers of each sequence, identical to what `MinimizerIterator`
+/// yields. Homopolymer compression and IUPAC policies other than
+/// `Reset` are not supported and give an `InvalidInput` error.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::offload::{scan_batch_offloaded, CpuKernel, PackedBatch};
+/// use seqkmer::{scan_slice, Meros};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+/// let seqs = [&b"ATCGATCGATCGTTAGCAGG"[..], b"GGCATCNNACGTTGCAACG\nTGG", b"AC"];
+/// let hits = scan_batch_offloaded(&CpuKernel, &PackedBatch::new(&seqs), &meros)?;
+/// for (seq, hits) in seqs.iter().zip(&hits) {
+///     assert_eq!(hits, &scan_slice(seq, &meros).collect::<Vec<_>>());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn scan_batch_offloaded<K: CandidateKernel>(
+    kernel: &K,
+    batch: &PackedBatch,
+    meros: &Meros,
+) -> io::Result<Vec<Vec<(usize, u64)>>> {
+    if meros.hpc || meros.iupac != IupacPolicy::Reset {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "offloaded scanning supports neither homopolymer compression nor IUPAC policies",
+        ));
+    }
+    let mut candidates = Vec::new();
+    kernel.candidates(batch, meros, &mut candidates);
+    assert_eq!(candidates.len(), batch.residues.len());
+
+    // 第二阶段: 窗口与哈希在 CPU 上完成
+    let mut window = MinimizerWindow::new(meros.window_size());
+    let mut hits = Vec::with_capacity(batch.len());
+    for index in 0..batch.len() {
+        window.clear();
+        let mut seq_hits = Vec::new();
+        let (start, end) = (batch.offsets[index], batch.offsets[index + 1]);
+        for (pos, candidate) in (start..end).zip(&candidates[start..end]) {
+            match candidate {
+                Some(candidate) => {
+                    if let Some(minimizer) = window
+                        .next(*candidate)
+                        .filter(|&minimizer| meros.passes_entropy(minimizer))
+                    {
+                        let hash = murmur_hash3(minimizer ^ meros.toggle_mask);
+                        seq_hits.push((seq_hits.len() + 1, hash));
+                    }
+                }
+                // 重置只发生在非法字符处, 其后的 l-mer 尚未填满
+                None if meros.encode_residue(batch.residues[pos], pos - start)
+                    == Residue::Reset =>
+                {
+                    window.clear()
+                }
+                None => {}
+            }
+        }
+        hits.push(seq_hits);
+    }
+    Ok(hits)
+}
@@ -0,0 +1,227 @@
+use crate::mmscanner::MinimizerIterator;
+use crate::reader::SpaceDist;
+use crate::seq::Base;
+use crate::utils::OptionPair;
+use std::collections::HashMap;
+use std::io::{Result, Write};
+
+/// Writes one line of Kraken2's standard output:
+/// `C|U <tab> id <tab> taxid <tab> length <tab> hits`.
+///
+/// The read is reported as classified when `taxid` is not 0. Lengths come
+/// from `fmt_seq_size` (`150|148` for pairs) and mate hit lists are joined
+/// with ` |:| `, as Kraken2 does.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{scan_sequence, write_kraken_output, Base, Meros, OptionPair, SpaceDist};
+/// use seqkmer::{SeqFormat, SeqHeader};
+///
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+/// let header = SeqHeader::new("read1", SeqFormat::Fasta);
+/// let seq = Base::new(header, OptionPair::Single(b"ATCGATCGATCGATCGATCG".to_vec()));
+/// let record = scan_sequence(&seq, &meros);
+///
+/// let mut dist = SpaceDist::new((0, 10));
+/// dist.add(562, 3);
+/// dist.fill_tail_with_zeros();
+///
+/// let mut out = Vec::new();
+/// write_kraken_output(&mut out, &record, 562, &OptionPair::Single(dist)).unwrap();
+/// assert_eq!(out, b"C\tread1\t562\t20\t0:2 562:1 0:7\n");
+/// ```
+pub fn write_kraken_output<W: Write>(
+    w: &mut W,
+    record: &Base<MinimizerIterator>,
+    taxid: u64,
+    hits: &OptionPair<SpaceDist>,
+) -> Result<()> {
+    let status = if taxid == 0 { "U" } else { "C" };
+    write!(
+        w,
+        "{}\t{}\t{}\t{}\t",
+        status,
+        record.header.id,
+        taxid,
+        record.fmt_seq_size()
+    )?;
+    match hits {
+        OptionPair::Single(dist) => dist.write_to(w)?,
+        OptionPair::Pair(dist1, dist2) => {
+            dist1.write_to(w)?;
+            w.write_all(b" |:| ")?;
+            dist2.write_to(w)?;
+        }
+    }
+    w.write_all(b"\n")
+}
+
+/// The parts of a taxonomy a Kraken2 report needs.
+pub trait TaxonTree {
+    /// Returns the parent of `taxid`, or `None` for the root.
+    fn parent(&self, taxid: u64) -> Option<u64>;
+    /// NCBI rank name, e.g. `species` or `no rank`.
+    fn rank(&self, taxid: u64) -> &str;
+    fn name(&self, taxid: u64) -> &str;
+}
+
+/// Kraken2 rank code letter of an NCBI rank name.
+fn rank_letter(rank: &str) -> Option<char> {
+    match rank {
+        "superkingdom" | "domain" => Some('D'),
+        "kingdom" => Some('K'),
+        "phylum" => Some('P'),
+        "class" => Some('C'),
+        "order" => Some('O'),
+        "family" => Some('F'),
+        "genus" => Some('G'),
+        "species" => Some('S'),
+        _ => None,
+    }
+}
+
+/// Rank code of a report line: the letter of the nearest ranked ancestor,
+/// followed by the number of levels below it (`S1` for a strain).
+fn rank_code<T: TaxonTree + ?Sized>(tree: &T, taxid: u64) -> String {
+    let mut depth = 0;
+    let mut node = taxid;
+    loop {
+        let letter = match tree.parent(node) {
+            None => Some('R'),
+            Some(_) => rank_letter(tree.rank(node)),
+        };
+        if let Some(letter) = letter {
+            return if depth == 0 {
+                letter.to_string()
+            } else {
+                format!("{}{}", letter, depth)
+            };
+        }
+        depth += 1;
+        node = tree.parent(node).expect("non-root node has a parent");
+    }
+}
+
+/// Writes a Kraken2 report (`--report` format) from the number of reads
+/// assigned to each taxon, with reads under taxid 0 counted as unclassified.
+///
+/// Each line holds the clade percentage, clade reads, reads assigned to the
+/// taxon itself, rank code, taxid and the name indented by depth. Siblings
+/// are sorted by clade size, largest first.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{write_kraken_report, TaxonTree};
+/// use std::collections::HashMap;
+///
+/// struct Tree;
+/// impl TaxonTree for Tree {
+///     fn parent(&self, taxid: u64) -> Option<u64> {
+///         match taxid {
+///             1 => None,
+///             2 => Some(1),
+///             _ => Some(2),
+///         }
+///     }
+///     fn rank(&self, taxid: u64) -> &str {
+///         if taxid == 2 { "genus" } else { "species" }
+///     }
+///     fn name(&self, taxid: u64) -> &str {
+///         match taxid {
+///             1 => "root",
+///             2 => "Escherichia",
+///             _ => "Escherichia coli",
+///         }
+///     }
+/// }
+///
+/// let counts = HashMap::from([(0, 2), (562, 6), (2, 2)]);
+/// let mut out = Vec::new();
+/// write_kraken_report(&mut out, &Tree, &counts).unwrap();
+/// assert_eq!(
+///     String::from_utf8(out).unwrap(),
+///     " 20.00\t2\t2\tU\t0\tunclassified\n\
+///      \x2080.00\t8\t0\tR\t1\troot\n\
+///      \x2080.00\t8\t2\tG\t2\t  Escherichia\n\
+///      \x2060.00\t6\t6\tS\t562\t    Escherichia coli\n"
+/// );
+/// ```
+pub fn write_kraken_report<W, T>(w: &mut W, tree: &T, counts: &HashMap<u64, usize>) -> Result<()>
+where
+    W: Write,
+    T: TaxonTree + ?Sized,
+{
+    let total: usize = counts.values().sum();
+    let percent = |n: usize| {
+        if total == 0 {
+            0.0
+        } else {
+            n as f64 * 100.0 / total as f64
+        }
+    };
+
+    // 累加每个分类单元及其祖先的 clade 计数
+    let mut clade: HashMap<u64, usize> = HashMap::new();
+    let mut children: HashMap<u64, Vec<u64>> = HashMap::new();
+    let mut roots = Vec::new();
+    for (&taxid, &count) in counts {
+        if taxid == 0 || count == 0 {
+            continue;
+        }
+        let mut node = taxid;
+        loop {
+            let seen = clade.contains_key(&node);
+            *clade.entry(node).or_default() += count;
+            match tree.parent(node) {
+                Some(parent) => {
+                    if !seen {
+                        children.entry(parent).or_default().push(node);
+                    }
+                    node = parent;
+                }
+                None => {
+                    if !seen {
+                        roots.push(node);
+                    }
+                    break;
+                }
+            }
+        }
+    }
+
+    let unclassified = counts.get(&0).copied().unwrap_or(0);
+    if unclassified > 0 {
+        writeln!(
+            w,
+            "{:6.2}\t{}\t{}\tU\t0\tunclassified",
+            percent(unclassified),
+            unclassified,
+            unclassified
+        )?;
+    }
+
+    let by_clade = |a: &u64, b: &u64| clade[b].cmp(&clade[a]).then(a.cmp(b));
+    roots.sort_by(by_clade);
+    let mut stack: Vec<(u64, usize)> = roots.into_iter().rev().map(|t| (t, 0)).collect();
+    while let Some((taxid, depth)) = stack.pop() {
+        let clade_reads = clade[&taxid];
+        writeln!(
+            w,
+            "{:6.2}\t{}\t{}\t{}\t{}\t{}{}",
+            percent(clade_reads),
+            clade_reads,
+            counts.get(&taxid).copied().unwrap_or(0),
+            rank_code(tree, taxid),
+            taxid,
+            "  ".repeat(depth),
+            tree.name(taxid)
+        )?;
+        if let Some(kids) = children.get_mut(&taxid) {
+            kids.sort_by(by_clade);
+            stack.extend(kids.iter().rev().map(|&kid| (kid, depth + 1)));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,224 @@
+use crate::feat::{dna_to_value, fmix64 as murmur_hash3, Alphabet, IupacPolicy, Meros};
+use crate::mmscanner::{to_candidate_lmer, Cursor, LmerCursor, MinimizerWindow};
+
+const BASES_PER_WORD: usize = 32;
+
+/// A DNA sequence packed at 2 bits per base, with a separate bit mask for
+/// positions that are not `A`, `C`, `G` or `T`.
+///
+/// Packing is lossy: line breaks are dropped, case is folded and every
+/// ambiguous base reads back as `N`.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::PackedSeq;
+///
+/// let packed = PackedSeq::from_bytes(b"ACGTn\nacgt");
+/// assert_eq!(packed.len(), 9);
+/// assert_eq!(packed.to_vec(), b"ACGTNACGT");
+/// assert_eq!(packed.get(2), Some(0b10));
+/// assert_eq!(packed.get(4), None);
+/// assert_eq!(packed.kmer_at(0, 4), Some(0b00011011));
+/// assert_eq!(packed.kmer_at(2, 4), None);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackedSeq {
+    // 高位在前, 第 i 个碱基位于 words[i / 32] 的 62 - 2 * (i % 32) 位
+    words: Vec<u64>,
+    n_mask: Vec<u64>,
+    len: usize,
+}
+
+impl PackedSeq {
+    /// Packs a nucleotide sequence.
+    pub fn from_bytes(seq: &[u8]) -> Self {
+        let mut packed = Self {
+            words: Vec::with_capacity(seq.len().div_ceil(BASES_PER_WORD)),
+            n_mask: Vec::with_capacity(seq.len().div_ceil(64)),
+            len: 0,
+        };
+        for &c in seq.iter().filter(|&&c| c != b'\n' && c != b'\r') {
+            packed.push(c);
+        }
+        packed
+    }
+
+    /// Appends one base.
+    pub fn push(&mut self, c: u8) {
+        let i = self.len;
+        if i.is_multiple_of(BASES_PER_WORD) {
+            self.words.push(0);
+        }
+        if i.is_multiple_of(64) {
+            self.n_mask.push(0);
+        }
+        match dna_to_value(c) {
+            Some(code) => self.words[i / BASES_PER_WORD] |= code << Self::shift(i),
+            None => self.n_mask[i / 64] |= 1 << (i % 64),
+        }
+        self.len += 1;
+    }
+
+    #[inline]
+    fn shift(i: usize) -> usize {
+        62 - 2 * (i % BASES_PER_WORD)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns true if the base at `i` is ambiguous.
+    #[inline]
+    pub fn is_n(&self, i: usize) -> bool {
+        self.n_mask[i / 64] >> (i % 64) & 1 == 1
+    }
+
+    /// Returns the 2-bit code of the base at `i`, or None if it is
+    /// ambiguous or out of range.
+    #[inline]
+    pub fn get(&self, i: usize) -> Option<u64> {
+        if i >= self.len || self.is_n(i) {
+            return None;
+        }
+        Some(self.words[i / BASES_PER_WORD] >> Self::shift(i) & 0b11)
+    }
+
+    /// Extracts the packed k-mer starting at `pos` (`k` at most 32) with
+    /// word-level shifts, or None if it runs past the end or holds an
+    /// ambiguous base.
+    pub fn kmer_at(&self, pos: usize, k: usize) -> Option<u64> {
+        assert!(k > 0 && k <= BASES_PER_WORD, "k must be in 1..=32");
+        if pos + k > self.len || (pos..pos + k).any(|i| self.is_n(i)) {
+            return None;
+        }
+        let word = pos / BASES_PER_WORD;
+        let offset = 2 * (pos % BASES_PER_WORD);
+        // 跨越两个字时拼接
+        let mut value = self.words[word] << offset;
+        if offset > 0 && word + 1 < self.words.len() {
+            value |= self.words[word + 1] >> (64 - offset);
+        }
+        Some(value >> (64 - 2 * k))
+    }
+
+    /// Unpacks to uppercase bytes, with `N` for ambiguous bases.
+    pub fn to_vec(&self) -> Vec<u8> {
+        (0..self.len)
+            .map(|i| self.get(i).map_or(b'N', |code| b"ACGT"[code as usize]))
+            .collect()
+    }
+
+    /// Scans the packed sequence for minimizers without unpacking it,
+    /// yielding the same `(pos, hash)` pairs as `MinimizerIterator` over the
+    /// original bytes. `meros` must use the DNA alphabet.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `meros` uses the DNA alphabet and
+    /// [`IupacPolicy::Reset`]: packing stores every ambiguity code as `N`,
+    /// so the other IUPAC policies cannot be applied.
+    ///
+    /// ```should_panic
+    /// use seqkmer::{IupacPolicy, Meros, PackedSeq};
+    ///
+    /// let meros = Meros::new(15, 9, None, None, None).with_iupac_policy(IupacPolicy::Skip);
+    /// PackedSeq::from_bytes(b"ACGTTGCAACGTRGCATTACGATCGA").minimizers(&meros);
+    /// ```
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{Cursor, Meros, MinimizerIterator, MinimizerWindow, PackedSeq};
+    ///
+    /// let seq = b"ACGTTGCAACGTGGCATTACGATCGANTCGGGATCGACTAGCACGTTGCAACGTGG";
+    /// let meros = Meros::new(15, 9, None, None, None);
+    /// let window = MinimizerWindow::new(meros.window_size());
+    /// let expected: Vec<_> = MinimizerIterator::new(seq, Cursor::new(&meros), window, &meros).collect();
+    ///
+    /// let packed = PackedSeq::from_bytes(seq);
+    /// assert_eq!(packed.minimizers(&meros).collect::<Vec<_>>(), expected);
+    /// ```
+    pub fn minimizers<'a>(&'a self, meros: &'a Meros) -> PackedMinimizerIterator<'a> {
+        assert!(
+            meros.alphabet == Alphabet::Dna,
+            "packed sequences hold DNA only"
+        );
+        assert!(
+            meros.iupac == IupacPolicy::Reset,
+            "packed sequences store IUPAC codes as N, so only IupacPolicy::Reset applies"
+        );
+        PackedMinimizerIterator {
+            seq: self,
+            meros,
+            cursor: Cursor::new(meros),
+            window: MinimizerWindow::new(meros.window_size()),
+            pos: 0,
+            size: 0,
+            last_code: None,
+        }
+    }
+}
+
+impl From<&[u8]> for PackedSeq {
+    fn from(seq: &[u8]) -> Self {
+        Self::from_bytes(seq)
+    }
+}
+
+impl From<&PackedSeq> for Vec<u8> {
+    fn from(seq: &PackedSeq) -> Self {
+        seq.to_vec()
+    }
+}
+
+/// Minimizer iterator over a [`PackedSeq`].
+pub struct PackedMinimizerIterator<'a> {
+    seq: &'a PackedSeq,
+    meros: &'a Meros,
+    cursor: Cursor,
+    window: MinimizerWindow,
+    pos: usize,
+    pub size: usize,
+    last_code: Option<u64>,
+}
+
+impl Iterator for PackedMinimizerIterator<'_> {
+    type Item = (usize, u64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pos < self.seq.len() {
+            let code = self.seq.get(self.pos);
+            self.pos += 1;
+            match code {
+                Some(code) if self.meros.hpc && self.last_code == Some(code) => {}
+                Some(code) => {
+                    self.last_code = Some(code);
+                    let Some(lmer) = self.cursor.next_lmer(code) else {
+                        continue;
+                    };
+                    let candidate_lmer = to_candidate_lmer(self.meros, lmer);
+                    if let Some(minimizer) = self
+                        .window
+                        .next(candidate_lmer)
+                        .filter(|&minimizer| self.meros.passes_entropy(minimizer))
+                    {
+                        self.size += 1;
+                        return Some((self.size, murmur_hash3(minimizer ^ self.meros.toggle_mask)));
+                    }
+                }
+                None => {
+                    self.cursor.clear();
+                    self.window.clear();
+                    self.last_code = None;
+                }
+            }
+        }
+        None
+    }
+}
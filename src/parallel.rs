@@ -1,15 +1,23 @@
+use crate::affinity::Affinity;
+use crate::demux::Demultiplexer;
+use crate::error::{Result, SeqKmerError};
 use crate::feat::Meros;
+use crate::filter::Filter;
+use crate::metrics::PipelineMetrics;
 use crate::mmscanner::scan_sequence;
-use crate::reader::detect_file_format;
+use crate::progress::{Progress, ProgressEvent};
 use crate::reader::Reader;
-use crate::seq::{Base, SeqFormat};
+use crate::seq::Base;
+use crate::trim::Transform;
+use crate::FastxReader;
 use crate::MinimizerIterator;
-use crate::{FastaReader, FastqReader};
-use crossbeam_channel::{bounded, Receiver};
+use crossbeam_channel::{bounded, select, unbounded, Receiver, Sender};
 use scoped_threadpool::Pool;
-use std::collections::HashMap;
-use std::io::Result;
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
 
 /// A wrapper for parallel processing items.
 ///
@@ -30,143 +38,2123 @@ impl<P> ParallelItem<P> {
     }
 }
 
+/// Shared flag that asks a parallel pipeline to stop early.
+///
+/// Once cancelled, the producer stops reading and workers drop the batches
+/// still queued, so the collector sees the end of the results shortly after.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::CancellationToken;
+///
+/// let token = CancellationToken::new();
+/// let handle = token.clone();
+/// assert!(!token.is_cancelled());
+/// handle.cancel();
+/// assert!(token.is_cancelled());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests cancellation. Every clone of the token observes it.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Settings of a parallel pipeline, shared by the `read_parallel` family.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{Affinity, CancellationToken, ParallelConfig};
+///
+/// let token = CancellationToken::new();
+/// let config = ParallelConfig::new(8)
+///     .with_queue_depth(32)
+///     .with_batch_size(500)
+///     .with_ordered(true)
+///     .with_cancellation(token.clone())
+///     .with_affinity(Affinity::NumaNodes(vec![0]));
+/// assert_eq!(config.n_threads, 8);
+/// assert_eq!(config.queue_depth(), 32);
+///
+/// // 默认队列深度为 n_threads + 2
+/// assert_eq!(ParallelConfig::new(4).queue_depth(), 6);
+/// assert_eq!(ParallelConfig::new(4).affinity, Affinity::Unpinned);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ParallelConfig {
+    /// Total threads: the producer, the collector and `n_threads - 2`
+    /// workers. Must be at least 3.
+    pub n_threads: usize,
+    /// Batches queued between the stages; `None` means `n_threads + 2`.
+    pub queue_depth: Option<usize>,
+    /// Records per reader batch, set on the reader before the first read;
+    /// `None` keeps the reader's own setting.
+    pub batch_size: Option<usize>,
+    /// Whether `ParallelResult::next` returns outputs in input order rather
+    /// than as workers finish.
+    pub ordered: bool,
+    /// Token that stops the pipeline early; `None` uses a fresh token for
+    /// every run.
+    pub token: Option<CancellationToken>,
+    /// Where the pipeline threads run.
+    pub affinity: Affinity,
+    /// Upper bound on the sequence and quality bytes queued for or held by
+    /// the workers; `None` bounds only the number of batches.
+    pub max_inflight_bytes: Option<usize>,
+    /// Batches of at least this many bytes are split for idle workers;
+    /// `None` never splits.
+    pub split_bytes: Option<usize>,
+    /// Counters updated as the pipeline runs.
+    pub metrics: Option<Arc<PipelineMetrics>>,
+    /// Whether reruns over the same input must give bit-identical results;
+    /// see [`ParallelConfig::with_deterministic`].
+    pub deterministic: bool,
+}
+
+impl ParallelConfig {
+    pub fn new(n_threads: usize) -> Self {
+        Self {
+            n_threads,
+            queue_depth: None,
+            batch_size: None,
+            ordered: false,
+            token: None,
+            affinity: Affinity::Unpinned,
+            max_inflight_bytes: None,
+            split_bytes: None,
+            metrics: None,
+            deterministic: false,
+        }
+    }
+
+    pub fn with_queue_depth(mut self, queue_depth: usize) -> Self {
+        self.queue_depth = Some(queue_depth.max(1));
+        self
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = Some(batch_size.max(1));
+        self
+    }
+
+    pub fn with_ordered(mut self, ordered: bool) -> Self {
+        self.ordered = ordered;
+        self
+    }
+
+    /// Stops the pipeline once `token` is cancelled; see
+    /// `read_parallel_cancellable`.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    /// Pins the pipeline threads; see [`Affinity`].
+    pub fn with_affinity(mut self, affinity: Affinity) -> Self {
+        self.affinity = affinity;
+        self
+    }
+
+    /// Bounds the memory of the batches in flight by bytes rather than by
+    /// count, so long reads cannot pile up. The producer waits while the
+    /// limit would be exceeded, except that a single batch larger than the
+    /// limit is let through alone. Recycled buffers larger than the limit's
+    /// share of the queue are freed instead of kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{read_parallel_with_config, FastaReader, Meros, ParallelConfig};
+    /// use seqkmer::{Base, MinimizerIterator, ParallelResult};
+    /// use std::path::Path;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut reader = FastaReader::from_path(Path::new("tests/data/test.fasta"), 0)?;
+    /// let meros = Meros::new(11, 3, Some(0), None, None);
+    /// // 每条记录都超过 1 字节, 所以一次只有一批在处理
+    /// let config = ParallelConfig::new(4)
+    ///     .with_batch_size(1)
+    ///     .with_max_inflight_bytes(1);
+    ///
+    /// let (active, peak) = (AtomicUsize::new(0), AtomicUsize::new(0));
+    /// let work = |seqs: &mut Vec<Base<MinimizerIterator>>| {
+    ///     peak.fetch_max(active.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+    ///     std::thread::sleep(std::time::Duration::from_millis(5));
+    ///     active.fetch_sub(1, Ordering::SeqCst);
+    ///     seqs.len()
+    /// };
+    /// let func = |result: &mut ParallelResult<usize>| while result.next().is_some() {};
+    ///
+    /// read_parallel_with_config(&mut reader, &config, &meros, work, func)?;
+    /// assert_eq!(peak.load(Ordering::SeqCst), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_max_inflight_bytes(mut self, bytes: usize) -> Self {
+        self.max_inflight_bytes = Some(bytes);
+        self
+    }
+
+    /// Lets a worker that receives a batch of at least `bytes` sequence and
+    /// quality bytes hand half of it to an idle worker, so a batch with a
+    /// few very long reads does not leave one straggler. Halves can be split
+    /// again. `work` then runs once per part, so the collector sees more
+    /// outputs than there were batches. Ignored in ordered mode.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{read_parallel_with_config, FastaReader, Meros, ParallelConfig};
+    /// use seqkmer::{Base, MinimizerIterator, ParallelResult};
+    /// use std::path::Path;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut reader = FastaReader::from_path(Path::new("tests/data/test.fasta"), 0)?;
+    /// let meros = Meros::new(11, 3, Some(0), None, None);
+    /// let config = ParallelConfig::new(4).with_split_bytes(1);
+    ///
+    /// let work = |seqs: &mut Vec<Base<MinimizerIterator>>| seqs.len();
+    /// // 无论是否拆分, 每条记录都恰好处理一次
+    /// let func = |result: &mut ParallelResult<usize>| assert_eq!(result.sum::<usize>(), 3);
+    /// read_parallel_with_config(&mut reader, &config, &meros, work, func)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_split_bytes(mut self, bytes: usize) -> Self {
+        self.split_bytes = Some(bytes);
+        self
+    }
+
+    /// Records throughput, queue occupancy and worker utilization into
+    /// `metrics`. See [`PipelineMetrics`].
+    pub fn with_metrics(mut self, metrics: Arc<PipelineMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Makes reruns over the same input reproducible, bit for bit, for
+    /// collectors that fold floats or fill hash maps. In deterministic mode:
+    ///
+    /// - outputs are collected in input order, as with `with_ordered(true)`;
+    /// - batch sizes stay fixed: `read_parallel_adaptive_with_config` does
+    ///   not consult its controller and batches are never split
+    ///   (`with_split_bytes`);
+    /// - `buffer_map_parallel_with_config` visits the entries sorted by key.
+    ///
+    /// `work` itself must not depend on which thread runs it; per-worker
+    /// state from `read_parallel_with_state` is not reproducible.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{read_parallel_with_config, FastaReader, Meros, ParallelConfig};
+    /// use seqkmer::{Base, MinimizerIterator, ParallelResult};
+    /// use std::path::Path;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let meros = Meros::new(11, 3, Some(0), None, None);
+    /// let config = ParallelConfig::new(4)
+    ///     .with_batch_size(1)
+    ///     .with_split_bytes(1)
+    ///     .with_deterministic(true);
+    ///
+    /// let mut runs = Vec::new();
+    /// for _ in 0..3 {
+    ///     let mut reader = FastaReader::from_path(Path::new("tests/data/test.fasta"), 0)?;
+    ///     let work = |seqs: &mut Vec<Base<MinimizerIterator>>| seqs.len() as f64 / 3.0;
+    ///     let mut bits = 0;
+    ///     let func = |result: &mut ParallelResult<f64>| {
+    ///         // 浮点累加与顺序有关
+    ///         bits = result.fold(0.1, |acc, x| acc * 0.5 + x).to_bits();
+    ///     };
+    ///     read_parallel_with_config(&mut reader, &config, &meros, work, func)?;
+    ///     runs.push(bits);
+    /// }
+    /// assert!(runs.windows(2).all(|pair| pair[0] == pair[1]));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Whether outputs are collected in input order.
+    fn collects_in_order(&self) -> bool {
+        self.ordered || self.deterministic
+    }
+
+    /// Returns the number of batches queued between the stages.
+    pub fn queue_depth(&self) -> usize {
+        self.queue_depth.unwrap_or(self.n_threads + 2)
+    }
+}
+
 /// Represents the result of a parallel operation.
 pub struct ParallelResult<P>
 where
-    P: Send,
+    P: Send,
+{
+    recv: Receiver<(usize, P)>,
+    filtered: Arc<AtomicUsize>,
+    token: CancellationToken,
+    /// 有序模式下, 提前到达的输出按批次序号暂存
+    pending: Option<BTreeMap<usize, P>>,
+    next_index: usize,
+    /// 生产者的读取错误, 由 `try_next` 在结果取完后交出
+    read_error: Arc<Mutex<Option<SeqKmerError>>>,
+}
+
+impl<P> ParallelResult<P>
+where
+    P: Send,
+{
+    fn new(recv: Receiver<(usize, P)>) -> Self {
+        Self {
+            recv,
+            filtered: Arc::new(AtomicUsize::new(0)),
+            token: CancellationToken::new(),
+            pending: None,
+            next_index: 0,
+            read_error: Arc::default(),
+        }
+    }
+
+    fn with_token(mut self, token: CancellationToken) -> Self {
+        self.token = token;
+        self
+    }
+
+    fn with_ordered(mut self, ordered: bool) -> Self {
+        self.pending = ordered.then(BTreeMap::new);
+        self
+    }
+
+    /// Stops the pipeline early: the reader is not polled again and queued
+    /// batches are discarded. Results already produced can still be read.
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    /// Returns the pipeline's cancellation token, e.g. to hand to another
+    /// thread.
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    /// Returns the number of records dropped by a filter so far. The count is
+    /// final once `next` has returned `None`.
+    pub fn filtered(&self) -> usize {
+        self.filtered.load(Ordering::Relaxed)
+    }
+
+    /// Retrieves the next item from the parallel result.
+    ///
+    /// `ParallelResult` is also an `Iterator` over the unwrapped outputs;
+    /// this method is kept for callers written against `ParallelItem`.
+    #[inline]
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<ParallelItem<P>> {
+        let Some(pending) = self.pending.as_mut() else {
+            return self
+                .recv
+                .recv()
+                .ok()
+                .map(|(_, output)| ParallelItem(output));
+        };
+        loop {
+            if let Some(output) = pending.remove(&self.next_index) {
+                self.next_index += 1;
+                return Some(ParallelItem(output));
+            }
+            match self.recv.recv() {
+                Ok((index, output)) => {
+                    pending.insert(index, output);
+                }
+                // 取消后会缺少批次, 剩下的仍按顺序交出
+                Err(_) => {
+                    let (index, output) = pending.pop_first()?;
+                    self.next_index = index + 1;
+                    return Some(ParallelItem(output));
+                }
+            }
+        }
+    }
+
+    /// Like `next`, but once the outputs are exhausted returns the error
+    /// that ended the input, if any, so a collector can stop with `?`. The
+    /// driver still returns the error as well.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{read_parallel, FastqReader, Meros, OptionPair, ParallelResult, SeqKmerError};
+    /// use seqkmer::{Base, MinimizerIterator};
+    ///
+    /// // 第二条记录缺少质量行
+    /// let input = b"@r1\nACGTACGTACGTA\n+\nIIIIIIIIIIIII\n@r2\nACGT\n".as_slice();
+    /// let mut reader = FastqReader::new(OptionPair::Single(input), 0, 0).with_batch_size(1);
+    /// let meros = Meros::new(11, 3, Some(0), None, None);
+    ///
+    /// let work = |seqs: &mut Vec<Base<MinimizerIterator>>| seqs.len();
+    /// let mut failed = false;
+    /// let func = |result: &mut ParallelResult<usize>| {
+    ///     let mut drain = || -> Result<usize, SeqKmerError> {
+    ///         let mut total = 0;
+    ///         while let Some(count) = result.try_next()? {
+    ///             total += count;
+    ///         }
+    ///         Ok(total)
+    ///     };
+    ///     failed = drain().is_err();
+    /// };
+    ///
+    /// let err = read_parallel(&mut reader, 3, &meros, work, func).unwrap_err();
+    /// assert!(failed);
+    /// assert!(matches!(err, SeqKmerError::Parse { .. }));
+    /// ```
+    pub fn try_next(&mut self) -> Result<Option<P>> {
+        match ParallelResult::next(self) {
+            Some(item) => Ok(Some(item.unwrap())),
+            None => self.read_error.lock().unwrap().take().map_or(Ok(None), Err),
+        }
+    }
+}
+
+/// Yields the outputs themselves, so collectors can use `for` loops and
+/// iterator adapters.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{read_parallel, FastaReader, Meros, ParallelResult};
+/// use seqkmer::{Base, MinimizerIterator};
+/// use std::path::Path;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut reader = FastaReader::from_path(Path::new("tests/data/test.fasta"), 0)?;
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+///
+/// let work = |seqs: &mut Vec<Base<MinimizerIterator>>| seqs.len();
+/// let func = |result: &mut ParallelResult<usize>| {
+///     assert_eq!(result.sum::<usize>(), 3);
+/// };
+/// read_parallel(&mut reader, 4, &meros, work, func)?;
+/// # Ok(())
+/// # }
+/// ```
+impl<P> Iterator for ParallelResult<P>
+where
+    P: Send,
+{
+    type Item = P;
+
+    fn next(&mut self) -> Option<P> {
+        ParallelResult::next(self).map(ParallelItem::unwrap)
+    }
+}
+
+/// Feedback controller that resizes reader batches to keep per-batch
+/// processing time inside a target latency window.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::BatchController;
+/// use std::time::Duration;
+///
+/// let controller = BatchController::new(
+///     100,
+///     (10, 1000),
+///     (Duration::from_millis(10), Duration::from_millis(20)),
+/// );
+///
+/// // 100 records took 40ms, twice the upper bound: the batch shrinks.
+/// controller.observe(100, Duration::from_millis(40));
+/// assert!(controller.batch_size() < 100);
+///
+/// // Inside the window nothing changes.
+/// let size = controller.batch_size();
+/// controller.observe(size, Duration::from_millis(15));
+/// assert_eq!(controller.batch_size(), size);
+/// ```
+#[derive(Debug)]
+pub struct BatchController {
+    batch_size: AtomicUsize,
+    /// 批量大小的上下限, 闭区间
+    bounds: (usize, usize),
+    /// 目标耗时窗口
+    target: (Duration, Duration),
+}
+
+impl BatchController {
+    /// Creates a controller starting at `initial` records per batch.
+    pub fn new(initial: usize, bounds: (usize, usize), target: (Duration, Duration)) -> Self {
+        assert!(bounds.0 > 0 && bounds.0 <= bounds.1);
+        assert!(target.0 <= target.1);
+        Self {
+            batch_size: AtomicUsize::new(initial.clamp(bounds.0, bounds.1)),
+            bounds,
+            target,
+        }
+    }
+
+    /// Returns the batch size the producer should request next.
+    #[inline]
+    pub fn batch_size(&self) -> usize {
+        self.batch_size.load(Ordering::Relaxed)
+    }
+
+    /// Records that a batch of `records` records took `elapsed` to process
+    /// and moves the batch size halfway towards the size that would have hit
+    /// the middle of the target window.
+    pub fn observe(&self, records: usize, elapsed: Duration) {
+        if records == 0 || (elapsed >= self.target.0 && elapsed <= self.target.1) {
+            return;
+        }
+        let mid = (self.target.0 + self.target.1).as_secs_f64() / 2.0;
+        let per_record = elapsed.as_secs_f64().max(1e-9) / records as f64;
+        let ideal = (mid / per_record) as usize;
+        let _ = self
+            .batch_size
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| {
+                let next = (current + ideal) / 2;
+                Some(next.clamp(self.bounds.0, self.bounds.1))
+            });
+    }
+}
+
+/// Creates a reader based on the file format.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::create_reader;
+/// use std::path::Path;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let file_path = Path::new("tests/data/test.fasta").to_str().unwrap().to_string();
+/// let reader = create_reader(&[file_path], 0, 0)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn create_reader(
+    file_pair: &[String],
+    file_index: usize,
+    score: i32,
+) -> Result<Box<dyn Reader + Send>> {
+    let paths = crate::OptionPair::from_slice(file_pair);
+    Ok(Box::new(FastxReader::from_paths(paths, file_index, score)?))
+}
+
+/// Sequence and quality bytes held by a batch.
+fn batch_bytes(seqs: &[Base<Vec<u8>>]) -> usize {
+    seqs.iter()
+        .map(|seq| {
+            let qual = seq
+                .qual
+                .as_ref()
+                .map_or(0, |q| q.reduce(0, |acc, q| acc + q.len()));
+            seq.body.reduce(0, |acc, s| acc + s.len()) + qual
+        })
+        .sum()
+}
+
+/// Bytes of the batches queued for or held by the workers, kept under
+/// `limit` when one is set.
+struct ByteBudget {
+    limit: Option<usize>,
+    used: Mutex<usize>,
+    freed: Condvar,
+}
+
+impl ByteBudget {
+    fn new(limit: Option<usize>) -> Self {
+        Self {
+            limit,
+            used: Mutex::new(0),
+            freed: Condvar::new(),
+        }
+    }
+
+    /// Waits until `bytes` more fit under the limit. A batch larger than the
+    /// limit goes through once nothing else is in flight, so it cannot stall
+    /// the pipeline.
+    fn acquire(&self, bytes: usize) {
+        let Some(limit) = self.limit else {
+            return;
+        };
+        let mut used = self.used.lock().unwrap();
+        while *used > 0 && *used + bytes > limit {
+            used = self.freed.wait(used).unwrap();
+        }
+        *used += bytes;
+    }
+
+    fn release(&self, bytes: usize) {
+        if self.limit.is_none() {
+            return;
+        }
+        *self.used.lock().unwrap() -= bytes;
+        self.freed.notify_one();
+    }
+}
+
+/// Batches handed back by the workers once scanned, so the producer can
+/// refill their buffers with `Reader::next_into` instead of allocating.
+struct BatchPool {
+    sender: Sender<Vec<Base<Vec<u8>>>>,
+    receiver: Receiver<Vec<Base<Vec<u8>>>>,
+    /// 超过此容量的批次不回收, 避免长读长的缓冲区一直占用内存
+    max_bytes: usize,
+}
+
+impl BatchPool {
+    fn new(capacity: usize, max_bytes: usize) -> Self {
+        let (sender, receiver) = bounded(capacity);
+        Self {
+            sender,
+            receiver,
+            max_bytes,
+        }
+    }
+
+    /// A returned batch, or a new one when none is free.
+    fn get(&self) -> Vec<Base<Vec<u8>>> {
+        self.receiver.try_recv().unwrap_or_default()
+    }
+
+    /// Returns a batch; it is dropped when the pool is full or its buffers
+    /// are larger than `max_bytes`.
+    fn put(&self, batch: Vec<Base<Vec<u8>>>) {
+        if self.max_bytes < usize::MAX {
+            let capacity: usize = batch
+                .iter()
+                .map(|seq| {
+                    let qual = seq
+                        .qual
+                        .as_ref()
+                        .map_or(0, |q| q.reduce(0, |acc, q| acc + q.capacity()));
+                    seq.body.reduce(0, |acc, s| acc + s.capacity()) + qual
+                })
+                .sum();
+            if capacity > self.max_bytes {
+                return;
+            }
+        }
+        let _ = self.sender.try_send(batch);
+    }
+}
+
+/// Halves of oversized batches, taken by workers before new input.
+struct StealQueue {
+    sender: Sender<(usize, Vec<Base<Vec<u8>>>)>,
+    receiver: Receiver<(usize, Vec<Base<Vec<u8>>>)>,
+    /// 正在等待输入的工作线程数
+    idle: AtomicUsize,
+    split_bytes: Option<usize>,
+}
+
+impl StealQueue {
+    fn new(split_bytes: Option<usize>) -> Self {
+        let (sender, receiver) = unbounded();
+        Self {
+            sender,
+            receiver,
+            idle: AtomicUsize::new(0),
+            split_bytes,
+        }
+    }
+
+    /// The next batch for a worker: a stolen half if there is one, else new
+    /// input. Returns None once the input is closed and nothing is left.
+    fn next(
+        &self,
+        input: &Receiver<(usize, Vec<Base<Vec<u8>>>)>,
+        input_open: &mut bool,
+    ) -> Option<(usize, Vec<Base<Vec<u8>>>)> {
+        if let Ok(batch) = self.receiver.try_recv() {
+            return Some(batch);
+        }
+        // 输入结束后只取剩余的一半; 拆分者自己稍后也会取走
+        if !*input_open {
+            return None;
+        }
+        self.idle.fetch_add(1, Ordering::SeqCst);
+        let batch = select! {
+            recv(self.receiver) -> batch => batch.ok(),
+            recv(input) -> batch => {
+                *input_open = batch.is_ok();
+                batch.ok()
+            }
+        };
+        self.idle.fetch_sub(1, Ordering::SeqCst);
+        batch.or_else(|| self.receiver.try_recv().ok())
+    }
+
+    /// Hands the second half of an oversized batch to the idle workers and
+    /// returns its bytes.
+    fn split(&self, index: usize, seqs: &mut Vec<Base<Vec<u8>>>, bytes: usize) -> usize {
+        let Some(split_bytes) = self.split_bytes else {
+            return 0;
+        };
+        if seqs.len() < 2 || bytes < split_bytes || self.idle.load(Ordering::SeqCst) == 0 {
+            return 0;
+        }
+        let half = seqs.split_off(seqs.len() / 2);
+        let moved = batch_bytes(&half);
+        self.sender
+            .send((index, half))
+            .expect("the steal queue is unbounded");
+        moved
+    }
+}
+
+/// Reads one batch and queues it for the workers, numbered by `index`.
+/// Returns false when the producer should stop: end of input, a read error,
+/// or no worker left.
+fn produce<R: Reader>(
+    reader: &mut R,
+    sender: &Sender<(usize, Vec<Base<Vec<u8>>>)>,
+    batches: &BatchPool,
+    budget: &ByteBudget,
+    index: &mut usize,
+    error_slot: &mut Option<SeqKmerError>,
+) -> bool {
+    let mut seqs = batches.get();
+    match reader.next_into(&mut seqs) {
+        Ok(true) => {
+            let bytes = batch_bytes(&seqs);
+            budget.acquire(bytes);
+            if sender.send((*index, seqs)).is_err() {
+                budget.release(bytes);
+                *error_slot = Some(SeqKmerError::ThreadError(
+                    "workers stopped before the input was consumed".to_string(),
+                ));
+                return false;
+            }
+            *index += 1;
+            true
+        }
+        Ok(false) => false,
+        Err(e) => {
+            *error_slot = Some(e);
+            false
+        }
+    }
+}
+
+/// Performs parallel reading and processing of sequences.
+///
+/// A read error ends the input; it is returned once the batches already
+/// queued have been processed.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{read_parallel, FastaReader, Meros, Base, MinimizerIterator};
+/// use std::path::Path;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let path = Path::new("tests/data/test.fasta");
+/// let mut reader = FastaReader::from_path(path, 0)?;
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+///
+/// let work = |seqs: &mut Vec<Base<MinimizerIterator>>| {
+///     // Process sequences
+///     seqs.len()
+/// };
+///
+/// let func = |result: &mut seqkmer::ParallelResult<usize>| {
+///     let mut total = 0;
+///     while let Some(count) = result.next() {
+///         total += count.unwrap();
+///     }
+///     total
+/// };
+///
+/// let total = read_parallel(&mut reader, 4, &meros, work, func)?;
+/// println!("Total sequences processed: {:?}", total);
+/// # Ok(())
+/// # }
+/// ```
+pub fn read_parallel<R, W, O, F, Out>(
+    reader: &mut R,
+    n_threads: usize,
+    meros: &Meros,
+    work: W,
+    func: F,
+) -> Result<()>
+where
+    R: Reader,
+    O: Send,
+    Out: Send + Default,
+    W: Send + Sync + Fn(&mut Vec<Base<MinimizerIterator>>) -> O,
+    F: FnOnce(&mut ParallelResult<O>) -> Out + Send,
+{
+    read_parallel_with_config(reader, &ParallelConfig::new(n_threads), meros, work, func)
+}
+
+/// Like `read_parallel`, with the pipeline settings in a [`ParallelConfig`]:
+/// queue depth, reader batch size, output order, cancellation and thread
+/// placement. The other `read_parallel_*` functions use the same pipeline
+/// with a default config.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{read_parallel_with_config, Affinity, FastaReader, Meros, ParallelConfig};
+/// use seqkmer::{Base, MinimizerIterator, ParallelResult};
+/// use std::path::Path;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut reader = FastaReader::from_path(Path::new("tests/data/test.fasta"), 0)?;
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+/// // 每批一条记录, 按输入顺序交出; 所有线程放在 0 号核上 (需启用 affinity 特性)
+/// let config = ParallelConfig::new(4)
+///     .with_batch_size(1)
+///     .with_ordered(true)
+///     .with_affinity(Affinity::Cores(vec![0]));
+///
+/// let work = |seqs: &mut Vec<Base<MinimizerIterator>>| seqs[0].header.id.clone();
+/// let func = |result: &mut ParallelResult<String>| {
+///     let mut ids = Vec::new();
+///     while let Some(id) = result.next() {
+///         ids.push(id.unwrap());
+///     }
+///     assert_eq!(ids, ["seq1", "seq2", "seq3"]);
+/// };
+///
+/// read_parallel_with_config(&mut reader, &config, &meros, work, func)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn read_parallel_with_config<R, W, O, F, Out>(
+    reader: &mut R,
+    config: &ParallelConfig,
+    meros: &Meros,
+    work: W,
+    func: F,
+) -> Result<()>
+where
+    R: Reader,
+    O: Send,
+    Out: Send + Default,
+    W: Send + Sync + Fn(&mut Vec<Base<MinimizerIterator>>) -> O,
+    F: FnOnce(&mut ParallelResult<O>) -> Out + Send,
+{
+    let mut pool = Pool::new(config.n_threads as u32);
+    let init = || ();
+    let work = |_: &mut (), seqs: &mut Vec<Base<Vec<u8>>>| scan_batch(seqs, meros, &work);
+    run_pipeline(
+        &mut pool,
+        reader,
+        config,
+        Extras::default(),
+        init,
+        work,
+        func,
+    )
+}
+
+/// Like `read_parallel`, without the collector closure: every `work`
+/// output is passed to `callback` on the calling thread, so the callback
+/// needs neither `Send` nor synchronisation.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{read_parallel_foreach, Base, FastaReader, Meros, MinimizerIterator};
+/// use std::path::Path;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut reader = FastaReader::from_path(Path::new("tests/data/test.fasta"), 0)?;
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+///
+/// let mut ids = Vec::new();
+/// let work = |seqs: &mut Vec<Base<MinimizerIterator>>| {
+///     seqs.iter().map(|seq| seq.header.id.clone()).collect::<Vec<_>>()
+/// };
+/// read_parallel_foreach(&mut reader, 4, &meros, work, |batch| ids.extend(batch))?;
+///
+/// ids.sort();
+/// assert_eq!(ids, ["seq1", "seq2", "seq3"]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn read_parallel_foreach<R, W, O, C>(
+    reader: &mut R,
+    n_threads: usize,
+    meros: &Meros,
+    work: W,
+    callback: C,
+) -> Result<()>
+where
+    R: Reader,
+    O: Send,
+    W: Send + Sync + Fn(&mut Vec<Base<MinimizerIterator>>) -> O,
+    C: FnMut(O),
+{
+    let config = ParallelConfig::new(n_threads);
+    read_parallel_foreach_with_config(reader, &config, meros, work, callback)
+}
+
+/// `read_parallel_foreach` with the pipeline settings in a
+/// [`ParallelConfig`]; with `ordered` set, `callback` sees the outputs in
+/// input order.
+pub fn read_parallel_foreach_with_config<R, W, O, C>(
+    reader: &mut R,
+    config: &ParallelConfig,
+    meros: &Meros,
+    work: W,
+    mut callback: C,
+) -> Result<()>
+where
+    R: Reader,
+    O: Send,
+    W: Send + Sync + Fn(&mut Vec<Base<MinimizerIterator>>) -> O,
+    C: FnMut(O),
+{
+    let (output_send, output_recv) = bounded::<O>(config.queue_depth());
+    std::thread::scope(|scope| {
+        // 流水线在另一个线程中运行, 收集端把结果转给调用线程
+        let pipeline = scope.spawn(move || {
+            let func = move |result: &mut ParallelResult<O>| {
+                for output in result.by_ref() {
+                    if output_send.send(output).is_err() {
+                        result.cancel();
+                        break;
+                    }
+                }
+            };
+            read_parallel_with_config(reader, config, meros, work, func)
+        });
+        for output in output_recv {
+            callback(output);
+        }
+        pipeline
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    })
+}
+
+/// Like `read_parallel`, but stops early once `token` is cancelled, either
+/// from another thread or by the collector through `ParallelResult::cancel`.
+///
+/// The producer checks the token before every batch and workers discard
+/// batches still queued, so the pipeline drains without scanning them. When
+/// the collector returns, the token is cancelled and any outputs it did not
+/// read are dropped.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{read_parallel_cancellable, CancellationToken, FastaReader, Meros};
+/// use seqkmer::{Base, MinimizerIterator, ParallelResult};
+/// use std::path::Path;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut reader = FastaReader::from_path(Path::new("tests/data/test.fasta"), 0)?
+///     .with_batch_size(1);
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+/// let token = CancellationToken::new();
+///
+/// let work = |seqs: &mut Vec<Base<MinimizerIterator>>| seqs.len();
+/// let func = |result: &mut ParallelResult<usize>| {
+///     // 拿到第一个结果就停止
+///     let first = result.next().map(|item| item.unwrap());
+///     result.cancel();
+///     first
+/// };
+///
+/// read_parallel_cancellable(&mut reader, 4, &meros, &token, work, func)?;
+/// assert!(token.is_cancelled());
+/// # Ok(())
+/// # }
+/// ```
+pub fn read_parallel_cancellable<R, W, O, F, Out>(
+    reader: &mut R,
+    n_threads: usize,
+    meros: &Meros,
+    token: &CancellationToken,
+    work: W,
+    func: F,
+) -> Result<()>
+where
+    R: Reader,
+    O: Send,
+    Out: Send + Default,
+    W: Send + Sync + Fn(&mut Vec<Base<MinimizerIterator>>) -> O,
+    F: FnOnce(&mut ParallelResult<O>) -> Out + Send,
+{
+    let config = ParallelConfig::new(n_threads);
+    read_parallel_cancellable_with_config(reader, &config, meros, token, work, func)
+}
+
+/// `read_parallel_cancellable` with the pipeline settings in a
+/// [`ParallelConfig`]; `token` replaces any token set on the config.
+pub fn read_parallel_cancellable_with_config<R, W, O, F, Out>(
+    reader: &mut R,
+    config: &ParallelConfig,
+    meros: &Meros,
+    token: &CancellationToken,
+    work: W,
+    func: F,
+) -> Result<()>
+where
+    R: Reader,
+    O: Send,
+    Out: Send + Default,
+    W: Send + Sync + Fn(&mut Vec<Base<MinimizerIterator>>) -> O,
+    F: FnOnce(&mut ParallelResult<O>) -> Out + Send,
+{
+    let config = config.clone().with_cancellation(token.clone());
+    read_parallel_with_config(reader, &config, meros, work, func)
+}
+
+/// Like `read_parallel`, but runs on a caller-owned pool instead of creating
+/// one per call, so an application scanning many files keeps a fixed set of
+/// threads. The pool needs at least three threads.
+///
+/// Threads are never pinned here: they outlive the call, so an affinity
+/// would leak into later uses of the pool.
+///
+/// # Examples
+///
+/// ```
+/// use scoped_threadpool::Pool;
+/// use seqkmer::{read_parallel_in, FastaReader, Meros, ParallelResult};
+/// use seqkmer::{Base, MinimizerIterator};
+/// use std::path::Path;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+/// let mut pool = Pool::new(4);
+///
+/// for _ in 0..2 {
+///     let mut reader = FastaReader::from_path(Path::new("tests/data/test.fasta"), 0)?;
+///     let work = |seqs: &mut Vec<Base<MinimizerIterator>>| seqs.len();
+///     let func = |result: &mut ParallelResult<usize>| {
+///         let mut total = 0;
+///         while let Some(count) = result.next() {
+///             total += count.unwrap();
+///         }
+///         assert_eq!(total, 3);
+///     };
+///     read_parallel_in(&mut pool, &mut reader, &meros, work, func)?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn read_parallel_in<R, W, O, F, Out>(
+    pool: &mut Pool,
+    reader: &mut R,
+    meros: &Meros,
+    work: W,
+    func: F,
+) -> Result<()>
+where
+    R: Reader,
+    O: Send,
+    Out: Send + Default,
+    W: Send + Sync + Fn(&mut Vec<Base<MinimizerIterator>>) -> O,
+    F: FnOnce(&mut ParallelResult<O>) -> Out + Send,
+{
+    let config = ParallelConfig::new(pool.thread_count() as usize);
+    read_parallel_in_with_config(pool, reader, &config, meros, work, func)
+}
+
+/// `read_parallel_in` with the pipeline settings in a [`ParallelConfig`].
+/// The pool decides the thread count, so `config.n_threads` is ignored, and
+/// `config.affinity` is ignored for the reason given above.
+pub fn read_parallel_in_with_config<R, W, O, F, Out>(
+    pool: &mut Pool,
+    reader: &mut R,
+    config: &ParallelConfig,
+    meros: &Meros,
+    work: W,
+    func: F,
+) -> Result<()>
+where
+    R: Reader,
+    O: Send,
+    Out: Send + Default,
+    W: Send + Sync + Fn(&mut Vec<Base<MinimizerIterator>>) -> O,
+    F: FnOnce(&mut ParallelResult<O>) -> Out + Send,
+{
+    let config = ParallelConfig {
+        affinity: Affinity::Unpinned,
+        ..config.clone()
+    };
+    let init = || ();
+    let work = |_: &mut (), seqs: &mut Vec<Base<Vec<u8>>>| scan_batch(seqs, meros, &work);
+    run_pipeline(pool, reader, &config, Extras::default(), init, work, func)
+}
+
+/// Entry-point specific parts of `run_pipeline`.
+#[derive(Default)]
+struct Extras<'a> {
+    /// 每次读取前按控制器设置批量大小
+    controller: Option<&'a BatchController>,
+    /// 过滤掉的记录数, 由 `ParallelResult::filtered` 报告
+    filtered: Arc<AtomicUsize>,
+}
+
+/// Scans a batch and hands the scanned records to `work`.
+fn scan_batch<O>(
+    seqs: &mut [Base<Vec<u8>>],
+    meros: &Meros,
+    work: impl FnOnce(&mut Vec<Base<MinimizerIterator>>) -> O,
+) -> O {
+    let mut markers: Vec<Base<MinimizerIterator<'_>>> = seqs
+        .iter_mut()
+        .map(|seq| scan_sequence(seq, meros))
+        .collect();
+    work(&mut markers)
+}
+
+/// The producer, worker and collector threads behind the `read_parallel`
+/// family. Every worker owns a state built by `init` and turns each batch
+/// into one output with `work`. `config.n_threads` is ignored: the pool
+/// decides the thread count.
+fn run_pipeline<R, S, I, W, O, F, Out>(
+    pool: &mut Pool,
+    reader: &mut R,
+    config: &ParallelConfig,
+    extras: Extras<'_>,
+    init: I,
+    work: W,
+    func: F,
+) -> Result<()>
+where
+    R: Reader,
+    I: Send + Sync + Fn() -> S,
+    O: Send,
+    Out: Send + Default,
+    W: Send + Sync + Fn(&mut S, &mut Vec<Base<Vec<u8>>>) -> O,
+    F: FnOnce(&mut ParallelResult<O>) -> Out + Send,
+{
+    let n_threads = pool.thread_count() as usize;
+    assert!(n_threads > 2);
+    let buffer_len = config.queue_depth.unwrap_or(n_threads + 2);
+    let (sender, receiver) = bounded::<(usize, Vec<Base<Vec<u8>>>)>(buffer_len);
+    let batches = &BatchPool::new(
+        buffer_len,
+        config
+            .max_inflight_bytes
+            .map_or(usize::MAX, |bytes| bytes / buffer_len),
+    );
+    let budget = &ByteBudget::new(config.max_inflight_bytes);
+    // 有序模式下每批只能有一个输出, 不拆分
+    let steal = &StealQueue::new(config.split_bytes.filter(|_| !config.collects_in_order()));
+    let (done_send, done_recv) = bounded::<(usize, O)>(buffer_len);
+    let receiver = Arc::new(receiver); // 使用 Arc 来共享 receiver
+    let done_send = Arc::new(done_send);
+    if let Some(batch_size) = config.batch_size {
+        reader.set_batch_size(batch_size);
+    }
+    let token = &config.token.clone().unwrap_or_default();
+    let affinity = &config.affinity;
+    // 确定性模式下批次大小固定
+    let controller = extras.controller.filter(|_| !config.deterministic);
+    let metrics = config.metrics.as_deref();
+    if let Some(metrics) = metrics {
+        metrics.start();
+    }
+
+    let mut parallel_result = ParallelResult::new(done_recv)
+        .with_token(token.clone())
+        .with_ordered(config.collects_in_order());
+    parallel_result.filtered = extras.filtered;
+    let shared_error = Arc::clone(&parallel_result.read_error);
+
+    let mut read_error = None;
+    let error_slot = &mut read_error;
+
+    pool.scoped(|pool_scope| {
+        // 生产者线程
+        pool_scope.execute(move || {
+            affinity.pin(0);
+            let mut index = 0;
+            while !token.is_cancelled() {
+                if let Some(controller) = controller {
+                    reader.set_batch_size(controller.batch_size());
+                }
+                if !produce(reader, &sender, batches, budget, &mut index, error_slot) {
+                    break;
+                }
+                if let Some(metrics) = metrics {
+                    metrics.queued(sender.len(), buffer_len);
+                }
+            }
+            // 在通道关闭前登记错误, 收集端取完结果即可看到
+            *shared_error.lock().unwrap() = error_slot.clone();
+        });
+
+        // 消费者线程, 每个线程持有自己的状态
+        for thread in 1..n_threads - 1 {
+            let receiver = Arc::clone(&receiver);
+            let (init, work) = (&init, &work);
+            let done_send = Arc::clone(&done_send);
+            pool_scope.execute(move || {
+                affinity.pin(thread);
+                let mut state = init();
+                let mut input_open = true;
+                let mut waiting = Instant::now();
+                while let Some((index, mut seqs)) = steal.next(&receiver, &mut input_open) {
+                    let mut bytes = batch_bytes(&seqs);
+                    // 取消后只清空队列, 不再处理
+                    if token.is_cancelled() {
+                        budget.release(bytes);
+                        continue;
+                    }
+                    bytes -= steal.split(index, &mut seqs, bytes);
+                    let started = Instant::now();
+                    let records = seqs.len();
+                    let output = work(&mut state, &mut seqs);
+                    if let Some(metrics) = metrics {
+                        metrics.idle(started - waiting);
+                        metrics.processed(records, bytes, started.elapsed());
+                    }
+                    waiting = Instant::now();
+                    batches.put(seqs);
+                    budget.release(bytes);
+                    done_send
+                        .send((index, output))
+                        .expect("Failed to send outputs");
+                }
+            });
+        }
+
+        // 引用计数减掉一个,这样都子线程结束时, done_send还能完全释放
+        drop(done_send);
+        pool_scope.execute(move || {
+            affinity.pin(n_threads - 1);
+            let _ = func(&mut parallel_result);
+            // 收集端提前返回时, 排空剩余结果, 避免工作线程阻塞
+            parallel_result.cancel();
+            while parallel_result.next().is_some() {}
+        });
+
+        pool_scope.join_all();
+    });
+    if let Some(metrics) = metrics {
+        metrics.finish();
+    }
+
+    read_error.map_or(Ok(()), Err)
+}
+
+/// Performs parallel reading and processing of sequences, resizing the
+/// reader's batches at runtime with a [`BatchController`].
+///
+/// Workers time each batch (scanning plus `work`) and report it to the
+/// controller; the producer asks the controller for the batch size before
+/// every read.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{read_parallel_adaptive, BatchController, FastaReader, Meros, Base, MinimizerIterator};
+/// use std::path::Path;
+/// use std::time::Duration;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let path = Path::new("tests/data/test.fasta");
+/// let mut reader = FastaReader::from_path(path, 0)?;
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+/// let controller = BatchController::new(
+///     30,
+///     (1, 10_000),
+///     (Duration::from_millis(5), Duration::from_millis(50)),
+/// );
+///
+/// let work = |seqs: &mut Vec<Base<MinimizerIterator>>| seqs.len();
+///
+/// let func = |result: &mut seqkmer::ParallelResult<usize>| {
+///     let mut total = 0;
+///     while let Some(count) = result.next() {
+///         total += count.unwrap();
+///     }
+///     total
+/// };
+///
+/// read_parallel_adaptive(&mut reader, 4, &meros, &controller, work, func)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn read_parallel_adaptive<R, W, O, F, Out>(
+    reader: &mut R,
+    n_threads: usize,
+    meros: &Meros,
+    controller: &BatchController,
+    work: W,
+    func: F,
+) -> Result<()>
+where
+    R: Reader,
+    O: Send,
+    Out: Send + Default,
+    W: Send + Sync + Fn(&mut Vec<Base<MinimizerIterator>>) -> O,
+    F: FnOnce(&mut ParallelResult<O>) -> Out + Send,
+{
+    let config = ParallelConfig::new(n_threads);
+    read_parallel_adaptive_with_config(reader, &config, meros, controller, work, func)
+}
+
+/// `read_parallel_adaptive` with the pipeline settings in a
+/// [`ParallelConfig`]. The controller overrides `config.batch_size` after
+/// the first read, except in deterministic mode, where it is not consulted.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{read_parallel_adaptive_with_config, BatchController, FastaReader, Meros};
+/// use seqkmer::{Base, MinimizerIterator, ParallelConfig, ParallelResult};
+/// use std::io::Cursor;
+/// use std::time::Duration;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let fasta: String = (0..40).map(|i| format!(">r{}\nACGTACGTACGTACGT\n", i)).collect();
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+/// // 批次远快于目标窗口, 控制器会不断放大批次
+/// let controller = BatchController::new(4, (1, 64), (Duration::from_secs(1), Duration::from_secs(2)));
+/// let config = ParallelConfig::new(4)
+///     .with_batch_size(4)
+///     .with_deterministic(true);
+///
+/// let mut reader = FastaReader::new(Cursor::new(fasta.into_bytes()), 0);
+/// let work = |seqs: &mut Vec<Base<MinimizerIterator>>| seqs.len();
+/// let mut sizes = Vec::new();
+/// let func = |result: &mut ParallelResult<usize>| sizes.extend(result);
+/// read_parallel_adaptive_with_config(&mut reader, &config, &meros, &controller, work, func)?;
+///
+/// assert!(controller.batch_size() > 4);
+/// assert_eq!(sizes, vec![4; 10]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn read_parallel_adaptive_with_config<R, W, O, F, Out>(
+    reader: &mut R,
+    config: &ParallelConfig,
+    meros: &Meros,
+    controller: &BatchController,
+    work: W,
+    func: F,
+) -> Result<()>
+where
+    R: Reader,
+    O: Send,
+    Out: Send + Default,
+    W: Send + Sync + Fn(&mut Vec<Base<MinimizerIterator>>) -> O,
+    F: FnOnce(&mut ParallelResult<O>) -> Out + Send,
+{
+    let mut pool = Pool::new(config.n_threads as u32);
+    let extras = Extras {
+        controller: Some(controller),
+        ..Extras::default()
+    };
+    let init = || ();
+    let work = |_: &mut (), seqs: &mut Vec<Base<Vec<u8>>>| {
+        let start = Instant::now();
+        let output = scan_batch(seqs, meros, &work);
+        controller.observe(seqs.len(), start.elapsed());
+        output
+    };
+    run_pipeline(&mut pool, reader, config, extras, init, work, func)
+}
+
+/// Performs parallel reading and processing of sequences, applying
+/// `transform` to every record in the worker threads before it is scanned.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{read_parallel_transform, AdapterTrimmer, FastqReader, Meros, OptionPair};
+/// use seqkmer::{Base, MinimizerIterator};
+/// use std::path::Path;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let path = Path::new("tests/data/test.fastq");
+/// let mut reader = FastqReader::from_path(OptionPair::Single(path), 0, 0)?;
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+/// let trimmer = AdapterTrimmer::new(&["AGATCGGAAGAGC"]);
+///
+/// let work = |seqs: &mut Vec<Base<MinimizerIterator>>| seqs.len();
+/// let func = |result: &mut seqkmer::ParallelResult<usize>| {
+///     let mut total = 0;
+///     while let Some(count) = result.next() {
+///         total += count.unwrap();
+///     }
+///     total
+/// };
+///
+/// read_parallel_transform(&mut reader, 4, &meros, &trimmer, work, func)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn read_parallel_transform<R, T, W, O, F, Out>(
+    reader: &mut R,
+    n_threads: usize,
+    meros: &Meros,
+    transform: &T,
+    work: W,
+    func: F,
+) -> Result<()>
+where
+    R: Reader,
+    T: Transform,
+    O: Send,
+    Out: Send + Default,
+    W: Send + Sync + Fn(&mut Vec<Base<MinimizerIterator>>) -> O,
+    F: FnOnce(&mut ParallelResult<O>) -> Out + Send,
+{
+    let config = ParallelConfig::new(n_threads);
+    read_parallel_transform_with_config(reader, &config, meros, transform, work, func)
+}
+
+/// `read_parallel_transform` with the pipeline settings in a
+/// [`ParallelConfig`].
+pub fn read_parallel_transform_with_config<R, T, W, O, F, Out>(
+    reader: &mut R,
+    config: &ParallelConfig,
+    meros: &Meros,
+    transform: &T,
+    work: W,
+    func: F,
+) -> Result<()>
+where
+    R: Reader,
+    T: Transform,
+    O: Send,
+    Out: Send + Default,
+    W: Send + Sync + Fn(&mut Vec<Base<MinimizerIterator>>) -> O,
+    F: FnOnce(&mut ParallelResult<O>) -> Out + Send,
+{
+    let mut pool = Pool::new(config.n_threads as u32);
+    let init = || ();
+    let work = |_: &mut (), seqs: &mut Vec<Base<Vec<u8>>>| {
+        seqs.iter_mut().for_each(|seq| transform.apply(seq));
+        scan_batch(seqs, meros, &work)
+    };
+    run_pipeline(
+        &mut pool,
+        reader,
+        config,
+        Extras::default(),
+        init,
+        work,
+        func,
+    )
+}
+
+/// Performs parallel reading and processing of sequences, dropping records
+/// rejected by `filter` before they are scanned. The number of dropped
+/// records is reported by `ParallelResult::filtered`.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{read_parallel_filtered, FastaReader, Meros, ReadFilter};
+/// use seqkmer::{Base, MinimizerIterator};
+/// use std::path::Path;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let path = Path::new("tests/data/test.fasta");
+/// let mut reader = FastaReader::from_path(path, 0)?;
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+/// let filter = ReadFilter::new().with_min_length(21);
+///
+/// let work = |seqs: &mut Vec<Base<MinimizerIterator>>| seqs.len();
+/// let func = |result: &mut seqkmer::ParallelResult<usize>| {
+///     let mut total = 0;
+///     while let Some(count) = result.next() {
+///         total += count.unwrap();
+///     }
+///     assert_eq!(total, 0);
+///     assert_eq!(result.filtered(), 3);
+///     total
+/// };
+///
+/// read_parallel_filtered(&mut reader, 4, &meros, &filter, work, func)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn read_parallel_filtered<R, P, W, O, F, Out>(
+    reader: &mut R,
+    n_threads: usize,
+    meros: &Meros,
+    filter: &P,
+    work: W,
+    func: F,
+) -> Result<()>
+where
+    R: Reader,
+    P: Filter,
+    O: Send,
+    Out: Send + Default,
+    W: Send + Sync + Fn(&mut Vec<Base<MinimizerIterator>>) -> O,
+    F: FnOnce(&mut ParallelResult<O>) -> Out + Send,
+{
+    let config = ParallelConfig::new(n_threads);
+    read_parallel_filtered_with_config(reader, &config, meros, filter, work, func)
+}
+
+/// `read_parallel_filtered` with the pipeline settings in a
+/// [`ParallelConfig`].
+pub fn read_parallel_filtered_with_config<R, P, W, O, F, Out>(
+    reader: &mut R,
+    config: &ParallelConfig,
+    meros: &Meros,
+    filter: &P,
+    work: W,
+    func: F,
+) -> Result<()>
+where
+    R: Reader,
+    P: Filter,
+    O: Send,
+    Out: Send + Default,
+    W: Send + Sync + Fn(&mut Vec<Base<MinimizerIterator>>) -> O,
+    F: FnOnce(&mut ParallelResult<O>) -> Out + Send,
+{
+    let mut pool = Pool::new(config.n_threads as u32);
+    let extras = Extras::default();
+    let filtered = Arc::clone(&extras.filtered);
+    let init = || ();
+    let work = |_: &mut (), seqs: &mut Vec<Base<Vec<u8>>>| {
+        let before = seqs.len();
+        seqs.retain(|seq| filter.accept(seq));
+        filtered.fetch_add(before - seqs.len(), Ordering::Relaxed);
+        scan_batch(seqs, meros, &work)
+    };
+    run_pipeline(&mut pool, reader, config, extras, init, work, func)
+}
+
+/// Like `read_parallel`, but every worker thread owns a state built by
+/// `init`, handed to `work` by mutable reference. Scratch buffers, hash
+/// tables or classifiers can live there without locking.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{read_parallel_with_state, FastaReader, Meros, ParallelResult};
+/// use seqkmer::{Base, MinimizerIterator};
+/// use std::collections::HashSet;
+/// use std::path::Path;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut reader = FastaReader::from_path(Path::new("tests/data/test.fasta"), 0)?;
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+///
+/// // 每个线程复用自己的 HashSet
+/// let init = HashSet::<u64>::new;
+/// let work = |seen: &mut HashSet<u64>, seqs: &mut Vec<Base<MinimizerIterator>>| {
+///     seqs.iter_mut()
+///         .map(|seq| {
+///             seen.clear();
+///             seq.body.apply_mut(|m| m.for_each(|(_, hash)| {
+///                 seen.insert(hash);
+///             }));
+///             seen.len()
+///         })
+///         .sum::<usize>()
+/// };
+/// let func = |result: &mut ParallelResult<usize>| {
+///     let mut total = 0;
+///     while let Some(count) = result.next() {
+///         total += count.unwrap();
+///     }
+///     assert!(total > 0);
+/// };
+///
+/// read_parallel_with_state(&mut reader, 4, &meros, init, work, func)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn read_parallel_with_state<R, S, I, W, O, F, Out>(
+    reader: &mut R,
+    n_threads: usize,
+    meros: &Meros,
+    init: I,
+    work: W,
+    func: F,
+) -> Result<()>
+where
+    R: Reader,
+    I: Send + Sync + Fn() -> S,
+    O: Send,
+    Out: Send + Default,
+    W: Send + Sync + Fn(&mut S, &mut Vec<Base<MinimizerIterator>>) -> O,
+    F: FnOnce(&mut ParallelResult<O>) -> Out + Send,
+{
+    let config = ParallelConfig::new(n_threads);
+    read_parallel_with_state_with_config(reader, &config, meros, init, work, func)
+}
+
+/// `read_parallel_with_state` with the pipeline settings in a
+/// [`ParallelConfig`].
+pub fn read_parallel_with_state_with_config<R, S, I, W, O, F, Out>(
+    reader: &mut R,
+    config: &ParallelConfig,
+    meros: &Meros,
+    init: I,
+    work: W,
+    func: F,
+) -> Result<()>
+where
+    R: Reader,
+    I: Send + Sync + Fn() -> S,
+    O: Send,
+    Out: Send + Default,
+    W: Send + Sync + Fn(&mut S, &mut Vec<Base<MinimizerIterator>>) -> O,
+    F: FnOnce(&mut ParallelResult<O>) -> Out + Send,
+{
+    let mut pool = Pool::new(config.n_threads as u32);
+    let work = |state: &mut S, seqs: &mut Vec<Base<Vec<u8>>>| {
+        scan_batch(seqs, meros, |markers| work(state, markers))
+    };
+    run_pipeline(
+        &mut pool,
+        reader,
+        config,
+        Extras::default(),
+        init,
+        work,
+        func,
+    )
+}
+
+/// Like `read_parallel`, reporting `ProgressEvent::RecordsProcessed` to
+/// `progress` as each batch is scanned. Reader-side events come from the
+/// reader itself (see `FastaReader::with_progress`).
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{read_parallel_progress, FastaReader, Meros, ParallelResult, ProgressCounter};
+/// use seqkmer::{Base, MinimizerIterator};
+/// use std::path::Path;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut reader = FastaReader::from_path(Path::new("tests/data/test.fasta"), 0)?;
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+/// let counter = ProgressCounter::default();
+///
+/// let work = |seqs: &mut Vec<Base<MinimizerIterator>>| seqs.len();
+/// let func = |result: &mut ParallelResult<usize>| while result.next().is_some() {};
+/// read_parallel_progress(&mut reader, 4, &meros, &counter, work, func)?;
+/// assert_eq!(counter.records_processed(), 3);
+/// # Ok(())
+/// # }
+/// ```
+pub fn read_parallel_progress<R, P, W, O, F, Out>(
+    reader: &mut R,
+    n_threads: usize,
+    meros: &Meros,
+    progress: &P,
+    work: W,
+    func: F,
+) -> Result<()>
+where
+    R: Reader,
+    P: Progress,
+    O: Send,
+    Out: Send + Default,
+    W: Send + Sync + Fn(&mut Vec<Base<MinimizerIterator>>) -> O,
+    F: FnOnce(&mut ParallelResult<O>) -> Out + Send,
 {
-    recv: Receiver<P>,
+    let config = ParallelConfig::new(n_threads);
+    read_parallel_progress_with_config(reader, &config, meros, progress, work, func)
 }
 
-impl<P> ParallelResult<P>
+/// `read_parallel_progress` with the pipeline settings in a
+/// [`ParallelConfig`].
+pub fn read_parallel_progress_with_config<R, P, W, O, F, Out>(
+    reader: &mut R,
+    config: &ParallelConfig,
+    meros: &Meros,
+    progress: &P,
+    work: W,
+    func: F,
+) -> Result<()>
 where
-    P: Send,
+    R: Reader,
+    P: Progress,
+    O: Send,
+    Out: Send + Default,
+    W: Send + Sync + Fn(&mut Vec<Base<MinimizerIterator>>) -> O,
+    F: FnOnce(&mut ParallelResult<O>) -> Out + Send,
 {
-    /// Retrieves the next item from the parallel result.
-    #[inline]
-    pub fn next(&mut self) -> Option<ParallelItem<P>> {
-        self.recv.recv().ok().map(ParallelItem)
+    let mut pool = Pool::new(config.n_threads as u32);
+    let init = || ();
+    let work = |_: &mut (), seqs: &mut Vec<Base<Vec<u8>>>| {
+        let output = scan_batch(seqs, meros, &work);
+        progress.update(ProgressEvent::RecordsProcessed(seqs.len()));
+        output
+    };
+    run_pipeline(
+        &mut pool,
+        reader,
+        config,
+        Extras::default(),
+        init,
+        work,
+        func,
+    )
+}
+
+/// Reads a list of samples one after another as a single input, opening
+/// each with `create_reader` only once the previous one is exhausted.
+struct MultiFileReader<'a> {
+    files: &'a [crate::OptionPair<PathBuf>],
+    score: i32,
+    next_file: usize,
+    current: Option<Box<dyn Reader + Send>>,
+    batch_size: Option<usize>,
+}
+
+impl<'a> MultiFileReader<'a> {
+    fn new(files: &'a [crate::OptionPair<PathBuf>], score: i32) -> Self {
+        Self {
+            files,
+            score,
+            next_file: 0,
+            current: None,
+            batch_size: None,
+        }
     }
 }
 
-/// Creates a reader based on the file format.
+impl Reader for MultiFileReader<'_> {
+    fn next(&mut self) -> Result<Option<Vec<Base<Vec<u8>>>>> {
+        loop {
+            if let Some(reader) = self.current.as_mut() {
+                match reader.next()? {
+                    Some(seqs) => return Ok(Some(seqs)),
+                    None => self.current = None,
+                }
+            }
+            let Some(paths) = self.files.get(self.next_file) else {
+                return Ok(None);
+            };
+            let file_pair: Vec<String> = paths
+                .iter()
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect();
+            let mut reader = create_reader(&file_pair, self.next_file, self.score)?;
+            if let Some(batch_size) = self.batch_size {
+                reader.set_batch_size(batch_size);
+            }
+            self.current = Some(reader);
+            self.next_file += 1;
+        }
+    }
+
+    fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = Some(batch_size);
+        if let Some(reader) = self.current.as_mut() {
+            reader.set_batch_size(batch_size);
+        }
+    }
+}
+
+/// Processes a list of samples on one pool. Each entry of `files` is opened
+/// with `create_reader` (a pair of paths for paired-end FASTQ), using its
+/// position in the list as `file_index`; files are read one after another
+/// while the workers stay busy across file boundaries.
+///
+/// Returns every `work` output, grouped by `file_index`. Outputs of one file
+/// are not guaranteed to be in batch order.
 ///
 /// # Examples
 ///
 /// ```
-/// use seqkmer::create_reader;
-/// use std::path::Path;
+/// use seqkmer::{read_parallel_multi, Base, Meros, MinimizerIterator, OptionPair};
+/// use std::path::PathBuf;
 ///
 /// # fn main() -> std::io::Result<()> {
-/// let file_path = Path::new("tests/data/test.fasta").to_str().unwrap().to_string();
-/// let reader = create_reader(&[file_path], 0, 0)?;
+/// let files = vec![
+///     OptionPair::Single(PathBuf::from("tests/data/test.fasta")),
+///     OptionPair::Single(PathBuf::from("tests/data/test.fastq")),
+/// ];
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+///
+/// let work = |seqs: &mut Vec<Base<MinimizerIterator>>| seqs.len();
+/// let outputs = read_parallel_multi(&files, 4, &meros, 0, work)?;
+///
+/// assert_eq!(outputs[&0].iter().sum::<usize>(), 3);
+/// assert!(outputs[&1].iter().sum::<usize>() > 0);
 /// # Ok(())
 /// # }
 /// ```
-pub fn create_reader(
-    file_pair: &[String],
-    file_index: usize,
+pub fn read_parallel_multi<W, O>(
+    files: &[crate::OptionPair<PathBuf>],
+    n_threads: usize,
+    meros: &Meros,
     score: i32,
-) -> Result<Box<dyn Reader + Send>> {
-    // let mut files_iter = file_pair.iter();
-    let paths = crate::OptionPair::from_slice(file_pair);
+    work: W,
+) -> Result<HashMap<usize, Vec<O>>>
+where
+    O: Send,
+    W: Send + Sync + Fn(&mut Vec<Base<MinimizerIterator>>) -> O,
+{
+    let config = ParallelConfig::new(n_threads);
+    read_parallel_multi_with_config(files, &config, meros, score, work)
+}
 
-    match detect_file_format(&file_pair[0])? {
-        SeqFormat::Fastq => Ok(Box::new(FastqReader::from_path(paths, file_index, score)?)),
-        SeqFormat::Fasta => Ok(Box::new(FastaReader::from_path(&file_pair[0], file_index)?)),
+/// `read_parallel_multi` with the pipeline settings in a [`ParallelConfig`].
+/// With `ordered` set, the outputs of each file are in batch order.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{read_parallel_multi_with_config, Base, Meros, MinimizerIterator};
+/// use seqkmer::{OptionPair, ParallelConfig};
+/// use std::path::PathBuf;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let files = vec![
+///     OptionPair::Single(PathBuf::from("tests/data/test.fasta")),
+///     OptionPair::Single(PathBuf::from("tests/data/test.fasta")),
+/// ];
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+/// let config = ParallelConfig::new(4).with_batch_size(1).with_ordered(true);
+///
+/// let work = |seqs: &mut Vec<Base<MinimizerIterator>>| seqs[0].header.id.clone();
+/// let outputs = read_parallel_multi_with_config(&files, &config, &meros, 0, work)?;
+/// assert_eq!(outputs[&0], ["seq1", "seq2", "seq3"]);
+/// assert_eq!(outputs[&1], outputs[&0]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn read_parallel_multi_with_config<W, O>(
+    files: &[crate::OptionPair<PathBuf>],
+    config: &ParallelConfig,
+    meros: &Meros,
+    score: i32,
+    work: W,
+) -> Result<HashMap<usize, Vec<O>>>
+where
+    O: Send,
+    W: Send + Sync + Fn(&mut Vec<Base<MinimizerIterator>>) -> O,
+{
+    let mut pool = Pool::new(config.n_threads as u32);
+    let mut reader = MultiFileReader::new(files, score);
+    let mut outputs: HashMap<usize, Vec<O>> = HashMap::new();
+    let outputs_ref = &mut outputs;
+    let init = || ();
+    let work = |_: &mut (), seqs: &mut Vec<Base<Vec<u8>>>| {
+        // 每批只来自一个文件
+        let file_index = seqs.first().map_or(0, |seq| seq.header.file_index);
+        (file_index, scan_batch(seqs, meros, &work))
+    };
+    let func = move |result: &mut ParallelResult<(usize, O)>| {
+        for (file_index, output) in result {
+            outputs_ref.entry(file_index).or_default().push(output);
+        }
+    };
+    run_pipeline(
+        &mut pool,
+        &mut reader,
+        config,
+        Extras::default(),
+        init,
+        work,
+        func,
+    )?;
+    Ok(outputs)
+}
+
+/// Splits the batches of a reader into one-record batches, so the pipeline
+/// hands records to the workers one at a time and an idle worker picks up
+/// the next record while another is still busy with a long one.
+struct RecordReader<'a, R: Reader> {
+    inner: &'a mut R,
+    pending: VecDeque<Base<Vec<u8>>>,
+}
+
+impl<'a, R: Reader> RecordReader<'a, R> {
+    fn new(inner: &'a mut R) -> Self {
+        Self {
+            inner,
+            pending: VecDeque::new(),
+        }
     }
 }
 
-/// Performs parallel reading and processing of sequences.
+impl<R: Reader> Reader for RecordReader<'_, R> {
+    fn next(&mut self) -> Result<Option<Vec<Base<Vec<u8>>>>> {
+        while self.pending.is_empty() {
+            match self.inner.next()? {
+                Some(seqs) => self.pending.extend(seqs),
+                None => return Ok(None),
+            }
+        }
+        Ok(self.pending.pop_front().map(|seq| vec![seq]))
+    }
+
+    /// 批量大小作用于底层读取器, 分发始终按记录进行
+    fn set_batch_size(&mut self, batch_size: usize) {
+        self.inner.set_batch_size(batch_size);
+    }
+}
+
+/// Hands every scanned record to `work` on the worker threads.
+///
+/// `work` is called once per record and delivers its own results, e.g.
+/// over a channel, so no outputs are collected. Reader batches are split
+/// and the records queued individually, so an idle worker picks up the
+/// next record as soon as it is read and one long read does not hold up
+/// the rest of its batch. `n_threads` counts the producer and the workers.
+/// A read error stops the producer and is returned once workers finish.
 ///
 /// # Examples
 ///
 /// ```
-/// use seqkmer::{read_parallel, FastaReader, Meros, Base, MinimizerIterator};
+/// use seqkmer::{read_streaming, FastaReader, Meros, OptionPair};
 /// use std::path::Path;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
 ///
 /// # fn main() -> std::io::Result<()> {
-/// let path = Path::new("tests/data/test.fasta");
-/// let mut reader = FastaReader::from_path(path, 0)?;
+/// let mut reader = FastaReader::from_path(Path::new("tests/data/test.fasta"), 0)?;
 /// let meros = Meros::new(11, 3, Some(0), None, None);
+/// let minimizers = AtomicUsize::new(0);
 ///
-/// let work = |seqs: &mut Vec<Base<MinimizerIterator>>| {
-///     // Process sequences
-///     seqs.len()
-/// };
-///
-/// let func = |result: &mut seqkmer::ParallelResult<usize>| {
-///     let mut total = 0;
-///     while let Some(count) = result.next() {
-///         total += count.unwrap();
+/// read_streaming(&mut reader, 3, &meros, |record| {
+///     if let OptionPair::Single(m_iter) = record.body {
+///         minimizers.fetch_add(m_iter.count(), Ordering::Relaxed);
 ///     }
-///     total
-/// };
+/// })?;
+/// assert!(minimizers.load(Ordering::Relaxed) > 0);
+/// # Ok(())
+/// # }
+/// ```
+pub fn read_streaming<R, W>(reader: &mut R, n_threads: usize, meros: &Meros, work: W) -> Result<()>
+where
+    R: Reader,
+    W: Send + Sync + Fn(Base<MinimizerIterator>),
+{
+    assert!(n_threads > 1);
+    // 流水线另有一个收集线程, 它不做实际工作
+    let config = ParallelConfig::new(n_threads + 1);
+    read_streaming_with_config(reader, &config, meros, work)
+}
+
+/// `read_streaming` with the pipeline settings in a [`ParallelConfig`].
+/// Unlike `read_streaming`, `config.n_threads` includes the collector
+/// thread, as for `read_parallel_with_config`. Records are still dispatched
+/// one at a time: `queue_depth` counts records and `batch_size` only sets
+/// how many the reader returns per read.
 ///
-/// let total = read_parallel(&mut reader, 4, &meros, work, func)?;
-/// println!("Total sequences processed: {:?}", total);
+/// # Examples
+///
+/// ```
+/// use seqkmer::{read_streaming_with_config, CancellationToken, FastaReader, Meros};
+/// use seqkmer::ParallelConfig;
+/// use std::collections::HashSet;
+/// use std::path::Path;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::Mutex;
+/// use std::time::Duration;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+///
+/// // 三条记录在同一批中读入, 仍由不同的工作线程处理
+/// let mut reader = FastaReader::from_path(Path::new("tests/data/test.fasta"), 0)?;
+/// let threads = Mutex::new(HashSet::new());
+/// read_streaming_with_config(&mut reader, &ParallelConfig::new(5), &meros, |_| {
+///     threads.lock().unwrap().insert(std::thread::current().id());
+///     std::thread::sleep(Duration::from_millis(50));
+/// })?;
+/// assert!(threads.lock().unwrap().len() > 1);
+///
+/// let mut reader = FastaReader::from_path(Path::new("tests/data/test.fasta"), 0)?;
+/// let token = CancellationToken::new();
+/// let config = ParallelConfig::new(3).with_cancellation(token.clone());
+///
+/// // 处理完第一条记录就取消, 之后排队的记录被丢弃
+/// let records = AtomicUsize::new(0);
+/// read_streaming_with_config(&mut reader, &config, &meros, |_| {
+///     records.fetch_add(1, Ordering::SeqCst);
+///     token.cancel();
+/// })?;
+/// assert!(records.load(Ordering::SeqCst) < 3);
 /// # Ok(())
 /// # }
 /// ```
-pub fn read_parallel<R, W, O, F, Out>(
+pub fn read_streaming_with_config<R, W>(
     reader: &mut R,
-    n_threads: usize,
+    config: &ParallelConfig,
     meros: &Meros,
     work: W,
-    func: F,
 ) -> Result<()>
 where
     R: Reader,
-    O: Send,
-    Out: Send + Default,
-    W: Send + Sync + Fn(&mut Vec<Base<MinimizerIterator>>) -> O,
-    F: FnOnce(&mut ParallelResult<O>) -> Out + Send,
+    W: Send + Sync + Fn(Base<MinimizerIterator>),
 {
-    assert!(n_threads > 2);
-    let buffer_len = n_threads + 2;
-    let (sender, receiver) = bounded::<Vec<Base<Vec<u8>>>>(buffer_len);
-    let (done_send, done_recv) = bounded::<O>(buffer_len);
-    let receiver = Arc::new(receiver); // 使用 Arc 来共享 receiver
-    let done_send = Arc::new(done_send);
-    let mut pool = Pool::new(n_threads as u32);
-
-    let mut parallel_result = ParallelResult { recv: done_recv };
+    let mut pool = Pool::new(config.n_threads as u32);
+    let mut reader = RecordReader::new(reader);
+    let init = || ();
+    let work = |_: &mut (), seqs: &mut Vec<Base<Vec<u8>>>| {
+        seqs.iter().for_each(|seq| work(scan_sequence(seq, meros)));
+    };
+    let func = |result: &mut ParallelResult<()>| while result.next().is_some() {};
+    run_pipeline(
+        &mut pool,
+        &mut reader,
+        config,
+        Extras::default(),
+        init,
+        work,
+        func,
+    )
+}
 
-    pool.scoped(|pool_scope| {
-        // 生产者线程
-        pool_scope.execute(move || {
-            while let Ok(Some(seqs)) = reader.next() {
-                sender.send(seqs).expect("Failed to send sequences");
-            }
-        });
+/// Splits a multiplexed run by sample and scans it in one pass.
+///
+/// Each record's barcode is extracted and matched against the whitelist;
+/// `work` is called with the sample index (None if unassigned) and the
+/// scanned record, then `sink` receives the record itself, e.g. to send it
+/// to a per-sample channel or writer. Returns the number of records per
+/// sample, with unassigned records counted last. Records are dispatched to
+/// the workers one at a time, as in `read_streaming`; `n_threads` counts the
+/// producer and the workers.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{
+///     demux_parallel, BarcodeWhitelist, Demultiplexer, FastqReader, Meros, OptionPair,
+///     TagExtractor,
+/// };
+/// use std::sync::Mutex;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let fastq = b"@r1\nAAAAACGTTGCAACGT\n+\nIIIIIIIIIIIIIIII\n@r2\nCCCCACGTTGCAACGT\n+\nIIIIIIIIIIIIIIII\n@r3\nGGGGACGTTGCAACGT\n+\nIIIIIIIIIIIIIIII\n";
+/// let mut reader = FastqReader::new(OptionPair::Single(fastq.as_slice()), 0, 0);
+/// let demux = Demultiplexer::new(
+///     TagExtractor::sequence_prefix(Some(0..4), None).with_clip(true),
+///     BarcodeWhitelist::new(["AAAA", "CCCA"]),
+/// );
+/// let meros = Meros::new(5, 3, None, None, None);
+/// let per_sample = Mutex::new(vec![Vec::new(); 3]);
+///
+/// let counts = demux_parallel(
+///     &mut reader,
+///     3,
+///     &meros,
+///     &demux,
+///     |_, _| {},
+///     |sample, seq| {
+///         per_sample.lock().unwrap()[sample.unwrap_or(2)].push(seq.header.id);
+///     },
+/// )?;
+/// assert_eq!(counts, vec![1, 1, 1]);
+/// assert_eq!(per_sample.lock().unwrap()[1], vec!["r2"]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn demux_parallel<R, W, S>(
+    reader: &mut R,
+    n_threads: usize,
+    meros: &Meros,
+    demux: &Demultiplexer,
+    work: W,
+    sink: S,
+) -> Result<Vec<usize>>
+where
+    R: Reader,
+    W: Send + Sync + Fn(Option<usize>, Base<MinimizerIterator>),
+    S: Send + Sync + Fn(Option<usize>, Base<Vec<u8>>),
+{
+    assert!(n_threads > 1);
+    // 流水线另有一个收集线程, 它不做实际工作
+    let config = ParallelConfig::new(n_threads + 1);
+    demux_parallel_with_config(reader, &config, meros, demux, work, sink)
+}
 
-        // 消费者线程
-        for _ in 0..n_threads - 2 {
-            let receiver = Arc::clone(&receiver);
-            let work = &work;
-            let done_send = Arc::clone(&done_send);
-            pool_scope.execute(move || {
-                while let Ok(mut seqs) = receiver.recv() {
-                    let mut markers: Vec<Base<MinimizerIterator<'_>>> = seqs
-                        .iter_mut()
-                        .map(|seq| scan_sequence(seq, &meros))
-                        .collect();
-                    let output = work(&mut markers);
-                    done_send.send(output).expect("Failed to send outputs");
-                }
-            });
+/// `demux_parallel` with the pipeline settings in a [`ParallelConfig`];
+/// `config.n_threads` includes the collector thread.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{demux_parallel_with_config, BarcodeWhitelist, Demultiplexer, FastqReader};
+/// use seqkmer::{Meros, OptionPair, ParallelConfig, PipelineMetrics, TagExtractor};
+/// use std::sync::Arc;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let fastq = b"@r1\nAAAAACGTTGCAACGT\n+\nIIIIIIIIIIIIIIII\n@r2\nCCCCACGTTGCAACGT\n+\nIIIIIIIIIIIIIIII\n";
+/// let mut reader = FastqReader::new(OptionPair::Single(fastq.as_slice()), 0, 0);
+/// let demux = Demultiplexer::new(
+///     TagExtractor::sequence_prefix(Some(0..4), None),
+///     BarcodeWhitelist::new(["AAAA"]),
+/// );
+/// let meros = Meros::new(5, 3, None, None, None);
+/// let metrics = Arc::new(PipelineMetrics::default());
+/// let config = ParallelConfig::new(3).with_metrics(Arc::clone(&metrics));
+///
+/// let counts =
+///     demux_parallel_with_config(&mut reader, &config, &meros, &demux, |_, _| {}, |_, _| {})?;
+/// assert_eq!(counts, vec![1, 1]);
+/// assert_eq!(metrics.records(), 2);
+/// # Ok(())
+/// # }
+/// ```
+pub fn demux_parallel_with_config<R, W, S>(
+    reader: &mut R,
+    config: &ParallelConfig,
+    meros: &Meros,
+    demux: &Demultiplexer,
+    work: W,
+    sink: S,
+) -> Result<Vec<usize>>
+where
+    R: Reader,
+    W: Send + Sync + Fn(Option<usize>, Base<MinimizerIterator>),
+    S: Send + Sync + Fn(Option<usize>, Base<Vec<u8>>),
+{
+    let counts: Vec<AtomicUsize> = (0..=demux.whitelist.len())
+        .map(|_| AtomicUsize::new(0))
+        .collect();
+    let mut pool = Pool::new(config.n_threads as u32);
+    let mut reader = RecordReader::new(reader);
+    let init = || ();
+    let work = |_: &mut (), seqs: &mut Vec<Base<Vec<u8>>>| {
+        for mut seq in seqs.drain(..) {
+            let sample = demux.assign(&mut seq);
+            counts[sample.unwrap_or(demux.whitelist.len())].fetch_add(1, Ordering::Relaxed);
+            work(sample, scan_sequence(&seq, meros));
+            sink(sample, seq);
         }
-
-        // 引用计数减掉一个,这样都子线程结束时, done_send还能完全释放
-        drop(done_send);
-        pool_scope.execute(move || {
-            let _ = func(&mut parallel_result);
-        });
-
-        pool_scope.join_all();
-    });
-
-    Ok(())
+    };
+    let func = |result: &mut ParallelResult<()>| while result.next().is_some() {};
+    run_pipeline(
+        &mut pool,
+        &mut reader,
+        config,
+        Extras::default(),
+        init,
+        work,
+        func,
+    )?;
+    Ok(counts.into_iter().map(AtomicUsize::into_inner).collect())
 }
 
 /// Performs parallel reading and processing of buffered data.
@@ -216,21 +2204,24 @@ where
 {
     assert!(n_threads > 2);
     let buffer_len = n_threads + 2;
-    let (sender, receiver) = bounded::<Vec<D>>(buffer_len);
-    let (done_send, done_recv) = bounded::<O>(buffer_len);
+    let (sender, receiver) = bounded::<(usize, Vec<D>)>(buffer_len);
+    let (done_send, done_recv) = bounded::<(usize, O)>(buffer_len);
     let receiver = Arc::new(receiver); // 使用 Arc 来共享 receiver
     let done_send = Arc::new(done_send);
     let mut pool = Pool::new(n_threads as u32);
 
     let slot_size = std::mem::size_of::<D>();
-    let mut parallel_result = ParallelResult { recv: done_recv };
+    let mut parallel_result = ParallelResult::new(done_recv);
 
     pool.scoped(|pool_scope| {
         // 生产者线程
         pool_scope.execute(move || {
             let mut batch_buffer = vec![0u8; slot_size * buffer_size];
 
-            while let Ok(bytes_read) = reader.read(&mut batch_buffer) {
+            for index in 0.. {
+                let Ok(bytes_read) = reader.read(&mut batch_buffer) else {
+                    break;
+                };
                 if bytes_read == 0 {
                     break;
                 } // 文件末尾
@@ -240,7 +2231,7 @@ where
                     std::slice::from_raw_parts(batch_buffer.as_ptr() as *const D, slots_in_batch)
                 };
                 sender
-                    .send(slots.to_vec())
+                    .send((index, slots.to_vec()))
                     .expect("Failed to send sequences");
             }
         });
@@ -251,9 +2242,11 @@ where
             let work = &work;
             let done_send = Arc::clone(&done_send);
             pool_scope.execute(move || {
-                while let Ok(seqs) = receiver.recv() {
+                while let Ok((index, seqs)) = receiver.recv() {
                     let output = work(seqs);
-                    done_send.send(output).expect("Failed to send outputs");
+                    done_send
+                        .send((index, output))
+                        .expect("Failed to send outputs");
                 }
             });
         }
@@ -270,13 +2263,15 @@ where
     Ok(())
 }
 
-/// Performs parallel processing on a HashMap.
+/// Performs parallel processing on the entries of a map: a `HashMap` or
+/// `BTreeMap` by reference, or any iterator of `(&K, &V)` pairs, e.g. over
+/// a slice of tuples. Each entry is one unit of `work`.
 ///
 /// # Examples
 ///
 /// ```
 /// use seqkmer::buffer_map_parallel;
-/// use std::collections::HashMap;
+/// use std::collections::{BTreeMap, HashMap, VecDeque};
 ///
 /// # fn main() -> std::io::Result<()> {
 /// let mut map = HashMap::new();
@@ -298,36 +2293,121 @@ where
 ///
 /// let total = buffer_map_parallel(&map, 4, work, func)?;
 /// println!("Total sum: {:?}", total);
+///
+/// // 按物种名索引的 BTreeMap, 以及元组切片
+/// let taxa = BTreeMap::from([("E. coli".to_string(), 4.6), ("S. aureus".to_string(), 2.8)]);
+/// let work = |(name, size): (&String, &f64)| format!("{}: {} Mb", name, size);
+/// let func = |result: &mut seqkmer::ParallelResult<String>| assert_eq!(result.count(), 2);
+/// buffer_map_parallel(&taxa, 4, work, func)?;
+///
+/// let pairs = [(1u64, "a"), (2, "b"), (3, "c")];
+/// let work = |(key, _): (&u64, &&str)| *key;
+/// let func = |result: &mut seqkmer::ParallelResult<u64>| assert_eq!(result.sum::<u64>(), 6);
+/// buffer_map_parallel(pairs.iter().map(|(k, v)| (k, v)), 4, work, func)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn buffer_map_parallel<'a, M, K, V, W, O, F, Out>(
+    map: M,
+    n_threads: usize,
+    work: W,
+    func: F,
+) -> Result<()>
+where
+    M: IntoIterator<Item = (&'a K, &'a V)>,
+    M::IntoIter: Send,
+    K: Sync + 'a,
+    V: Sync + 'a,
+    O: Send,
+    Out: Send + Default,
+    W: Send + Sync + Fn((&K, &V)) -> O,
+    F: FnOnce(&mut ParallelResult<O>) -> Out + Send,
+{
+    map_pipeline(map.into_iter(), n_threads, false, work, func)
+}
+
+/// Like `buffer_map_parallel`, driven by a [`ParallelConfig`]. In ordered
+/// mode outputs follow the map's iteration order; in deterministic mode the
+/// entries are first sorted by key, so a `HashMap` gives the same outputs in
+/// the same order on every run. Only `n_threads`, `ordered` and
+/// `deterministic` apply to maps.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{buffer_map_parallel_with_config, ParallelConfig, ParallelResult};
+/// use std::collections::HashMap;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let map: HashMap<u32, f64> = (0..100).map(|key| (key, key as f64 / 7.0)).collect();
+/// let config = ParallelConfig::new(4).with_deterministic(true);
+///
+/// let mut keys = Vec::new();
+/// let work = |(key, value): (&u32, &f64)| (*key, value * 2.0);
+/// let func = |result: &mut ParallelResult<(u32, f64)>| {
+///     keys = result.map(|(key, _)| key).collect();
+/// };
+/// buffer_map_parallel_with_config(&map, &config, work, func)?;
+/// assert_eq!(keys, (0..100).collect::<Vec<_>>());
 /// # Ok(())
 /// # }
 /// ```
-pub fn buffer_map_parallel<D, W, O, F, Out>(
-    map: &HashMap<u32, Vec<D>>,
+pub fn buffer_map_parallel_with_config<'a, M, K, V, W, O, F, Out>(
+    map: M,
+    config: &ParallelConfig,
+    work: W,
+    func: F,
+) -> Result<()>
+where
+    M: IntoIterator<Item = (&'a K, &'a V)>,
+    M::IntoIter: Send,
+    K: Ord + Sync + 'a,
+    V: Sync + 'a,
+    O: Send,
+    Out: Send + Default,
+    W: Send + Sync + Fn((&K, &V)) -> O,
+    F: FnOnce(&mut ParallelResult<O>) -> Out + Send,
+{
+    let ordered = config.collects_in_order();
+    if config.deterministic {
+        let mut entries: Vec<_> = map.into_iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        map_pipeline(entries.into_iter(), config.n_threads, ordered, work, func)
+    } else {
+        map_pipeline(map.into_iter(), config.n_threads, ordered, work, func)
+    }
+}
+
+fn map_pipeline<'a, E, K, V, W, O, F, Out>(
+    entries: E,
     n_threads: usize,
+    ordered: bool,
     work: W,
     func: F,
 ) -> Result<()>
 where
-    D: Send + Sized + Sync,
+    E: Iterator<Item = (&'a K, &'a V)> + Send,
+    K: Sync + 'a,
+    V: Sync + 'a,
     O: Send,
     Out: Send + Default,
-    W: Send + Sync + Fn((&u32, &Vec<D>)) -> O,
+    W: Send + Sync + Fn((&K, &V)) -> O,
     F: FnOnce(&mut ParallelResult<O>) -> Out + Send,
 {
     assert!(n_threads > 2);
     let buffer_len = n_threads + 2;
-    let (sender, receiver) = bounded::<(&u32, &Vec<D>)>(buffer_len);
-    let (done_send, done_recv) = bounded::<O>(buffer_len);
+    let (sender, receiver) = bounded::<(usize, (&K, &V))>(buffer_len);
+    let (done_send, done_recv) = bounded::<(usize, O)>(buffer_len);
     let receiver = Arc::new(receiver); // 使用 Arc 来共享 receiver
     let done_send = Arc::new(done_send);
     let mut pool = Pool::new(n_threads as u32);
 
-    let mut parallel_result = ParallelResult { recv: done_recv };
+    let mut parallel_result = ParallelResult::new(done_recv).with_ordered(ordered);
 
     pool.scoped(|pool_scope| {
         // 生产者线程
         pool_scope.execute(move || {
-            for entry in map {
+            for entry in entries.enumerate() {
                 sender.send(entry).expect("Failed to send sequences");
             }
         });
@@ -338,9 +2418,11 @@ where
             let work = &work;
             let done_send = Arc::clone(&done_send);
             pool_scope.execute(move || {
-                while let Ok(seqs) = receiver.recv() {
+                while let Ok((index, seqs)) = receiver.recv() {
                     let output = work(seqs);
-                    done_send.send(output).expect("Failed to send outputs");
+                    done_send
+                        .send((index, output))
+                        .expect("Failed to send outputs");
                 }
             });
         }
@@ -356,3 +2438,52 @@ where
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET: (Duration, Duration) = (Duration::from_millis(10), Duration::from_millis(20));
+
+    /// 模拟每条记录耗时固定的负载, 返回每轮之后的批量大小
+    fn run(controller: &BatchController, per_record: Duration, rounds: usize) -> Vec<usize> {
+        (0..rounds)
+            .map(|_| {
+                let size = controller.batch_size();
+                controller.observe(size, per_record * size as u32);
+                controller.batch_size()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn batch_controller_converges_into_target_window() {
+        let per_record = Duration::from_micros(100);
+        for initial in [10, 1000] {
+            let controller = BatchController::new(initial, (10, 1000), TARGET);
+            let sizes = run(&controller, per_record, 20);
+            assert!(sizes.iter().all(|&size| (10..=1000).contains(&size)));
+            // 最终耗时落在目标窗口内, 之后不再变化
+            let last = *sizes.last().unwrap();
+            let elapsed = per_record * last as u32;
+            assert!(elapsed >= TARGET.0 && elapsed <= TARGET.1, "{:?}", sizes);
+            assert_eq!(run(&controller, per_record, 5), vec![last; 5]);
+        }
+    }
+
+    #[test]
+    fn batch_controller_stays_within_bounds() {
+        let fast = BatchController::new(100, (10, 1000), TARGET);
+        let sizes = run(&fast, Duration::from_nanos(10), 20);
+        assert!(sizes.iter().all(|&size| size <= 1000));
+        assert_eq!(fast.batch_size(), 1000);
+
+        let slow = BatchController::new(100, (10, 1000), TARGET);
+        let sizes = run(&slow, Duration::from_secs(1), 20);
+        assert!(sizes.iter().all(|&size| size >= 10));
+        assert_eq!(slow.batch_size(), 10);
+
+        let clamped = BatchController::new(5000, (10, 1000), TARGET);
+        assert_eq!(clamped.batch_size(), 1000);
+    }
+}
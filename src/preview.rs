@@ -0,0 +1,239 @@
+use crate::fastx::FastxReader;
+use crate::feat::Alphabet;
+use crate::filter::Filter;
+use crate::reader::Reader;
+use crate::seq::Base;
+use crate::trim::Transform;
+use crate::utils::OptionPair;
+use std::collections::BTreeMap;
+use std::io::Result;
+
+/// Expected post-QC read-length and yield distribution estimated from the
+/// first records of an input.
+///
+/// The retained length of a read is the number of bases the scanner can use,
+/// i.e. bases left after quality masking and any user transform; masked and
+/// ambiguous bases do not count.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::PreviewReport;
+///
+/// let mut report = PreviewReport::default();
+/// report.add(20, 20);
+/// report.add(20, 10);
+/// assert_eq!(report.reads, 2);
+/// assert_eq!(report.yield_fraction(), 0.75);
+/// assert_eq!(report.length_quantile(0.5), 10);
+/// ```
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PreviewReport {
+    /// 采样的记录数 (双端算一条)
+    pub records: usize,
+    /// 被过滤掉的记录数
+    pub dropped: usize,
+    /// 采样的 read 数 (双端算两条)
+    pub reads: usize,
+    pub input_bases: usize,
+    pub retained_bases: usize,
+    /// 过滤后长度 -> read 数
+    pub lengths: BTreeMap<usize, usize>,
+}
+
+impl PreviewReport {
+    /// Records one read with its raw and retained length.
+    pub fn add(&mut self, input_len: usize, retained_len: usize) {
+        self.reads += 1;
+        self.input_bases += input_len;
+        self.retained_bases += retained_len;
+        *self.lengths.entry(retained_len).or_insert(0) += 1;
+    }
+
+    /// Fraction of input bases retained after QC.
+    pub fn yield_fraction(&self) -> f64 {
+        if self.input_bases == 0 {
+            return 0.0;
+        }
+        self.retained_bases as f64 / self.input_bases as f64
+    }
+
+    /// Mean retained read length.
+    pub fn mean_length(&self) -> f64 {
+        if self.reads == 0 {
+            return 0.0;
+        }
+        self.retained_bases as f64 / self.reads as f64
+    }
+
+    /// Smallest retained length such that at least `q` of the reads are no longer.
+    pub fn length_quantile(&self, q: f64) -> usize {
+        let target = ((self.reads as f64) * q.clamp(0.0, 1.0)).ceil().max(1.0) as usize;
+        let mut seen = 0;
+        for (&len, &count) in &self.lengths {
+            seen += count;
+            if seen >= target {
+                return len;
+            }
+        }
+        0
+    }
+}
+
+/// Counts the bases of a sequence the minimizer scanner will use.
+#[inline]
+fn retained_len(seq: &[u8]) -> usize {
+    let alphabet = Alphabet::default();
+    seq.iter()
+        .filter(|&&c| alphabet.encode(c).is_some())
+        .count()
+}
+
+/// Counts the bases of a sequence, ignoring line breaks.
+#[inline]
+fn input_len(seq: &[u8]) -> usize {
+    seq.iter().filter(|&&c| c != b'\n' && c != b'\r').count()
+}
+
+/// Samples the first `n_records` records of a reader, applies `transform`
+/// to each and reports the resulting length distribution. `transform` may
+/// trim or mask the record in place and returns `false` to drop it.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{preview_reader, FastaReader};
+/// use std::path::Path;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut reader = FastaReader::from_path(Path::new("tests/data/test.fasta"), 0)?;
+/// let report = preview_reader(&mut reader, 2, |seq| {
+///     seq.body.apply_mut(|s| s.truncate(15));
+///     true
+/// })?;
+/// assert_eq!(report.records, 2);
+/// assert_eq!(report.retained_bases, 30);
+/// assert_eq!(report.input_bases, 40);
+/// # Ok(())
+/// # }
+/// ```
+pub fn preview_reader<R, F>(
+    reader: &mut R,
+    n_records: usize,
+    mut transform: F,
+) -> Result<PreviewReport>
+where
+    R: Reader,
+    F: FnMut(&mut Base<Vec<u8>>) -> bool,
+{
+    let mut report = PreviewReport::default();
+    while report.records < n_records {
+        let Some(seqs) = reader.next()? else {
+            break;
+        };
+        for mut seq in seqs.into_iter().take(n_records - report.records) {
+            report.records += 1;
+            let raw = seq.body.apply(|s| input_len(s));
+            if !transform(&mut seq) {
+                report.dropped += 1;
+                continue;
+            }
+            let kept = seq.body.apply(|s| retained_len(s));
+            match (raw, kept) {
+                (OptionPair::Pair(r1, r2), OptionPair::Pair(k1, k2)) => {
+                    report.add(r1, k1);
+                    report.add(r2, k2);
+                }
+                (raw, kept) => report.add(
+                    raw.reduce(0, |acc, &len| acc + len),
+                    kept.reduce(0, |acc, &len| acc + len),
+                ),
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Previews each input (a single file or an R1/R2 pair) with the same
+/// quality masking the full run would use, returning one report per input.
+/// Trimming and read filters are not applied, so the report is of the raw
+/// input; see [`preview_inputs_with`] to apply them.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::preview_inputs;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let inputs = vec![
+///     vec!["tests/data/test.fasta".to_string()],
+///     vec!["tests/data/test.fastq".to_string()],
+/// ];
+/// let reports = preview_inputs(&inputs, 100, 0)?;
+/// assert_eq!(reports.len(), 2);
+/// assert_eq!(reports[1].yield_fraction(), 1.0);
+/// # Ok(())
+/// # }
+/// ```
+pub fn preview_inputs(
+    inputs: &[Vec<String>],
+    n_records: usize,
+    quality_score: i32,
+) -> Result<Vec<PreviewReport>> {
+    preview_inputs_with(
+        inputs,
+        n_records,
+        quality_score,
+        &|_: &mut Base<Vec<u8>>| {},
+        &|_: &Base<Vec<u8>>| true,
+    )
+}
+
+/// Like [`preview_inputs`], applying `transform` and then `filter` to every
+/// sampled record, as `read_parallel_transform` and
+/// `read_parallel_filtered` would in the full run. Records the filter
+/// rejects are counted in [`PreviewReport::dropped`].
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{preview_inputs_with, Base, ReadFilter};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let inputs = vec![vec!["tests/data/test.fasta".to_string()]];
+/// let trim = |seq: &mut Base<Vec<u8>>| {
+///     seq.body.apply_mut(|s| s.truncate(15));
+/// };
+/// let filter = ReadFilter::new().with_min_length(16);
+///
+/// let reports = preview_inputs_with(&inputs, 100, 0, &trim, &filter)?;
+/// // 截短后每条都短于 16, 全部被过滤
+/// assert_eq!(reports[0].records, 3);
+/// assert_eq!(reports[0].dropped, 3);
+/// # Ok(())
+/// # }
+/// ```
+pub fn preview_inputs_with<T, P>(
+    inputs: &[Vec<String>],
+    n_records: usize,
+    quality_score: i32,
+    transform: &T,
+    filter: &P,
+) -> Result<Vec<PreviewReport>>
+where
+    T: Transform,
+    P: Filter,
+{
+    inputs
+        .iter()
+        .enumerate()
+        .map(|(file_index, files)| {
+            let paths = OptionPair::from_slice(files);
+            let mut reader = FastxReader::from_paths(paths, file_index, quality_score)?;
+            preview_reader(&mut reader, n_records, |seq| {
+                transform.apply(seq);
+                filter.accept(seq)
+            })
+        })
+        .collect()
+}
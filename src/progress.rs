@@ -0,0 +1,91 @@
+use crossbeam_channel::Sender;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A progress update from a reader or a parallel driver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressEvent {
+    /// Bytes consumed from the input, after decompression.
+    BytesRead(u64),
+    /// Records handed out by a reader.
+    RecordsEmitted(usize),
+    /// Records scanned and passed to `work` by a parallel driver.
+    RecordsProcessed(usize),
+}
+
+/// Receives progress updates. Closures taking a `ProgressEvent` and
+/// crossbeam `Sender<ProgressEvent>`s implement it, so a progress bar can
+/// be driven by a callback or from another thread.
+pub trait Progress: Send + Sync {
+    fn update(&self, event: ProgressEvent);
+}
+
+impl<F> Progress for F
+where
+    F: Fn(ProgressEvent) + Send + Sync,
+{
+    fn update(&self, event: ProgressEvent) {
+        self(event)
+    }
+}
+
+impl Progress for Sender<ProgressEvent> {
+    /// Sends the event; a dropped receiver is ignored.
+    fn update(&self, event: ProgressEvent) {
+        let _ = self.send(event);
+    }
+}
+
+/// Running totals of progress events, readable from any thread.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{FastaReader, Progress, ProgressCounter, Reader};
+/// use std::path::Path;
+/// use std::sync::Arc;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let counter = Arc::new(ProgressCounter::default());
+/// let mut reader = FastaReader::from_path(Path::new("tests/data/test.fasta"), 0)?
+///     .with_progress(counter.clone());
+/// while reader.next()?.is_some() {}
+///
+/// assert_eq!(counter.records_emitted(), 3);
+/// assert_eq!(counter.bytes_read(), std::fs::metadata("tests/data/test.fasta")?.len());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct ProgressCounter {
+    bytes_read: AtomicU64,
+    records_emitted: AtomicU64,
+    records_processed: AtomicU64,
+}
+
+impl ProgressCounter {
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read.load(Ordering::Relaxed)
+    }
+
+    pub fn records_emitted(&self) -> u64 {
+        self.records_emitted.load(Ordering::Relaxed)
+    }
+
+    pub fn records_processed(&self) -> u64 {
+        self.records_processed.load(Ordering::Relaxed)
+    }
+}
+
+impl Progress for ProgressCounter {
+    fn update(&self, event: ProgressEvent) {
+        match event {
+            ProgressEvent::BytesRead(n) => self.bytes_read.fetch_add(n, Ordering::Relaxed),
+            ProgressEvent::RecordsEmitted(n) => {
+                self.records_emitted.fetch_add(n as u64, Ordering::Relaxed)
+            }
+            ProgressEvent::RecordsProcessed(n) => self
+                .records_processed
+                .fetch_add(n as u64, Ordering::Relaxed),
+        };
+    }
+}
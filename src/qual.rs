@@ -0,0 +1,202 @@
+use crate::error::{Result, SeqKmerError};
+use crate::fasta::FastaReader;
+use crate::reader::{dyn_reader, Reader, BATCH_BYTES, BUFSIZE};
+use crate::seq::{Base, QualityPolicy};
+use crate::utils::OptionPair;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+fn invalid_data(msg: String) -> SeqKmerError {
+    SeqKmerError::parse(None, msg)
+}
+
+/// Reads legacy 454/Sanger archives that keep sequences in a FASTA file and
+/// numeric Phred scores in a parallel `.qual` file, zipping them into
+/// records whose `Base::qual` holds Phred+33 qualities, as if they had been
+/// read from FASTQ.
+///
+/// Records must appear in the same order in both files; a mismatched id or
+/// score count is a [`SeqKmerError::Parse`] error.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{FastaQualReader, QualityPolicy, Reader};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut reader = FastaQualReader::from_path("tests/data/test.fasta", "tests/data/test.qual", 0)?
+///     .with_quality_policy(QualityPolicy::MaskBelow(20));
+/// let seqs = reader.next()?.unwrap();
+///
+/// assert_eq!(seqs.len(), 3);
+/// assert_eq!(seqs[0].qual.as_ref().unwrap().single().unwrap()[0], b'I');
+/// assert_eq!(seqs[0].body.single().unwrap(), b"ATCGATCGATCGATCNNNNN");
+/// assert_eq!(seqs[2].body.single().unwrap(), b"NNNNNATATATATATATATA");
+/// # Ok(())
+/// # }
+/// ```
+pub struct FastaQualReader<R, Q>
+where
+    R: Read + Send,
+    Q: Read + Send,
+{
+    fasta: FastaReader<R>,
+    qual: BufReader<Q>,
+    quality_policy: QualityPolicy,
+    header: Vec<u8>,
+    scores: Vec<u8>,
+
+    // 批量读取
+    batch_size: usize,
+    batch_bytes: usize,
+}
+
+impl<R, Q> FastaQualReader<R, Q>
+where
+    R: Read + Send,
+    Q: Read + Send,
+{
+    /// Creates a reader over a FASTA stream and its `.qual` stream.
+    pub fn new(fasta: R, qual: Q, file_index: usize) -> Self {
+        Self {
+            fasta: FastaReader::new(fasta, file_index),
+            qual: BufReader::with_capacity(BUFSIZE, qual),
+            quality_policy: QualityPolicy::NoMask,
+            header: Vec::new(),
+            scores: Vec::new(),
+            batch_size: 30,
+            batch_bytes: BATCH_BYTES,
+        }
+    }
+
+    /// Sets how qualities are applied to the sequence, as for FASTQ.
+    pub fn with_quality_policy(mut self, quality_policy: QualityPolicy) -> Self {
+        self.quality_policy = quality_policy;
+        self
+    }
+
+    /// Sets the maximum number of records returned per batch.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Reads the next `.qual` record and returns its id and Phred+33 scores.
+    fn next_qual(&mut self) -> Result<Option<(String, Vec<u8>)>> {
+        // 读取qual文件header部分
+        self.header.clear();
+        if self.qual.read_until(b'\n', &mut self.header)? == 0 {
+            return Ok(None);
+        }
+        // 读取qual文件分数部分, 直到下一个 '>'
+        self.scores.clear();
+        self.qual.read_until(b'>', &mut self.scores)?;
+        if self.scores.last() == Some(&b'>') {
+            self.scores.pop();
+        }
+
+        let header = String::from_utf8_lossy(&self.header);
+        let id = header
+            .trim_start_matches('>')
+            .split(|c: char| c.is_whitespace() || c == '\u{1}')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        let quals = String::from_utf8_lossy(&self.scores)
+            .split_ascii_whitespace()
+            .map(|score| match score.parse::<u8>() {
+                Ok(q) if q <= 93 => Ok(q + b'!'),
+                _ => Err(invalid_data(format!(
+                    "invalid quality score {:?} in record {}",
+                    score, id
+                ))),
+            })
+            .collect::<Result<Vec<u8>>>()?;
+        Ok(Some((id, quals)))
+    }
+
+    /// Reads the next sequence together with its qualities.
+    pub fn read_next(&mut self) -> Result<Option<Base<Vec<u8>>>> {
+        let Some((_, mut record)) = self.fasta._next()? else {
+            return Ok(None);
+        };
+        let (id, mut quals) = self.next_qual()?.ok_or_else(|| {
+            invalid_data(format!(
+                "qual file ended before record {}",
+                record.header.id
+            ))
+        })?;
+        if id != record.header.id {
+            return Err(invalid_data(format!(
+                "qual record {} does not match sequence {}",
+                id, record.header.id
+            )));
+        }
+
+        let mut seq: Vec<u8> = record
+            .body
+            .single()
+            .map(|seq| {
+                seq.iter()
+                    .copied()
+                    .filter(|&c| c != b'\n' && c != b'\r')
+                    .collect()
+            })
+            .unwrap_or_default();
+        if seq.len() != quals.len() {
+            return Err(invalid_data(format!(
+                "record {} has {} bases but {} quality scores",
+                id,
+                seq.len(),
+                quals.len()
+            )));
+        }
+        self.quality_policy.apply(&mut seq, &mut quals);
+
+        record.header.quality_policy = self.quality_policy;
+        record.body = OptionPair::Single(seq);
+        Ok(Some(record.with_qual(OptionPair::Single(quals))))
+    }
+}
+
+impl FastaQualReader<Box<dyn Read + Send>, Box<dyn Read + Send>> {
+    /// Opens a FASTA file and its `.qual` file, either of which may be
+    /// gzipped.
+    pub fn from_path<P: AsRef<Path>, Q: AsRef<Path>>(
+        fasta: P,
+        qual: Q,
+        file_index: usize,
+    ) -> Result<Self> {
+        Ok(Self::new(dyn_reader(fasta)?, dyn_reader(qual)?, file_index))
+    }
+}
+
+impl<R, Q> Reader for FastaQualReader<R, Q>
+where
+    R: Read + Send,
+    Q: Read + Send,
+{
+    fn next(&mut self) -> Result<Option<Vec<Base<Vec<u8>>>>> {
+        let mut seqs = Vec::new();
+        let mut total_bytes = 0;
+
+        for _ in 0..self.batch_size {
+            match self.read_next()? {
+                Some(seq) => {
+                    total_bytes += seq.body.reduce(0, |acc, s| acc + s.len());
+                    seqs.push(seq);
+                    if total_bytes > self.batch_bytes {
+                        break;
+                    }
+                }
+                None => break,
+            }
+        }
+
+        Ok(Some(seqs).filter(|v| !v.is_empty()))
+    }
+
+    fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size.max(1);
+    }
+}
@@ -1,12 +1,20 @@
+use crate::error::{self, SeqKmerError};
 use crate::seq::{Base, SeqFormat};
+use crate::slice::{Shard, TakeRecords};
 use crate::utils::OptionPair;
-use flate2::read::GzDecoder;
+use flate2::read::MultiGzDecoder;
 use std::fmt;
 use std::fs::File;
-use std::io::{self, BufRead, BufReader, Read, Result, Seek};
-use std::path::Path;
+use std::io::{self, BufRead, BufReader, Read, Result, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
 
 /// Creates a dynamic reader that can handle both gzipped and non-gzipped files.
+/// Concatenated gzip members (`cat a.fq.gz b.fq.gz`) are decoded in turn.
+///
+/// With the `remote` feature, `http://` and `s3://` URLs are streamed with
+/// range requests instead of being opened as local paths.
 ///
 /// # Examples
 ///
@@ -21,15 +29,174 @@ use std::path::Path;
 /// # }
 /// ```
 pub fn dyn_reader<P: AsRef<Path>>(path: P) -> Result<Box<dyn Read + Send>> {
+    #[cfg(feature = "remote")]
+    if let Some(url) = path.as_ref().to_str().filter(|p| crate::remote::is_url(p)) {
+        return crate::remote::open_url(url);
+    }
     let mut file = open_file(path)?;
     if is_gzipped(&mut file)? {
-        let decoder = GzDecoder::new(file);
+        let decoder = GzStream(MultiGzDecoder::new(file));
         Ok(Box::new(decoder))
     } else {
         Ok(Box::new(file))
     }
 }
 
+/// Reports decoding failures of a gzip stream as
+/// [`SeqKmerError::CompressionError`], so they can be told apart from
+/// malformed records.
+struct GzStream<R: Read>(MultiGzDecoder<R>);
+
+impl<R: Read> Read for GzStream<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.0.read(buf).map_err(|e| match e.kind() {
+            io::ErrorKind::InvalidInput
+            | io::ErrorKind::InvalidData
+            | io::ErrorKind::UnexpectedEof => {
+                SeqKmerError::CompressionError(format!("gzip: {}", e)).into()
+            }
+            _ => e,
+        })
+    }
+}
+
+/// Shared record of a truncated input, filled in by [`TolerantGzDecoder`].
+#[derive(Debug, Clone, Default)]
+pub struct Truncation {
+    truncated: Arc<AtomicBool>,
+    recovered: Arc<AtomicU64>,
+}
+
+impl Truncation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the input ended in the middle of a compressed block.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated.load(Ordering::Relaxed)
+    }
+
+    /// Decompressed bytes delivered before the input ended; only meaningful
+    /// once [`is_truncated`](Self::is_truncated) is set.
+    pub fn recovered_bytes(&self) -> u64 {
+        self.recovered.load(Ordering::Relaxed)
+    }
+}
+
+/// Notes when the underlying (compressed) stream has been read to its end.
+struct EofFlag<R: Read> {
+    inner: R,
+    eof: bool,
+}
+
+impl<R: Read> Read for EofFlag<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let n = self.inner.read(buf)?;
+        if n == 0 && !buf.is_empty() {
+            self.eof = true;
+        }
+        Ok(n)
+    }
+}
+
+/// Multi-member gzip decoder that treats a stream cut off inside its last
+/// block as a normal end of file, so the records decoded before an
+/// interrupted transfer stopped can still be used. A decoding error raised
+/// after the compressed input was exhausted counts as truncation and is
+/// reported through a [`Truncation`] handle; errors in the middle of the
+/// input are returned as usual. A partial last record may remain: FASTQ
+/// readers reject it, FASTA readers return it shortened.
+///
+/// # Examples
+///
+/// ```
+/// use flate2::write::GzEncoder;
+/// use flate2::{read::MultiGzDecoder, Compression};
+/// use seqkmer::{TolerantGzDecoder, Truncation};
+/// use std::io::{Read, Write};
+///
+/// let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+/// gz.write_all(&b">seq1\nACGT\n".repeat(1000)).unwrap();
+/// let gz = gz.finish().unwrap();
+/// let cut = &gz[..gz.len() - 20];
+///
+/// let mut text = Vec::new();
+/// assert!(MultiGzDecoder::new(cut).read_to_end(&mut text).is_err());
+///
+/// let report = Truncation::new();
+/// let mut reader = TolerantGzDecoder::new(cut, &report);
+/// text.clear();
+/// reader.read_to_end(&mut text).unwrap();
+/// assert!(report.is_truncated());
+/// assert_eq!(report.recovered_bytes(), text.len() as u64);
+/// assert!(text.starts_with(b">seq1\nACGT\n"));
+/// ```
+pub struct TolerantGzDecoder<R: Read> {
+    inner: MultiGzDecoder<EofFlag<R>>,
+    report: Truncation,
+    bytes: u64,
+}
+
+impl<R: Read> TolerantGzDecoder<R> {
+    /// Decodes the gzip stream `inner`.
+    pub fn new(inner: R, report: &Truncation) -> Self {
+        Self {
+            inner: MultiGzDecoder::new(EofFlag { inner, eof: false }),
+            report: report.clone(),
+            bytes: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for TolerantGzDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        match self.inner.read(buf) {
+            Ok(n) => {
+                self.bytes += n as u64;
+                Ok(n)
+            }
+            // 压缩输入已读完仍解码失败: 视为截断
+            Err(_) if self.inner.get_ref().eof => {
+                self.report.truncated.store(true, Ordering::Relaxed);
+                self.report.recovered.store(self.bytes, Ordering::Relaxed);
+                Ok(0)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Like [`dyn_reader`], but a gzip file cut off in its last block ends
+/// early instead of failing; `report` tells whether that happened and how
+/// many bytes were recovered.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{dyn_reader_tolerant, FastaReader, Reader, Truncation};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let report = Truncation::new();
+/// let file = dyn_reader_tolerant("tests/data/test.fasta", &report)?;
+/// let mut reader = FastaReader::new(file, 0);
+/// assert_eq!(reader.next()?.unwrap().len(), 3);
+/// assert!(!report.is_truncated());
+/// # Ok(())
+/// # }
+/// ```
+pub fn dyn_reader_tolerant<P: AsRef<Path>>(
+    path: P,
+    report: &Truncation,
+) -> Result<Box<dyn Read + Send>> {
+    let mut file = open_file(path)?;
+    if is_gzipped(&mut file)? {
+        Ok(Box::new(TolerantGzDecoder::new(file, report)))
+    } else {
+        Ok(Box::new(file))
+    }
+}
+
 /// Checks if a file is gzipped.
 ///
 /// # Examples
@@ -52,6 +219,20 @@ pub fn is_gzipped(file: &mut File) -> Result<bool> {
     Ok(buffer == [0x1F, 0x8B])
 }
 
+/// Returns the header text after the id, without surrounding whitespace,
+/// if `keep` is set and there is any.
+pub(crate) fn header_desc(rest: &str, keep: bool) -> Option<String> {
+    trimmed_desc(rest, keep).map(str::to_string)
+}
+
+fn trimmed_desc(rest: &str, keep: bool) -> Option<&str> {
+    if !keep {
+        return None;
+    }
+    let desc = rest.trim_matches(|c: char| c.is_whitespace() || c == '\u{1}');
+    (!desc.is_empty()).then_some(desc)
+}
+
 /// Trims pair information from a sequence ID.
 ///
 /// # Examples
@@ -64,14 +245,81 @@ pub fn is_gzipped(file: &mut File) -> Result<bool> {
 /// assert_eq!(trimmed, "seq1");
 /// ```
 pub fn trim_pair_info(id: &str) -> String {
+    trim_pair_info_with(id, PairSuffixes::default())
+}
+
+/// Which mate suffixes `trim_pair_info_with` strips from read ids.
+///
+/// Casava 1.8+ headers (`@id 1:N:0:ACGT`) carry the mate number in the
+/// comment, which the readers already drop with everything after the first
+/// whitespace, so they need no rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PairSuffixes {
+    /// `/1` and `/2`.
+    pub slash: bool,
+    /// `_1` and `_2`.
+    pub underscore: bool,
+    /// `.1` and `.2`.
+    pub dot: bool,
+}
+
+impl Default for PairSuffixes {
+    /// Only the slash form, as `trim_pair_info` has always done; the other
+    /// forms also end ordinary ids such as `contig_1`.
+    fn default() -> Self {
+        Self {
+            slash: true,
+            underscore: false,
+            dot: false,
+        }
+    }
+}
+
+impl PairSuffixes {
+    /// Strips every known suffix form.
+    pub fn all() -> Self {
+        Self {
+            slash: true,
+            underscore: true,
+            dot: true,
+        }
+    }
+}
+
+/// Trims a mate suffix from a sequence ID according to `rules`.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{trim_pair_info_with, PairSuffixes};
+///
+/// assert_eq!(trim_pair_info_with("SRR001_1", PairSuffixes::default()), "SRR001_1");
+/// assert_eq!(trim_pair_info_with("SRR001_1", PairSuffixes::all()), "SRR001");
+/// assert_eq!(trim_pair_info_with("SRR001.2", PairSuffixes::all()), "SRR001");
+/// assert_eq!(trim_pair_info_with("r/2", PairSuffixes::all()), "r");
+/// assert_eq!(trim_pair_info_with("_1", PairSuffixes::all()), "_1");
+/// ```
+pub fn trim_pair_info_with(id: &str, rules: PairSuffixes) -> String {
+    strip_pair_suffix(id, rules).to_string()
+}
+
+/// `trim_pair_info_with` without the copy.
+pub(crate) fn strip_pair_suffix(id: &str, rules: PairSuffixes) -> &str {
     let sz = id.len();
     if sz <= 2 {
-        return id.to_string();
+        return id;
     }
-    if id.ends_with("/1") || id.ends_with("/2") {
-        return id[0..sz - 2].to_string();
+    let bytes = id.as_bytes();
+    let separator_enabled = match bytes[sz - 2] {
+        b'/' => rules.slash,
+        b'_' => rules.underscore,
+        b'.' => rules.dot,
+        _ => false,
+    };
+    if separator_enabled && matches!(bytes[sz - 1], b'1' | b'2') {
+        return &id[0..sz - 2];
     }
-    id.to_string()
+    id
 }
 
 /// Opens a file and provides a more informative error message if the file is not found.
@@ -98,51 +346,402 @@ pub fn open_file<P: AsRef<Path>>(path: P) -> Result<File> {
     })
 }
 
-/// Detects the format of a sequence file (FASTA or FASTQ).
+/// File name suffixes taken as FASTA by [`fasta_files_in`], also with a
+/// trailing `.gz`.
+pub const FASTA_EXTENSIONS: [&str; 7] = ["fa", "fasta", "fna", "ffn", "faa", "frn", "fas"];
+
+/// Lists the FASTA files directly inside `dir` (see [`FASTA_EXTENSIONS`]),
+/// sorted by name. Subdirectories are not searched.
 ///
 /// # Examples
 ///
 /// ```
-/// use seqkmer::{detect_file_format, SeqFormat};
-/// use std::path::Path;
+/// use seqkmer::fasta_files_in;
+/// use std::path::PathBuf;
 ///
 /// # fn main() -> std::io::Result<()> {
-/// let path = Path::new("tests/data/test.fasta");
-/// let format = detect_file_format(path)?;
-/// assert_eq!(format, SeqFormat::Fasta);
+/// let files = fasta_files_in("tests/data")?;
+/// assert_eq!(files, vec![PathBuf::from("tests/data/test.fasta")]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn fasta_files_in<P: AsRef<Path>>(dir: P) -> Result<Vec<PathBuf>> {
+    let is_fasta = |path: &Path| {
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("");
+        let name = name.strip_suffix(".gz").unwrap_or(name);
+        name.rsplit_once('.')
+            .is_some_and(|(_, ext)| FASTA_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+    };
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && is_fasta(&path) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Expands a glob such as `refs/*.fna.gz` to the matching files, sorted
+/// by name. `*` and `?` are supported in the last path component only; the
+/// directory part is taken literally.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::glob_paths;
+/// use std::path::PathBuf;
+///
+/// # fn main() -> std::io::Result<()> {
+/// assert_eq!(glob_paths("tests/data/*.fasta")?, vec![PathBuf::from("tests/data/test.fasta")]);
+/// assert_eq!(glob_paths("tests/data/test.fast?")?.len(), 2);
+/// assert!(glob_paths("tests/data/*.none")?.is_empty());
 /// # Ok(())
 /// # }
 /// ```
-pub fn detect_file_format<P: AsRef<Path>>(path: P) -> io::Result<SeqFormat> {
-    let read1: Box<dyn io::Read + Send> = dyn_reader(path)?;
-    let reader = BufReader::new(read1);
-    let mut lines = reader.lines();
-
-    if let Some(first_line) = lines.next() {
-        let line = first_line?;
-
-        if line.starts_with('>') {
-            return Ok(SeqFormat::Fasta);
-        } else if line.starts_with('@') {
-            let _ = lines.next();
-            if let Some(third_line) = lines.next() {
-                let line: String = third_line?;
-                if line.starts_with('+') {
-                    return Ok(SeqFormat::Fastq);
+pub fn glob_paths(pattern: &str) -> Result<Vec<PathBuf>> {
+    let pattern = Path::new(pattern);
+    let name = pattern
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("glob has no file name: {:?}", pattern),
+            )
+        })?;
+    let dir = match pattern.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let matched = entry
+            .file_name()
+            .to_str()
+            .is_some_and(|file| wildcard_match(name.as_bytes(), file.as_bytes()));
+        if matched && entry.path().is_file() {
+            files.push(dir.join(entry.file_name()));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// `*` 匹配任意长度, `?` 匹配单个字节
+fn wildcard_match(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // 最近一个 `*` 的位置及其匹配到的文本位置, 用于回溯
+    let mut star = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == b'?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
                 }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// Returns the offset of the first record starting at or after `offset`,
+/// or the length of the input if there is none. FASTA records start at a
+/// line beginning with `>`. FASTQ records start at a line beginning with
+/// `@` whose second following line begins with `+`, which tells a header
+/// from a quality line starting with `@`; multi-line FASTQ is not
+/// supported.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{record_start, SeqFormat};
+/// use std::io::Cursor;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let fastq = b"@r1\nACGT\n+\n@III\n@r2\nGGCC\n+\nIIII\n";
+/// // 从 r1 的质量行 "@III" 开始, 跳到 r2
+/// assert_eq!(record_start(&mut Cursor::new(fastq), 11, SeqFormat::Fastq)?, 16);
+/// assert_eq!(record_start(&mut Cursor::new(fastq), 0, SeqFormat::Fastq)?, 0);
+/// assert_eq!(record_start(&mut Cursor::new(fastq), 17, SeqFormat::Fastq)?, 32);
+/// # Ok(())
+/// # }
+/// ```
+pub fn record_start<R: Read + Seek>(input: &mut R, offset: u64, format: SeqFormat) -> Result<u64> {
+    if offset == 0 {
+        return Ok(0);
+    }
+    let marker = match format {
+        SeqFormat::Fastq => b'@',
+        _ => b'>',
+    };
+    // 从前一个字节开始, 丢弃 offset 所在行的剩余部分
+    input.seek(SeekFrom::Start(offset - 1))?;
+    let mut reader = BufReader::new(input);
+    let mut line = Vec::new();
+    let mut pos = offset - 1 + reader.read_until(b'\n', &mut line)? as u64;
+    // 最近三行的 (起始位置, 首字节)
+    let mut window: std::collections::VecDeque<(u64, Option<u8>)> = Default::default();
+    loop {
+        line.clear();
+        let len = reader.read_until(b'\n', &mut line)? as u64;
+        if len == 0 {
+            return Ok(pos);
+        }
+        window.push_back((pos, line.first().copied()));
+        pos += len;
+        if format != SeqFormat::Fastq {
+            if window[0].1 == Some(marker) {
+                return Ok(window[0].0);
             }
-        } else {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "Unrecognized fasta(fastq) file format",
-            ));
+            window.clear();
+        } else if window.len() == 3 {
+            if window[0].1 == Some(marker) && window[2].1 == Some(b'+') {
+                return Ok(window[0].0);
+            }
+            window.pop_front();
         }
     }
+}
 
-    Err(io::Error::new(
-        io::ErrorKind::Other,
-        "Unrecognized fasta(fastq) file format",
-    ))
+/// Opens the records of an uncompressed FASTA or FASTQ file that start in
+/// `start..end`. Ranges that tile the file select every record exactly
+/// once, so a large file can be split across processes by byte offsets.
+/// Compressed files are rejected, since their offsets cannot be sought.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{open_byte_range, FastaReader, Reader, SeqFormat};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let path = "tests/data/test.fasta";
+/// let len = std::fs::metadata(path)?.len();
+/// let mut ids = Vec::new();
+/// for (start, end) in [(0, 30), (30, 60), (60, len)] {
+///     let mut reader = FastaReader::new(open_byte_range(path, start, end, SeqFormat::Fasta)?, 0);
+///     while let Some(seqs) = reader.next()? {
+///         ids.extend(seqs.into_iter().map(|seq| seq.header.id));
+///     }
+/// }
+/// assert_eq!(ids, vec!["seq1", "seq2", "seq3"]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn open_byte_range<P: AsRef<Path>>(
+    path: P,
+    start: u64,
+    end: u64,
+    format: SeqFormat,
+) -> Result<Box<dyn Read + Send>> {
+    let mut file = open_file(&path)?;
+    if is_gzipped(&mut file)? {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "byte ranges need an uncompressed file: {}",
+                path.as_ref().display()
+            ),
+        ));
+    }
+    let first = record_start(&mut file, start, format)?;
+    let last = record_start(&mut file, end.max(start), format)?;
+    file.seek(SeekFrom::Start(first))?;
+    Ok(Box::new(file.take(last.saturating_sub(first))))
+}
+
+/// Input stream returned by [`sniff_format`]: the sniffed prefix followed
+/// by the rest of the stream.
+pub type Sniffed<R> = io::Chain<io::Cursor<Vec<u8>>, R>;
+
+/// Decides FASTA or FASTQ from the beginning of a buffer, or returns `None`
+/// if more input is needed.
+fn classify_prefix(prefix: &[u8], eof: bool) -> Option<error::Result<SeqFormat>> {
+    let unrecognized = |line| {
+        Some(Err(SeqKmerError::parse(
+            line,
+            "Unrecognized fasta(fastq) file format",
+        )))
+    };
+    match prefix.first() {
+        None if eof => unrecognized(None),
+        None => None,
+        Some(b'>') => Some(Ok(SeqFormat::Fasta)),
+        Some(b'@') => {
+            // 第三行以 '+' 开头才是 fastq
+            let mut newlines = prefix.iter().enumerate().filter(|&(_, &c)| c == b'\n');
+            match newlines.nth(1) {
+                Some((i, _)) if i + 1 < prefix.len() => {
+                    if prefix[i + 1] == b'+' {
+                        Some(Ok(SeqFormat::Fastq))
+                    } else {
+                        unrecognized(Some(3))
+                    }
+                }
+                _ if eof => unrecognized(None),
+                _ => None,
+            }
+        }
+        Some(_) => unrecognized(Some(1)),
+    }
+}
+
+/// Detects FASTA or FASTQ by reading just enough of `reader`, and returns
+/// the format together with a stream that replays the bytes read, so the
+/// input is opened and decompressed only once. Works on non-seekable
+/// inputs such as pipes.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{sniff_format, FastqReader, OptionPair, Reader, SeqFormat};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let input: &[u8] = b"@read1\nACGT\n+\nIIII\n";
+/// let (format, stream) = sniff_format(input)?;
+/// assert_eq!(format, SeqFormat::Fastq);
+///
+/// let mut reader = FastqReader::new(OptionPair::Single(stream), 0, 0);
+/// assert_eq!(reader.next()?.unwrap()[0].header.id, "read1");
+/// # Ok(())
+/// # }
+/// ```
+pub fn sniff_format<R: Read>(mut reader: R) -> error::Result<(SeqFormat, Sniffed<R>)> {
+    let mut prefix = Vec::new();
+    let mut chunk = [0u8; 8192];
+    let format = loop {
+        let n = reader.read(&mut chunk)?;
+        prefix.extend_from_slice(&chunk[..n]);
+        if let Some(format) = classify_prefix(&prefix, n == 0) {
+            break format?;
+        }
+    };
+    Ok((format, io::Cursor::new(prefix).chain(reader)))
+}
+
+/// Opens a file like [`dyn_reader`] and sniffs its format, returning the
+/// stream to hand to the matching reader.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{open_sniffed, FastaReader, Reader, SeqFormat};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let (format, stream) = open_sniffed("tests/data/test.fasta")?;
+/// assert_eq!(format, SeqFormat::Fasta);
+/// let mut reader = FastaReader::new(stream, 0);
+/// assert_eq!(reader.next()?.unwrap().len(), 3);
+/// # Ok(())
+/// # }
+/// ```
+pub fn open_sniffed<P: AsRef<Path>>(path: P) -> error::Result<(SeqFormat, Box<dyn Read + Send>)> {
+    let (format, stream) = sniff_format(dyn_reader(&path)?).map_err(|e| e.with_file(&path))?;
+    Ok((format, Box::new(stream)))
+}
+
+/// Input files whose formats cannot be read together, such as a FASTA
+/// file paired with a FASTQ file. Returned as
+/// [`SeqKmerError::FormatMismatch`]; as an `io::Error` it has kind
+/// `InvalidInput`.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{FastxReader, OptionPair, SeqFormat, SeqKmerError};
+///
+/// let paths = OptionPair::Pair("tests/data/test.fasta", "tests/data/test.fastq");
+/// match FastxReader::from_paths(paths, 0, 0) {
+///     Err(SeqKmerError::FormatMismatch(mismatch)) => {
+///         assert_eq!(mismatch.formats, OptionPair::Pair(SeqFormat::Fasta, SeqFormat::Fastq));
+///     }
+///     _ => panic!("expected a format mismatch"),
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatMismatch {
+    pub formats: OptionPair<SeqFormat>,
+}
+
+impl fmt::Display for FormatMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.formats {
+            OptionPair::Single(format) => write!(f, "unsupported input format {:?}", format),
+            OptionPair::Pair(format1, format2) => write!(
+                f,
+                "cannot pair a {:?} file with a {:?} file",
+                format1, format2
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FormatMismatch {}
+
+impl From<FormatMismatch> for io::Error {
+    fn from(err: FormatMismatch) -> Self {
+        io::Error::new(io::ErrorKind::InvalidInput, err)
+    }
+}
+
+/// Formats and opened streams of a (possibly paired) input.
+pub(crate) type SniffedPair = (OptionPair<SeqFormat>, OptionPair<Box<dyn Read + Send>>);
+
+/// Sniffs every file of a (possibly paired) input.
+pub(crate) fn open_sniffed_pair<P: AsRef<Path>>(
+    paths: &OptionPair<P>,
+) -> error::Result<SniffedPair> {
+    Ok(match paths {
+        OptionPair::Single(path) => {
+            let (format, stream) = open_sniffed(path)?;
+            (OptionPair::Single(format), OptionPair::Single(stream))
+        }
+        OptionPair::Pair(path1, path2) => {
+            let (format1, stream1) = open_sniffed(path1)?;
+            let (format2, stream2) = open_sniffed(path2)?;
+            (
+                OptionPair::Pair(format1, format2),
+                OptionPair::Pair(stream1, stream2),
+            )
+        }
+    })
+}
+
+/// Detects the format of a sequence file (FASTA or FASTQ). Readers built
+/// from the same file should use [`open_sniffed`] instead, which keeps the
+/// stream open.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{detect_file_format, SeqFormat};
+/// use std::path::Path;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let path = Path::new("tests/data/test.fasta");
+/// let format = detect_file_format(path)?;
+/// assert_eq!(format, SeqFormat::Fasta);
+/// # Ok(())
+/// # }
+/// ```
+pub fn detect_file_format<P: AsRef<Path>>(path: P) -> error::Result<SeqFormat> {
+    Ok(open_sniffed(path)?.0)
 }
 
 /// Trims trailing newlines, carriage returns, and '>' or '@' characters from a buffer.
@@ -163,16 +762,144 @@ pub fn trim_end(buffer: &mut Vec<u8>) {
 }
 
 pub const BUFSIZE: usize = 16 * 1024 * 1024;
+/// Default upper bound on the sequence bytes returned by one `Reader::next` call.
+pub const BATCH_BYTES: usize = 10 * 1024 * 1024;
 
 /// A trait for reading sequences.
 pub trait Reader: Send {
-    fn next(&mut self) -> Result<Option<Vec<Base<Vec<u8>>>>>;
+    fn next(&mut self) -> error::Result<Option<Vec<Base<Vec<u8>>>>>;
+
+    /// Reads the next batch into `batch`, replacing its records, and returns
+    /// false at the end of input. FASTA and FASTQ readers refill the
+    /// sequence and quality buffers of the records already in `batch`, so
+    /// passing the previous batch back avoids per-record allocations.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{FastqReader, OptionPair, Reader};
+    /// use std::path::Path;
+    ///
+    /// # fn main() -> Result<(), seqkmer::SeqKmerError> {
+    /// let path = Path::new("tests/data/test.fastq");
+    /// let mut reader = FastqReader::from_path(OptionPair::Single(path), 0, 0)?;
+    /// let mut batch = Vec::new();
+    /// let mut total = 0;
+    /// while reader.next_into(&mut batch)? {
+    ///     total += batch.len();
+    /// }
+    /// assert!(total > 0);
+    /// assert!(batch.is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn next_into(&mut self, batch: &mut Vec<Base<Vec<u8>>>) -> error::Result<bool> {
+        match self.next()? {
+            Some(seqs) => {
+                *batch = seqs;
+                Ok(true)
+            }
+            None => {
+                batch.clear();
+                Ok(false)
+            }
+        }
+    }
+
+    /// Adjusts the number of records returned by subsequent `next` calls.
+    /// Readers without a record-based batch ignore it.
+    fn set_batch_size(&mut self, _batch_size: usize) {}
+
+    /// Stops after the first `n` records; see [`TakeRecords`].
+    fn take_records(self, n: usize) -> TakeRecords<Self>
+    where
+        Self: Sized,
+    {
+        TakeRecords::new(self, n)
+    }
+
+    /// Keeps every `count`-th record starting at `index`; see [`Shard`].
+    fn shard(self, index: usize, count: usize) -> Shard<Self>
+    where
+        Self: Sized,
+    {
+        Shard::new(self, index, count)
+    }
 }
 
 impl Reader for Box<dyn Reader + Send> {
-    fn next(&mut self) -> Result<Option<Vec<Base<Vec<u8>>>>> {
+    fn next(&mut self) -> error::Result<Option<Vec<Base<Vec<u8>>>>> {
         (**self).next()
     }
+
+    fn next_into(&mut self, batch: &mut Vec<Base<Vec<u8>>>) -> error::Result<bool> {
+        (**self).next_into(batch)
+    }
+
+    fn set_batch_size(&mut self, batch_size: usize) {
+        (**self).set_batch_size(batch_size)
+    }
+}
+
+/// Buffers of a used record, refilled for the next one.
+#[derive(Default)]
+pub(crate) struct Recycled {
+    id: Option<String>,
+    desc: Option<String>,
+    body: [Option<Vec<u8>>; 2],
+    qual: [Option<Vec<u8>>; 2],
+}
+
+impl Recycled {
+    pub(crate) fn new(record: Option<Base<Vec<u8>>>) -> Self {
+        let Some(record) = record else {
+            return Self::default();
+        };
+        let split = |pair: OptionPair<Vec<u8>>| match pair {
+            OptionPair::Single(a) => [Some(a), None],
+            OptionPair::Pair(a, b) => [Some(a), Some(b)],
+        };
+        Self {
+            id: Some(record.header.id),
+            desc: record.header.desc,
+            body: split(record.body),
+            qual: record.qual.map(split).unwrap_or_default(),
+        }
+    }
+
+    fn fill(buf: &mut Option<Vec<u8>>, src: &[u8]) -> Vec<u8> {
+        let mut buf = buf.take().unwrap_or_default();
+        buf.clear();
+        buf.extend_from_slice(src);
+        buf
+    }
+
+    fn fill_str(buf: &mut Option<String>, src: &str) -> String {
+        let mut buf = buf.take().unwrap_or_default();
+        buf.clear();
+        buf.push_str(src);
+        buf
+    }
+
+    /// A copy of `src` in the id buffer.
+    pub(crate) fn id(&mut self, src: &str) -> String {
+        Self::fill_str(&mut self.id, src)
+    }
+
+    /// `header_desc` in the description buffer.
+    pub(crate) fn desc(&mut self, rest: &str, keep: bool) -> Option<String> {
+        trimmed_desc(rest, keep).map(|desc| Self::fill_str(&mut self.desc, desc))
+    }
+
+    /// A copy of `src` in the sequence buffer of mate `mate`.
+    pub(crate) fn body(&mut self, mate: usize, src: &[u8]) -> Vec<u8> {
+        Self::fill(&mut self.body[mate], src)
+    }
+
+    /// A copy of `src` in the quality buffer of mate `mate`.
+    pub(crate) fn qual(&mut self, mate: usize, src: &[u8]) -> Vec<u8> {
+        Self::fill(&mut self.qual[mate], src)
+    }
 }
 
 /// Represents position data for a sequence.
@@ -186,7 +913,7 @@ impl Reader for Box<dyn Reader + Send> {
 /// assert_eq!(pos_data.ext_code, 42);
 /// assert_eq!(pos_data.count, 5);
 /// ```
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct PosData {
     /// 外部 taxonomy id
     pub ext_code: u64,
@@ -198,6 +925,39 @@ impl PosData {
     pub fn new(ext_code: u64, count: usize) -> Self {
         Self { ext_code, count }
     }
+
+    /// Writes `ext_code:count` without going through `fmt`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::PosData;
+    ///
+    /// let mut out = Vec::new();
+    /// PosData::new(562, 17).write_to(&mut out).unwrap();
+    /// assert_eq!(out, b"562:17");
+    /// ```
+    pub fn write_to<W: io::Write>(&self, w: &mut W) -> Result<()> {
+        write_decimal(w, self.ext_code)?;
+        w.write_all(b":")?;
+        write_decimal(w, self.count as u64)
+    }
+}
+
+/// 整数转十进制, 不经过 fmt, 不分配
+#[inline]
+fn write_decimal<W: io::Write>(w: &mut W, mut n: u64) -> Result<()> {
+    let mut buf = [0u8; 20];
+    let mut i = buf.len();
+    loop {
+        i -= 1;
+        buf[i] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            break;
+        }
+    }
+    w.write_all(&buf[i..])
 }
 
 impl fmt::Display for PosData {
@@ -221,7 +981,7 @@ impl fmt::Display for PosData {
 ///
 /// println!("{}", dist); // Output: 0:4 42:2 0:1 43:1 0:2
 /// ```
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SpaceDist {
     pub value: Vec<PosData>,
     /// example: (0, 10], 左开右闭
@@ -273,6 +1033,114 @@ impl SpaceDist {
             self.pos = self.range.1;
         }
     }
+
+    /// Appends the runs of `other`, as if its positions followed this
+    /// distribution's range; positions of this range not yet reached are
+    /// recorded as zeros first. Adjacent runs of the same value are joined and
+    /// the range grows by the length of `other`'s range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::SpaceDist;
+    ///
+    /// let mut first = SpaceDist::new((0, 4));
+    /// first.add(42, 3);
+    /// first.add(42, 4);
+    ///
+    /// let mut second = SpaceDist::new((4, 8));
+    /// second.add(42, 5);
+    /// second.fill_tail_with_zeros();
+    ///
+    /// first.merge(second);
+    /// assert_eq!(first.range, (0, 8));
+    /// assert_eq!(first.runs(), vec![(0, 2), (42, 3), (0, 3)]);
+    /// assert_eq!(first.total_hits(), 3);
+    /// assert_eq!(first.hit_fraction(42), 3.0 / 8.0);
+    ///
+    /// // 未填满的第一段先补零, 第二段的位置紧接在其范围之后
+    /// let mut first = SpaceDist::new((0, 4));
+    /// first.add(42, 1);
+    /// let mut second = SpaceDist::new((4, 8));
+    /// second.fill_tail_with_zeros();
+    /// first.merge(second);
+    /// assert_eq!(first.runs(), vec![(42, 1), (0, 7)]);
+    /// assert_eq!(first.runs().iter().map(|&(_, n)| n).sum::<usize>(), 8);
+    /// ```
+    pub fn merge(&mut self, other: SpaceDist) {
+        self.fill_tail_with_zeros();
+        let span = other.range.1 - other.range.0;
+        for data in other.value {
+            match self.value.last_mut() {
+                Some(last) if last.ext_code == data.ext_code => last.count += data.count,
+                _ => self.value.push(data),
+            }
+        }
+        self.pos = self.range.1 + (other.pos - other.range.0);
+        self.range.1 += span;
+    }
+
+    /// Returns the number of positions with a non-zero value.
+    pub fn total_hits(&self) -> usize {
+        self.value
+            .iter()
+            .filter(|data| data.ext_code != 0)
+            .map(|data| data.count)
+            .sum()
+    }
+
+    /// Returns the share of recorded positions holding `ext_code`, or 0 for
+    /// an empty distribution.
+    pub fn hit_fraction(&self, ext_code: u64) -> f64 {
+        let total: usize = self.value.iter().map(|data| data.count).sum();
+        if total == 0 {
+            return 0.0;
+        }
+        let hits: usize = self
+            .value
+            .iter()
+            .filter(|data| data.ext_code == ext_code)
+            .map(|data| data.count)
+            .sum();
+        hits as f64 / total as f64
+    }
+
+    /// Returns the runs as `(ext_code, count)` pairs.
+    pub fn runs(&self) -> Vec<(u64, usize)> {
+        self.value
+            .iter()
+            .map(|data| (data.ext_code, data.count))
+            .collect()
+    }
+
+    /// Writes the space-separated hit runs, producing the same text as the
+    /// `Display` impl without allocating.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::SpaceDist;
+    ///
+    /// let mut dist = SpaceDist::new((0, 10));
+    /// dist.add(42, 5);
+    /// dist.add(42, 6);
+    /// dist.add(43, 8);
+    /// dist.fill_tail_with_zeros();
+    ///
+    /// let mut out = Vec::new();
+    /// dist.write_to(&mut out).unwrap();
+    /// assert_eq!(out, dist.to_string().as_bytes());
+    /// assert_eq!(out, b"0:4 42:2 0:1 43:1 0:2");
+    /// ```
+    pub fn write_to<W: io::Write>(&self, w: &mut W) -> Result<()> {
+        for (i, data) in self.value.iter().enumerate() {
+            if i > 0 {
+                w.write_all(b" ")?;
+            }
+            data.write_to(w)?;
+        }
+        Ok(())
+    }
 }
 
 impl fmt::Display for SpaceDist {
@@ -304,4 +1172,29 @@ impl OptionPair<SpaceDist> {
     pub fn fill_tail_with_zeros(&mut self) {
         self.apply_mut(|sd| sd.fill_tail_with_zeros());
     }
+
+    /// Combines the mates into one distribution, mate 2 after mate 1.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{OptionPair, SpaceDist};
+    ///
+    /// let mut sd1 = SpaceDist::new((0, 4));
+    /// sd1.add(7, 2);
+    /// let mut sd2 = SpaceDist::new((4, 8));
+    /// sd2.add(7, 5);
+    /// let merged = OptionPair::Pair(sd1, sd2).merged();
+    /// assert_eq!(merged.range, (0, 8));
+    /// assert_eq!(merged.to_string(), "0:1 7:1 0:2 7:1");
+    /// ```
+    pub fn merged(self) -> SpaceDist {
+        match self {
+            OptionPair::Single(sd) => sd,
+            OptionPair::Pair(mut sd1, sd2) => {
+                sd1.merge(sd2);
+                sd1
+            }
+        }
+    }
 }
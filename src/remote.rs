@@ -0,0 +1,408 @@
+use flate2::read::MultiGzDecoder;
+use std::io::{self, BufRead, BufReader, Read, Result, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::Duration;
+
+/// Default number of bytes requested per range request.
+pub const RANGE_SIZE: u64 = 8 * 1024 * 1024;
+
+fn remote_error(msg: String) -> io::Error {
+    io::Error::other(msg)
+}
+
+/// Returns true for the URL schemes [`open_url`] knows about.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::remote::is_url;
+///
+/// assert!(is_url("s3://bucket/reads.fq.gz"));
+/// assert!(!is_url("tests/data/test.fasta"));
+/// ```
+pub fn is_url(path: &str) -> bool {
+    ["http://", "https://", "s3://"]
+        .iter()
+        .any(|scheme| path.starts_with(scheme))
+}
+
+/// Host, port and request path of a plain-HTTP location.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct HttpLocation {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl HttpLocation {
+    /// Parses `http://` URLs; `s3://bucket/key` is mapped to the
+    /// virtual-hosted endpoint `http://bucket.s3.amazonaws.com/key`, which
+    /// serves public objects.
+    fn parse(url: &str) -> Result<Self> {
+        let rest = if let Some(rest) = url.strip_prefix("http://") {
+            rest.to_string()
+        } else if let Some(rest) = url.strip_prefix("s3://") {
+            let (bucket, key) = rest.split_once('/').unwrap_or((rest, ""));
+            format!("{}.s3.amazonaws.com/{}", bucket, key)
+        } else if url.starts_with("https://") {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("https is not supported without a TLS backend: {}", url),
+            ));
+        } else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("not a remote URL: {}", url),
+            ));
+        };
+
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], rest[i..].to_string()),
+            None => (rest.as_str(), "/".to_string()),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse()
+                    .map_err(|_| remote_error(format!("bad port in URL: {}", url)))?,
+            ),
+            None => (authority, 80),
+        };
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            path,
+        })
+    }
+}
+
+/// Body of one range response.
+struct RangeBody {
+    stream: BufReader<TcpStream>,
+    /// 本次响应剩余字节数; None 表示读到连接关闭
+    remaining: Option<u64>,
+}
+
+/// Streams a remote object with HTTP range requests of [`RANGE_SIZE`]
+/// bytes by default. A failed or truncated request is retried from the current offset
+/// up to `max_retries` times, with doubling back-off, so a dropped
+/// connection does not restart the download. Redirects to `http://`
+/// locations are followed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use seqkmer::remote::RemoteReader;
+/// use std::io::Read;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut reader = RemoteReader::new("s3://my-bucket/reads.fa")?.with_retries(5);
+/// let mut text = String::new();
+/// reader.read_to_string(&mut text)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct RemoteReader {
+    location: HttpLocation,
+    url: String,
+    pos: u64,
+    /// 由 Content-Range 得知的总长度
+    total: Option<u64>,
+    body: Option<RangeBody>,
+    range_size: u64,
+    max_retries: usize,
+    backoff: Duration,
+}
+
+impl RemoteReader {
+    /// Prepares a reader; no request is made before the first read.
+    pub fn new(url: &str) -> Result<Self> {
+        Ok(Self {
+            location: HttpLocation::parse(url)?,
+            url: url.to_string(),
+            pos: 0,
+            total: None,
+            body: None,
+            range_size: RANGE_SIZE,
+            max_retries: 3,
+            backoff: Duration::from_millis(500),
+        })
+    }
+
+    /// Sets the number of bytes requested per range request.
+    pub fn with_range_size(mut self, range_size: u64) -> Self {
+        self.range_size = range_size.max(1);
+        self
+    }
+
+    /// Sets how many times a failed request is retried (default 3).
+    pub fn with_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets the delay before the first retry (default 500 ms); it doubles
+    /// on each further attempt.
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Issues one range request from the current offset. Returns `None`
+    /// past the end of the object.
+    fn request(&mut self) -> Result<Option<RangeBody>> {
+        for _ in 0..5 {
+            let HttpLocation { host, port, path } = &self.location;
+            let mut stream = TcpStream::connect((host.as_str(), *port))?;
+            // HTTP/1.0: 响应不会使用 chunked 编码
+            write!(
+                stream,
+                "GET {} HTTP/1.0\r\nHost: {}\r\nRange: bytes={}-{}\r\nUser-Agent: seqkmer\r\n\r\n",
+                path,
+                host,
+                self.pos,
+                self.pos + self.range_size - 1
+            )?;
+            let mut stream = BufReader::new(stream);
+
+            let mut line = String::new();
+            stream.read_line(&mut line)?;
+            let status: u16 = line
+                .split_whitespace()
+                .nth(1)
+                .and_then(|code| code.parse().ok())
+                .ok_or_else(|| remote_error(format!("bad HTTP status line: {}", line.trim())))?;
+
+            let (mut content_length, mut content_range, mut location) = (None, None, None);
+            loop {
+                line.clear();
+                if stream.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    let value = value.trim().to_string();
+                    match name.trim().to_ascii_lowercase().as_str() {
+                        "content-length" => content_length = value.parse::<u64>().ok(),
+                        "content-range" => content_range = Some(value),
+                        "location" => location = Some(value),
+                        _ => {}
+                    }
+                }
+            }
+
+            match status {
+                206 => {
+                    // Content-Range: bytes start-end/total
+                    if let Some(total) = content_range
+                        .as_deref()
+                        .and_then(|range| range.rsplit_once('/'))
+                        .and_then(|(_, total)| total.parse().ok())
+                    {
+                        self.total = Some(total);
+                    }
+                    return Ok(Some(RangeBody {
+                        stream,
+                        remaining: content_length,
+                    }));
+                }
+                200 => {
+                    // 服务器忽略了 Range: 跳过已读部分
+                    io::copy(&mut (&mut stream).take(self.pos), &mut io::sink())?;
+                    self.total = content_length;
+                    return Ok(Some(RangeBody {
+                        stream,
+                        remaining: content_length.map(|len| len.saturating_sub(self.pos)),
+                    }));
+                }
+                416 => return Ok(None),
+                301 | 302 | 303 | 307 | 308 => {
+                    let target = location.ok_or_else(|| {
+                        remote_error(format!("redirect without location: {}", self.url))
+                    })?;
+                    if target.starts_with('/') {
+                        self.location.path = target;
+                    } else {
+                        self.location = HttpLocation::parse(&target)?;
+                    }
+                }
+                _ => {
+                    let kind = match status {
+                        404 => io::ErrorKind::NotFound,
+                        401 | 403 => io::ErrorKind::PermissionDenied,
+                        400..=499 => io::ErrorKind::InvalidInput,
+                        _ => io::ErrorKind::Other,
+                    };
+                    let msg = format!("HTTP {} for {}", status, self.url);
+                    return Err(io::Error::new(kind, msg));
+                }
+            }
+        }
+        Err(remote_error(format!("too many redirects: {}", self.url)))
+    }
+
+    /// Reads from the current range, opening the next one as needed.
+    fn try_read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        loop {
+            if self.total.is_some_and(|total| self.pos >= total) {
+                return Ok(0);
+            }
+            let body = match self.body.as_mut() {
+                Some(body) => body,
+                None => match self.request()? {
+                    Some(body) => self.body.insert(body),
+                    None => return Ok(0),
+                },
+            };
+            let limit = body
+                .remaining
+                .map_or(buf.len() as u64, |n| n.min(buf.len() as u64));
+            if limit == 0 {
+                self.body = None;
+                continue;
+            }
+            let n = body.stream.read(&mut buf[..limit as usize])?;
+            if n == 0 {
+                let truncated = body.remaining.is_some();
+                self.body = None;
+                if truncated {
+                    // 响应提前结束: 交给重试逻辑从当前位置续传
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        format!("connection closed early: {}", self.url),
+                    ));
+                }
+                if self.total.is_some_and(|total| self.pos < total) {
+                    continue;
+                }
+                return Ok(0);
+            }
+            if let Some(remaining) = body.remaining.as_mut() {
+                *remaining -= n as u64;
+            }
+            self.pos += n as u64;
+            return Ok(n);
+        }
+    }
+}
+
+impl Read for RemoteReader {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let mut delay = self.backoff;
+        let mut attempt = 0;
+        loop {
+            match self.try_read(buf) {
+                Ok(n) => return Ok(n),
+                Err(e) if attempt < self.max_retries && is_transient(&e) => {
+                    attempt += 1;
+                    self.body = None;
+                    thread::sleep(delay);
+                    delay *= 2;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+fn is_transient(e: &io::Error) -> bool {
+    !matches!(
+        e.kind(),
+        io::ErrorKind::Unsupported
+            | io::ErrorKind::InvalidInput
+            | io::ErrorKind::NotFound
+            | io::ErrorKind::PermissionDenied
+    )
+}
+
+/// Opens a remote object as a stream, decompressing it when it starts with
+/// the gzip magic bytes, like `dyn_reader` does for local files.
+pub fn open_url(url: &str) -> Result<Box<dyn Read + Send>> {
+    let mut reader = BufReader::new(RemoteReader::new(url)?);
+    let gzipped = reader.fill_buf()?.starts_with(&[0x1F, 0x8B]);
+    if gzipped {
+        Ok(Box::new(MultiGzDecoder::new(reader)))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    /// 本地 Range 服务器; 第一个连接只返回一半内容, 模拟断线
+    fn serve(data: &'static [u8], connections: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for (i, stream) in listener.incoming().take(connections).enumerate() {
+                let mut stream = stream.unwrap();
+                let mut request = BufReader::new(stream.try_clone().unwrap());
+                let mut range = (0, data.len() - 1);
+                let mut line = String::new();
+                while request.read_line(&mut line).unwrap() > 2 {
+                    if let Some(spec) = line.trim().strip_prefix("Range: bytes=") {
+                        let (a, b) = spec.split_once('-').unwrap();
+                        range = (a.parse().unwrap(), b.parse().unwrap());
+                    }
+                    line.clear();
+                }
+                let (start, end) = (range.0, range.1.min(data.len() - 1));
+                if start >= data.len() {
+                    write!(stream, "HTTP/1.0 416 Range Not Satisfiable\r\n\r\n").unwrap();
+                    continue;
+                }
+                let body = &data[start..=end];
+                write!(
+                    stream,
+                    "HTTP/1.0 206 Partial Content\r\nContent-Length: {}\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+                    body.len(),
+                    start,
+                    end,
+                    data.len()
+                )
+                .unwrap();
+                let sent = if i == 0 { body.len() / 2 } else { body.len() };
+                stream.write_all(&body[..sent]).unwrap();
+            }
+        });
+        format!("http://{}/reads.fa", addr)
+    }
+
+    #[test]
+    fn streams_ranges_and_resumes() {
+        let data: &[u8] = b">seq1\nACGTACGTACGTACGTACGT\n>seq2\nTTTTGGGGCCCCAAAA\n";
+        let url = serve(data, 32);
+        let mut reader = RemoteReader::new(&url)
+            .unwrap()
+            .with_range_size(10)
+            .with_backoff(Duration::from_millis(1));
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        assert_eq!(out, data);
+    }
+
+    #[test]
+    fn readers_open_urls() {
+        use crate::{FastaReader, Reader};
+
+        let url = serve(b">seq1 remote\nACGT\n>seq2\nTTGG\n", 8);
+        let mut reader = FastaReader::from_path(&url, 0).unwrap();
+        let seqs = reader.next().unwrap().unwrap();
+        assert_eq!(seqs.len(), 2);
+        assert_eq!(seqs[1].body.single().unwrap(), b"TTGG");
+    }
+
+    #[test]
+    fn parses_locations() {
+        let location = HttpLocation::parse("s3://bucket/dir/reads.fq.gz").unwrap();
+        assert_eq!(location.host, "bucket.s3.amazonaws.com");
+        assert_eq!(location.path, "/dir/reads.fq.gz");
+        let location = HttpLocation::parse("http://localhost:8080").unwrap();
+        assert_eq!((location.port, location.path.as_str()), (8080, "/"));
+        let err = HttpLocation::parse("https://example.org/a.fa").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+}
@@ -0,0 +1,356 @@
+use crate::mmscanner::MinimizerIterator;
+use crate::reader::SpaceDist;
+use crate::seq::Base;
+use crate::utils::OptionPair;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::File;
+use std::io::{BufWriter, Result, Write};
+use std::path::Path;
+
+/// A column of a [`TableWriter`] row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    /// Record id.
+    Id,
+    /// Header text after the id, empty if none.
+    Desc,
+    FileIndex,
+    ReadsIndex,
+    /// Sequence length, `150|148` for pairs (`fmt_seq_size`).
+    Length,
+    /// Minimizers yielded so far, `12|10` for pairs (`fmt_size`).
+    Minimizers,
+    /// Hit runs as `value:count`, mates joined with ` |:| ` as in Kraken2's
+    /// output; empty without hits.
+    Hits,
+    /// Positions with a non-zero hit, over both mates.
+    HitCount,
+}
+
+impl Column {
+    /// The header name of the column.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Column::Id => "id",
+            Column::Desc => "desc",
+            Column::FileIndex => "file_index",
+            Column::ReadsIndex => "reads_index",
+            Column::Length => "length",
+            Column::Minimizers => "minimizers",
+            Column::Hits => "hits",
+            Column::HitCount => "hit_count",
+        }
+    }
+
+    /// Looks a column up by its header name, e.g. from a command line.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::Column;
+    ///
+    /// assert_eq!(Column::from_name("hit_count"), Some(Column::HitCount));
+    /// assert_eq!(Column::from_name("taxid"), None);
+    /// ```
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            Column::Id,
+            Column::Desc,
+            Column::FileIndex,
+            Column::ReadsIndex,
+            Column::Length,
+            Column::Minimizers,
+            Column::Hits,
+            Column::HitCount,
+        ]
+        .into_iter()
+        .find(|column| column.name() == name)
+    }
+}
+
+/// Field separator and quoting of a [`TableWriter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TableFormat {
+    /// Tab-separated; tabs and line breaks in fields are replaced with
+    /// spaces.
+    #[default]
+    Tsv,
+    /// Comma-separated, quoting fields as RFC 4180 requires.
+    Csv,
+    /// One JSON object per line keyed by column name, without a header
+    /// line. Indices and counts are numbers, pair lengths and minimizer
+    /// counts arrays, and a missing description or hit list is `null`.
+    JsonLines,
+}
+
+/// Output file of [`TableWriter::create`], gzip-compressed when the path
+/// ends in `.gz`.
+pub enum TableOutput {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+}
+
+impl TableOutput {
+    /// Flushes the file, writing the gzip trailer if compressed.
+    pub fn finish(self) -> Result<()> {
+        match self {
+            TableOutput::Plain(mut w) => w.flush(),
+            TableOutput::Gzip(w) => w.finish()?.flush(),
+        }
+    }
+}
+
+impl Write for TableOutput {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        match self {
+            TableOutput::Plain(w) => w.write(buf),
+            TableOutput::Gzip(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        match self {
+            TableOutput::Plain(w) => w.flush(),
+            TableOutput::Gzip(w) => w.flush(),
+        }
+    }
+}
+
+/// Writes one row per read with a configurable set of columns, as TSV,
+/// CSV or JSON Lines, with a header line unless disabled.
+///
+/// Rows are written from a scanned record after its minimizers were
+/// consumed (e.g. by [`lookup_hits`](crate::lookup_hits)), since the
+/// minimizer count is that of the iterator.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{scan_sequence, Base, Column, Meros, OptionPair, SpaceDist};
+/// use seqkmer::{SeqFormat, SeqHeader, TableFormat, TableWriter};
+///
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+/// let header = SeqHeader {
+///     desc: Some("sample, A".to_string()),
+///     ..SeqHeader::new("read1", SeqFormat::Fasta)
+/// };
+/// let seq = Base::new(header, OptionPair::Single(b"ATCGATCGATCGATCGATCG".to_vec()));
+/// let mut record = scan_sequence(&seq, &meros);
+/// let count = record.body.iter_mut().map(|m| m.count()).sum::<usize>();
+///
+/// let mut dist = SpaceDist::new((0, count));
+/// dist.add(562, 3);
+/// dist.fill_tail_with_zeros();
+/// let hits = OptionPair::Single(dist);
+///
+/// let mut table = TableWriter::new(Vec::new());
+/// table.write_row(&record, Some(&hits)).unwrap();
+/// let out = String::from_utf8(table.finish().unwrap()).unwrap();
+/// assert_eq!(out, "id\tlength\tminimizers\thits\nread1\t20\t6\t0:2 562:1 0:3\n");
+///
+/// let mut table = TableWriter::new(Vec::new())
+///     .with_format(TableFormat::Csv)
+///     .with_columns(vec![Column::Id, Column::Desc, Column::HitCount])
+///     .with_header(false);
+/// table.write_row(&record, Some(&hits)).unwrap();
+/// assert_eq!(table.finish().unwrap(), b"read1,\"sample, A\",1\n");
+///
+/// // JSON Lines, e.g. for `jq`
+/// let mut table = TableWriter::new(Vec::new())
+///     .with_format(TableFormat::JsonLines)
+///     .with_columns(vec![Column::Id, Column::Desc, Column::Length, Column::Hits]);
+/// table.write_row(&record, None).unwrap();
+/// let out = String::from_utf8(table.finish().unwrap()).unwrap();
+/// assert_eq!(out, "{\"id\":\"read1\",\"desc\":\"sample, A\",\"length\":20,\"hits\":null}\n");
+/// ```
+pub struct TableWriter<W: Write> {
+    writer: W,
+    columns: Vec<Column>,
+    format: TableFormat,
+    header: bool,
+    started: bool,
+    field: String,
+}
+
+impl TableWriter<TableOutput> {
+    /// Creates the table file at `path`, gzip-compressed if it ends in
+    /// `.gz`. Call [`TableOutput::finish`] on the writer returned by
+    /// [`finish`](Self::finish) to complete a gzip file.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let file = BufWriter::new(File::create(path)?);
+        let output = if path.extension().is_some_and(|ext| ext == "gz") {
+            TableOutput::Gzip(GzEncoder::new(file, Compression::default()))
+        } else {
+            TableOutput::Plain(file)
+        };
+        Ok(Self::new(output))
+    }
+}
+
+impl<W: Write> TableWriter<W> {
+    /// A TSV writer with the columns id, length, minimizers and hits.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            columns: vec![Column::Id, Column::Length, Column::Minimizers, Column::Hits],
+            format: TableFormat::Tsv,
+            header: true,
+            started: false,
+            field: String::new(),
+        }
+    }
+
+    pub fn with_columns(mut self, columns: Vec<Column>) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    pub fn with_format(mut self, format: TableFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Whether to write the column names before the first row.
+    pub fn with_header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    fn write_header(&mut self) -> Result<()> {
+        self.started = true;
+        if !self.header || self.format == TableFormat::JsonLines {
+            return Ok(());
+        }
+        for i in 0..self.columns.len() {
+            self.field.clear();
+            self.field.push_str(self.columns[i].name());
+            self.write_field(i, false)?;
+        }
+        self.writer.write_all(b"\n")
+    }
+
+    /// Writes `self.field` as the `index`-th field of the current line;
+    /// `null` marks a missing value.
+    fn write_field(&mut self, index: usize, null: bool) -> Result<()> {
+        let separator = match self.format {
+            TableFormat::Tsv => b'\t',
+            TableFormat::Csv | TableFormat::JsonLines => b',',
+        };
+        if index > 0 {
+            self.writer.write_all(&[separator])?;
+        }
+        match self.format {
+            TableFormat::JsonLines => {
+                let column = self.columns[index];
+                if index == 0 {
+                    self.writer.write_all(b"{")?;
+                }
+                write!(self.writer, "\"{}\":", column.name())?;
+                match column {
+                    _ if null => self.writer.write_all(b"null"),
+                    Column::FileIndex | Column::ReadsIndex | Column::HitCount => {
+                        self.writer.write_all(self.field.as_bytes())
+                    }
+                    // 双端序列的 "150|148" 写成数组
+                    Column::Length | Column::Minimizers if self.field.contains('|') => {
+                        write!(self.writer, "[{}]", self.field.replace('|', ","))
+                    }
+                    Column::Length | Column::Minimizers => {
+                        self.writer.write_all(self.field.as_bytes())
+                    }
+                    _ => write_json_string(&mut self.writer, &self.field),
+                }
+            }
+            // 字段内的制表符和换行会破坏列对齐
+            TableFormat::Tsv if self.field.contains(['\t', '\n', '\r']) => {
+                let field = self.field.replace(['\t', '\n', '\r'], " ");
+                self.writer.write_all(field.as_bytes())
+            }
+            TableFormat::Csv if self.field.contains([',', '"', '\n', '\r']) => {
+                write!(self.writer, "\"{}\"", self.field.replace('"', "\"\""))
+            }
+            _ => self.writer.write_all(self.field.as_bytes()),
+        }
+    }
+
+    /// Writes the row of a scanned record; `hits` fills the hit columns,
+    /// which are empty (or 0) without it.
+    pub fn write_row(
+        &mut self,
+        record: &Base<MinimizerIterator>,
+        hits: Option<&OptionPair<SpaceDist>>,
+    ) -> Result<()> {
+        if !self.started {
+            self.write_header()?;
+        }
+        let header = &record.header;
+        for i in 0..self.columns.len() {
+            self.field.clear();
+            let null = match self.columns[i] {
+                Column::Desc => header.desc.is_none(),
+                Column::Hits => hits.is_none(),
+                _ => false,
+            };
+            match self.columns[i] {
+                Column::Id => self.field.push_str(&header.id),
+                Column::Desc => self.field.push_str(header.desc.as_deref().unwrap_or("")),
+                Column::FileIndex => self.field.push_str(&header.file_index.to_string()),
+                Column::ReadsIndex => self.field.push_str(&header.reads_index.to_string()),
+                Column::Length => self.field.push_str(&record.fmt_seq_size()),
+                Column::Minimizers => self.field.push_str(&record.fmt_size()),
+                Column::Hits => {
+                    if let Some(hits) = hits {
+                        self.field
+                            .push_str(&hits.reduce_str(" |:| ", |dist| dist.to_string()));
+                    }
+                }
+                Column::HitCount => {
+                    let count = hits.map_or(0, |hits| {
+                        hits.iter().map(SpaceDist::total_hits).sum::<usize>()
+                    });
+                    self.field.push_str(&count.to_string());
+                }
+            }
+            self.write_field(i, null)?;
+        }
+        if self.format == TableFormat::JsonLines {
+            // 没有列时仍输出一个空对象
+            self.writer
+                .write_all(if self.columns.is_empty() { b"{}" } else { b"}" })?;
+        }
+        self.writer.write_all(b"\n")
+    }
+
+    /// Writes the header if no row was written, flushes and returns the
+    /// inner writer.
+    pub fn finish(mut self) -> Result<W> {
+        if !self.started {
+            self.write_header()?;
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Writes `value` as a quoted JSON string.
+fn write_json_string<W: Write>(w: &mut W, value: &str) -> Result<()> {
+    w.write_all(b"\"")?;
+    for c in value.chars() {
+        match c {
+            '"' => w.write_all(b"\\\"")?,
+            '\\' => w.write_all(b"\\\\")?,
+            '\n' => w.write_all(b"\\n")?,
+            '\r' => w.write_all(b"\\r")?,
+            '\t' => w.write_all(b"\\t")?,
+            c if (c as u32) < 0x20 => write!(w, "\\u{:04x}", c as u32)?,
+            c => write!(w, "{}", c)?,
+        }
+    }
+    w.write_all(b"\"")
+}
@@ -1,3 +1,5 @@
+use crate::mmscanner::Mate;
+use crate::trim::Transform;
 use crate::utils::OptionPair;
 
 /// Represents the format of a sequence file.
@@ -19,6 +21,260 @@ pub enum SeqFormat {
     Fastq,
 }
 
+/// How base qualities are applied to a FASTQ record before scanning.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::QualityPolicy;
+///
+/// assert_eq!(QualityPolicy::from_score(0), QualityPolicy::NoMask);
+/// assert_eq!(QualityPolicy::from_score(20), QualityPolicy::MaskBelow(20));
+///
+/// let mut seq = b"ACGTACGT".to_vec();
+/// let mut quals = b"!!IIII!I".to_vec();
+/// QualityPolicy::MaskBelow(10).apply(&mut seq, &mut quals);
+/// assert_eq!(seq, b"NNGTACNT");
+///
+/// let mut seq = b"ACGTACGT".to_vec();
+/// let mut quals = b"!!IIII!I".to_vec();
+/// QualityPolicy::TrimEnds(10).apply(&mut seq, &mut quals);
+/// assert_eq!(seq, b"GTACGT");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QualityPolicy {
+    /// Qualities are ignored.
+    #[default]
+    NoMask,
+    /// Bases with a Phred score below the threshold are replaced with `N`,
+    /// which resets the minimizer scanner.
+    MaskBelow(u8),
+    /// Leading and trailing bases with a Phred score below the threshold are
+    /// removed; interior low-quality bases are kept.
+    TrimEnds(u8),
+}
+
+impl QualityPolicy {
+    /// Maps the legacy `quality_score` reader argument to a policy: scores
+    /// above zero mask bases below them.
+    pub fn from_score(score: i32) -> Self {
+        if score > 0 {
+            QualityPolicy::MaskBelow(score.min(u8::MAX as i32) as u8)
+        } else {
+            QualityPolicy::NoMask
+        }
+    }
+
+    /// Applies the policy to a sequence and its Phred+33 qualities in place.
+    pub fn apply(&self, seq: &mut Vec<u8>, quals: &mut Vec<u8>) {
+        let phred = |q: u8| q.saturating_sub(b'!');
+        match *self {
+            QualityPolicy::NoMask => {}
+            QualityPolicy::MaskBelow(threshold) => {
+                for (base, &q) in seq.iter_mut().zip(quals.iter()) {
+                    if phred(q) < threshold {
+                        *base = b'N';
+                    }
+                }
+            }
+            QualityPolicy::TrimEnds(threshold) => {
+                let len = seq.len().min(quals.len());
+                let end = quals[..len]
+                    .iter()
+                    .rposition(|&q| phred(q) >= threshold)
+                    .map_or(0, |i| i + 1);
+                let start = quals[..end]
+                    .iter()
+                    .position(|&q| phred(q) >= threshold)
+                    .unwrap_or(end);
+                seq.truncate(end);
+                seq.drain(..start);
+                quals.truncate(end);
+                quals.drain(..start);
+            }
+        }
+    }
+}
+
+/// Offset of the ASCII quality encoding of a FASTQ file.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::PhredEncoding;
+///
+/// let quals: [&[u8]; 2] = [b"IIII#", b"FFFF"];
+/// assert_eq!(PhredEncoding::detect(quals), Some(PhredEncoding::Phred33));
+///
+/// let quals: [&[u8]; 1] = [b"hhhhB"];
+/// assert_eq!(PhredEncoding::detect(quals), Some(PhredEncoding::Phred64));
+///
+/// // 只含 '@'..'J' 时两种编码都可能
+/// let quals: [&[u8]; 1] = [b"JJJJ"];
+/// assert_eq!(PhredEncoding::detect(quals), None);
+///
+/// let mut quals = b"hhB".to_vec();
+/// PhredEncoding::Phred64.to_phred33(&mut quals);
+/// assert_eq!(quals, b"II#");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PhredEncoding {
+    /// Sanger / Illumina 1.8+: quality + 33.
+    #[default]
+    Phred33,
+    /// Illumina 1.3 - 1.7: quality + 64.
+    Phred64,
+}
+
+impl PhredEncoding {
+    /// The ASCII code of quality 0.
+    pub fn offset(&self) -> u8 {
+        match self {
+            PhredEncoding::Phred33 => 33,
+            PhredEncoding::Phred64 => 64,
+        }
+    }
+
+    /// Guesses the encoding from quality strings: a character below ';'
+    /// only occurs in Phred+33, one above 'J' only in Phred+64. Returns
+    /// `None` when the sample fits both.
+    pub fn detect<'a, I>(quals: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = &'a [u8]>,
+    {
+        let mut max = 0u8;
+        for &q in quals.into_iter().flatten() {
+            if q < b';' {
+                return Some(PhredEncoding::Phred33);
+            }
+            max = max.max(q);
+        }
+        (max > b'J').then_some(PhredEncoding::Phred64)
+    }
+
+    /// Rewrites qualities of this encoding as Phred+33 in place.
+    pub fn to_phred33(self, quals: &mut [u8]) {
+        let shift = self.offset() - 33;
+        if shift > 0 {
+            quals
+                .iter_mut()
+                .for_each(|q| *q = q.saturating_sub(shift).max(b'!'));
+        }
+    }
+}
+
+/// What a paired reader does when the mate ids differ beyond the `/1` and
+/// `/2` suffixes, e.g. after one file went through an external trimmer.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::IdPolicy;
+///
+/// assert_eq!(IdPolicy::PreferR1.resolve("r7", "r7.trimmed").unwrap(), "r7");
+/// assert_eq!(IdPolicy::RewriteBoth.resolve("r7:a", "r7:b").unwrap(), "r7");
+/// assert_eq!(IdPolicy::RewriteBoth.resolve("x", "y").unwrap(), "x|y");
+/// assert!(IdPolicy::Error.resolve("r7", "r8").is_err());
+/// assert_eq!(IdPolicy::Error.resolve("r7", "r7").unwrap(), "r7");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IdPolicy {
+    /// Fails with [`SeqKmerError::PairMismatch`](crate::SeqKmerError::PairMismatch).
+    Error,
+    /// Uses the R1 id for the pair.
+    #[default]
+    PreferR1,
+    /// Uses the ids' common prefix (without trailing separators) for both
+    /// mates, or `r1|r2` if they share none.
+    RewriteBoth,
+}
+
+impl IdPolicy {
+    /// Returns the id for a pair whose mates are `id1` and `id2`.
+    pub fn resolve(&self, id1: &str, id2: &str) -> crate::error::Result<String> {
+        if id1 == id2 {
+            return Ok(id1.to_string());
+        }
+        match self {
+            IdPolicy::Error => Err(crate::SeqKmerError::PairMismatch(format!(
+                "mate ids differ: {} and {}",
+                id1, id2
+            ))),
+            IdPolicy::PreferR1 => Ok(id1.to_string()),
+            IdPolicy::RewriteBoth => {
+                let common = id1
+                    .char_indices()
+                    .zip(id2.chars())
+                    .find(|((_, a), b)| a != b)
+                    .map_or(id1.len().min(id2.len()), |((i, _), _)| i);
+                let prefix = id1[..common].trim_end_matches(['.', '_', ':', '-', '/', '|']);
+                Ok(if prefix.is_empty() {
+                    format!("{}|{}", id1, id2)
+                } else {
+                    prefix.to_string()
+                })
+            }
+        }
+    }
+}
+
+/// What a paired reader does with the records left in one mate file after
+/// the other has ended.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::OrphanPolicy;
+///
+/// assert_eq!(OrphanPolicy::default(), OrphanPolicy::DropOrphans);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OrphanPolicy {
+    /// Fails with [`SeqKmerError::PairMismatch`](crate::SeqKmerError::PairMismatch)
+    /// at the first orphan.
+    ErrorOut,
+    /// Reads and counts the orphans but returns no records for them.
+    #[default]
+    DropOrphans,
+    /// Returns each orphan as a single-end record, with
+    /// [`SeqHeader::mate`] telling which file it came from.
+    EmitAsSingle,
+}
+
+/// Position of a chunk within the record it was split from.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::SeqChunk;
+///
+/// let chunk = SeqChunk { index: 1, count: 3, offset: 990 };
+/// assert!(!chunk.is_last());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeqChunk {
+    /// 当前分块的序号, 从 0 开始
+    pub index: usize,
+    /// 原序列被切分成的分块总数
+    pub count: usize,
+    /// 分块第一个碱基在原序列中的位置
+    pub offset: usize,
+}
+
+impl SeqChunk {
+    /// Returns true if this is the final chunk of the record.
+    pub fn is_last(&self) -> bool {
+        self.index + 1 == self.count
+    }
+}
+
+/// Cell/sample barcode and UMI of a read.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReadTags {
+    pub barcode: Option<String>,
+    pub umi: Option<String>,
+}
+
 /// Represents the header information of a sequence.
 ///
 /// # Examples
@@ -27,10 +283,8 @@ pub enum SeqFormat {
 /// use seqkmer::{SeqHeader, SeqFormat};
 ///
 /// let header = SeqHeader {
-///     id: "seq1".to_string(),
-///     file_index: 0,
 ///     reads_index: 1,
-///     format: SeqFormat::Fasta,
+///     ..SeqHeader::new("seq1", SeqFormat::Fasta)
 /// };
 ///
 /// assert_eq!(header.id, "seq1");
@@ -44,6 +298,56 @@ pub struct SeqHeader {
     pub file_index: usize,
     pub reads_index: usize,
     pub format: SeqFormat,
+    /// Set when the record is a chunk of a longer sequence.
+    pub chunk: Option<SeqChunk>,
+    /// Quality policy applied by the reader that produced the record.
+    pub quality_policy: QualityPolicy,
+    /// Header text after the id (taxid, barcode, UMI, ...), unless the
+    /// reader was told to drop it.
+    pub desc: Option<String>,
+    /// Barcode and UMI, once extracted (see `TagExtractor`).
+    pub tags: Option<ReadTags>,
+    /// Number of lower-case (soft-masked) bases over all mates, when the
+    /// reader's [`SoftMask`] policy counts them.
+    pub soft_masked: Option<usize>,
+    /// Mate file of a single-end record split from a pair, such as an
+    /// orphan emitted under [`OrphanPolicy::EmitAsSingle`].
+    pub mate: Option<Mate>,
+}
+
+impl SeqHeader {
+    /// Creates a header with the given id and format; the indexes are 0 and
+    /// the optional fields are unset. Set other fields with struct update
+    /// syntax.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{QualityPolicy, SeqFormat, SeqHeader};
+    ///
+    /// let header = SeqHeader {
+    ///     reads_index: 7,
+    ///     ..SeqHeader::new("read7", SeqFormat::Fastq)
+    /// };
+    /// assert_eq!(header.id, "read7");
+    /// assert_eq!(header.file_index, 0);
+    /// assert_eq!(header.quality_policy, QualityPolicy::NoMask);
+    /// assert!(header.desc.is_none());
+    /// ```
+    pub fn new(id: impl Into<String>, format: SeqFormat) -> Self {
+        Self {
+            id: id.into(),
+            file_index: 0,
+            reads_index: 0,
+            format,
+            chunk: None,
+            quality_policy: QualityPolicy::NoMask,
+            desc: None,
+            tags: None,
+            soft_masked: None,
+            mate: None,
+        }
+    }
 }
 
 /// Represents a base structure containing a header and a body.
@@ -54,10 +358,8 @@ pub struct SeqHeader {
 /// use seqkmer::{Base, SeqHeader, SeqFormat, OptionPair};
 ///
 /// let header = SeqHeader {
-///     id: "seq1".to_string(),
-///     file_index: 0,
 ///     reads_index: 1,
-///     format: SeqFormat::Fasta,
+///     ..SeqHeader::new("seq1", SeqFormat::Fasta)
 /// };
 ///
 /// let body = OptionPair::Single(vec![65, 84, 67, 71]); // "ATCG"
@@ -71,6 +373,9 @@ pub struct SeqHeader {
 pub struct Base<T> {
     pub header: SeqHeader,
     pub body: OptionPair<T>,
+    /// Phred+33 base qualities matching `body`, present only when the
+    /// reader was asked to keep them.
+    pub qual: Option<OptionPair<Vec<u8>>>,
 }
 
 impl<T> Base<T> {
@@ -82,10 +387,8 @@ impl<T> Base<T> {
     /// use seqkmer::{Base, SeqHeader, SeqFormat, OptionPair};
     ///
     /// let header = SeqHeader {
-    ///     id: "seq1".to_string(),
-    ///     file_index: 0,
     ///     reads_index: 1,
-    ///     format: SeqFormat::Fasta,
+    ///     ..SeqHeader::new("seq1", SeqFormat::Fasta)
     /// };
     ///
     /// let body = OptionPair::Single(vec![65, 84, 67, 71]); // "ATCG"
@@ -96,7 +399,17 @@ impl<T> Base<T> {
     /// assert_eq!(base.body.single().unwrap(), &vec![65, 84, 67, 71]);
     /// ```
     pub fn new(header: SeqHeader, body: OptionPair<T>) -> Self {
-        Self { header, body }
+        Self {
+            header,
+            body,
+            qual: None,
+        }
+    }
+
+    /// Attaches base qualities to the record.
+    pub fn with_qual(mut self, qual: OptionPair<Vec<u8>>) -> Self {
+        self.qual = Some(qual);
+        self
     }
 
     /// Maps the body of the Base instance using a provided function.
@@ -107,10 +420,8 @@ impl<T> Base<T> {
     /// use seqkmer::{Base, SeqHeader, SeqFormat, OptionPair};
     ///
     /// let header = SeqHeader {
-    ///     id: "seq1".to_string(),
-    ///     file_index: 0,
     ///     reads_index: 1,
-    ///     format: SeqFormat::Fasta,
+    ///     ..SeqHeader::new("seq1", SeqFormat::Fasta)
     /// };
     ///
     /// let body = OptionPair::Single(vec![65, 84, 67, 71]); // "ATCG"
@@ -126,9 +437,89 @@ impl<T> Base<T> {
     where
         F: FnMut(&T) -> Result<U, E>,
     {
-        self.body.map(|t| f(&t)).map(|body| Base {
+        self.body.map(|t| f(t)).map(|body| Base {
             header: self.header.clone(),
             body,
+            qual: self.qual.clone(),
         })
     }
 }
+
+impl Base<Vec<u8>> {
+    /// Fraction of the bases that were soft-masked, if they were counted.
+    pub fn soft_masked_fraction(&self) -> Option<f64> {
+        let masked = self.header.soft_masked?;
+        let len = self.body.reduce(0, |acc, s| acc + s.len());
+        Some(if len == 0 {
+            0.0
+        } else {
+            masked as f64 / len as f64
+        })
+    }
+}
+
+/// How lower-case (soft-masked, e.g. by RepeatMasker) bases are treated.
+/// Scanners read them like upper-case bases unless they are turned to `N`.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{FastaReader, Reader, SoftMask};
+///
+/// # fn main() -> Result<(), seqkmer::SeqKmerError> {
+/// let input = b">chr1\nACGTacgtacgtACGT\n".as_slice();
+///
+/// let mut reader = FastaReader::new(input, 0).with_soft_mask(SoftMask::Record);
+/// let seq = reader.next()?.unwrap().remove(0);
+/// assert_eq!(seq.header.soft_masked, Some(8));
+/// assert_eq!(seq.soft_masked_fraction(), Some(0.5));
+/// assert_eq!(seq.body.single().unwrap(), b"ACGTacgtacgtACGT");
+///
+/// let mut reader = FastaReader::new(input, 0).with_soft_mask(SoftMask::AsN);
+/// let seq = reader.next()?.unwrap().remove(0);
+/// assert_eq!(seq.body.single().unwrap(), b"ACGTNNNNNNNNACGT");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SoftMask {
+    /// Lower-case bases are ordinary bases.
+    #[default]
+    Keep,
+    /// Lower-case bases are replaced with `N`, so no k-mer spans them. The
+    /// count is recorded as for `Record`.
+    AsN,
+    /// The sequence is kept and the lower-case bases are counted in
+    /// `SeqHeader::soft_masked`.
+    Record,
+}
+
+impl SoftMask {
+    /// Applies the policy to a record in place.
+    pub fn apply(&self, seq: &mut Base<Vec<u8>>) {
+        if *self == SoftMask::Keep {
+            return;
+        }
+        let masked = seq
+            .body
+            .apply_mut(|s| {
+                let mut n = 0;
+                for b in s.iter_mut().filter(|b| b.is_ascii_lowercase()) {
+                    n += 1;
+                    if *self == SoftMask::AsN {
+                        *b = b'N';
+                    }
+                }
+                n
+            })
+            .reduce(0, |acc, &n| acc + n);
+        seq.header.soft_masked = Some(masked);
+    }
+}
+
+/// As a pre-scan stage, e.g. in `read_parallel_transform`.
+impl Transform for SoftMask {
+    fn apply(&self, seq: &mut Base<Vec<u8>>) {
+        SoftMask::apply(self, seq);
+    }
+}
@@ -0,0 +1,200 @@
+use crate::error::Result;
+use crate::reader::Reader;
+use crate::seq::Base;
+
+/// Passes on only the first `n` records of another reader, e.g. for a
+/// quick QC pass over the head of a run. Built with
+/// [`Reader::take_records`]. The inner reader may read one batch past the
+/// limit.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{FastaReader, Reader};
+/// use std::path::Path;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let reader = FastaReader::from_path(Path::new("tests/data/test.fasta"), 0)?;
+/// let mut reader = reader.take_records(2);
+/// let seqs = reader.next()?.unwrap();
+/// assert_eq!(seqs.len(), 2);
+/// assert!(reader.next()?.is_none());
+/// # Ok(())
+/// # }
+/// ```
+pub struct TakeRecords<R: Reader> {
+    inner: R,
+    remaining: usize,
+}
+
+impl<R: Reader> TakeRecords<R> {
+    pub fn new(inner: R, n: usize) -> Self {
+        Self {
+            inner,
+            remaining: n,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Reader> Reader for TakeRecords<R> {
+    fn next(&mut self) -> Result<Option<Vec<Base<Vec<u8>>>>> {
+        let mut seqs = Vec::new();
+        Ok(self.next_into(&mut seqs)?.then_some(seqs))
+    }
+
+    fn next_into(&mut self, batch: &mut Vec<Base<Vec<u8>>>) -> Result<bool> {
+        if self.remaining == 0 || !self.inner.next_into(batch)? {
+            batch.clear();
+            return Ok(false);
+        }
+        batch.truncate(self.remaining);
+        self.remaining -= batch.len();
+        Ok(true)
+    }
+
+    fn set_batch_size(&mut self, batch_size: usize) {
+        self.inner.set_batch_size(batch_size)
+    }
+}
+
+/// Passes on every `count`-th record of another reader, starting with
+/// record `index` (0-based), so `count` processes given `index`
+/// `0..count` each read a disjoint share of the same input and together
+/// read all of it. Built with [`Reader::shard`]. A paired record is one
+/// record, so mates always stay in the same shard.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{FastaReader, Reader};
+/// use std::path::Path;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let path = Path::new("tests/data/test.fasta");
+/// let mut shard = FastaReader::from_path(path, 0)?.shard(1, 2);
+/// let ids: Vec<_> = shard.next()?.unwrap().into_iter().map(|s| s.header.id).collect();
+/// assert_eq!(ids, vec!["seq2"]);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Shard<R: Reader> {
+    inner: R,
+    index: usize,
+    count: usize,
+    /// 已读过的记录数, 跨批次计数
+    seen: usize,
+}
+
+impl<R: Reader> Shard<R> {
+    pub fn new(inner: R, index: usize, count: usize) -> Self {
+        assert!(index < count, "shard index must be below the shard count");
+        Self {
+            inner,
+            index,
+            count,
+            seen: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Reader> Reader for Shard<R> {
+    fn next(&mut self) -> Result<Option<Vec<Base<Vec<u8>>>>> {
+        let mut seqs = Vec::new();
+        Ok(self.next_into(&mut seqs)?.then_some(seqs))
+    }
+
+    fn next_into(&mut self, batch: &mut Vec<Base<Vec<u8>>>) -> Result<bool> {
+        while self.inner.next_into(batch)? {
+            let (first, count) = (self.seen, self.count);
+            self.seen += batch.len();
+            // 下一条属于本分片的记录在批次中的位置
+            let offset = (self.index + count - first % count) % count;
+            let mut position = 0;
+            batch.retain(|_| {
+                let keep = position >= offset && (position - offset).is_multiple_of(count);
+                position += 1;
+                keep
+            });
+            if !batch.is_empty() {
+                return Ok(true);
+            }
+        }
+        batch.clear();
+        Ok(false)
+    }
+
+    fn set_batch_size(&mut self, batch_size: usize) {
+        self.inner.set_batch_size(batch_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FastaReader, FastqReader, OptionPair};
+    use std::collections::BTreeSet;
+
+    fn read_ids<R: Reader>(mut reader: R) -> Vec<String> {
+        let mut ids = Vec::new();
+        while let Some(seqs) = reader.next().unwrap() {
+            ids.extend(seqs.into_iter().map(|seq| seq.header.id));
+        }
+        ids
+    }
+
+    #[test]
+    fn test_shards_are_disjoint_and_complete() {
+        let input: String = (0..103).map(|i| format!(">r{}\nACGT\n", i)).collect();
+        let all = read_ids(FastaReader::new(input.as_bytes(), 0));
+        assert_eq!(all.len(), 103);
+
+        for count in [1, 2, 3, 7, 200] {
+            let mut union = Vec::new();
+            for index in 0..count {
+                // 批次大小与分片数互质, 检查跨批次计数
+                let reader = FastaReader::new(input.as_bytes(), 0).with_batch_size(4);
+                let ids = read_ids(reader.shard(index, count));
+                let expected: Vec<_> = all.iter().skip(index).step_by(count).cloned().collect();
+                assert_eq!(ids, expected, "shard {} of {}", index, count);
+                union.extend(ids);
+            }
+            let distinct: BTreeSet<_> = union.iter().collect();
+            assert_eq!(distinct.len(), union.len(), "shards of {} overlap", count);
+            assert_eq!(
+                distinct.len(),
+                all.len(),
+                "shards of {} miss records",
+                count
+            );
+        }
+    }
+
+    #[test]
+    fn test_shards_keep_mates_together() {
+        let mate = |n: usize| -> String {
+            (0..10)
+                .map(|i| format!("@p{}/{}\nACGTACGT\n+\nIIIIIIII\n", i, n))
+                .collect()
+        };
+        let (r1, r2) = (mate(1), mate(2));
+        for index in 0..3 {
+            let reader = FastqReader::new(OptionPair::Pair(r1.as_bytes(), r2.as_bytes()), 0, 0);
+            let mut shard = reader.shard(index, 3);
+            while let Some(seqs) = shard.next().unwrap() {
+                for seq in seqs {
+                    assert!(seq.body.is_pair());
+                    let n: usize = seq.header.id[1..].parse().unwrap();
+                    assert_eq!(n % 3, index);
+                }
+            }
+        }
+    }
+}
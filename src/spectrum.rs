@@ -0,0 +1,199 @@
+use crate::count::KmerCounts;
+use crate::export::for_each_canonical_kmer;
+use crate::feat::fmix64;
+use crate::frequency::MinimizerFrequency;
+use std::collections::BTreeMap;
+use std::io::{Result, Write};
+
+/// Count-min sketch: approximate occurrence counts in fixed memory. Counts
+/// are never underestimated; collisions can only inflate them.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::CountMinSketch;
+///
+/// let mut sketch = CountMinSketch::new(1 << 10, 4);
+/// sketch.add(42);
+/// sketch.add(42);
+/// sketch.add(7);
+/// assert_eq!(sketch.estimate(42), 2);
+/// assert_eq!(sketch.estimate(7), 1);
+/// assert_eq!(sketch.estimate(8), 0);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CountMinSketch {
+    width: usize,
+    depth: usize,
+    cells: Vec<u32>,
+}
+
+impl CountMinSketch {
+    /// Creates a sketch of `depth` rows of `width` counters.
+    pub fn new(width: usize, depth: usize) -> Self {
+        assert!(width > 0 && depth > 0, "width and depth must be positive");
+        Self {
+            width,
+            depth,
+            cells: vec![0; width * depth],
+        }
+    }
+
+    #[inline]
+    fn cell(&self, row: usize, key: u64) -> usize {
+        // 每行用不同的种子重新混合
+        let hash = fmix64(key ^ (row as u64 + 1).wrapping_mul(0x9e3779b97f4a7c15));
+        row * self.width + (hash % self.width as u64) as usize
+    }
+
+    #[inline]
+    pub fn add(&mut self, key: u64) {
+        for row in 0..self.depth {
+            let i = self.cell(row, key);
+            self.cells[i] = self.cells[i].saturating_add(1);
+        }
+    }
+
+    #[inline]
+    pub fn estimate(&self, key: u64) -> u32 {
+        (0..self.depth)
+            .map(|row| self.cells[self.cell(row, key)])
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// Counts every canonical k-mer of a sequence.
+    pub fn add_sequence(&mut self, seq: &[u8], k: usize) {
+        for_each_canonical_kmer(seq, k, |kmer| self.add(kmer));
+    }
+
+    /// Adds the counters of a sketch with the same dimensions.
+    pub fn merge(&mut self, other: &CountMinSketch) {
+        assert_eq!(
+            (self.width, self.depth),
+            (other.width, other.depth),
+            "cannot merge sketches of different shapes"
+        );
+        for (a, &b) in self.cells.iter_mut().zip(&other.cells) {
+            *a = a.saturating_add(b);
+        }
+    }
+}
+
+/// Multiplicity histogram: how many distinct k-mers (or minimizers) occur
+/// once, twice, ... The text output is the two-column `multiplicity count`
+/// format of `jellyfish histo`, which GenomeScope reads.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{KmerCounts, Spectrum};
+///
+/// let mut counts = KmerCounts::new(3);
+/// counts.add_sequence(b"AAAAAC");
+/// let spectrum = Spectrum::from_kmer_counts(&counts);
+/// // AAA three times, AAC once
+/// assert_eq!(spectrum.histogram(), vec![(1, 1), (3, 1)]);
+///
+/// let mut out = Vec::new();
+/// spectrum.write_histo(&mut out, None).unwrap();
+/// assert_eq!(out, b"1 1\n3 1\n");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Spectrum {
+    /// 计数 -> 不同 k-mer 的数量; 由草图估计时可能是小数
+    bins: BTreeMap<u64, f64>,
+}
+
+impl Spectrum {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records one distinct k-mer seen `multiplicity` times.
+    pub fn add(&mut self, multiplicity: u64) {
+        if multiplicity > 0 {
+            *self.bins.entry(multiplicity).or_default() += 1.0;
+        }
+    }
+
+    /// Builds the histogram of exact k-mer counts.
+    pub fn from_kmer_counts(counts: &KmerCounts) -> Self {
+        let mut spectrum = Self::new();
+        counts.counts.values().for_each(|&n| spectrum.add(n));
+        spectrum
+    }
+
+    /// Builds the histogram of minimizer counts.
+    pub fn from_frequency(freq: &MinimizerFrequency) -> Self {
+        let mut spectrum = Self::new();
+        freq.counts.values().for_each(|&n| spectrum.add(n));
+        spectrum
+    }
+
+    /// Second pass of the bounded-memory mode: call it for every occurrence
+    /// of every key already counted in `sketch`. An occurrence of a key with
+    /// estimated count `c` adds `1/c` to bin `c`, so each distinct key adds
+    /// up to one without keeping the set of keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::{CountMinSketch, Spectrum};
+    ///
+    /// let seq = b"AAAAACGTTT";
+    /// let mut sketch = CountMinSketch::new(1 << 12, 4);
+    /// sketch.add_sequence(seq, 3);
+    ///
+    /// let mut spectrum = Spectrum::new();
+    /// spectrum.add_sequence_sketched(seq, 3, &sketch);
+    /// // AAA (TTT) four times, AAC (GTT) and ACG (CGT) twice
+    /// assert_eq!(spectrum.histogram(), vec![(2, 2), (4, 1)]);
+    /// ```
+    pub fn add_sketched(&mut self, key: u64, sketch: &CountMinSketch) {
+        let count = sketch.estimate(key) as u64;
+        if count > 0 {
+            *self.bins.entry(count).or_default() += 1.0 / count as f64;
+        }
+    }
+
+    /// Runs [`add_sketched`](Self::add_sketched) over the canonical k-mers
+    /// of a sequence.
+    pub fn add_sequence_sketched(&mut self, seq: &[u8], k: usize, sketch: &CountMinSketch) {
+        for_each_canonical_kmer(seq, k, |kmer| self.add_sketched(kmer, sketch));
+    }
+
+    /// Adds the bins of another histogram.
+    pub fn merge(&mut self, other: &Spectrum) {
+        for (&multiplicity, &n) in &other.bins {
+            *self.bins.entry(multiplicity).or_default() += n;
+        }
+    }
+
+    /// Returns the non-empty `(multiplicity, distinct count)` bins in
+    /// increasing multiplicity, rounding sketched estimates.
+    pub fn histogram(&self) -> Vec<(u64, u64)> {
+        self.bins
+            .iter()
+            .map(|(&multiplicity, &n)| (multiplicity, n.round() as u64))
+            .filter(|&(_, n)| n > 0)
+            .collect()
+    }
+
+    /// Writes `multiplicity count` lines. With `max_multiplicity`, higher
+    /// multiplicities are folded into the last bin, like `jellyfish histo
+    /// --high`.
+    pub fn write_histo<W: Write>(&self, w: &mut W, max_multiplicity: Option<u64>) -> Result<()> {
+        let mut overflow = 0;
+        for (multiplicity, n) in self.histogram() {
+            match max_multiplicity {
+                Some(max) if multiplicity >= max => overflow += n,
+                _ => writeln!(w, "{} {}", multiplicity, n)?,
+            }
+        }
+        if let (Some(max), true) = (max_multiplicity, overflow > 0) {
+            writeln!(w, "{} {}", max, overflow)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,331 @@
+use crate::mmscanner::MinimizerIterator;
+use crate::seq::Base;
+
+/// Accumulates seqkit-stats-like figures over sequences. Each mate of a pair
+/// counts as one sequence. Accumulators built on different threads are
+/// combined with [`merge`](Self::merge).
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{read_parallel, FastaReader, Meros, ParallelResult, SeqStats};
+/// use seqkmer::{Base, MinimizerIterator};
+/// use std::path::Path;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut reader = FastaReader::from_path(Path::new("tests/data/test.fasta"), 0)?;
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+///
+/// let work = |seqs: &mut Vec<Base<MinimizerIterator>>| SeqStats::from_records(seqs);
+/// let func = |result: &mut ParallelResult<SeqStats>| {
+///     let mut stats = SeqStats::new();
+///     while let Some(batch) = result.next() {
+///         stats.merge(batch.unwrap());
+///     }
+///     let summary = stats.summary();
+///     assert_eq!(summary.num_seqs, 3);
+///     assert_eq!(summary.min_len, 20);
+///     assert_eq!(summary.q20, None);
+/// };
+/// read_parallel(&mut reader, 4, &meros, work, func)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SeqStats {
+    lengths: Vec<usize>,
+    gc: u64,
+    /// 非 ACGT 的碱基数
+    ambiguous: u64,
+    qual_bases: u64,
+    q20: u64,
+    q30: u64,
+}
+
+/// Summary produced by [`SeqStats::summary`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatsSummary {
+    pub num_seqs: usize,
+    pub sum_len: u64,
+    pub min_len: usize,
+    pub avg_len: f64,
+    pub max_len: usize,
+    pub n50: usize,
+    /// GC share of the `ACGT` bases.
+    pub gc: f64,
+    /// Number of bases other than `ACGT`.
+    pub n_count: u64,
+    /// Share of bases with Phred ≥ 20, when qualities were seen.
+    pub q20: Option<f64>,
+    /// Share of bases with Phred ≥ 30, when qualities were seen.
+    pub q30: Option<f64>,
+}
+
+impl SeqStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds one sequence and its Phred+33 qualities, if any. Line breaks
+    /// are not counted.
+    pub fn add_seq(&mut self, seq: &[u8], qual: Option<&[u8]>) {
+        let mut len = 0;
+        for &c in seq {
+            match c.to_ascii_uppercase() {
+                b'\n' | b'\r' => continue,
+                b'G' | b'C' => self.gc += 1,
+                b'A' | b'T' => {}
+                _ => self.ambiguous += 1,
+            }
+            len += 1;
+        }
+        self.lengths.push(len);
+        if let Some(qual) = qual {
+            for &q in qual {
+                let phred = q.saturating_sub(b'!');
+                self.qual_bases += 1;
+                self.q20 += u64::from(phred >= 20);
+                self.q30 += u64::from(phred >= 30);
+            }
+        }
+    }
+
+    /// Adds a record, using its qualities when present.
+    pub fn add(&mut self, record: &Base<Vec<u8>>) {
+        match &record.qual {
+            Some(qual) => {
+                for (seq, q) in record.body.iter().zip(qual.iter()) {
+                    self.add_seq(seq, Some(q));
+                }
+            }
+            None => {
+                for seq in record.body.iter() {
+                    self.add_seq(seq, None);
+                }
+            }
+        }
+    }
+
+    /// Collects the statistics of a batch of scanned records, e.g. inside a
+    /// `read_parallel` worker. Qualities are used when the reader kept them.
+    pub fn from_records(records: &[Base<MinimizerIterator>]) -> Self {
+        let mut stats = Self::new();
+        for record in records {
+            for (mate, m_iter) in record.body.iter().enumerate() {
+                let qual = record.qual.as_ref().and_then(|q| q.iter().nth(mate));
+                stats.add_seq(m_iter.seq(), qual.map(Vec::as_slice));
+            }
+        }
+        stats
+    }
+
+    /// Folds another accumulator into this one.
+    pub fn merge(&mut self, other: SeqStats) {
+        self.lengths.extend(other.lengths);
+        self.gc += other.gc;
+        self.ambiguous += other.ambiguous;
+        self.qual_bases += other.qual_bases;
+        self.q20 += other.q20;
+        self.q30 += other.q30;
+    }
+
+    /// Number of sequences seen.
+    pub fn len(&self) -> usize {
+        self.lengths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lengths.is_empty()
+    }
+
+    /// Computes the summary; lengths are sorted once for N50.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::SeqStats;
+    ///
+    /// let mut stats = SeqStats::new();
+    /// stats.add_seq(b"ACGTNN", Some(b"IIII++"));
+    /// stats.add_seq(b"GGCC", None);
+    /// stats.add_seq(b"AT", None);
+    ///
+    /// let summary = stats.summary();
+    /// assert_eq!((summary.num_seqs, summary.sum_len), (3, 12));
+    /// assert_eq!((summary.min_len, summary.max_len, summary.n50), (2, 6, 6));
+    /// assert_eq!(summary.n_count, 2);
+    /// assert_eq!(summary.gc, 0.6);
+    /// assert_eq!(summary.q30, Some(4.0 / 6.0));
+    /// ```
+    pub fn summary(&self) -> StatsSummary {
+        let mut lengths = self.lengths.clone();
+        lengths.sort_unstable_by(|a, b| b.cmp(a));
+        let sum_len: u64 = lengths.iter().map(|&len| len as u64).sum();
+
+        // N50: 从长到短累加, 首次超过一半总长时的长度
+        let mut acc = 0u64;
+        let n50 = lengths
+            .iter()
+            .find(|&&len| {
+                acc += len as u64;
+                acc * 2 >= sum_len
+            })
+            .copied()
+            .unwrap_or(0);
+
+        let acgt = sum_len - self.ambiguous;
+        let share = |n: u64, total: u64| {
+            if total == 0 {
+                0.0
+            } else {
+                n as f64 / total as f64
+            }
+        };
+        let with_quals = |n: u64| (self.qual_bases > 0).then(|| share(n, self.qual_bases));
+
+        StatsSummary {
+            num_seqs: lengths.len(),
+            sum_len,
+            min_len: lengths.last().copied().unwrap_or(0),
+            avg_len: share(sum_len, lengths.len() as u64),
+            max_len: lengths.first().copied().unwrap_or(0),
+            n50,
+            gc: share(self.gc, acgt),
+            n_count: self.ambiguous,
+            q20: with_quals(self.q20),
+            q30: with_quals(self.q30),
+        }
+    }
+}
+
+/// Base and quality counts at one read position.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CycleCounts {
+    /// Counts of `A`, `C`, `G`, `T` and anything else, in that order.
+    pub bases: [u64; 5],
+    pub qual_sum: u64,
+    pub qual_count: u64,
+}
+
+impl CycleCounts {
+    /// Fractions of `A`, `C`, `G`, `T` and `N` at this position.
+    pub fn base_fractions(&self) -> [f64; 5] {
+        let total: u64 = self.bases.iter().sum();
+        self.bases.map(|n| {
+            if total == 0 {
+                0.0
+            } else {
+                n as f64 / total as f64
+            }
+        })
+    }
+
+    /// Mean Phred score at this position, if qualities were seen.
+    pub fn mean_quality(&self) -> Option<f64> {
+        (self.qual_count > 0).then(|| self.qual_sum as f64 / self.qual_count as f64)
+    }
+}
+
+/// Cycle-wise profile of base composition and quality, kept separately for
+/// R1 and R2, as used for FastQC-style per-position plots. Like
+/// [`SeqStats`], it can be built per batch in a `read_parallel` worker and
+/// merged.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::PositionProfile;
+///
+/// let mut profile = PositionProfile::new();
+/// profile.add_seq(0, b"ACGT", Some(b"II5+"));
+/// profile.add_seq(0, b"AGN", Some(b"I+!"));
+/// profile.add_seq(1, b"TT", None);
+///
+/// let r1 = profile.cycles(0);
+/// assert_eq!(r1.len(), 4);
+/// assert_eq!(r1[0].base_fractions(), [1.0, 0.0, 0.0, 0.0, 0.0]);
+/// assert_eq!(r1[1].base_fractions(), [0.0, 0.5, 0.5, 0.0, 0.0]);
+/// assert_eq!(r1[0].mean_quality(), Some(40.0));
+/// assert_eq!(r1[2].mean_quality(), Some(10.0));
+/// assert_eq!(profile.cycles(1)[0].mean_quality(), None);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PositionProfile {
+    mates: [Vec<CycleCounts>; 2],
+}
+
+impl PositionProfile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds one sequence of mate `mate` (0 for R1 or single reads, 1 for
+    /// R2) with its Phred+33 qualities, if any.
+    pub fn add_seq(&mut self, mate: usize, seq: &[u8], qual: Option<&[u8]>) {
+        let cycles = &mut self.mates[mate.min(1)];
+        let bases = seq.iter().filter(|&&c| c != b'\n' && c != b'\r');
+        for (pos, &c) in bases.enumerate() {
+            if cycles.len() <= pos {
+                cycles.push(CycleCounts::default());
+            }
+            let slot = match c.to_ascii_uppercase() {
+                b'A' => 0,
+                b'C' => 1,
+                b'G' => 2,
+                b'T' => 3,
+                _ => 4,
+            };
+            cycles[pos].bases[slot] += 1;
+        }
+        if let Some(qual) = qual {
+            for (pos, &q) in qual.iter().enumerate() {
+                if cycles.len() <= pos {
+                    cycles.push(CycleCounts::default());
+                }
+                cycles[pos].qual_sum += q.saturating_sub(b'!') as u64;
+                cycles[pos].qual_count += 1;
+            }
+        }
+    }
+
+    /// Adds a record, using its qualities when present.
+    pub fn add(&mut self, record: &Base<Vec<u8>>) {
+        for (mate, seq) in record.body.iter().enumerate() {
+            let qual = record.qual.as_ref().and_then(|q| q.iter().nth(mate));
+            self.add_seq(mate, seq, qual.map(Vec::as_slice));
+        }
+    }
+
+    /// Profiles a batch of scanned records.
+    pub fn from_records(records: &[Base<MinimizerIterator>]) -> Self {
+        let mut profile = Self::new();
+        for record in records {
+            for (mate, m_iter) in record.body.iter().enumerate() {
+                let qual = record.qual.as_ref().and_then(|q| q.iter().nth(mate));
+                profile.add_seq(mate, m_iter.seq(), qual.map(Vec::as_slice));
+            }
+        }
+        profile
+    }
+
+    /// Folds another profile into this one.
+    pub fn merge(&mut self, other: PositionProfile) {
+        for (cycles, other) in self.mates.iter_mut().zip(other.mates) {
+            if cycles.len() < other.len() {
+                cycles.resize(other.len(), CycleCounts::default());
+            }
+            for (counts, other) in cycles.iter_mut().zip(other) {
+                for (n, m) in counts.bases.iter_mut().zip(other.bases) {
+                    *n += m;
+                }
+                counts.qual_sum += other.qual_sum;
+                counts.qual_count += other.qual_count;
+            }
+        }
+    }
+
+    /// Per-position counts of mate `mate` (0 or 1).
+    pub fn cycles(&self, mate: usize) -> &[CycleCounts] {
+        &self.mates[mate.min(1)]
+    }
+}
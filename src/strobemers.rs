@@ -0,0 +1,189 @@
+use crate::feat::{fmix64, Alphabet};
+use std::io;
+
+/// How the second and third strobes are chosen from their windows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StrobeMethod {
+    /// Each strobe minimizes a hash that depends on the previous strobes,
+    /// as in strobealign.
+    #[default]
+    Randstrobe,
+    /// Each strobe is the minimum k-mer of its window, independent of the
+    /// previous strobes.
+    Minstrobe,
+}
+
+/// A linked seed: the start of its first and last strobes and its hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Strobemer {
+    pub pos: usize,
+    pub last_pos: usize,
+    pub hash: u64,
+}
+
+/// Strobemer parameters: `order` strobes of length `k`, each strobe after
+/// the first taken from `w_min..=w_max` k-mer positions past the window of
+/// the previous one.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{StrobeConfig, StrobeMethod};
+///
+/// let config = StrobeConfig::new(2, 5, 2, 6).unwrap();
+/// let seq = b"ACGTTGCAACGTGGCATTACGATCGATCGGGATCGACTAGC";
+/// let seeds = config.strobemers(seq);
+/// assert!(!seeds.is_empty());
+/// assert!(seeds.iter().all(|s| s.last_pos >= s.pos + 2 && s.last_pos <= s.pos + 6));
+///
+/// // both methods seed the same first strobes, with different partners
+/// let minstrobes = config.with_method(StrobeMethod::Minstrobe).strobemers(seq);
+/// assert_eq!(minstrobes.len(), seeds.len());
+///
+/// assert!(StrobeConfig::new(4, 5, 2, 6).is_err());
+/// // a DNA strobe must pack into fewer than 64 bits
+/// assert!(StrobeConfig::new(2, 31, 2, 6).is_ok());
+/// assert!(StrobeConfig::new(2, 32, 2, 6).is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StrobeConfig {
+    pub order: usize,
+    pub k: usize,
+    pub w_min: usize,
+    pub w_max: usize,
+    pub method: StrobeMethod,
+    pub alphabet: Alphabet,
+}
+
+impl StrobeConfig {
+    /// Creates a randstrobe configuration over DNA. `order` must be 2 or 3
+    /// and `1 <= w_min <= w_max`, and a strobe must pack into fewer than 64
+    /// bits, so `k <= 31`.
+    pub fn new(order: usize, k: usize, w_min: usize, w_max: usize) -> io::Result<Self> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidInput, msg);
+        if !(2..=3).contains(&order) {
+            return Err(invalid(format!(
+                "strobemer order must be 2 or 3, got {}",
+                order
+            )));
+        }
+        if w_min == 0 || w_min > w_max {
+            return Err(invalid(format!(
+                "invalid strobe window {}..={}",
+                w_min, w_max
+            )));
+        }
+        if k == 0 || k * Alphabet::Dna.bits_per_char() >= 64 {
+            return Err(invalid(format!(
+                "strobe length {} does not fit in 64 bits",
+                k
+            )));
+        }
+        Ok(Self {
+            order,
+            k,
+            w_min,
+            w_max,
+            method: StrobeMethod::default(),
+            alphabet: Alphabet::Dna,
+        })
+    }
+
+    pub fn with_method(mut self, method: StrobeMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Switches the residue alphabet. Fails if a strobe no longer fits in
+    /// 64 bits.
+    pub fn with_alphabet(mut self, alphabet: Alphabet) -> io::Result<Self> {
+        if self.k * alphabet.bits_per_char() >= 64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("strobe length {} does not fit in 64 bits", self.k),
+            ));
+        }
+        self.alphabet = alphabet;
+        Ok(self)
+    }
+
+    /// Hashes every k-mer of `seq`, with None where the k-mer holds a
+    /// character outside the alphabet. Line breaks are skipped.
+    fn kmer_hashes(&self, seq: &[u8]) -> Vec<Option<u64>> {
+        let bits = self.alphabet.bits_per_char();
+        let mask = (1u64 << (self.k * bits)) - 1;
+        let mut hashes = Vec::with_capacity(seq.len());
+        let (mut value, mut valid, mut len) = (0u64, 0usize, 0usize);
+        for &c in seq.iter().filter(|&&c| c != b'\n' && c != b'\r') {
+            match self.alphabet.encode(c) {
+                Some(code) => {
+                    value = ((value << bits) | code) & mask;
+                    valid += 1;
+                }
+                None => valid = 0,
+            }
+            len += 1;
+            // 第 i 个元素对应从 i 开始的 k-mer
+            if len >= self.k {
+                hashes.push(
+                    Some(value)
+                        .filter(|_| valid >= self.k)
+                        .map(|v| fmix64(self.alphabet.canonical(v, self.k))),
+                );
+            }
+        }
+        hashes
+    }
+
+    /// Picks the strobe in `window` minimizing the method's criterion.
+    fn pick(
+        &self,
+        hashes: &[Option<u64>],
+        window: std::ops::Range<usize>,
+        prev: u64,
+    ) -> Option<(usize, u64)> {
+        let end = window.end.min(hashes.len());
+        (window.start..end)
+            .filter_map(|j| hashes[j].map(|h| (j, h)))
+            .min_by_key(|&(j, h)| match self.method {
+                StrobeMethod::Randstrobe => (fmix64(prev ^ h), j),
+                StrobeMethod::Minstrobe => (h, j),
+            })
+    }
+
+    /// Generates the strobemers of a sequence, one per valid first strobe
+    /// whose later windows still hold a valid k-mer. Positions are k-mer
+    /// offsets after line breaks are removed.
+    pub fn strobemers(&self, seq: &[u8]) -> Vec<Strobemer> {
+        let hashes = self.kmer_hashes(seq);
+        let mut seeds = Vec::new();
+        for (pos, h1) in hashes.iter().enumerate() {
+            let Some(h1) = *h1 else { continue };
+            let (mut hash, mut last_pos, mut window_start) = (h1, pos, pos);
+            let mut complete = true;
+            for strobe in 1..self.order {
+                let window = window_start + self.w_min..window_start + self.w_max + 1;
+                match self.pick(&hashes, window, hash) {
+                    Some((j, h)) => {
+                        // 按顺序组合, 保证 strobe 顺序不同的种子哈希不同
+                        hash ^= h.rotate_left((strobe * 21) as u32);
+                        last_pos = j;
+                        window_start += self.w_max;
+                    }
+                    None => {
+                        complete = false;
+                        break;
+                    }
+                }
+            }
+            if complete {
+                seeds.push(Strobemer {
+                    pos,
+                    last_pos,
+                    hash: fmix64(hash),
+                });
+            }
+        }
+        seeds
+    }
+}
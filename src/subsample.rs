@@ -0,0 +1,147 @@
+use crate::error::Result;
+use crate::feat::fmix64;
+use crate::reader::Reader;
+use crate::seq::Base;
+
+/// Small seeded generator (SplitMix64), enough for sampling decisions.
+#[derive(Debug, Clone)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        fmix64(self.0)
+    }
+
+    /// Uniform in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in `[0, n)`.
+    fn below(&mut self, n: u64) -> u64 {
+        ((self.next_u64() as u128 * n as u128) >> 64) as u64
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    Fraction(f64),
+    Count(usize),
+}
+
+/// Passes a random subset of another reader's records through. Pairs are
+/// kept or dropped together, and the same seed always selects the same
+/// records.
+///
+/// With a fraction each record is kept independently while streaming. With
+/// a count the whole input is read once and a uniform sample of exactly that
+/// many records (fewer if the input is shorter) is returned in input order,
+/// so the records must fit in memory.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{FastaReader, Reader, SubsampleReader};
+/// use std::path::Path;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let path = Path::new("tests/data/test.fasta");
+///
+/// let mut reader = SubsampleReader::count(FastaReader::from_path(path, 0)?, 2, 7);
+/// let mut ids = Vec::new();
+/// while let Some(seqs) = reader.next()? {
+///     ids.extend(seqs.into_iter().map(|seq| seq.header.id));
+/// }
+/// assert_eq!(ids.len(), 2);
+///
+/// let mut reader = SubsampleReader::fraction(FastaReader::from_path(path, 0)?, 0.0, 7);
+/// assert!(reader.next()?.is_none());
+/// # Ok(())
+/// # }
+/// ```
+pub struct SubsampleReader<R: Reader> {
+    inner: R,
+    mode: Mode,
+    rng: SplitMix64,
+    /// 定量抽样的结果, 读完输入后按批次返回
+    sample: Option<std::vec::IntoIter<Base<Vec<u8>>>>,
+    batch_size: usize,
+}
+
+impl<R: Reader> SubsampleReader<R> {
+    /// Keeps each record with probability `fraction`.
+    pub fn fraction(inner: R, fraction: f64, seed: u64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&fraction),
+            "fraction must be in [0, 1]"
+        );
+        Self::with_mode(inner, Mode::Fraction(fraction), seed)
+    }
+
+    /// Keeps `count` records chosen by reservoir sampling.
+    pub fn count(inner: R, count: usize, seed: u64) -> Self {
+        Self::with_mode(inner, Mode::Count(count), seed)
+    }
+
+    fn with_mode(inner: R, mode: Mode, seed: u64) -> Self {
+        Self {
+            inner,
+            mode,
+            rng: SplitMix64(seed),
+            sample: None,
+            batch_size: 1000,
+        }
+    }
+
+    /// Reads the whole input, keeping a reservoir of `count` records.
+    fn fill_reservoir(&mut self, count: usize) -> Result<Vec<Base<Vec<u8>>>> {
+        let mut reservoir: Vec<(u64, Base<Vec<u8>>)> = Vec::with_capacity(count);
+        let mut seen = 0u64;
+        while let Some(seqs) = self.inner.next()? {
+            for seq in seqs {
+                if reservoir.len() < count {
+                    reservoir.push((seen, seq));
+                } else {
+                    let j = self.rng.below(seen + 1) as usize;
+                    if j < count {
+                        reservoir[j] = (seen, seq);
+                    }
+                }
+                seen += 1;
+            }
+        }
+        // 恢复输入顺序
+        reservoir.sort_unstable_by_key(|&(order, _)| order);
+        Ok(reservoir.into_iter().map(|(_, seq)| seq).collect())
+    }
+}
+
+impl<R: Reader> Reader for SubsampleReader<R> {
+    fn next(&mut self) -> Result<Option<Vec<Base<Vec<u8>>>>> {
+        match self.mode {
+            Mode::Fraction(fraction) => {
+                while let Some(mut seqs) = self.inner.next()? {
+                    seqs.retain(|_| self.rng.next_f64() < fraction);
+                    if !seqs.is_empty() {
+                        return Ok(Some(seqs));
+                    }
+                }
+                Ok(None)
+            }
+            Mode::Count(count) => {
+                if self.sample.is_none() {
+                    self.sample = Some(self.fill_reservoir(count)?.into_iter());
+                }
+                let sample = self.sample.as_mut().expect("sample was just filled");
+                let batch: Vec<_> = sample.by_ref().take(self.batch_size).collect();
+                Ok(Some(batch).filter(|v| !v.is_empty()))
+            }
+        }
+    }
+
+    fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size.max(1);
+        self.inner.set_batch_size(batch_size)
+    }
+}
@@ -0,0 +1,377 @@
+use crate::export::invalid_data;
+use crate::output::TaxonTree;
+use crate::reader::{open_file, SpaceDist};
+use crate::utils::OptionPair;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Result, Write};
+use std::path::Path;
+
+/// Magic bytes opening a binary taxonomy file.
+pub const TAXONOMY_MAGIC: &[u8; 4] = b"SKTX";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TaxonNode {
+    parent: u64,
+    rank: String,
+    name: String,
+}
+
+/// NCBI-style taxonomy: a tree of taxids with ranks and scientific names.
+///
+/// Taxid 0 stands for "no taxon": it is the identity of [`lca`](Self::lca)
+/// and has no lineage.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::Taxonomy;
+///
+/// let nodes = "1\t|\t1\t|\tno rank\t|\n\
+///              2\t|\t1\t|\tsuperkingdom\t|\n\
+///              561\t|\t2\t|\tgenus\t|\n\
+///              562\t|\t561\t|\tspecies\t|\n\
+///              620\t|\t2\t|\tgenus\t|\n";
+/// let names = "1\t|\troot\t|\t\t|\tscientific name\t|\n\
+///              2\t|\tBacteria\t|\t\t|\tscientific name\t|\n\
+///              2\t|\teubacteria\t|\t\t|\tgenbank common name\t|\n\
+///              561\t|\tEscherichia\t|\t\t|\tscientific name\t|\n\
+///              562\t|\tEscherichia coli\t|\t\t|\tscientific name\t|\n\
+///              620\t|\tShigella\t|\t\t|\tscientific name\t|\n";
+/// let taxonomy = Taxonomy::from_dmp_readers(nodes.as_bytes(), names.as_bytes()).unwrap();
+///
+/// assert_eq!(taxonomy.lca(562, 620), 2);
+/// assert_eq!(taxonomy.lca(562, 561), 561);
+/// assert_eq!(taxonomy.lca(0, 562), 562);
+/// assert_eq!(taxonomy.rank(562), Some("species"));
+/// assert_eq!(taxonomy.name(2), Some("Bacteria"));
+/// assert_eq!(
+///     taxonomy.lineage_string(562),
+///     "root;Bacteria;Escherichia;Escherichia coli"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Taxonomy {
+    nodes: HashMap<u64, TaxonNode>,
+}
+
+/// Splits a `.dmp` line into its `\t|\t`-separated fields.
+fn dmp_fields(line: &str) -> Vec<&str> {
+    line.trim_end_matches(['\n', '\r'])
+        .trim_end_matches("\t|")
+        .split("\t|\t")
+        .collect()
+}
+
+fn parse_taxid(field: &str, line_no: usize) -> Result<u64> {
+    field
+        .trim()
+        .parse()
+        .map_err(|_| invalid_data(format!("line {}: invalid taxid {:?}", line_no + 1, field)))
+}
+
+impl Taxonomy {
+    /// Loads `nodes.dmp` and `names.dmp` from an NCBI taxdump.
+    pub fn from_dmp<P: AsRef<Path>>(nodes: P, names: P) -> Result<Self> {
+        Self::from_dmp_readers(open_file(nodes)?, open_file(names)?)
+    }
+
+    /// Parses `nodes.dmp` and `names.dmp` content. Only scientific names
+    /// are kept.
+    pub fn from_dmp_readers<N: Read, M: Read>(nodes: N, names: M) -> Result<Self> {
+        let mut taxonomy = Taxonomy::default();
+        for (line_no, line) in BufReader::new(nodes).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let fields = dmp_fields(&line);
+            if fields.len() < 3 {
+                return Err(invalid_data(format!(
+                    "nodes.dmp line {}: expected at least 3 fields",
+                    line_no + 1
+                )));
+            }
+            let taxid = parse_taxid(fields[0], line_no)?;
+            let parent = parse_taxid(fields[1], line_no)?;
+            taxonomy.insert(taxid, parent, fields[2].trim(), "");
+        }
+
+        for (line_no, line) in BufReader::new(names).lines().enumerate() {
+            let line = line?;
+            let fields = dmp_fields(&line);
+            if fields.len() < 4 || fields[3].trim() != "scientific name" {
+                continue;
+            }
+            let taxid = parse_taxid(fields[0], line_no)?;
+            if let Some(node) = taxonomy.nodes.get_mut(&taxid) {
+                node.name = fields[1].trim().to_string();
+            }
+        }
+        Ok(taxonomy)
+    }
+
+    /// Adds or replaces a node. A node that is its own parent is a root.
+    pub fn insert(&mut self, taxid: u64, parent: u64, rank: &str, name: &str) {
+        let node = TaxonNode {
+            parent,
+            rank: rank.to_string(),
+            name: name.to_string(),
+        };
+        self.nodes.insert(taxid, node);
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn contains(&self, taxid: u64) -> bool {
+        self.nodes.contains_key(&taxid)
+    }
+
+    /// Returns the parent of `taxid`, or `None` for roots and unknown taxids.
+    pub fn parent(&self, taxid: u64) -> Option<u64> {
+        self.nodes
+            .get(&taxid)
+            .map(|node| node.parent)
+            .filter(|&parent| parent != taxid && parent != 0)
+    }
+
+    pub fn rank(&self, taxid: u64) -> Option<&str> {
+        self.nodes.get(&taxid).map(|node| node.rank.as_str())
+    }
+
+    pub fn name(&self, taxid: u64) -> Option<&str> {
+        self.nodes.get(&taxid).map(|node| node.name.as_str())
+    }
+
+    /// Returns the taxids from the root down to `taxid`. Unknown taxids and
+    /// 0 have an empty lineage.
+    pub fn lineage(&self, taxid: u64) -> Vec<u64> {
+        if !self.contains(taxid) {
+            return Vec::new();
+        }
+        let mut lineage = vec![taxid];
+        let mut node = taxid;
+        while let Some(parent) = self.parent(node) {
+            // 防御环状输入
+            if lineage.len() > self.nodes.len() {
+                break;
+            }
+            lineage.push(parent);
+            node = parent;
+        }
+        lineage.reverse();
+        lineage
+    }
+
+    /// Joins the lineage names with `;`.
+    pub fn lineage_string(&self, taxid: u64) -> String {
+        self.lineage(taxid)
+            .iter()
+            .map(|&t| self.name(t).unwrap_or(""))
+            .collect::<Vec<_>>()
+            .join(";")
+    }
+
+    /// Returns the number of edges between `taxid` and its root.
+    pub fn depth(&self, taxid: u64) -> usize {
+        self.lineage(taxid).len().saturating_sub(1)
+    }
+
+    /// Returns the lowest common ancestor of `a` and `b`. 0 acts as the
+    /// identity; taxa in disjoint trees or unknown taxids give 0.
+    pub fn lca(&self, a: u64, b: u64) -> u64 {
+        if a == 0 || b == 0 {
+            return a.max(b);
+        }
+        if a == b {
+            return a;
+        }
+        let lineage_a = self.lineage(a);
+        let lineage_b = self.lineage(b);
+        lineage_a
+            .iter()
+            .zip(&lineage_b)
+            .take_while(|(x, y)| x == y)
+            .last()
+            .map_or(0, |(&x, _)| x)
+    }
+
+    /// Returns true if `ancestor` is `taxid` or one of its ancestors.
+    pub fn is_ancestor(&self, ancestor: u64, taxid: u64) -> bool {
+        ancestor != 0 && self.lineage(taxid).contains(&ancestor)
+    }
+
+    /// Writes the compact binary form: `TAXONOMY_MAGIC`, the node count as a
+    /// little-endian u64, then per node (sorted by taxid) the taxid and
+    /// parent as little-endian u64s and the rank and name, each as a u32
+    /// length followed by UTF-8 bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::Taxonomy;
+    ///
+    /// let mut taxonomy = Taxonomy::default();
+    /// taxonomy.insert(1, 1, "no rank", "root");
+    /// taxonomy.insert(562, 1, "species", "Escherichia coli");
+    ///
+    /// let mut out = Vec::new();
+    /// taxonomy.write_binary(&mut out).unwrap();
+    /// assert_eq!(Taxonomy::read_binary(out.as_slice()).unwrap(), taxonomy);
+    /// ```
+    pub fn write_binary<W: Write>(&self, mut out: W) -> Result<()> {
+        let mut taxids: Vec<&u64> = self.nodes.keys().collect();
+        taxids.sort_unstable();
+        out.write_all(TAXONOMY_MAGIC)?;
+        out.write_all(&(taxids.len() as u64).to_le_bytes())?;
+        for taxid in taxids {
+            let node = &self.nodes[taxid];
+            out.write_all(&taxid.to_le_bytes())?;
+            out.write_all(&node.parent.to_le_bytes())?;
+            for text in [&node.rank, &node.name] {
+                out.write_all(&(text.len() as u32).to_le_bytes())?;
+                out.write_all(text.as_bytes())?;
+            }
+        }
+        out.flush()
+    }
+
+    /// Reads a taxonomy written by [`write_binary`](Self::write_binary).
+    pub fn read_binary<R: Read>(input: R) -> Result<Self> {
+        let mut input = BufReader::new(input);
+        let mut magic = [0u8; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != TAXONOMY_MAGIC {
+            return Err(invalid_data("not a binary taxonomy file".to_string()));
+        }
+        let mut word = [0u8; 8];
+        input.read_exact(&mut word)?;
+        let count = u64::from_le_bytes(word);
+
+        let read_text = |input: &mut BufReader<R>| -> Result<String> {
+            let mut len = [0u8; 4];
+            input.read_exact(&mut len)?;
+            let mut bytes = vec![0u8; u32::from_le_bytes(len) as usize];
+            input.read_exact(&mut bytes)?;
+            String::from_utf8(bytes).map_err(|e| invalid_data(e.to_string()))
+        };
+
+        let mut taxonomy = Taxonomy::default();
+        for _ in 0..count {
+            input.read_exact(&mut word)?;
+            let taxid = u64::from_le_bytes(word);
+            input.read_exact(&mut word)?;
+            let parent = u64::from_le_bytes(word);
+            let rank = read_text(&mut input)?;
+            let name = read_text(&mut input)?;
+            taxonomy
+                .nodes
+                .insert(taxid, TaxonNode { parent, rank, name });
+        }
+        Ok(taxonomy)
+    }
+}
+
+impl TaxonTree for Taxonomy {
+    fn parent(&self, taxid: u64) -> Option<u64> {
+        Taxonomy::parent(self, taxid)
+    }
+
+    fn rank(&self, taxid: u64) -> &str {
+        Taxonomy::rank(self, taxid).unwrap_or("no rank")
+    }
+
+    fn name(&self, taxid: u64) -> &str {
+        Taxonomy::name(self, taxid).unwrap_or("")
+    }
+}
+
+/// Assigns a read to a taxon from its hit distribution, following Kraken2's
+/// scoring: each hit taxon scores the hits on its root-to-leaf path, the best
+/// scoring taxon wins (ties go to their LCA), and the call then moves up the
+/// tree until its clade holds at least `confidence` of all the read's
+/// minimizers, misses included.
+///
+/// Returns the taxid (0 when unclassified) and the fraction of minimizers
+/// supporting it.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{resolve, OptionPair, SpaceDist, Taxonomy};
+///
+/// let mut taxonomy = Taxonomy::default();
+/// taxonomy.insert(1, 1, "no rank", "root");
+/// taxonomy.insert(561, 1, "genus", "Escherichia");
+/// taxonomy.insert(562, 561, "species", "Escherichia coli");
+/// taxonomy.insert(620, 1, "genus", "Shigella");
+///
+/// let mut dist = SpaceDist::new((0, 10));
+/// for pos in 1..=4 {
+///     dist.add(562, pos);
+/// }
+/// dist.add(561, 5);
+/// dist.add(620, 6);
+/// dist.fill_tail_with_zeros();
+/// let hits = OptionPair::Single(dist);
+///
+/// assert_eq!(resolve(&hits, &taxonomy, 0.0), (562, 0.4));
+/// // 50% 需要整个属的支持
+/// assert_eq!(resolve(&hits, &taxonomy, 0.5), (561, 0.5));
+/// assert_eq!(resolve(&hits, &taxonomy, 0.9), (0, 0.0));
+/// ```
+pub fn resolve(hits: &OptionPair<SpaceDist>, taxonomy: &Taxonomy, confidence: f64) -> (u64, f64) {
+    let mut counts: HashMap<u64, usize> = HashMap::new();
+    let mut total = 0;
+    for dist in hits {
+        for data in &dist.value {
+            total += data.count;
+            if data.ext_code != 0 {
+                *counts.entry(data.ext_code).or_default() += data.count;
+            }
+        }
+    }
+    if total == 0 {
+        return (0, 0.0);
+    }
+
+    // 路径得分: 该节点及其祖先上的命中数之和
+    let mut best = 0;
+    let mut best_score = 0;
+    let mut taxa: Vec<&u64> = counts.keys().collect();
+    taxa.sort_unstable();
+    for &taxid in taxa {
+        let score: usize = taxonomy
+            .lineage(taxid)
+            .iter()
+            .filter_map(|t| counts.get(t))
+            .sum();
+        if score > best_score {
+            best = taxid;
+            best_score = score;
+        } else if score == best_score {
+            best = taxonomy.lca(best, taxid);
+        }
+    }
+
+    // clade 得分: 该节点子树内的命中数之和
+    let clade_score = |taxid: u64| -> usize {
+        counts
+            .iter()
+            .filter(|&(&t, _)| taxonomy.is_ancestor(taxid, t))
+            .map(|(_, &n)| n)
+            .sum()
+    };
+    let required = confidence * total as f64;
+    let mut score = clade_score(best);
+    while best != 0 && (score as f64) < required {
+        best = taxonomy.parent(best).unwrap_or(0);
+        score = if best == 0 { 0 } else { clade_score(best) };
+    }
+    (best, score as f64 / total as f64)
+}
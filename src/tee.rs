@@ -0,0 +1,162 @@
+use crate::error::Result;
+use crate::reader::Reader;
+use crate::seq::Base;
+use std::io::Write;
+
+/// Receives a copy of every batch a [`TeeReader`] passes on.
+pub trait BatchSink: Send {
+    fn write_batch(&mut self, batch: &[Base<Vec<u8>>]) -> Result<()>;
+
+    /// Called once when the input is exhausted, e.g. to flush a writer.
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<F> BatchSink for F
+where
+    F: FnMut(&[Base<Vec<u8>>]) -> Result<()> + Send,
+{
+    fn write_batch(&mut self, batch: &[Base<Vec<u8>>]) -> Result<()> {
+        self(batch)
+    }
+}
+
+/// Writes records as FASTQ when they carry qualities and as FASTA
+/// otherwise, with the mates of a pair interleaved under the record id.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{BatchSink, FastaReader, FastxSink, Reader};
+///
+/// # fn main() -> Result<(), seqkmer::SeqKmerError> {
+/// let mut reader = FastaReader::new(b">r1 first\nACGT\n>r2\nGGCC\n".as_slice(), 0);
+/// let mut sink = FastxSink::new(Vec::new());
+/// sink.write_batch(&reader.next()?.unwrap())?;
+/// assert_eq!(sink.into_inner(), b">r1 first\nACGT\n>r2\nGGCC\n");
+/// # Ok(())
+/// # }
+/// ```
+pub struct FastxSink<W: Write + Send> {
+    writer: W,
+}
+
+impl<W: Write + Send> FastxSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write + Send> BatchSink for FastxSink<W> {
+    fn write_batch(&mut self, batch: &[Base<Vec<u8>>]) -> Result<()> {
+        for record in batch {
+            let header = &record.header;
+            // desc 已去掉首尾空白, 写回时补上分隔的空格
+            let desc = header
+                .desc
+                .as_deref()
+                .map_or(String::new(), |desc| format!(" {}", desc));
+            let quals = record
+                .qual
+                .as_ref()
+                .map(|qual| qual.iter().collect::<Vec<_>>());
+            for (mate, seq) in record.body.iter().enumerate() {
+                match quals.as_ref().and_then(|quals| quals.get(mate)) {
+                    Some(qual) => {
+                        writeln!(self.writer, "@{}{}", header.id, desc)?;
+                        self.writer.write_all(seq)?;
+                        self.writer.write_all(b"\n+\n")?;
+                        self.writer.write_all(qual)?;
+                    }
+                    None => {
+                        writeln!(self.writer, ">{}{}", header.id, desc)?;
+                        self.writer.write_all(seq)?;
+                    }
+                }
+                self.writer.write_all(b"\n")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+/// Passes another reader's batches through unchanged while handing a copy
+/// of each to a [`BatchSink`], e.g. to archive exactly the reads a pipeline
+/// processed. A sink error is returned from `next` like a read error.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{read_parallel, Base, FastaReader, FastxSink, Meros, MinimizerIterator};
+/// use seqkmer::{ParallelResult, TeeReader};
+/// use std::path::Path;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let inner = FastaReader::from_path(Path::new("tests/data/test.fasta"), 0)?;
+/// let mut reader = TeeReader::new(inner, FastxSink::new(Vec::new()));
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+///
+/// let work = |seqs: &mut Vec<Base<MinimizerIterator>>| seqs.len();
+/// let func = |result: &mut ParallelResult<usize>| while result.next().is_some() {};
+/// read_parallel(&mut reader, 4, &meros, work, func)?;
+///
+/// let (_, sink) = reader.into_parts();
+/// let archived = String::from_utf8(sink.into_inner()).unwrap();
+/// assert_eq!(archived.matches('>').count(), 3);
+/// assert!(archived.starts_with(">seq1 Sample sequence 1\n"));
+/// # Ok(())
+/// # }
+/// ```
+pub struct TeeReader<R: Reader, S: BatchSink> {
+    inner: R,
+    sink: S,
+    finished: bool,
+}
+
+impl<R: Reader, S: BatchSink> TeeReader<R, S> {
+    pub fn new(inner: R, sink: S) -> Self {
+        Self {
+            inner,
+            sink,
+            finished: false,
+        }
+    }
+
+    /// Returns the inner reader and the sink.
+    pub fn into_parts(self) -> (R, S) {
+        (self.inner, self.sink)
+    }
+}
+
+impl<R: Reader, S: BatchSink> Reader for TeeReader<R, S> {
+    fn next(&mut self) -> Result<Option<Vec<Base<Vec<u8>>>>> {
+        let mut seqs = Vec::new();
+        Ok(self.next_into(&mut seqs)?.then_some(seqs))
+    }
+
+    fn next_into(&mut self, batch: &mut Vec<Base<Vec<u8>>>) -> Result<bool> {
+        if self.inner.next_into(batch)? {
+            self.sink.write_batch(batch)?;
+            return Ok(true);
+        }
+        // 输入结束时只通知一次
+        if !self.finished {
+            self.finished = true;
+            self.sink.finish()?;
+        }
+        Ok(false)
+    }
+
+    fn set_batch_size(&mut self, batch_size: usize) {
+        self.inner.set_batch_size(batch_size)
+    }
+}
@@ -0,0 +1,143 @@
+use crate::mmscanner::MinimizerIterator;
+use crate::seq::Base;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::{Result, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Time spent on one read.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ReadTiming {
+    pub elapsed: Duration,
+    /// 序列长度 (双端为两条之和)
+    pub length: usize,
+    pub id: String,
+}
+
+/// Collects per-read processing times across worker threads and keeps the
+/// slowest reads, to find inputs that stall workers (huge contigs, extreme
+/// homopolymers).
+///
+/// Scanning is lazy, so wrapping the per-read `work` of a parallel driver
+/// with [`SlowReads::time`] measures both scanning and whatever the caller
+/// does with the minimizers.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{read_streaming, FastaReader, Meros, OptionPair, SlowReads};
+/// use std::path::Path;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut reader = FastaReader::from_path(Path::new("tests/data/test.fasta"), 0)?;
+/// let meros = Meros::new(11, 3, Some(0), None, None);
+/// let slow = SlowReads::new(5);
+///
+/// read_streaming(&mut reader, 3, &meros, |record| {
+///     slow.time(record, |record| match record.body {
+///         OptionPair::Single(m_iter) => m_iter.count(),
+///         OptionPair::Pair(m1, m2) => m1.count() + m2.count(),
+///     });
+/// })?;
+///
+/// assert!(slow.reads() > 0);
+/// let mut report = Vec::new();
+/// slow.write_report(&mut report)?;
+/// assert!(String::from_utf8(report).unwrap().starts_with("id\tlength\tseconds\n"));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SlowReads {
+    limit: usize,
+    reads: AtomicUsize,
+    total_nanos: AtomicUsize,
+    // 小顶堆, 只保留最慢的 limit 条
+    slowest: Mutex<BinaryHeap<Reverse<ReadTiming>>>,
+}
+
+impl SlowReads {
+    /// Creates a collector that keeps the `limit` slowest reads.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            reads: AtomicUsize::new(0),
+            total_nanos: AtomicUsize::new(0),
+            slowest: Mutex::new(BinaryHeap::with_capacity(limit + 1)),
+        }
+    }
+
+    /// Records the time spent on one read.
+    pub fn record(&self, id: &str, length: usize, elapsed: Duration) {
+        self.reads.fetch_add(1, Ordering::Relaxed);
+        self.total_nanos
+            .fetch_add(elapsed.as_nanos() as usize, Ordering::Relaxed);
+        if self.limit == 0 {
+            return;
+        }
+        let mut slowest = self.slowest.lock().unwrap();
+        if slowest.len() == self.limit
+            && slowest
+                .peek()
+                .is_some_and(|Reverse(t)| t.elapsed >= elapsed)
+        {
+            return;
+        }
+        slowest.push(Reverse(ReadTiming {
+            elapsed,
+            length,
+            id: id.to_string(),
+        }));
+        if slowest.len() > self.limit {
+            slowest.pop();
+        }
+    }
+
+    /// Runs `f` on a scanned record and records how long it took.
+    pub fn time<'a, T, F>(&self, record: Base<MinimizerIterator<'a>>, f: F) -> T
+    where
+        F: FnOnce(Base<MinimizerIterator<'a>>) -> T,
+    {
+        let id = record.header.id.clone();
+        let length = record.body.reduce(0, |n, m_iter| n + m_iter.seq_size());
+        let start = Instant::now();
+        let output = f(record);
+        self.record(&id, length, start.elapsed());
+        output
+    }
+
+    /// Number of reads recorded.
+    pub fn reads(&self) -> usize {
+        self.reads.load(Ordering::Relaxed)
+    }
+
+    /// Total time recorded over all reads.
+    pub fn total(&self) -> Duration {
+        Duration::from_nanos(self.total_nanos.load(Ordering::Relaxed) as u64)
+    }
+
+    /// Returns the slowest reads, slowest first.
+    pub fn slowest(&self) -> Vec<ReadTiming> {
+        let slowest = self.slowest.lock().unwrap();
+        let mut timings: Vec<ReadTiming> = slowest.iter().map(|t| t.0.clone()).collect();
+        timings.sort_by(|a, b| b.cmp(a));
+        timings
+    }
+
+    /// Writes the slowest reads as a tab-separated table.
+    pub fn write_report<W: Write>(&self, w: &mut W) -> Result<()> {
+        writeln!(w, "id\tlength\tseconds")?;
+        for timing in self.slowest() {
+            writeln!(
+                w,
+                "{}\t{}\t{:.6}",
+                timing.id,
+                timing.length,
+                timing.elapsed.as_secs_f64()
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+use crate::feat::{dna_to_value, revcomp, Alphabet, Meros};
+use crate::mmscanner::{Cursor, MinimizerIterator, MinimizerWindow};
+use crate::seq::Base;
+
+/// NCBI genetic code tables, as amino acids for the 64 codons in `TCAG` order.
+const STANDARD: &[u8; 64] = b"FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG";
+const VERTEBRATE_MITOCHONDRIAL: &[u8; 64] =
+    b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSS**VVVVAAAADDEEGGGG";
+const YEAST_MITOCHONDRIAL: &[u8; 64] =
+    b"FFLLSSSSYY**CCWWTTTTPPPPHHQQRRRRIIMMTTTTNNKKSSRRVVVVAAAADDEEGGGG";
+const MOLD_MITOCHONDRIAL: &[u8; 64] =
+    b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG";
+const INVERTEBRATE_MITOCHONDRIAL: &[u8; 64] =
+    b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSSSVVVVAAAADDEEGGGG";
+
+/// Genetic code used to translate codons.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::GeneticCode;
+///
+/// assert_eq!(GeneticCode::from_ncbi_id(11), Some(GeneticCode::Standard));
+/// assert_eq!(GeneticCode::Standard.translate_codon(b"ATG"), b'M');
+/// assert_eq!(GeneticCode::Standard.translate_codon(b"TGA"), b'*');
+/// assert_eq!(GeneticCode::VertebrateMitochondrial.translate_codon(b"TGA"), b'W');
+/// assert_eq!(GeneticCode::Standard.translate_codon(b"ANG"), b'X');
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GeneticCode {
+    /// NCBI tables 1 and 11 (bacterial, archaeal and plant plastid).
+    #[default]
+    Standard,
+    /// NCBI table 2.
+    VertebrateMitochondrial,
+    /// NCBI table 3.
+    YeastMitochondrial,
+    /// NCBI table 4 (mold, protozoan and Mycoplasma/Spiroplasma).
+    MoldMitochondrial,
+    /// NCBI table 5.
+    InvertebrateMitochondrial,
+}
+
+impl GeneticCode {
+    /// Looks up a genetic code by its NCBI translation table id.
+    pub fn from_ncbi_id(id: u8) -> Option<Self> {
+        match id {
+            1 | 11 => Some(GeneticCode::Standard),
+            2 => Some(GeneticCode::VertebrateMitochondrial),
+            3 => Some(GeneticCode::YeastMitochondrial),
+            4 => Some(GeneticCode::MoldMitochondrial),
+            5 => Some(GeneticCode::InvertebrateMitochondrial),
+            _ => None,
+        }
+    }
+
+    fn table(&self) -> &'static [u8; 64] {
+        match self {
+            GeneticCode::Standard => STANDARD,
+            GeneticCode::VertebrateMitochondrial => VERTEBRATE_MITOCHONDRIAL,
+            GeneticCode::YeastMitochondrial => YEAST_MITOCHONDRIAL,
+            GeneticCode::MoldMitochondrial => MOLD_MITOCHONDRIAL,
+            GeneticCode::InvertebrateMitochondrial => INVERTEBRATE_MITOCHONDRIAL,
+        }
+    }
+
+    /// Translates one codon, returning `X` if it contains a non-ACGT base.
+    #[inline]
+    pub fn translate_codon(&self, codon: &[u8]) -> u8 {
+        // dna_to_value 按 ACGT 编码, 表按 TCAG 排列
+        const TCAG: [usize; 4] = [2, 1, 3, 0];
+        codon
+            .iter()
+            .try_fold(0usize, |acc, &c| {
+                dna_to_value(c).map(|code| acc * 4 + TCAG[code as usize])
+            })
+            .map_or(b'X', |index| self.table()[index])
+    }
+
+    /// Translates a nucleotide sequence in frame 0, dropping a trailing
+    /// partial codon.
+    pub fn translate(&self, seq: &[u8]) -> Vec<u8> {
+        seq.chunks_exact(3)
+            .map(|codon| self.translate_codon(codon))
+            .collect()
+    }
+}
+
+/// Translates a nucleotide sequence in all six reading frames: the three
+/// forward frames first, then the three frames of the reverse complement.
+/// Line breaks are ignored.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{translate_six_frames, GeneticCode};
+///
+/// let frames = translate_six_frames(b"ATGGCCTAA", GeneticCode::Standard);
+/// assert_eq!(frames[0], b"MA*");
+/// assert_eq!(frames[1], b"WP");
+/// assert_eq!(frames[3], b"LGH");
+/// ```
+pub fn translate_six_frames(seq: &[u8], code: GeneticCode) -> Vec<Vec<u8>> {
+    let forward: Vec<u8> = seq
+        .iter()
+        .copied()
+        .filter(|&c| c != b'\n' && c != b'\r')
+        .collect();
+    let reverse = revcomp(&forward);
+
+    [&forward, &reverse]
+        .iter()
+        .flat_map(|strand| {
+            (0..3).map(move |frame| code.translate(strand.get(frame..).unwrap_or_default()))
+        })
+        .collect()
+}
+
+/// Translates each mate of a record in six frames and scans every frame for
+/// protein minimizers, returning `(pos, hash)` pairs per frame in the order
+/// of [`translate_six_frames`]. `meros` must use a protein alphabet.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{
+///     scan_sequence_translated, Alphabet, Base, GeneticCode, Meros, OptionPair, SeqFormat,
+///     SeqHeader,
+/// };
+///
+/// let header = SeqHeader::new("read1", SeqFormat::Fasta);
+/// let seq = Base::new(header, OptionPair::Single(b"ATGGCCAAGCTGGTTCGTGAAATCTGGCCC".to_vec()));
+/// let meros = Meros::new(6, 4, None, None, None).with_alphabet(Alphabet::Reduced15);
+///
+/// let frames = scan_sequence_translated(&seq, &meros, GeneticCode::Standard);
+/// let frames = frames.body.single().unwrap();
+/// assert_eq!(frames.len(), 6);
+/// assert!(!frames[0].is_empty());
+/// ```
+pub fn scan_sequence_translated(
+    sequence: &Base<Vec<u8>>,
+    meros: &Meros,
+    code: GeneticCode,
+) -> Base<Vec<Vec<(usize, u64)>>> {
+    assert!(
+        meros.alphabet != Alphabet::Dna,
+        "translated scanning needs a protein alphabet"
+    );
+    let body = sequence.body.apply(|seq| {
+        translate_six_frames(seq, code)
+            .iter()
+            .map(|frame| {
+                let cursor = Cursor::new(meros);
+                let window = MinimizerWindow::new(meros.window_size());
+                MinimizerIterator::new(frame, cursor, window, meros).collect()
+            })
+            .collect()
+    });
+    Base::new(sequence.header.clone(), body)
+}
@@ -0,0 +1,397 @@
+use crate::seq::Base;
+use std::ops::Range;
+
+/// A record transform applied between the reader and `scan_sequence`.
+///
+/// Closures taking `&mut Base<Vec<u8>>` implement it, so ad-hoc transforms
+/// can be passed wherever a trimmer is accepted.
+pub trait Transform: Send + Sync {
+    fn apply(&self, seq: &mut Base<Vec<u8>>);
+}
+
+impl<F> Transform for F
+where
+    F: Fn(&mut Base<Vec<u8>>) + Send + Sync,
+{
+    fn apply(&self, seq: &mut Base<Vec<u8>>) {
+        self(seq)
+    }
+}
+
+/// Removes 3' adapter sequence from reads.
+///
+/// An adapter is found where the read either contains the whole adapter or
+/// ends with a prefix of it at least `min_overlap` bases long, allowing up to
+/// `max_mismatches` mismatches (0 or 1 in practice). The read is cut at the
+/// leftmost such position.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::AdapterTrimmer;
+///
+/// let trimmer = AdapterTrimmer::new(&["AGATCGGAAGAGC"]);
+///
+/// // whole adapter inside the read
+/// let mut seq = b"ACGTACGTAGATCGGAAGAGCTTT".to_vec();
+/// assert_eq!(trimmer.trim(&mut seq), 16);
+/// assert_eq!(seq, b"ACGTACGT");
+///
+/// // read ends with the start of the adapter
+/// let mut seq = b"ACGTACGTAGATC".to_vec();
+/// trimmer.trim(&mut seq);
+/// assert_eq!(seq, b"ACGTACGT");
+///
+/// // one mismatch is only tolerated when allowed
+/// let mut seq = b"ACGTACGTAGTTCGGA".to_vec();
+/// assert_eq!(trimmer.trim(&mut seq), 0);
+/// let trimmer = trimmer.with_max_mismatches(1);
+/// trimmer.trim(&mut seq);
+/// assert_eq!(seq, b"ACGTACGT");
+/// ```
+#[derive(Debug, Clone)]
+pub struct AdapterTrimmer {
+    adapters: Vec<Vec<u8>>,
+    max_mismatches: usize,
+    min_overlap: usize,
+}
+
+impl AdapterTrimmer {
+    /// Creates an exact-match trimmer for the given adapters with a minimum
+    /// overlap of 3 bases.
+    pub fn new<A: AsRef<[u8]>>(adapters: &[A]) -> Self {
+        Self {
+            adapters: adapters
+                .iter()
+                .map(|a| a.as_ref().to_ascii_uppercase())
+                .filter(|a| !a.is_empty())
+                .collect(),
+            max_mismatches: 0,
+            min_overlap: 3,
+        }
+    }
+
+    /// Sets the number of mismatches tolerated in a match.
+    pub fn with_max_mismatches(mut self, max_mismatches: usize) -> Self {
+        self.max_mismatches = max_mismatches;
+        self
+    }
+
+    /// Sets the shortest adapter prefix matched at the end of a read.
+    pub fn with_min_overlap(mut self, min_overlap: usize) -> Self {
+        self.min_overlap = min_overlap.max(1);
+        self
+    }
+
+    fn matches_at(&self, seq: &[u8], adapter: &[u8], pos: usize) -> bool {
+        let overlap = (seq.len() - pos).min(adapter.len());
+        if overlap < self.min_overlap.min(adapter.len()) {
+            return false;
+        }
+        let mut mismatches = 0;
+        for (&a, &b) in seq[pos..pos + overlap].iter().zip(adapter) {
+            if a.to_ascii_uppercase() != b {
+                mismatches += 1;
+                if mismatches > self.max_mismatches {
+                    return false;
+                }
+            }
+        }
+        // 单个错配不能占满整个短重叠
+        mismatches == 0 || overlap > mismatches * 4
+    }
+
+    /// Returns where the leftmost adapter match starts, if any.
+    pub fn find(&self, seq: &[u8]) -> Option<usize> {
+        (0..seq.len()).find(|&pos| {
+            self.adapters
+                .iter()
+                .any(|adapter| self.matches_at(seq, adapter, pos))
+        })
+    }
+
+    /// Cuts the read at the leftmost adapter match and returns the number of
+    /// bases removed.
+    pub fn trim(&self, seq: &mut Vec<u8>) -> usize {
+        match self.find(seq) {
+            Some(pos) => {
+                let removed = seq.len() - pos;
+                seq.truncate(pos);
+                removed
+            }
+            None => 0,
+        }
+    }
+}
+
+/// Trims every mate, cutting the kept qualities to the same length.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{AdapterTrimmer, FastqReader, OptionPair, Reader, Transform};
+///
+/// # fn main() -> Result<(), seqkmer::SeqKmerError> {
+/// let fastq = b"@r1\nACGTACGTAGATCGGAAGAGCTTT\n+\nIIIIIIIIIIIIIIIIIIIIIIII\n";
+/// let mut reader = FastqReader::new(OptionPair::Single(fastq.as_slice()), 0, 0)
+///     .with_qualities(true);
+/// let mut seq = reader.next()?.unwrap().remove(0);
+///
+/// AdapterTrimmer::new(&["AGATCGGAAGAGC"]).apply(&mut seq);
+/// let body = seq.body.single().unwrap();
+/// let qual = seq.qual.as_ref().unwrap().single().unwrap();
+/// assert_eq!(body, b"ACGTACGT");
+/// assert_eq!(body.len(), qual.len());
+/// # Ok(())
+/// # }
+/// ```
+impl Transform for AdapterTrimmer {
+    fn apply(&self, seq: &mut Base<Vec<u8>>) {
+        match seq.qual.as_mut() {
+            Some(qual) => {
+                for (body, quals) in seq.body.iter_mut().zip(qual.iter_mut()) {
+                    self.trim(body);
+                    quals.truncate(body.len());
+                }
+            }
+            None => {
+                seq.body.apply_mut(|s| self.trim(s));
+            }
+        }
+    }
+}
+
+/// Quality trimming of read ends, using the Phred+33 qualities kept on the
+/// record (see `FastqReader::with_qualities`).
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::QualityTrim;
+///
+/// // Trimmomatic SLIDINGWINDOW:3:20 - 在第一个均值低于 20 的窗口处截断
+/// let quals = b"IIIIII+#I#";
+/// assert_eq!(QualityTrim::sliding_window(3, 20).keep_range(quals), 0..5);
+///
+/// // BWA -q 20, 两端都修剪
+/// let quals = b"#IIIII5I##";
+/// assert_eq!(QualityTrim::bwa(20).keep_range(quals), 1..8);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QualityTrim {
+    /// Scans windows of `window` bases from the 5' end and cuts the read at
+    /// the start of the first one whose mean quality is below `min_mean`.
+    SlidingWindow { window: usize, min_mean: u8 },
+    /// Cuts each end at the point that maximises the sum of
+    /// `threshold - quality` over the removed bases, as `bwa aln -q` does
+    /// for the 3' end (and `cutadapt -q t,t` for both).
+    Bwa { threshold: u8 },
+}
+
+#[inline]
+fn phred(q: u8) -> i64 {
+    q.saturating_sub(b'!') as i64
+}
+
+/// BWA 算法: 从一端开始, 返回应切除的碱基数
+fn bwa_cut<'a, I: Iterator<Item = &'a u8>>(quals: I, threshold: u8) -> usize {
+    let (mut sum, mut best, mut cut) = (0, 0, 0);
+    for (i, &q) in quals.enumerate() {
+        sum += threshold as i64 - phred(q);
+        if sum < 0 {
+            break;
+        }
+        if sum > best {
+            best = sum;
+            cut = i + 1;
+        }
+    }
+    cut
+}
+
+impl QualityTrim {
+    pub fn sliding_window(window: usize, min_mean: u8) -> Self {
+        QualityTrim::SlidingWindow {
+            window: window.max(1),
+            min_mean,
+        }
+    }
+
+    pub fn bwa(threshold: u8) -> Self {
+        QualityTrim::Bwa { threshold }
+    }
+
+    /// Returns the range of bases kept for these qualities.
+    pub fn keep_range(&self, quals: &[u8]) -> Range<usize> {
+        match *self {
+            QualityTrim::SlidingWindow { window, min_mean } => {
+                if quals.is_empty() {
+                    return 0..0;
+                }
+                // 读段比窗口短时用整条读段
+                let window = window.clamp(1, quals.len());
+                let min_sum = min_mean as i64 * window as i64;
+                let mut sum: i64 = quals[..window].iter().map(|&q| phred(q)).sum();
+                for start in 0..=quals.len() - window {
+                    if start > 0 {
+                        sum += phred(quals[start + window - 1]) - phred(quals[start - 1]);
+                    }
+                    if sum < min_sum {
+                        return 0..start;
+                    }
+                }
+                0..quals.len()
+            }
+            QualityTrim::Bwa { threshold } => {
+                let end = quals.len() - bwa_cut(quals.iter().rev(), threshold);
+                let start = bwa_cut(quals[..end].iter(), threshold);
+                start..end
+            }
+        }
+    }
+}
+
+/// Trims the sequence and qualities of every mate of `seq` and returns the
+/// number of bases removed. Records without qualities are left unchanged.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{quality_trim, FastqReader, OptionPair, QualityTrim};
+///
+/// # fn main() -> Result<(), seqkmer::SeqKmerError> {
+/// let input = b"@r1\nACGTACGTAC\n+\nIIIIII+#I#\n".as_slice();
+/// let mut reader = FastqReader::new(OptionPair::Single(input), 0, 0).with_qualities(true);
+/// let mut seq = reader.read_next()?.unwrap();
+///
+/// assert_eq!(quality_trim(&mut seq, &QualityTrim::sliding_window(3, 20)), 5);
+/// assert_eq!(seq.body.single().unwrap(), b"ACGTA");
+/// assert_eq!(seq.qual.unwrap().single().unwrap(), b"IIIII");
+/// # Ok(())
+/// # }
+/// ```
+pub fn quality_trim(seq: &mut Base<Vec<u8>>, trim: &QualityTrim) -> usize {
+    let Some(qual) = seq.qual.as_mut() else {
+        return 0;
+    };
+    let mut removed = 0;
+    for (body, quals) in seq.body.iter_mut().zip(qual.iter_mut()) {
+        let keep = trim.keep_range(quals);
+        let len = body.len();
+        body.truncate(keep.end);
+        body.drain(..keep.start.min(body.len()));
+        quals.truncate(keep.end);
+        quals.drain(..keep.start);
+        removed += len - body.len();
+    }
+    removed
+}
+
+/// As a pre-scan stage, e.g. in `read_parallel_transform`.
+impl Transform for QualityTrim {
+    fn apply(&self, seq: &mut Base<Vec<u8>>) {
+        quality_trim(seq, self);
+    }
+}
+
+/// Removes poly-G artifacts (no signal in two-color chemistry reads as G)
+/// and poly-A tails from the 3' end of reads, which would otherwise yield
+/// runs of identical low-complexity minimizers.
+///
+/// A tail is removed when it is at least `min_run` bases long; one
+/// mismatch per 8 bases is tolerated, as in fastp.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::PolyTailTrimmer;
+///
+/// let trimmer = PolyTailTrimmer::new(10);
+///
+/// let mut seq = b"ACGTACGTCAGGGGGGGGGGGG".to_vec();
+/// assert_eq!(trimmer.trim(&mut seq), 12);
+/// assert_eq!(seq, b"ACGTACGTCA");
+///
+/// // 允许少量错配, 短于 min_run 的尾巴保留
+/// let mut seq = b"ACGTCAAAAAAACAAAAAAA".to_vec();
+/// trimmer.trim(&mut seq);
+/// assert_eq!(seq, b"ACGTC");
+/// let mut seq = b"ACGTCGGGG".to_vec();
+/// assert_eq!(trimmer.trim(&mut seq), 0);
+///
+/// let trimmer = PolyTailTrimmer::new(10).with_bases(b"G");
+/// let mut seq = b"ACGTCAAAAAAAAAAAAAAA".to_vec();
+/// assert_eq!(trimmer.trim(&mut seq), 0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct PolyTailTrimmer {
+    bases: Vec<u8>,
+    min_run: usize,
+}
+
+impl PolyTailTrimmer {
+    /// Trims poly-G and poly-A tails of at least `min_run` bases.
+    pub fn new(min_run: usize) -> Self {
+        Self {
+            bases: b"GA".to_vec(),
+            min_run: min_run.max(1),
+        }
+    }
+
+    /// Sets the tail bases, e.g. `b"G"` for poly-G only.
+    pub fn with_bases(mut self, bases: &[u8]) -> Self {
+        self.bases = bases.to_ascii_uppercase();
+        self
+    }
+
+    /// Length of the run of `base` ending the read, allowing one mismatch
+    /// per 8 bases. The run always ends on a matching base.
+    fn tail_len(seq: &[u8], base: u8) -> usize {
+        let mut len = 0;
+        let mut mismatches = 0;
+        for (i, &b) in seq.iter().rev().enumerate() {
+            if b.to_ascii_uppercase() == base {
+                len = i + 1;
+            } else {
+                mismatches += 1;
+                if mismatches > (i + 1) / 8 {
+                    break;
+                }
+            }
+        }
+        len
+    }
+
+    /// Returns how many bases to cut from the end of `seq`.
+    pub fn find(&self, seq: &[u8]) -> usize {
+        self.bases
+            .iter()
+            .map(|&base| Self::tail_len(seq, base))
+            .filter(|&len| len >= self.min_run)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Cuts the tail and returns the number of bases removed.
+    pub fn trim(&self, seq: &mut Vec<u8>) -> usize {
+        let removed = self.find(seq);
+        seq.truncate(seq.len() - removed);
+        removed
+    }
+}
+
+impl Transform for PolyTailTrimmer {
+    fn apply(&self, seq: &mut Base<Vec<u8>>) {
+        match seq.qual.as_mut() {
+            Some(qual) => {
+                for (body, quals) in seq.body.iter_mut().zip(qual.iter_mut()) {
+                    let removed = self.trim(body);
+                    quals.truncate(quals.len().saturating_sub(removed));
+                }
+            }
+            None => {
+                seq.body.apply_mut(|s| self.trim(s));
+            }
+        }
+    }
+}
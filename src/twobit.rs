@@ -0,0 +1,321 @@
+use crate::error::{Result, SeqKmerError};
+use crate::reader::{open_file, Reader, BATCH_BYTES};
+use crate::seq::{Base, SeqFormat, SeqHeader};
+use crate::utils::OptionPair;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Signature at the start of a `.2bit` file, in the file's byte order.
+pub const TWOBIT_SIGNATURE: u32 = 0x1A41_2743;
+
+/// 2bit 编码: T=0, C=1, A=2, G=3
+const TWOBIT_BASES: [u8; 4] = [b'T', b'C', b'A', b'G'];
+
+/// Layout of one sequence record, read lazily from its offset.
+#[derive(Debug, Clone)]
+struct SeqLayout {
+    dna_size: usize,
+    /// (起点, 长度)
+    n_blocks: Vec<(usize, usize)>,
+    mask_blocks: Vec<(usize, usize)>,
+    packed_offset: u64,
+}
+
+/// Reads UCSC `.2bit` references, with random access by sequence name.
+///
+/// Packed bases are expanded to upper-case `ACGT`, N blocks to `N`, and
+/// mask blocks are ignored unless [`with_soft_mask`](Self::with_soft_mask)
+/// is set. As a [`Reader`] it returns the sequences in file order, whole.
+/// Both byte orders and the 64-bit offsets of version 1 files are handled.
+///
+/// # Examples
+///
+/// ```
+/// use seqkmer::{Reader, TwoBitReader};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut reader = TwoBitReader::from_path("tests/data/test.2bit", 0)?;
+/// assert_eq!(reader.names(), ["seq1", "seq2"]);
+/// assert_eq!(reader.seq_len("seq1")?, 18);
+///
+/// // 随机访问
+/// assert_eq!(reader.fetch("seq1", 2, 10)?, b"GTNNNNAC");
+/// let seq2 = reader.sequence("seq2")?;
+/// assert_eq!(seq2.body.single().unwrap(), b"TTTTGGGGCCCCAAAAT");
+///
+/// let seqs = reader.next()?.unwrap();
+/// assert_eq!(seqs.len(), 2);
+/// assert_eq!(seqs[0].body.single().unwrap(), b"ACGTNNNNACGTACGTAC");
+/// # Ok(())
+/// # }
+/// ```
+pub struct TwoBitReader<R>
+where
+    R: Read + Seek + Send,
+{
+    reader: R,
+    big_endian: bool,
+    file_index: usize,
+    names: Vec<String>,
+    offsets: Vec<u64>,
+    by_name: HashMap<String, usize>,
+    layouts: HashMap<usize, SeqLayout>,
+    soft_mask: bool,
+
+    // 批量读取
+    next_index: usize,
+    batch_size: usize,
+    batch_bytes: usize,
+}
+
+impl<R> TwoBitReader<R>
+where
+    R: Read + Seek + Send,
+{
+    /// Parses the header and the sequence index.
+    pub fn new(mut reader: R, file_index: usize) -> Result<Self> {
+        let mut word = [0u8; 4];
+        reader.read_exact(&mut word)?;
+        let big_endian = if u32::from_le_bytes(word) == TWOBIT_SIGNATURE {
+            false
+        } else if u32::from_be_bytes(word) == TWOBIT_SIGNATURE {
+            true
+        } else {
+            return Err(SeqKmerError::parse(None, "not a 2bit file: bad signature"));
+        };
+
+        let mut this = Self {
+            reader,
+            big_endian,
+            file_index,
+            names: Vec::new(),
+            offsets: Vec::new(),
+            by_name: HashMap::new(),
+            layouts: HashMap::new(),
+            soft_mask: false,
+            next_index: 0,
+            batch_size: 30,
+            batch_bytes: BATCH_BYTES,
+        };
+        let version = this.read_u32()?;
+        if version > 1 {
+            return Err(SeqKmerError::parse(
+                None,
+                format!("unsupported 2bit version {}", version),
+            ));
+        }
+        let count = this.read_u32()? as usize;
+        this.read_u32()?; // reserved
+
+        for index in 0..count {
+            let mut len = [0u8; 1];
+            this.reader.read_exact(&mut len)?;
+            let mut name = vec![0u8; len[0] as usize];
+            this.reader.read_exact(&mut name)?;
+            let name = String::from_utf8(name)
+                .map_err(|_| SeqKmerError::parse(None, "2bit sequence name is not UTF-8"))?;
+            let offset = if version == 1 {
+                this.read_u64()?
+            } else {
+                this.read_u32()? as u64
+            };
+            this.by_name.insert(name.clone(), index);
+            this.names.push(name);
+            this.offsets.push(offset);
+        }
+        Ok(this)
+    }
+
+    /// Lower-cases the bases in mask blocks (repeats), as `twoBitToFa`
+    /// does. Off by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::TwoBitReader;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut reader = TwoBitReader::from_path("tests/data/test.2bit", 0)?.with_soft_mask(true);
+    /// assert_eq!(reader.fetch("seq1", 0, 18)?, b"ACGTNNNNacgtACGTAC");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_soft_mask(mut self, soft_mask: bool) -> Self {
+        self.soft_mask = soft_mask;
+        self
+    }
+
+    /// Sets the maximum number of sequences returned per batch.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Sequence names in file order.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    fn read_u32(&mut self) -> Result<u32> {
+        let mut word = [0u8; 4];
+        self.reader.read_exact(&mut word)?;
+        Ok(if self.big_endian {
+            u32::from_be_bytes(word)
+        } else {
+            u32::from_le_bytes(word)
+        })
+    }
+
+    fn read_u64(&mut self) -> Result<u64> {
+        let mut word = [0u8; 8];
+        self.reader.read_exact(&mut word)?;
+        Ok(if self.big_endian {
+            u64::from_be_bytes(word)
+        } else {
+            u64::from_le_bytes(word)
+        })
+    }
+
+    fn read_blocks(&mut self) -> Result<Vec<(usize, usize)>> {
+        let count = self.read_u32()? as usize;
+        let starts = (0..count)
+            .map(|_| self.read_u32())
+            .collect::<Result<Vec<_>>>()?;
+        let sizes = (0..count)
+            .map(|_| self.read_u32())
+            .collect::<Result<Vec<_>>>()?;
+        Ok(starts
+            .into_iter()
+            .zip(sizes)
+            .map(|(start, size)| (start as usize, size as usize))
+            .collect())
+    }
+
+    fn index_of(&self, name: &str) -> Result<usize> {
+        self.by_name.get(name).copied().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("sequence not found in 2bit file: {}", name),
+            )
+            .into()
+        })
+    }
+
+    /// Reads (and caches) the block tables of sequence `index`.
+    fn layout(&mut self, index: usize) -> Result<SeqLayout> {
+        if let Some(layout) = self.layouts.get(&index) {
+            return Ok(layout.clone());
+        }
+        self.reader.seek(SeekFrom::Start(self.offsets[index]))?;
+        let dna_size = self.read_u32()? as usize;
+        let n_blocks = self.read_blocks()?;
+        let mask_blocks = self.read_blocks()?;
+        self.read_u32()?; // reserved
+        let layout = SeqLayout {
+            dna_size,
+            n_blocks,
+            mask_blocks,
+            packed_offset: self.reader.stream_position()?,
+        };
+        self.layouts.insert(index, layout.clone());
+        Ok(layout)
+    }
+
+    /// Length in bases of sequence `name`.
+    pub fn seq_len(&mut self, name: &str) -> Result<usize> {
+        let index = self.index_of(name)?;
+        Ok(self.layout(index)?.dna_size)
+    }
+
+    fn fetch_index(&mut self, index: usize, start: usize, end: usize) -> Result<Vec<u8>> {
+        let layout = self.layout(index)?;
+        let end = end.min(layout.dna_size);
+        if start >= end {
+            return Ok(Vec::new());
+        }
+
+        // 只读取覆盖 [start, end) 的打包字节
+        let first_byte = start / 4;
+        let mut packed = vec![0u8; (end - 1) / 4 + 1 - first_byte];
+        self.reader
+            .seek(SeekFrom::Start(layout.packed_offset + first_byte as u64))?;
+        self.reader.read_exact(&mut packed)?;
+
+        let mut bases: Vec<u8> = (start..end)
+            .map(|pos| {
+                let byte = packed[pos / 4 - first_byte];
+                let shift = 6 - 2 * (pos % 4);
+                TWOBIT_BASES[((byte >> shift) & 3) as usize]
+            })
+            .collect();
+
+        let overlap = |&(block_start, size): &(usize, usize)| {
+            let from = block_start.max(start);
+            let to = (block_start + size).min(end);
+            (from < to).then_some((from - start, to - start))
+        };
+        for (from, to) in layout.n_blocks.iter().filter_map(overlap) {
+            bases[from..to].fill(b'N');
+        }
+        if self.soft_mask {
+            for (from, to) in layout.mask_blocks.iter().filter_map(overlap) {
+                bases[from..to].make_ascii_lowercase();
+            }
+        }
+        Ok(bases)
+    }
+
+    /// Returns bases `[start, end)` (0-based, clipped to the sequence) of
+    /// sequence `name`, reading only the bytes that cover the range.
+    pub fn fetch(&mut self, name: &str, start: usize, end: usize) -> Result<Vec<u8>> {
+        let index = self.index_of(name)?;
+        self.fetch_index(index, start, end)
+    }
+
+    fn record(&mut self, index: usize) -> Result<Base<Vec<u8>>> {
+        let bases = self.fetch_index(index, 0, usize::MAX)?;
+        let header = SeqHeader {
+            file_index: self.file_index,
+            reads_index: index + 1,
+            ..SeqHeader::new(self.names[index].clone(), SeqFormat::Fasta)
+        };
+        Ok(Base::new(header, OptionPair::Single(bases)))
+    }
+
+    /// Returns the whole sequence `name` as a record.
+    pub fn sequence(&mut self, name: &str) -> Result<Base<Vec<u8>>> {
+        let index = self.index_of(name)?;
+        self.record(index)
+    }
+}
+
+impl TwoBitReader<BufReader<File>> {
+    /// Opens a `.2bit` file. It must be seekable, so gzipped files are not
+    /// accepted.
+    pub fn from_path<P: AsRef<Path>>(path: P, file_index: usize) -> Result<Self> {
+        Self::new(BufReader::new(open_file(path)?), file_index)
+    }
+}
+
+impl<R: Read + Seek + Send> Reader for TwoBitReader<R> {
+    fn next(&mut self) -> Result<Option<Vec<Base<Vec<u8>>>>> {
+        let mut seqs = Vec::new();
+        let mut total_bytes = 0;
+        while seqs.len() < self.batch_size && self.next_index < self.names.len() {
+            let seq = self.record(self.next_index)?;
+            self.next_index += 1;
+            total_bytes += seq.body.single().map_or(0, Vec::len);
+            seqs.push(seq);
+            if total_bytes > self.batch_bytes {
+                break;
+            }
+        }
+        Ok(if seqs.is_empty() { None } else { Some(seqs) })
+    }
+
+    fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size.max(1);
+    }
+}
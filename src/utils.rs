@@ -1,3 +1,6 @@
+use std::iter::{once, Chain, Once};
+use std::option;
+
 #[derive(Debug, Clone)]
 pub enum OptionPair<T> {
     Single(T),
@@ -161,6 +164,114 @@ impl<T> OptionPair<T> {
     }
 }
 
+/// Iterator over the values of an `OptionPair`: one for a single read, two
+/// for a pair, mate 1 first.
+pub type Iter<T> = Chain<Once<T>, option::IntoIter<T>>;
+
+#[allow(clippy::len_without_is_empty)] // 永远不为空
+impl<T> OptionPair<T> {
+    /// Returns 1 for a single value and 2 for a pair.
+    pub fn len(&self) -> usize {
+        match self {
+            OptionPair::Single(_) => 1,
+            OptionPair::Pair(..) => 2,
+        }
+    }
+
+    pub fn is_pair(&self) -> bool {
+        matches!(self, OptionPair::Pair(..))
+    }
+
+    /// Returns the single value or the first mate.
+    pub fn first(&self) -> &T {
+        match self {
+            OptionPair::Single(t) | OptionPair::Pair(t, _) => t,
+        }
+    }
+
+    /// Returns the single value or the first mate, mutably.
+    pub fn first_mut(&mut self) -> &mut T {
+        match self {
+            OptionPair::Single(t) | OptionPair::Pair(t, _) => t,
+        }
+    }
+
+    /// Returns the second mate, if any.
+    pub fn second(&self) -> Option<&T> {
+        match self {
+            OptionPair::Single(_) => None,
+            OptionPair::Pair(_, t) => Some(t),
+        }
+    }
+
+    /// Borrows the values, keeping the single/pair shape.
+    pub fn as_ref(&self) -> OptionPair<&T> {
+        match self {
+            OptionPair::Single(t) => OptionPair::Single(t),
+            OptionPair::Pair(t1, t2) => OptionPair::Pair(t1, t2),
+        }
+    }
+
+    /// Iterates over the values, mate 1 first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqkmer::OptionPair;
+    ///
+    /// let pair = OptionPair::Pair(b"ACGT".to_vec(), b"TT".to_vec());
+    /// assert_eq!(pair.len(), 2);
+    /// assert_eq!(pair.iter().map(|s| s.len()).sum::<usize>(), 6);
+    ///
+    /// let mut single = OptionPair::Single(1);
+    /// for value in single.iter_mut() {
+    ///     *value += 1;
+    /// }
+    /// assert_eq!(single.into_iter().collect::<Vec<_>>(), vec![2]);
+    /// ```
+    pub fn iter(&self) -> Iter<&T> {
+        self.into_iter()
+    }
+
+    /// Iterates mutably over the values, mate 1 first.
+    pub fn iter_mut(&mut self) -> Iter<&mut T> {
+        self.into_iter()
+    }
+}
+
+impl<T> IntoIterator for OptionPair<T> {
+    type Item = T;
+    type IntoIter = Iter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self {
+            OptionPair::Single(t) => once(t).chain(None),
+            OptionPair::Pair(t1, t2) => once(t1).chain(Some(t2)),
+        }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a OptionPair<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<&'a T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.as_ref().into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut OptionPair<T> {
+    type Item = &'a mut T;
+    type IntoIter = Iter<&'a mut T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self {
+            OptionPair::Single(t) => once(t).chain(None),
+            OptionPair::Pair(t1, t2) => once(t1).chain(Some(t2)),
+        }
+    }
+}
+
 impl<T: Clone> OptionPair<T> {
     /// Creates an OptionPair from a slice.
     ///